@@ -0,0 +1,36 @@
+use std::{path::PathBuf, process::Command};
+
+/// exposes the commit being built as `ATLAS_GIT_COMMIT`: taken as is when set
+/// (docker builds without `.git`), otherwise asked from git
+fn main() {
+    println!("cargo:rerun-if-env-changed=ATLAS_GIT_COMMIT");
+    let commit = std::env::var("ATLAS_GIT_COMMIT")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ATLAS_GIT_COMMIT={commit}");
+}
+
+fn git_commit() -> Option<String> {
+    let git_dir = PathBuf::from(git(&["rev-parse", "--absolute-git-dir"])?);
+    // HEAD moves on checkout, the branch ref on commit
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        println!(
+            "cargo:rerun-if-changed={}",
+            git_dir.join(head_ref).display()
+        );
+    }
+    git(&["rev-parse", "--short=12", "HEAD"])
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
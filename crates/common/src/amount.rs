@@ -0,0 +1,157 @@
+use anyhow::{Error, anyhow};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
+
+/// max decimals a `Decimal` can scale by
+const MAX_DECIMALS: u32 = 28;
+
+/// decimals of the amounts the indexer stores, and the `Decimal128` scale the
+/// server sums them at
+pub const STORED_DECIMALS: u32 = 18;
+
+/// a token quantity in whole-token units (e.g. `1.5` USDS, not its 18-decimals
+/// base units). parsed once where it enters the system and serialized as a
+/// precise decimal string, so it round-trips through JSON and ClickHouse
+/// `String` columns without float rounding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenAmount(Decimal);
+
+impl TokenAmount {
+    pub const ZERO: Self = Self(Decimal::ZERO);
+
+    pub fn new(value: Decimal) -> Self {
+        Self(value.normalize())
+    }
+
+    /// converts a raw integer amount in base units, as published by the oracles,
+    /// into whole tokens for a token with `decimals` decimals
+    pub fn from_base_units(raw: &str, decimals: u32) -> Result<Self, Error> {
+        if decimals > MAX_DECIMALS {
+            return Err(anyhow!("unsupported token decimals {decimals}"));
+        }
+        let raw = Decimal::from_str(raw.trim())
+            .map_err(|err| anyhow!("invalid base units amount {raw:?}: {err}"))?;
+        Ok(Self::new(
+            raw / Decimal::from_i128_with_scale(10i128.pow(decimals), 0),
+        ))
+    }
+
+    pub fn value(&self) -> Decimal {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// lossy conversion for float based aggregates and JSON numbers
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().expect("a Decimal always converts to f64")
+    }
+}
+
+impl From<Decimal> for TokenAmount {
+    fn from(value: Decimal) -> Self {
+        Self::new(value)
+    }
+}
+
+/// parses a whole-token decimal string. an empty string reads as zero: that's
+/// what rows written before an amount column was added hold.
+impl FromStr for TokenAmount {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(Self::ZERO);
+        }
+        Decimal::from_str(s)
+            .map(Self::new)
+            .map_err(|err| anyhow!("invalid token amount {s:?}: {err}"))
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for TokenAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Add for TokenAmount {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.0 + rhs.0)
+    }
+}
+
+impl Sub for TokenAmount {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for TokenAmount {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for TokenAmount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_base_units_test() {
+        let amount = TokenAmount::from_base_units("1500000000000000000", 18).unwrap();
+        assert_eq!(amount.to_string(), "1.5");
+        assert!(TokenAmount::from_base_units("12abc", 18).is_err());
+        assert!(TokenAmount::from_base_units("1", 40).is_err());
+    }
+
+    #[test]
+    fn parse_is_explicit_test() {
+        assert_eq!("".parse::<TokenAmount>().unwrap(), TokenAmount::ZERO);
+        assert!("not a number".parse::<TokenAmount>().is_err());
+        let sum: TokenAmount = ["0.1", "0.2"]
+            .iter()
+            .map(|v| v.parse::<TokenAmount>().unwrap())
+            .sum();
+        assert_eq!(sum.to_string(), "0.3");
+    }
+
+    #[test]
+    fn serde_round_trip_test() {
+        let amount: TokenAmount = "123456789.123456789123456789".parse().unwrap();
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, "\"123456789.123456789123456789\"");
+        assert_eq!(serde_json::from_str::<TokenAmount>(&json).unwrap(), amount);
+        assert!(serde_json::from_str::<TokenAmount>("\"1e\"").is_err());
+    }
+}
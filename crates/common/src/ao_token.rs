@@ -1,4 +1,6 @@
-use crate::constants::{AO_AUTHORITY, AO_TOKEN_PROCESS, arweave_gateway};
+use crate::constants::{AO_TOKEN_PROCESS, ao_authority, arweave_gateway};
+use crate::gateway::{check_page_size, post_json};
+use crate::height::{BlockHeight, node_block_height};
 use anyhow::{Error, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -20,7 +22,7 @@ pub struct AoTokenMessageMeta {
     pub msg_id: String,
     pub owner: String,
     pub recipient: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub block_timestamp: u64,
     pub bundled_in: String,
     pub data_size: String,
@@ -34,87 +36,28 @@ pub struct AoTokenMessagesPage {
     pub end_cursor: Option<String>,
 }
 
+/// one page of at most `page_size` (up to `GQL_MAX_PAGE_SIZE`) messages
 pub fn scan_arweave_block_for_token_msgs(
     process_id: &str,
     query: AoTokenQuery,
-    blockheight: u32,
+    blockheight: BlockHeight,
     after: Option<&str>,
+    page_size: u32,
 ) -> Result<AoTokenMessagesPage, Error> {
-    let (filter_clause, query_label) = match query {
-        AoTokenQuery::Transfer => (
-            format!(
-                "owners: [\"{AO_AUTHORITY}\"]\n    recipients: [\"{process_id}\"]\n    tags: [{{ name: \"Action\", values: [\"Transfer\"] }}]"
-            ),
-            "aoTokenTransfers",
-        ),
-        AoTokenQuery::Process => (
-            format!(
-                "owners: [\"{AO_AUTHORITY}\"]\n    tags: [{{ name: \"From-Process\", values: [\"{process_id}\"] }}]"
-            ),
-            "aoTokenProcessMsgs",
-        ),
-    };
-
-    let template = r#"
-query $querylabel {
-  transactions(
-    first: 100
-    sort: HEIGHT_ASC
-    $afterclause
-    $filterclause
-    block: { min: $blockheight, max: $blockheight }
-  ) {
-    edges {
-      cursor
-      node {
-        id
-        owner {
-          address
-        }
-        recipient
-        tags {
-          name
-          value
-        }
-        block {
-          id
-          height
-          timestamp
-        }
-        bundledIn {
-          id
-        }
-        data {
-          size
-        }
-      }
-    }
-    pageInfo {
-      hasNextPage
-    }
-  }
-}
-"#;
-
-    let after_clause = after
-        .map(|cursor| format!("    after: \"{cursor}\"\n"))
-        .unwrap_or_default();
-    let gql_query = template
-        .replace("$querylabel", query_label)
-        .replace("$afterclause", &after_clause)
-        .replace("$filterclause", &filter_clause)
-        .replace("$blockheight", &blockheight.to_string());
+    let gql_query = token_msgs_query(
+        process_id,
+        query,
+        blockheight,
+        after,
+        check_page_size(page_size)?,
+    );
 
     let body = json!({
         "query": gql_query,
         "variables": {}
     });
 
-    let req = ureq::post(format!("{}/graphql", arweave_gateway()))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{}/graphql", arweave_gateway()), &body)?;
 
     let txs = res
         .get("data")
@@ -142,12 +85,7 @@ query $querylabel {
             let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
                 continue;
             };
-            let block_height = node
-                .get("block")
-                .and_then(|v| v.get("height"))
-                .and_then(|v| v.as_u64())
-                .map(|v| v as u32)
-                .unwrap_or(0);
+            let block_height = node_block_height(node)?;
             let block_timestamp = node
                 .get("block")
                 .and_then(|v| v.get("timestamp"))
@@ -219,12 +157,88 @@ query $querylabel {
     })
 }
 
+fn token_msgs_query(
+    process_id: &str,
+    query: AoTokenQuery,
+    blockheight: BlockHeight,
+    after: Option<&str>,
+    page_size: u32,
+) -> String {
+    let authority = ao_authority();
+    let (filter_clause, query_label) = match query {
+        AoTokenQuery::Transfer => (
+            format!(
+                "owners: [\"{authority}\"]\n    recipients: [\"{process_id}\"]\n    tags: [{{ name: \"Action\", values: [\"Transfer\"] }}]"
+            ),
+            "aoTokenTransfers",
+        ),
+        AoTokenQuery::Process => (
+            format!(
+                "owners: [\"{authority}\"]\n    tags: [{{ name: \"From-Process\", values: [\"{process_id}\"] }}]"
+            ),
+            "aoTokenProcessMsgs",
+        ),
+    };
+
+    let template = r#"
+query $querylabel {
+  transactions(
+    first: $pagesize
+    sort: HEIGHT_ASC
+    $afterclause
+    $filterclause
+    block: { min: $blockheight, max: $blockheight }
+  ) {
+    edges {
+      cursor
+      node {
+        id
+        owner {
+          address
+        }
+        recipient
+        tags {
+          name
+          value
+        }
+        block {
+          id
+          height
+          timestamp
+        }
+        bundledIn {
+          id
+        }
+        data {
+          size
+        }
+      }
+    }
+    pageInfo {
+      hasNextPage
+    }
+  }
+}
+"#;
+
+    let after_clause = after
+        .map(|cursor| format!("    after: \"{cursor}\"\n"))
+        .unwrap_or_default();
+    template
+        .replace("$querylabel", query_label)
+        .replace("$afterclause", &after_clause)
+        .replace("$filterclause", &filter_clause)
+        .replace("$pagesize", &page_size.to_string())
+        .replace("$blockheight", &blockheight.to_string())
+}
+
 pub fn scan_arweave_block_for_ao_token_msgs(
     query: AoTokenQuery,
-    blockheight: u32,
+    blockheight: BlockHeight,
     after: Option<&str>,
+    page_size: u32,
 ) -> Result<AoTokenMessagesPage, Error> {
-    scan_arweave_block_for_token_msgs(AO_TOKEN_PROCESS, query, blockheight, after)
+    scan_arweave_block_for_token_msgs(AO_TOKEN_PROCESS, query, blockheight, after, page_size)
 }
 
 fn has_action_transfer(tags: &[Tag]) -> bool {
@@ -232,3 +246,21 @@ fn has_action_transfer(tags: &[Tag]) -> bool {
         tag.key.eq_ignore_ascii_case("action") && tag.value.eq_ignore_ascii_case("transfer")
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_size_is_templated_test() {
+        let query = token_msgs_query(
+            AO_TOKEN_PROCESS,
+            AoTokenQuery::Transfer,
+            1_606_012,
+            None,
+            1000,
+        );
+        assert!(query.contains("first: 1000\n"));
+        assert!(!query.contains('$'));
+    }
+}
@@ -0,0 +1,47 @@
+//! which build is running, so stored data can be traced back to the code
+//! that produced it
+
+use serde::Serialize;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// short commit hash the workspace was built from, `ATLAS_GIT_COMMIT` at
+/// build time or "unknown" outside a git checkout
+pub const GIT_COMMIT: &str = env!("ATLAS_GIT_COMMIT");
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub commit: &'static str,
+}
+
+impl BuildInfo {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            version: VERSION,
+            commit: GIT_COMMIT,
+        }
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ({})", self.name, self.version, self.commit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info_displays_version_and_commit_test() {
+        let info = BuildInfo::new("atlas-indexer");
+        assert!(!info.commit.is_empty());
+        assert_eq!(
+            info.to_string(),
+            format!("atlas-indexer {VERSION} ({GIT_COMMIT})")
+        );
+    }
+}
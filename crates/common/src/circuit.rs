@@ -0,0 +1,344 @@
+//! per-host circuit breaker in front of the gateways: after repeated
+//! failures calls are rejected locally for a cooldown instead of piling
+//! retries onto an upstream that is down
+
+use crate::env::get_env_var;
+use crate::gateway::GatewayUnavailable;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use ureq::http::{Request, Response};
+use ureq::middleware::{Middleware, MiddlewareNext};
+use ureq::{Body, SendBody};
+
+/// consecutive failures that open a host's circuit unless configured otherwise
+pub const DEFAULT_CIRCUIT_FAILURES: u32 = 5;
+/// failures further apart than this don't count as consecutive
+pub const DEFAULT_CIRCUIT_WINDOW: Duration = Duration::from_secs(30);
+/// how long an open circuit rejects calls before letting a probe through
+pub const DEFAULT_CIRCUIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitConfig {
+    /// 0 disables the breaker
+    pub failures: u32,
+    pub window: Duration,
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        Self {
+            failures: DEFAULT_CIRCUIT_FAILURES,
+            window: DEFAULT_CIRCUIT_WINDOW,
+            cooldown: DEFAULT_CIRCUIT_COOLDOWN,
+        }
+    }
+}
+
+impl CircuitConfig {
+    /// `GATEWAY_CIRCUIT_FAILURES`, `GATEWAY_CIRCUIT_WINDOW_SECS` and
+    /// `GATEWAY_CIRCUIT_COOLDOWN_SECS`, each falling back to its default
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |key: &str| {
+            get_env_var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+        };
+        Self {
+            failures: get_env_var("GATEWAY_CIRCUIT_FAILURES")
+                .ok()
+                .and_then(|v| v.trim().parse::<u32>().ok())
+                .unwrap_or(defaults.failures),
+            window: secs("GATEWAY_CIRCUIT_WINDOW_SECS").unwrap_or(defaults.window),
+            cooldown: secs("GATEWAY_CIRCUIT_COOLDOWN_SECS").unwrap_or(defaults.cooldown),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Closed {
+        failures: u32,
+        last_failure: Option<Instant>,
+    },
+    Open {
+        until: Instant,
+    },
+    /// cooldown elapsed, a single probe call is in flight
+    HalfOpen,
+}
+
+/// breaker of a single host. `allow` before each call, `record` its outcome.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitConfig,
+    state: State,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitConfig) -> Self {
+        Self {
+            config,
+            state: State::Closed {
+                failures: 0,
+                last_failure: None,
+            },
+        }
+    }
+
+    /// `Err` with the remaining cooldown while the call must be rejected.
+    /// once the cooldown is over the next caller becomes the half-open probe
+    /// and everyone else keeps being rejected until it reports back.
+    pub fn allow(&mut self, now: Instant) -> Result<(), Duration> {
+        match self.state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now < until => Err(until - now),
+            State::Open { .. } => {
+                self.state = State::HalfOpen;
+                Ok(())
+            }
+            State::HalfOpen => Err(self.config.cooldown),
+        }
+    }
+
+    /// records a call outcome, returning true when this opened the circuit
+    pub fn record(&mut self, now: Instant, ok: bool) -> bool {
+        if ok {
+            self.state = State::Closed {
+                failures: 0,
+                last_failure: None,
+            };
+            return false;
+        }
+        let failures = match self.state {
+            State::Closed {
+                failures,
+                last_failure: Some(last),
+            } if now.saturating_duration_since(last) <= self.config.window => failures + 1,
+            State::Closed { .. } => 1,
+            // a failed probe reopens straight away
+            State::HalfOpen => self.config.failures,
+            // calls started before the circuit opened
+            State::Open { .. } => return false,
+        };
+        if failures >= self.config.failures {
+            self.state = State::Open {
+                until: now + self.config.cooldown,
+            };
+            true
+        } else {
+            self.state = State::Closed {
+                failures,
+                last_failure: Some(now),
+            };
+            false
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(self.state, State::Closed { .. })
+    }
+}
+
+/// counters of the shared gateway breaker, for `/metrics`
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// times a host's circuit opened
+    pub opened: u64,
+    /// calls rejected without reaching the gateway
+    pub rejected: u64,
+    /// hosts currently open or half-open
+    pub open_hosts: Vec<String>,
+}
+
+/// the breakers of every host the agent talks to, installed as middleware on
+/// the shared agent
+#[derive(Debug)]
+pub struct GatewayCircuit {
+    config: CircuitConfig,
+    hosts: Mutex<HashMap<String, CircuitBreaker>>,
+    opened: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl GatewayCircuit {
+    pub fn new(config: CircuitConfig) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+            opened: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn allow(&self, host: &str, now: Instant) -> Result<(), GatewayUnavailable> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| CircuitBreaker::new(self.config));
+        breaker.allow(now).map_err(|retry_in| {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            GatewayUnavailable {
+                status: 503,
+                content_type: None,
+                retry_in: Some(retry_in),
+            }
+        })
+    }
+
+    fn record(&self, host: &str, now: Instant, ok: bool) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(breaker) = hosts.get_mut(host) else {
+            return;
+        };
+        if breaker.record(now, ok) {
+            self.opened.fetch_add(1, Ordering::Relaxed);
+            eprintln!(
+                "gateway circuit open for {host}: {} consecutive failures, cooling down {}s",
+                self.config.failures,
+                self.config.cooldown.as_secs()
+            );
+        }
+    }
+
+    pub fn stats(&self) -> CircuitStats {
+        let hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut open_hosts: Vec<String> = hosts
+            .iter()
+            .filter(|(_, breaker)| breaker.is_open())
+            .map(|(host, _)| host.clone())
+            .collect();
+        open_hosts.sort();
+        CircuitStats {
+            opened: self.opened.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            open_hosts,
+        }
+    }
+}
+
+/// transport errors and 5xx count against the host; 4xx and 429 mean the
+/// gateway is up and answering
+fn is_failure(res: &Result<Response<Body>, ureq::Error>) -> bool {
+    match res {
+        Ok(res) => res.status().is_server_error(),
+        Err(ureq::Error::StatusCode(code)) => *code >= 500,
+        Err(_) => true,
+    }
+}
+
+impl Middleware for &'static GatewayCircuit {
+    fn handle(
+        &self,
+        request: Request<SendBody>,
+        next: MiddlewareNext,
+    ) -> Result<Response<Body>, ureq::Error> {
+        if self.config.failures == 0 {
+            return next.handle(request);
+        }
+        let host = request.uri().host().unwrap_or_default().to_string();
+        if let Err(err) = self.allow(&host, Instant::now()) {
+            return Err(ureq::Error::Other(Box::new(err)));
+        }
+        let res = next.handle(request);
+        self.record(&host, Instant::now(), !is_failure(&res));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitConfig {
+        CircuitConfig {
+            failures: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures_test() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(config());
+        assert!(!breaker.record(start, false));
+        assert!(!breaker.record(start + Duration::from_secs(1), false));
+        assert!(breaker.allow(start + Duration::from_secs(2)).is_ok());
+        assert!(breaker.record(start + Duration::from_secs(2), false));
+        assert!(breaker.is_open());
+        assert_eq!(
+            breaker.allow(start + Duration::from_secs(12)),
+            Err(Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn failures_outside_the_window_or_after_success_start_over_test() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(config());
+        breaker.record(start, false);
+        breaker.record(start + Duration::from_secs(1), false);
+        // too long after the previous failure
+        assert!(!breaker.record(start + Duration::from_secs(20), false));
+        breaker.record(start + Duration::from_secs(21), true);
+        assert!(!breaker.record(start + Duration::from_secs(22), false));
+        assert!(!breaker.record(start + Duration::from_secs(23), false));
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens_test() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(config());
+        for _ in 0..3 {
+            breaker.record(start, false);
+        }
+        // cooldown over: one probe goes through, concurrent calls wait for it
+        let probe = start + Duration::from_secs(30);
+        assert!(breaker.allow(probe).is_ok());
+        assert!(breaker.allow(probe).is_err());
+        assert!(breaker.record(probe, false));
+        assert!(breaker.allow(probe + Duration::from_secs(29)).is_err());
+
+        let probe = probe + Duration::from_secs(30);
+        assert!(breaker.allow(probe).is_ok());
+        assert!(!breaker.record(probe, true));
+        assert!(!breaker.is_open());
+        assert!(breaker.allow(probe).is_ok());
+    }
+
+    #[test]
+    fn shared_circuit_counts_per_host_test() {
+        let circuit = GatewayCircuit::new(config());
+        let now = Instant::now();
+        for _ in 0..3 {
+            circuit.allow("arweave.net", now).unwrap();
+            circuit.record("arweave.net", now, false);
+        }
+        let err = circuit.allow("arweave.net", now).unwrap_err();
+        assert_eq!(err.retry_in, Some(Duration::from_secs(30)));
+        assert!(circuit.allow("permagate.io", now).is_ok());
+        assert_eq!(
+            circuit.stats(),
+            CircuitStats {
+                opened: 1,
+                rejected: 1,
+                open_hosts: vec!["arweave.net".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn server_errors_count_as_failures_test() {
+        assert!(is_failure(&Err(ureq::Error::StatusCode(502))));
+        assert!(is_failure(&Err(ureq::Error::ConnectionFailed)));
+        assert!(!is_failure(&Err(ureq::Error::StatusCode(404))));
+        assert!(!is_failure(&Err(ureq::Error::StatusCode(429))));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::env::get_env_var;
+use crate::height::BlockHeight;
 use serde::Deserialize;
 use std::{fs, io::ErrorKind, sync::OnceLock};
 
@@ -9,6 +10,10 @@ pub const STETH_ORACLE_PID: &str = "wJV8FMkpoeLsTjJ6O7YZEuQgMqj-sDjPHhTeA73RsCc"
 pub const STETH_STAKING_ADDRESS: &str = "0xfe08d40eee53d64936d3128838867c867602665c";
 pub const DAI_ORACLE_PID: &str = "5q8vpzC5QAKOAJFM26MAKfZw1gwtw7WA_J2861ZiKhI";
 pub const DAI_STAKING_ADDRESS: &str = "0x6a1b588b0684dace1f53c5820111f400b3dbfebf";
+// Ethereum mainnet ERC-20s staked into the contracts above
+pub const USDS_TOKEN_ADDRESS: &str = "0xdc035d45d973e3ec169d2276ddab16f1e407384f";
+pub const STETH_TOKEN_ADDRESS: &str = "0xae7ab96520de3a18e5e111b5eaab095312c7fe84";
+pub const DAI_TOKEN_ADDRESS: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
 // FLP system yield oracle processes -- ao.N.1
 pub const USDS_ORACLE_MAINNET_PID: &str = "JJPMirAJb2RR7mqIAilAGWjA3EwHIEXAnc__CfNKqNs";
 pub const STETH_ORACLE_MAINNET_PID: &str = "U4IrjxcKVsEya5kQbZPbjLCoj868P129Z4IlArMOzuc";
@@ -19,24 +24,100 @@ pub const FLP_AUTHORITY_MAINNET: &str = "XRDPy6e5zYaQ74oVESYZYz9DBwucohcdgRIYplV
 pub const AO_AUTHORITY: &str = "fcoN_xJeisVsPXA-trzVAuIiqO3ydLQxM-L4XbrQKzY";
 pub const DELEGATION_PID: &str = "cuxSKjGJ-WDB9PzSkVkVVrIBSh3DrYHYz44usQOj5yE";
 pub const AO_TOKEN_PROCESS: &str = "0syT13r0s0tgPmIed95bJnuSqaD29HQNN8D3ElLSrsc";
-pub const AO_TOKEN_START: u32 = 1_606_012;
+pub const AO_TOKEN_START: BlockHeight = 1_606_012;
 pub const PI_TOKEN_PROCESS: &str = "4hXj_E-5fAKmo4E8KjgQvuDJKAFk9P2grhycVmISDLs";
-pub const PI_TOKEN_START: u32 = 1_638_421;
+pub const PI_TOKEN_START: BlockHeight = 1_638_421;
 // ao mainnet data protocols
 // the mainnet have 2 type of tags for mainnet txs,
 // type A follows lower-case tags key format
 // type B follows Header-Case tags key format
-pub const DATA_PROTOCOL_A_START: u32 = 1_594_020; // Jan 22 2025
-pub const DATA_PROTOCOL_B_START: u32 = 1_616_999; // Feb 25 2025
+pub const DATA_PROTOCOL_A_START: BlockHeight = 1_594_020; // Jan 22 2025
+pub const DATA_PROTOCOL_B_START: BlockHeight = 1_616_999; // Feb 25 2025
 // endpoints
 const DEFAULT_ARWEAVE_GATEWAY: &str = "https://arweave.net";
+const DEFAULT_EXPLORER_GRAPHQL: &str = "https://permagate.io/graphql";
 
+// network identity accessors -- the consts above are the mainnet defaults,
+// each one can be overridden by its env var (e.g. to point at a fork/testnet
+// or to follow an authority rotation without a rebuild)
+macro_rules! env_constant {
+    ($fn_name:ident, $env_key:expr, $default:expr) => {
+        pub fn $fn_name() -> &'static str {
+            static VALUE: OnceLock<String> = OnceLock::new();
+            VALUE
+                .get_or_init(|| load_env_constant($env_key, $default))
+                .as_str()
+        }
+    };
+}
+
+env_constant!(ao_authority, "AO_AUTHORITY", AO_AUTHORITY);
+env_constant!(delegation_pid, "DELEGATION_PID", DELEGATION_PID);
+env_constant!(
+    flp_authority_mainnet,
+    "FLP_AUTHORITY_MAINNET",
+    FLP_AUTHORITY_MAINNET
+);
+env_constant!(
+    usds_oracle_mainnet_pid,
+    "USDS_ORACLE_MAINNET_PID",
+    USDS_ORACLE_MAINNET_PID
+);
+env_constant!(
+    steth_oracle_mainnet_pid,
+    "STETH_ORACLE_MAINNET_PID",
+    STETH_ORACLE_MAINNET_PID
+);
+env_constant!(
+    dai_oracle_mainnet_pid,
+    "DAI_ORACLE_MAINNET_PID",
+    DAI_ORACLE_MAINNET_PID
+);
+env_constant!(
+    usds_staking_address,
+    "USDS_STAKING_ADDRESS",
+    USDS_STAKING_ADDRESS
+);
+env_constant!(
+    steth_staking_address,
+    "STETH_STAKING_ADDRESS",
+    STETH_STAKING_ADDRESS
+);
+env_constant!(
+    dai_staking_address,
+    "DAI_STAKING_ADDRESS",
+    DAI_STAKING_ADDRESS
+);
+env_constant!(usds_token_address, "USDS_TOKEN_ADDRESS", USDS_TOKEN_ADDRESS);
+env_constant!(
+    steth_token_address,
+    "STETH_TOKEN_ADDRESS",
+    STETH_TOKEN_ADDRESS
+);
+env_constant!(dai_token_address, "DAI_TOKEN_ADDRESS", DAI_TOKEN_ADDRESS);
+
+fn load_env_constant(key: &str, default: &str) -> String {
+    get_env_var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// resolution order: `ARWEAVE_GATEWAY` env, then `PRIMARY_ARWEAVE_GATEWAY`
+/// from the atlas config file, then arweave.net
 pub fn arweave_gateway() -> &'static str {
     static GATEWAY: OnceLock<String> = OnceLock::new();
     GATEWAY.get_or_init(load_arweave_gateway).as_str()
 }
 
 fn load_arweave_gateway() -> String {
+    if let Some(gateway) = get_env_var("ARWEAVE_GATEWAY")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+    {
+        return gateway.trim().to_string();
+    }
     let path = get_env_var("ATLAS_CONFIG").unwrap_or_else(|_| "atlas.toml".into());
     let contents = match fs::read_to_string(&path) {
         Ok(contents) => contents,
@@ -61,6 +142,23 @@ fn load_arweave_gateway() -> String {
         .unwrap_or_else(|| DEFAULT_ARWEAVE_GATEWAY.to_string())
 }
 
+/// GraphQL endpoint of the explorer's per-block stats queries. resolution
+/// order: `EXPLORER_GRAPHQL_URL` env, then the configured `arweave_gateway()`
+/// (so one setting moves all Arweave traffic), then permagate.io
+pub fn explorer_graphql_url() -> &'static str {
+    static URL: OnceLock<String> = OnceLock::new();
+    URL.get_or_init(|| {
+        let gateway = arweave_gateway();
+        let fallback = if gateway == DEFAULT_ARWEAVE_GATEWAY {
+            DEFAULT_EXPLORER_GRAPHQL.to_string()
+        } else {
+            format!("{gateway}/graphql")
+        };
+        load_env_constant("EXPLORER_GRAPHQL_URL", &fallback)
+    })
+    .as_str()
+}
+
 #[derive(Deserialize, Default)]
 struct AtlasConfig {
     #[serde(rename = "PRIMARY_ARWEAVE_GATEWAY", alias = "primary_arweave_gateway")]
@@ -1,10 +1,21 @@
-use crate::constants::{AO_AUTHORITY, DELEGATION_PID, arweave_gateway};
+use crate::constants::{DELEGATION_PID, ao_authority, arweave_gateway, delegation_pid};
+use crate::gateway::post_json;
+use crate::height::{BlockHeight, node_block_height};
 use crate::projects::INTERNAL_PI_PID;
 use anyhow::{Error, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-pub const DELEGATION_PID_START_HEIGHT: u32 = 1_608_145;
+/// first block of the current delegation process. `Set-Delegation` messages
+/// below it went to the contract it replaced and no longer apply.
+pub const DELEGATION_PID_START_HEIGHT: BlockHeight = 1_608_145;
+
+/// lowest height a wallet's `Set-Delegation` counts from: the start of
+/// `DELEGATION_PID`, or no bound when `DELEGATION_PID` is overridden to a
+/// process whose start isn't known
+pub fn delegation_era_start() -> Option<BlockHeight> {
+    (delegation_pid() == DELEGATION_PID).then_some(DELEGATION_PID_START_HEIGHT)
+}
 
 pub fn get_user_delegation_txid(last_delegation_txid: &str) -> Result<String, Error> {
     let template = r#"
@@ -43,8 +54,8 @@ pub fn get_user_delegation_txid(last_delegation_txid: &str) -> Result<String, Er
     "#;
 
     let query = template
-        .replace("$addressvar", AO_AUTHORITY)
-        .replace("$delegationpidvar", DELEGATION_PID)
+        .replace("$addressvar", ao_authority())
+        .replace("$delegationpidvar", delegation_pid())
         .replace("$lastdelegationvar", last_delegation_txid);
 
     let body = json!({
@@ -52,11 +63,7 @@ pub fn get_user_delegation_txid(last_delegation_txid: &str) -> Result<String, Er
         "variables": {}
     });
 
-    let req = ureq::post(format!("{}/graphql", arweave_gateway()))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{}/graphql", arweave_gateway()), &body)?;
 
     let id = res
         .get("data")
@@ -71,7 +78,35 @@ pub fn get_user_delegation_txid(last_delegation_txid: &str) -> Result<String, Er
     Ok(id.to_string())
 }
 
-pub fn get_user_last_delegation_txid(address: &str) -> Result<Vec<String>, Error> {
+/// a wallet's `Set-Delegation` message and its block, `None` while pending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelegationMsg {
+    pub id: String,
+    pub height: Option<u64>,
+    /// block timestamp, unix seconds
+    pub timestamp: Option<u64>,
+}
+
+pub fn get_user_last_delegation_txid(
+    address: &str,
+    min_height: Option<BlockHeight>,
+) -> Result<Vec<String>, Error> {
+    let msgs = get_user_last_delegation_msgs(address, min_height)?;
+    if msgs.is_empty() {
+        return Ok(vec![INTERNAL_PI_PID.to_string()]);
+    }
+    Ok(msgs.into_iter().map(|msg| msg.id).collect())
+}
+
+/// the wallet's `Set-Delegation` messages of its newest block (several can
+/// share it), empty when it never set a delegation. with `min_height`
+/// (usually `delegation_era_start()`) messages confirmed below it are
+/// ignored, so a wallet whose only delegation predates the current process
+/// resolves like one that never delegated. pending messages are kept.
+pub fn get_user_last_delegation_msgs(
+    address: &str,
+    min_height: Option<BlockHeight>,
+) -> Result<Vec<DelegationMsg>, Error> {
     let template = r#"
     query GetDetailedTransactions {
   transactions(
@@ -96,6 +131,7 @@ pub fn get_user_last_delegation_txid(address: &str) -> Result<Vec<String>, Error
         block {
           id
           height
+          timestamp
         }
       }
     }
@@ -113,56 +149,49 @@ pub fn get_user_last_delegation_txid(address: &str) -> Result<Vec<String>, Error
         "variables": {}
     });
 
-    let req = ureq::post(format!("{}/graphql", arweave_gateway()))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{}/graphql", arweave_gateway()), &body)?;
+    Ok(last_delegation_msgs(&res, min_height))
+}
 
-    let edges = res
+fn last_delegation_msgs(res: &Value, min_height: Option<BlockHeight>) -> Vec<DelegationMsg> {
+    let Some(edges) = res
         .get("data")
         .and_then(|v| v.get("transactions"))
         .and_then(|v| v.get("edges"))
-        .and_then(|v| v.as_array());
-
-    if let Some(edges) = edges {
-        let mut nodes = Vec::new();
-        for edge in edges {
-            let Some(node) = edge.get("node") else {
-                continue;
-            };
-            let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
-                continue;
-            };
-            let height = node
-                .get("block")
-                .and_then(|v| v.get("height"))
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0);
-            nodes.push((id.to_string(), height));
-        }
-        if nodes.is_empty() {
-            return Ok(vec![INTERNAL_PI_PID.to_string()]);
-        }
-        let max_height = nodes.iter().map(|(_, h)| *h).max().unwrap_or(0);
-        let ids: Vec<String> = nodes
-            .into_iter()
-            .filter(|(_, h)| *h == max_height)
-            .map(|(id, _)| id)
-            .collect();
-        if ids.is_empty() {
-            return Ok(vec![INTERNAL_PI_PID.to_string()]);
-        }
-        return Ok(ids);
-    }
-    Ok(vec![INTERNAL_PI_PID.to_string()])
+        .and_then(|v| v.as_array())
+    else {
+        return Vec::new();
+    };
+    let msgs: Vec<DelegationMsg> = edges
+        .iter()
+        .filter_map(|edge| {
+            let node = edge.get("node")?;
+            let id = node.get("id")?.as_str()?;
+            let block = node.get("block");
+            Some(DelegationMsg {
+                id: id.to_string(),
+                height: block.and_then(|b| b.get("height")).and_then(|v| v.as_u64()),
+                timestamp: block
+                    .and_then(|b| b.get("timestamp"))
+                    .and_then(|v| v.as_u64()),
+            })
+        })
+        .filter(|msg: &DelegationMsg| match (msg.height, min_height) {
+            (Some(height), Some(min)) => height >= u64::from(min),
+            _ => true,
+        })
+        .collect();
+    let max_height = msgs.iter().map(|msg| msg.height.unwrap_or(0)).max();
+    msgs.into_iter()
+        .filter(|msg| Some(msg.height.unwrap_or(0)) == max_height)
+        .collect()
 }
 
 /// Action : Delegation-Mappings
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DelegationMappingMeta {
     pub tx_id: String,
-    pub height: u32,
+    pub height: BlockHeight,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -172,18 +201,75 @@ pub struct DelegationMappingsPage {
     pub end_cursor: Option<String>,
 }
 
+/// page size of `get_all_delegation_mappings`, the gateway's max `first`
+const DELEGATION_MAPPINGS_PAGE_SIZE: u32 = 100;
+/// pages `get_all_delegation_mappings` follows before giving up
+const MAX_DELEGATION_MAPPINGS_PAGES: usize = 500;
+
 pub fn get_delegation_mappings(
     first: Option<u32>,
     after: Option<&str>,
 ) -> Result<DelegationMappingsPage, Error> {
-    let first = first.unwrap_or(1).to_string();
+    let page = fetch_delegation_mappings_page(first.unwrap_or(1), after, None)?;
+    if page.mappings.is_empty() {
+        return Err(anyhow!("error: no ao message id found for the given query"));
+    }
+    Ok(page)
+}
+
+/// every `Delegation-Mappings` tx between `min_height` and `max_height`
+/// (inclusive), newest first, following `end_cursor` until the last page.
+pub fn get_all_delegation_mappings(
+    min_height: BlockHeight,
+    max_height: BlockHeight,
+) -> Result<Vec<DelegationMappingMeta>, Error> {
+    collect_delegation_mappings(min_height, max_height, DELEGATION_MAPPINGS_PAGE_SIZE)
+}
+
+fn collect_delegation_mappings(
+    min_height: BlockHeight,
+    max_height: BlockHeight,
+    page_size: u32,
+) -> Result<Vec<DelegationMappingMeta>, Error> {
+    collect_delegation_mapping_pages(min_height, max_height, |cursor| {
+        fetch_delegation_mappings_page(page_size, cursor, Some((min_height, max_height)))
+    })
+}
+
+/// follows `end_cursor` through the pages `fetch_page` returns for a cursor
+fn collect_delegation_mapping_pages(
+    min_height: BlockHeight,
+    max_height: BlockHeight,
+    mut fetch_page: impl FnMut(Option<&str>) -> Result<DelegationMappingsPage, Error>,
+) -> Result<Vec<DelegationMappingMeta>, Error> {
+    let mut cursor = None;
+    let mut all = Vec::new();
+    for _ in 0..MAX_DELEGATION_MAPPINGS_PAGES {
+        let page = fetch_page(cursor.as_deref())?;
+        all.extend(page.mappings);
+        cursor = page.end_cursor;
+        if !page.has_next_page || cursor.is_none() {
+            return Ok(all);
+        }
+    }
+    Err(anyhow!(
+        "error: delegation mappings between heights {min_height} and {max_height} span more than {MAX_DELEGATION_MAPPINGS_PAGES} pages"
+    ))
+}
+
+fn fetch_delegation_mappings_page(
+    first: u32,
+    after: Option<&str>,
+    heights: Option<(BlockHeight, BlockHeight)>,
+) -> Result<DelegationMappingsPage, Error> {
+    let first = first.to_string();
     let template = r#"
 query GetDetailedTransactions {
   transactions(
     first: $firstvar
     sort: HEIGHT_DESC
     owners: ["$addressvar"]
-$afterclause
+$afterclause$blockclause
     tags: [
       { name: "Action", values: ["Delegation-Mappings"] }
     ]
@@ -215,21 +301,21 @@ $afterclause
     let after_clause = after
         .map(|cursor| format!("    after: \"{cursor}\"\n"))
         .unwrap_or_default();
+    let block_clause = heights
+        .map(|(min, max)| format!("    block: {{ min: {min}, max: {max} }}\n"))
+        .unwrap_or_default();
     let query = template
-        .replace("$addressvar", AO_AUTHORITY)
+        .replace("$addressvar", ao_authority())
         .replace("$firstvar", &first)
-        .replace("$afterclause", &after_clause);
+        .replace("$afterclause", &after_clause)
+        .replace("$blockclause", &block_clause);
 
     let body = json!({
         "query": query,
         "variables": {}
     });
 
-    let req = ureq::post(format!("{}/graphql", arweave_gateway()))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{}/graphql", arweave_gateway()), &body)?;
 
     let txs = res
         .get("data")
@@ -258,21 +344,13 @@ $afterclause
         let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
             continue;
         };
-        let height = node
-            .get("block")
-            .and_then(|v| v.get("height"))
-            .and_then(|v| v.as_u64())
-            .map(|v| v as u32)
-            .unwrap_or(0);
+        let height = node_block_height(node)?;
         out.push(DelegationMappingMeta {
             tx_id: id.to_string(),
             height,
         });
     }
 
-    if out.is_empty() {
-        return Err(anyhow!("error: no ao message id found for the given query"));
-    }
     Ok(DelegationMappingsPage {
         mappings: out,
         has_next_page,
@@ -282,12 +360,104 @@ $afterclause
 
 #[cfg(test)]
 mod tests {
-    use crate::delegation::get_delegation_mappings;
+    use crate::delegation::{
+        DELEGATION_PID_START_HEIGHT, DelegationMappingMeta, DelegationMappingsPage,
+        collect_delegation_mapping_pages, get_delegation_mappings, last_delegation_msgs,
+    };
+    use crate::height::BlockHeight;
+    use serde_json::json;
+
+    #[test]
+    fn last_delegation_msgs_keep_newest_block_test() {
+        let res = json!({ "data": { "transactions": { "edges": [
+            { "node": { "id": "msg_b", "block": { "height": 1_700_001, "timestamp": 1_750_000_100 } } },
+            { "node": { "id": "msg_c", "block": { "height": 1_700_001, "timestamp": 1_750_000_100 } } },
+            { "node": { "id": "msg_a", "block": { "height": 1_600_000, "timestamp": 1_740_000_000 } } },
+        ] } } });
+        let msgs = last_delegation_msgs(&res, None);
+        let ids: Vec<&str> = msgs.iter().map(|msg| msg.id.as_str()).collect();
+        assert_eq!(ids, ["msg_b", "msg_c"]);
+        assert_eq!(msgs[0].height, Some(1_700_001));
+        assert_eq!(msgs[0].timestamp, Some(1_750_000_100));
+
+        // a pending message has no block yet
+        let pending = json!({ "data": { "transactions": { "edges": [
+            { "node": { "id": "msg_p", "block": null } },
+        ] } } });
+        let msgs = last_delegation_msgs(&pending, Some(DELEGATION_PID_START_HEIGHT));
+        assert_eq!((msgs[0].height, msgs[0].timestamp), (None, None));
+        assert!(last_delegation_msgs(&json!({ "data": null }), None).is_empty());
+    }
+
+    #[test]
+    fn pre_boundary_delegations_are_excluded_test() {
+        let before = u64::from(DELEGATION_PID_START_HEIGHT) - 1;
+        let res = json!({ "data": { "transactions": { "edges": [
+            { "node": { "id": "msg_old", "block": { "height": before, "timestamp": 1_740_000_000 } } },
+        ] } } });
+        let era = Some(DELEGATION_PID_START_HEIGHT);
+        assert!(last_delegation_msgs(&res, era).is_empty());
+        assert_eq!(last_delegation_msgs(&res, None)[0].id, "msg_old");
+
+        let at_start = u64::from(DELEGATION_PID_START_HEIGHT);
+        let res = json!({ "data": { "transactions": { "edges": [
+            { "node": { "id": "msg_new", "block": { "height": at_start, "timestamp": 1_740_000_100 } } },
+            { "node": { "id": "msg_old", "block": { "height": before, "timestamp": 1_740_000_000 } } },
+        ] } } });
+        let ids: Vec<String> = last_delegation_msgs(&res, era)
+            .into_iter()
+            .map(|msg| msg.id)
+            .collect();
+        assert_eq!(ids, ["msg_new"]);
+    }
 
     #[test]
     fn get_latest_delegation_mappings_test() {
         let res = get_delegation_mappings(None, None).unwrap();
         println!("{:?}", res);
-        assert_eq!(res.has_next_page, true);
+        assert!(res.has_next_page);
+    }
+
+    fn mappings_page(heights: &[BlockHeight], end_cursor: Option<&str>) -> DelegationMappingsPage {
+        DelegationMappingsPage {
+            mappings: heights
+                .iter()
+                .map(|height| DelegationMappingMeta {
+                    tx_id: format!("tx_{height}"),
+                    height: *height,
+                })
+                .collect(),
+            has_next_page: end_cursor.is_some(),
+            end_cursor: end_cursor.map(String::from),
+        }
+    }
+
+    #[test]
+    fn collect_delegation_mappings_follows_cursor_test() {
+        let max_height = DELEGATION_PID_START_HEIGHT + 50_000;
+        let mut cursors = Vec::new();
+        let res =
+            collect_delegation_mapping_pages(DELEGATION_PID_START_HEIGHT, max_height, |cursor| {
+                cursors.push(cursor.map(String::from));
+                Ok(match cursor {
+                    None => mappings_page(&[max_height, max_height - 10], Some("c1")),
+                    Some("c1") => mappings_page(&[max_height - 20, max_height - 30], Some("c2")),
+                    _ => mappings_page(&[DELEGATION_PID_START_HEIGHT], None),
+                })
+            })
+            .unwrap();
+        assert_eq!(
+            cursors,
+            [None, Some("c1".to_string()), Some("c2".to_string())]
+        );
+        assert_eq!(res.len(), 5);
+        assert!(res.windows(2).all(|w| w[0].height >= w[1].height));
+
+        // a cursor that never runs out is cut off instead of looping forever
+        let endless =
+            collect_delegation_mapping_pages(DELEGATION_PID_START_HEIGHT, max_height, |_| {
+                Ok(mappings_page(&[max_height], Some("again")))
+            });
+        assert!(endless.is_err());
     }
 }
@@ -5,3 +5,51 @@ pub fn get_env_var(key: &str) -> Result<String, Error> {
     dotenv().ok();
     Ok(dotenvy::var(key)?)
 }
+
+/// `CLICKHOUSE_URL`, a local instance by default
+pub fn clickhouse_url() -> String {
+    get_env_var("CLICKHOUSE_URL").unwrap_or_else(|_| "http://localhost:8123".into())
+}
+
+/// `CLICKHOUSE_DATABASE`, `atlas_oracles` by default
+pub fn clickhouse_database() -> String {
+    get_env_var("CLICKHOUSE_DATABASE").unwrap_or_else(|_| "atlas_oracles".into())
+}
+
+/// `url` without the `user:pass@` of its authority, safe to log or serve
+pub fn redact_url_credentials(url: &str) -> String {
+    let authority_start = url.find("://").map_or(0, |i| i + 3);
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| authority_start + i);
+    match url[authority_start..authority_end].rfind('@') {
+        Some(at) => format!(
+            "{}{}",
+            &url[..authority_start],
+            &url[authority_start + at + 1..]
+        ),
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_url_credentials_test() {
+        assert_eq!(
+            redact_url_credentials("https://atlas:s3cr@t@ch.example:8443/db?x=1"),
+            "https://ch.example:8443/db?x=1"
+        );
+        assert_eq!(
+            redact_url_credentials("http://user@localhost:8123"),
+            "http://localhost:8123"
+        );
+        assert_eq!(
+            redact_url_credentials("http://localhost:8123/?user=a@b"),
+            "http://localhost:8123/?user=a@b"
+        );
+        assert_eq!(redact_url_credentials("localhost:8123"), "localhost:8123");
+    }
+}
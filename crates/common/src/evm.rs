@@ -0,0 +1,121 @@
+//! minimal Ethereum JSON-RPC reads, to cross-check the stake the oracles
+//! report on AO against the staking contracts on chain
+
+use crate::amount::TokenAmount;
+use crate::gateway::post_json;
+use crate::gql::OracleMetadata;
+use anyhow::{Error, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// `balanceOf(address)` selector
+const BALANCE_OF: &str = "0x70a08231";
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<String>,
+    error: Option<Value>,
+}
+
+/// stake held by an oracle's staking contract at one block
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvmStake {
+    pub block: u64,
+    pub total: TokenAmount,
+}
+
+fn rpc_call(rpc_url: &str, method: &str, params: Value) -> Result<String, Error> {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let res: RpcResponse = post_json(rpc_url, &body)?;
+    rpc_result(method, res)
+}
+
+fn rpc_result(method: &str, res: RpcResponse) -> Result<String, Error> {
+    if let Some(err) = res.error {
+        return Err(anyhow!("evm rpc {method} failed: {err}"));
+    }
+    res.result
+        .ok_or_else(|| anyhow!("evm rpc {method} returned no result"))
+}
+
+/// `0x`-prefixed big-endian quantity, as long as it fits 128 bits
+fn parse_quantity(hex: &str) -> Result<u128, Error> {
+    let digits = hex
+        .strip_prefix("0x")
+        .ok_or_else(|| anyhow!("evm quantity {hex:?} is not 0x-prefixed"))?
+        .trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    if digits.len() > 32 {
+        return Err(anyhow!("evm quantity {hex} overflows 128 bits"));
+    }
+    Ok(u128::from_str_radix(digits, 16)?)
+}
+
+/// `balanceOf(holder)` call data
+fn balance_of_data(holder: &str) -> Result<String, Error> {
+    let address = holder.strip_prefix("0x").unwrap_or(holder);
+    if address.len() != 40 || !address.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("invalid evm address {holder}"));
+    }
+    Ok(format!("{BALANCE_OF}{:0>64}", address.to_ascii_lowercase()))
+}
+
+/// the staked token balance of the oracle's staking contract, read at the
+/// latest block so `block` and `total` belong together
+pub fn oracle_stake(rpc_url: &str, oracle: &OracleMetadata) -> Result<EvmStake, Error> {
+    let block = parse_quantity(&rpc_call(rpc_url, "eth_blockNumber", json!([]))?)?;
+    let block = u64::try_from(block)?;
+    let call = json!({
+        "to": oracle.evm_token_address,
+        "data": balance_of_data(&oracle.evm_address)?,
+    });
+    let raw = parse_quantity(&rpc_call(
+        rpc_url,
+        "eth_call",
+        json!([call, format!("{block:#x}")]),
+    )?)?;
+    Ok(EvmStake {
+        block,
+        total: TokenAmount::from_base_units(&raw.to_string(), oracle.denomination)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantities_decode_big_endian_hex_test() {
+        assert_eq!(parse_quantity("0x0").unwrap(), 0);
+        assert_eq!(parse_quantity("0x15e1a7c").unwrap(), 22_944_380);
+        let word = format!("0x{:0>64}", "de0b6b3a7640000");
+        assert_eq!(parse_quantity(&word).unwrap(), 1_000_000_000_000_000_000);
+        assert!(parse_quantity(&format!("0x1{}", "0".repeat(32))).is_err());
+        assert!(parse_quantity("12").is_err());
+    }
+
+    #[test]
+    fn balance_of_pads_the_holder_test() {
+        let data = balance_of_data("0x7CD01D5CAD4BA0CAEBA02583A5C61D35B23E08EB").unwrap();
+        assert_eq!(
+            data,
+            "0x70a08231\
+             0000000000000000000000007cd01d5cad4ba0caeba02583a5c61d35b23e08eb"
+        );
+        assert!(balance_of_data("0x1234").is_err());
+    }
+
+    #[test]
+    fn rpc_errors_are_surfaced_test() {
+        let res: RpcResponse = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"execution reverted"}}"#,
+        )
+        .unwrap();
+        let err = rpc_result("eth_call", res).unwrap_err();
+        assert!(err.to_string().contains("execution reverted"));
+        let res: RpcResponse = serde_json::from_str(r#"{"jsonrpc":"2.0","id":1}"#).unwrap();
+        assert!(rpc_result("eth_call", res).is_err());
+    }
+}
@@ -1,18 +1,537 @@
+use crate::circuit::{CircuitConfig, CircuitStats, GatewayCircuit};
 use crate::constants::arweave_gateway;
+use crate::env::get_env_var;
 use anyhow::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io::Read;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ureq::http::Response;
+use ureq::typestate::{WithBody, WithoutBody};
+use ureq::{Agent, Body, RequestBuilder};
 
-/// downloads an Arweave `txid` data and return Vec<u8> Body
+/// max retries for transient gateway failures (on top of the first attempt)
+pub const GATEWAY_MAX_RETRIES: u32 = 3;
+/// base delay of the exponential backoff between gateway retries
+pub const GATEWAY_BASE_BACKOFF_MS: u64 = 250;
+
+/// longest `Retry-After` a rate-limited caller will honour
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// transactions per GraphQL page (`first`) unless configured otherwise
+pub const GQL_DEFAULT_PAGE_SIZE: u32 = 100;
+/// the largest `first` the gateways accept
+pub const GQL_MAX_PAGE_SIZE: u32 = 1000;
+
+/// `size` if it is a page size the gateways accept
+pub fn check_page_size(size: u32) -> Result<u32, Error> {
+    if !(1..=GQL_MAX_PAGE_SIZE).contains(&size) {
+        return Err(anyhow::anyhow!(
+            "graphql page size {size} out of range 1..={GQL_MAX_PAGE_SIZE}"
+        ));
+    }
+    Ok(size)
+}
+
+/// `User-Agent` sent on every outgoing call unless `ATLAS_USER_AGENT` is set
+pub const DEFAULT_USER_AGENT: &str = concat!("atlas/", env!("CARGO_PKG_VERSION"));
+/// header carrying the per-request id, for correlating with gateway logs
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// process-wide agent all outgoing HTTP goes through, so our traffic is
+/// attributable on the gateway side and a failing host trips a shared
+/// circuit breaker
+pub fn agent() -> &'static Agent {
+    static AGENT: OnceLock<Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        let user_agent =
+            get_env_var("ATLAS_USER_AGENT").unwrap_or_else(|_| DEFAULT_USER_AGENT.to_string());
+        Agent::config_builder()
+            .user_agent(user_agent)
+            .middleware(gateway_circuit())
+            .build()
+            .new_agent()
+    })
+}
+
+fn gateway_circuit() -> &'static GatewayCircuit {
+    static CIRCUIT: OnceLock<GatewayCircuit> = OnceLock::new();
+    CIRCUIT.get_or_init(|| GatewayCircuit::new(CircuitConfig::from_env()))
+}
+
+/// opened / rejected counters of the agent's circuit breaker
+pub fn circuit_stats() -> CircuitStats {
+    gateway_circuit().stats()
+}
+
+/// GET request on the shared agent, tagged with a fresh request id
+pub fn get(url: &str) -> RequestBuilder<WithoutBody> {
+    agent().get(url).header(REQUEST_ID_HEADER, request_id())
+}
+
+/// POST request on the shared agent, tagged with a fresh request id
+pub fn post(url: &str) -> RequestBuilder<WithBody> {
+    agent().post(url).header(REQUEST_ID_HEADER, request_id())
+}
+
+/// unique per process: start time of the process plus a counter
+pub fn request_id() -> String {
+    static START: OnceLock<u64> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let start = *START.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    });
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("atlas-{start:x}-{}-{seq}", std::process::id())
+}
+
+/// the gateway answered with a non-JSON body (typically an HTML 502/503 page
+/// from the edge), i.e. an upstream outage rather than a parsing bug.
+/// `retry_in` is set instead when the circuit breaker rejected the call
+/// without reaching the gateway.
+#[derive(Debug)]
+pub struct GatewayUnavailable {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub retry_in: Option<Duration>,
+}
+
+impl fmt::Display for GatewayUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(retry_in) = self.retry_in {
+            return write!(
+                f,
+                "gateway unavailable: circuit open after repeated failures, retrying in {}s",
+                retry_in.as_secs()
+            );
+        }
+        write!(
+            f,
+            "gateway unavailable: HTTP {} returned {} instead of JSON",
+            self.status,
+            self.content_type
+                .as_deref()
+                .unwrap_or("an unknown content type")
+        )
+    }
+}
+
+impl std::error::Error for GatewayUnavailable {}
+
+/// the `GatewayUnavailable` behind `err`, also when the circuit breaker
+/// raised it from inside ureq
+pub fn gateway_unavailable(err: &Error) -> Option<&GatewayUnavailable> {
+    err.downcast_ref::<GatewayUnavailable>()
+        .or_else(|| match err.downcast_ref::<ureq::Error>() {
+            Some(ureq::Error::Other(inner)) => inner.downcast_ref::<GatewayUnavailable>(),
+            _ => None,
+        })
+}
+
+/// the remaining cooldown when `err` is a call the circuit breaker rejected
+pub fn circuit_retry_in(err: &Error) -> Option<Duration> {
+    gateway_unavailable(err).and_then(|err| err.retry_in)
+}
+
+/// the gateway answered 429. `retry_after` is its `Retry-After` hint when sent
+/// in the delay-seconds form (HTTP-date values are ignored)
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// how long to wait before the next call: the gateway's hint capped at
+    /// `MAX_RETRY_AFTER`, or `default` without one
+    pub fn backoff(&self, default: Duration) -> Duration {
+        self.retry_after
+            .map_or(default, |retry_after| retry_after.min(MAX_RETRY_AFTER))
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.retry_after {
+            Some(retry_after) => write!(
+                f,
+                "gateway rate limited: HTTP 429, retry after {}s",
+                retry_after.as_secs()
+            ),
+            None => write!(f, "gateway rate limited: HTTP 429"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// GETs `url` and decodes the JSON body
+pub fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, Error> {
+    let res = get(url)
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .call()?;
+    read_json(res)
+}
+
+/// POSTs `body` as JSON to `url` and decodes the JSON response
+pub fn post_json<T: DeserializeOwned, B: Serialize>(url: &str, body: &B) -> Result<T, Error> {
+    let res = post(url)
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .send_json(body)?;
+    read_json(res)
+}
+
+fn read_json<T: DeserializeOwned>(mut res: Response<Body>) -> Result<T, Error> {
+    let status = res.status().as_u16();
+    let retry_after = res
+        .headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let content_type = res.body().mime_type().map(str::to_string);
+    let body = res.body_mut().read_to_string()?;
+    parse_json_body(
+        status,
+        content_type.as_deref(),
+        retry_after.as_deref(),
+        &body,
+    )
+}
+
+/// 429s become `RateLimited` carrying the `Retry-After` hint, other non-JSON
+/// bodies `GatewayUnavailable`; JSON error responses keep surfacing as ureq
+/// status errors like before
+fn parse_json_body<T: DeserializeOwned>(
+    status: u16,
+    content_type: Option<&str>,
+    retry_after: Option<&str>,
+    body: &str,
+) -> Result<T, Error> {
+    if status == 429 {
+        return Err(RateLimited {
+            retry_after: retry_after.and_then(parse_retry_after),
+        }
+        .into());
+    }
+    let is_json = match content_type {
+        Some(content_type) => content_type.contains("json"),
+        None => !body.trim_start().starts_with('<'),
+    };
+    if !is_json {
+        return Err(GatewayUnavailable {
+            status,
+            content_type: content_type.map(str::to_string),
+            retry_in: None,
+        }
+        .into());
+    }
+    if !(200..300).contains(&status) {
+        return Err(ureq::Error::StatusCode(status).into());
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+/// the gateway has no data for the requested tx id; retrying won't help
+#[derive(Debug)]
+pub struct TxNotFound {
+    pub txid: String,
+}
+
+impl fmt::Display for TxNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tx {} not found on the gateway", self.txid)
+    }
+}
+
+impl std::error::Error for TxNotFound {}
+
+/// downloads an Arweave `txid` data and return Vec<u8> Body, retrying
+/// transient gateway failures with exponential backoff
 pub fn download_tx_data(txid: &str) -> Result<Vec<u8>, Error> {
     let url = format!("{}/{txid}", arweave_gateway());
-    let mut req = ureq::get(url).call()?;
-    Ok(req.body_mut().read_to_vec()?)
+    download_with_retry(
+        txid,
+        GATEWAY_MAX_RETRIES,
+        Duration::from_millis(GATEWAY_BASE_BACKOFF_MS),
+        || {
+            let mut req = get(&url).call()?;
+            Ok(req.body_mut().read_to_vec()?)
+        },
+    )
+}
+
+/// opens an Arweave `txid` data for incremental reads. retries like
+/// `download_tx_data` until the response starts; the body itself is not
+/// buffered nor size-capped.
+pub fn open_tx_data(txid: &str) -> Result<impl Read + Send + 'static, Error> {
+    let url = format!("{}/{txid}", arweave_gateway());
+    let res = download_with_retry(
+        txid,
+        GATEWAY_MAX_RETRIES,
+        Duration::from_millis(GATEWAY_BASE_BACKOFF_MS),
+        || Ok(get(&url).call()?),
+    )?;
+    Ok(res.into_body().into_reader())
 }
 
-/// gets the AR balance of a given Arweave address
+fn download_with_retry<T, F>(
+    txid: &str,
+    max_retries: u32,
+    base_delay: Duration,
+    mut fetch: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    retry_with_backoff(max_retries, base_delay, || {
+        fetch().map_err(|err| match err.downcast_ref::<ureq::Error>() {
+            Some(ureq::Error::StatusCode(404)) => TxNotFound {
+                txid: txid.to_string(),
+            }
+            .into(),
+            _ => err,
+        })
+    })
+}
+
+/// gets the AR balance of a given Arweave address, retrying transient
+/// gateway failures with exponential backoff
 pub fn get_ar_balance(address: &str) -> Result<f64, Error> {
     let url = format!("{}/wallet/{address}/balance", arweave_gateway());
-    let mut req = ureq::get(url).call()?;
-    let winston = req.body_mut().read_to_string()?;
-    let winston = winston.parse::<f64>()?;
+    retry_with_backoff(
+        GATEWAY_MAX_RETRIES,
+        Duration::from_millis(GATEWAY_BASE_BACKOFF_MS),
+        || {
+            let mut req = get(&url).call()?;
+            let winston = req.body_mut().read_to_string()?;
+            winston_to_ar(&winston)
+        },
+    )
+}
+
+fn winston_to_ar(winston: &str) -> Result<f64, Error> {
+    let winston = winston.trim().parse::<f64>()?;
     Ok(winston * 1e-12)
 }
+
+/// runs `op` until it succeeds, a non-transient error is hit, or `max_retries`
+/// retries are exhausted. the delay doubles after each failed attempt.
+pub fn retry_with_backoff<T, F>(
+    max_retries: u32,
+    base_delay: Duration,
+    mut op: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < max_retries && is_transient_error(&err) => {
+                sleep(base_delay * 2u32.pow(attempt));
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// network failures, 429s and 5xx are worth retrying; anything else
+/// (4xx, malformed bodies) will fail the same way again. calls rejected by an
+/// open circuit fail fast rather than retrying into it.
+pub fn is_transient_error(err: &Error) -> bool {
+    if err.downcast_ref::<RateLimited>().is_some() {
+        return true;
+    }
+    if let Some(err) = gateway_unavailable(err) {
+        return err.retry_in.is_none() && (err.status == 429 || !(400..500).contains(&err.status));
+    }
+    match err.downcast_ref::<ureq::Error>() {
+        Some(ureq::Error::StatusCode(code)) => *code == 429 || *code >= 500,
+        Some(_) => true,
+        None => err.downcast_ref::<std::io::Error>().is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn retry_then_success_returns_balance_test() {
+        let mut attempts = 0;
+        let res = retry_with_backoff(3, Duration::ZERO, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(ureq::Error::StatusCode(503).into())
+            } else {
+                winston_to_ar("1500000000000")
+            }
+        })
+        .unwrap();
+        assert_eq!(attempts, 3);
+        assert_eq!(res, 1.5);
+    }
+
+    #[test]
+    fn retry_gives_up_after_max_retries_test() {
+        let mut attempts = 0;
+        let res: Result<f64, Error> = retry_with_backoff(2, Duration::ZERO, || {
+            attempts += 1;
+            Err(ureq::Error::ConnectionFailed.into())
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn retry_skips_non_transient_errors_test() {
+        let mut attempts = 0;
+        let res = retry_with_backoff(3, Duration::ZERO, || {
+            attempts += 1;
+            winston_to_ar("not a number")
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn request_ids_are_unique_test() {
+        let first = request_id();
+        let second = request_id();
+        assert!(first.starts_with("atlas-"));
+        assert_ne!(first, second);
+        assert!(DEFAULT_USER_AGENT.starts_with("atlas/"));
+    }
+
+    #[test]
+    fn download_retries_503_then_succeeds_test() {
+        let mut attempts = 0;
+        let data = download_with_retry("tx", 3, Duration::ZERO, || {
+            attempts += 1;
+            if attempts == 1 {
+                Err(ureq::Error::StatusCode(503).into())
+            } else {
+                Ok(b"wallet,amount".to_vec())
+            }
+        })
+        .unwrap();
+        assert_eq!(attempts, 2);
+        assert_eq!(data, b"wallet,amount");
+    }
+
+    #[test]
+    fn download_404_is_not_retried_test() {
+        let mut attempts = 0;
+        let err = download_with_retry::<Vec<u8>, _>("missing_tx", 3, Duration::ZERO, || {
+            attempts += 1;
+            Err(ureq::Error::StatusCode(404).into())
+        })
+        .unwrap_err();
+        assert_eq!(attempts, 1);
+        assert_eq!(err.downcast_ref::<TxNotFound>().unwrap().txid, "missing_tx");
+    }
+
+    #[test]
+    fn html_error_page_is_gateway_unavailable_test() {
+        let html = "<html><head><title>502 Bad Gateway</title></head></html>";
+        let err = parse_json_body::<Value>(502, Some("text/html"), None, html).unwrap_err();
+        let unavailable = err.downcast_ref::<GatewayUnavailable>().unwrap();
+        assert_eq!(unavailable.status, 502);
+        assert_eq!(
+            err.to_string(),
+            "gateway unavailable: HTTP 502 returned text/html instead of JSON"
+        );
+        assert!(is_transient_error(&err));
+        // same page without a content type header
+        let err = parse_json_body::<Value>(503, None, None, html).unwrap_err();
+        assert!(err.downcast_ref::<GatewayUnavailable>().is_some());
+    }
+
+    #[test]
+    fn open_circuit_fails_fast_test() {
+        let rejected = GatewayUnavailable {
+            status: 503,
+            content_type: None,
+            retry_in: Some(Duration::from_secs(12)),
+        };
+        let err: Error = ureq::Error::Other(Box::new(rejected)).into();
+        assert_eq!(gateway_unavailable(&err).unwrap().status, 503);
+        assert!(!is_transient_error(&err));
+
+        let mut attempts = 0;
+        let res: Result<(), Error> = retry_with_backoff(3, Duration::ZERO, || {
+            attempts += 1;
+            Err(ureq::Error::Other(Box::new(GatewayUnavailable {
+                status: 503,
+                content_type: None,
+                retry_in: Some(Duration::from_secs(12)),
+            }))
+            .into())
+        });
+        assert_eq!(attempts, 1);
+        assert_eq!(
+            gateway_unavailable(&res.unwrap_err()).unwrap().to_string(),
+            "gateway unavailable: circuit open after repeated failures, retrying in 12s"
+        );
+    }
+
+    #[test]
+    fn json_body_is_decoded_test() {
+        let res: Value =
+            parse_json_body(200, Some("application/json"), None, r#"{"height":1}"#).unwrap();
+        assert_eq!(res["height"], 1);
+        let err = parse_json_body::<Value>(404, Some("application/json"), None, "{}").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ureq::Error>(),
+            Some(ureq::Error::StatusCode(404))
+        ));
+    }
+
+    #[test]
+    fn rate_limit_carries_retry_after_test() {
+        let err =
+            parse_json_body::<Value>(429, Some("text/html"), Some("30"), "<html/>").unwrap_err();
+        let limited = err.downcast_ref::<RateLimited>().unwrap();
+        assert_eq!(limited.retry_after, Some(Duration::from_secs(30)));
+        assert_eq!(
+            limited.backoff(Duration::from_secs(5)),
+            Duration::from_secs(30)
+        );
+        assert!(is_transient_error(&err));
+
+        // capped, and HTTP-date / missing hints fall back to the default
+        let long = RateLimited {
+            retry_after: parse_retry_after("86400"),
+        };
+        assert_eq!(long.backoff(Duration::from_secs(5)), MAX_RETRY_AFTER);
+        let err = parse_json_body::<Value>(
+            429,
+            Some("application/json"),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT"),
+            "{}",
+        )
+        .unwrap_err();
+        let limited = err.downcast_ref::<RateLimited>().unwrap();
+        assert_eq!(
+            limited.backoff(Duration::from_secs(5)),
+            Duration::from_secs(5)
+        );
+    }
+}
@@ -1,10 +1,17 @@
 use crate::constants::{
-    DAI_ORACLE_MAINNET_PID, DAI_ORACLE_PID, DAI_STAKING_ADDRESS, FLP_AUTHORITY_MAINNET,
-    STETH_ORACLE_MAINNET_PID, STETH_ORACLE_PID, STETH_STAKING_ADDRESS, USDS_ORACLE_MAINNET_PID,
-    USDS_ORACLE_PID, USDS_STAKING_ADDRESS, arweave_gateway,
+    DAI_ORACLE_PID, STETH_ORACLE_PID, USDS_ORACLE_PID, arweave_gateway, dai_oracle_mainnet_pid,
+    dai_staking_address, dai_token_address, flp_authority_mainnet, steth_oracle_mainnet_pid,
+    steth_staking_address, steth_token_address, usds_oracle_mainnet_pid, usds_staking_address,
+    usds_token_address,
 };
-pub use crate::delegation::{get_user_delegation_txid, get_user_last_delegation_txid};
+pub use crate::delegation::{
+    DelegationMsg, get_user_delegation_txid, get_user_last_delegation_msgs,
+    get_user_last_delegation_txid,
+};
+use crate::env::get_env_var;
+use crate::gateway::post_json;
 use anyhow::{Error, anyhow};
+use serde::Serialize;
 use serde_json::{Value, json};
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -16,22 +23,62 @@ pub enum Oracle {
     Unknown,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize)]
 pub struct OracleMetadata {
     pub ao_pid_legacy: String,
     pub ao_pid_mainnet: String,
     pub evm_address: String,
+    /// ERC-20 staked into `evm_address`
+    pub evm_token_address: String,
+    /// decimals of the raw amounts the oracle reports
+    pub denomination: u32,
+}
+
+/// every oracle the indexer knows how to read, in display order
+pub const ORACLES: [Oracle; 3] = [Oracle::USDS, Oracle::DAI, Oracle::STETH];
+
+/// oracle tickers to index, `ORACLE_TICKERS` (comma separated) or all of them
+pub fn configured_oracle_tickers() -> Vec<String> {
+    get_env_var("ORACLE_TICKERS")
+        .unwrap_or_else(|_| "usds,dai,steth".into())
+        .split(',')
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect()
 }
 
 impl Oracle {
+    pub fn from_ticker(ticker: &str) -> Self {
+        match ticker.to_ascii_lowercase().as_str() {
+            "usds" => Oracle::USDS,
+            "dai" => Oracle::DAI,
+            "steth" => Oracle::STETH,
+            "all" => Oracle::All,
+            _ => Oracle::Unknown,
+        }
+    }
+
+    /// lowercase ticker of a single oracle
+    pub fn ticker(&self) -> Option<&'static str> {
+        match self {
+            Oracle::USDS => Some("usds"),
+            Oracle::DAI => Some("dai"),
+            Oracle::STETH => Some("steth"),
+            Oracle::All | Oracle::Unknown => None,
+        }
+    }
+
     pub fn resolve(&self) -> String {
         match *self {
-            Oracle::USDS => format!("[\"{USDS_ORACLE_MAINNET_PID}\"]"),
-            Oracle::DAI => format!("[\"{DAI_ORACLE_MAINNET_PID}\"]"),
-            Oracle::STETH => format!("[\"{STETH_ORACLE_MAINNET_PID}\"]"),
+            Oracle::USDS => format!("[\"{}\"]", usds_oracle_mainnet_pid()),
+            Oracle::DAI => format!("[\"{}\"]", dai_oracle_mainnet_pid()),
+            Oracle::STETH => format!("[\"{}\"]", steth_oracle_mainnet_pid()),
             Oracle::All => {
                 format!(
-                    "[\"{USDS_ORACLE_MAINNET_PID}\", \"{DAI_ORACLE_MAINNET_PID}\", \"{STETH_ORACLE_MAINNET_PID}\"]"
+                    "[\"{}\", \"{}\", \"{}\"]",
+                    usds_oracle_mainnet_pid(),
+                    dai_oracle_mainnet_pid(),
+                    steth_oracle_mainnet_pid()
                 )
             }
             Oracle::Unknown => String::new(),
@@ -41,19 +88,25 @@ impl Oracle {
     pub fn metadata(&self) -> Result<OracleMetadata, Error> {
         match self {
             Oracle::USDS => Ok(OracleMetadata {
-                ao_pid_mainnet: USDS_ORACLE_MAINNET_PID.to_string(),
-                evm_address: USDS_STAKING_ADDRESS.to_string(),
+                ao_pid_mainnet: usds_oracle_mainnet_pid().to_string(),
+                evm_address: usds_staking_address().to_string(),
+                evm_token_address: usds_token_address().to_string(),
                 ao_pid_legacy: USDS_ORACLE_PID.to_string(),
+                denomination: 18,
             }),
             Oracle::DAI => Ok(OracleMetadata {
-                ao_pid_mainnet: DAI_ORACLE_MAINNET_PID.to_string(),
-                evm_address: DAI_STAKING_ADDRESS.to_string(),
+                ao_pid_mainnet: dai_oracle_mainnet_pid().to_string(),
+                evm_address: dai_staking_address().to_string(),
+                evm_token_address: dai_token_address().to_string(),
                 ao_pid_legacy: DAI_ORACLE_PID.to_string(),
+                denomination: 18,
             }),
             Oracle::STETH => Ok(OracleMetadata {
-                ao_pid_mainnet: STETH_ORACLE_MAINNET_PID.to_string(),
-                evm_address: STETH_STAKING_ADDRESS.to_string(),
+                ao_pid_mainnet: steth_oracle_mainnet_pid().to_string(),
+                evm_address: steth_staking_address().to_string(),
+                evm_token_address: steth_token_address().to_string(),
                 ao_pid_legacy: STETH_ORACLE_PID.to_string(),
+                denomination: 18,
             }),
             _ => Err(anyhow!("metadata not supported for this oracle type")),
         }
@@ -70,37 +123,11 @@ pub struct OracleStakers {
 
 impl OracleStakers {
     pub fn new(oracle: &str) -> Self {
-        match oracle.to_ascii_lowercase().as_str() {
-            "usds" => OracleStakers {
-                oracle: Oracle::USDS,
-                query: None,
-                server_resp: None,
-                last_updates: None,
-            },
-            "dai" => OracleStakers {
-                oracle: Oracle::DAI,
-                query: None,
-                server_resp: None,
-                last_updates: None,
-            },
-            "steth" => OracleStakers {
-                oracle: Oracle::STETH,
-                query: None,
-                server_resp: None,
-                last_updates: None,
-            },
-            "all" => OracleStakers {
-                oracle: Oracle::All,
-                query: None,
-                server_resp: None,
-                last_updates: None,
-            },
-            _ => OracleStakers {
-                oracle: Oracle::Unknown,
-                query: None,
-                server_resp: None,
-                last_updates: None,
-            },
+        OracleStakers {
+            oracle: Oracle::from_ticker(oracle),
+            query: None,
+            server_resp: None,
+            last_updates: None,
         }
     }
 
@@ -149,11 +176,11 @@ impl OracleStakers {
         // formatting as arweave.net doesnt support dynamic vars on server level
         let query = template
             .replace("$firstvar", &first_var.to_string())
-            .replace("$ownervar", FLP_AUTHORITY_MAINNET)
+            .replace("$ownervar", flp_authority_mainnet())
             .replace("$oraclevar", &self.oracle.resolve());
 
         let vars = json!({
-            "owner": FLP_AUTHORITY_MAINNET,
+            "owner": flp_authority_mainnet(),
             "oracle": self.oracle.resolve()
         });
 
@@ -169,11 +196,7 @@ impl OracleStakers {
 
     pub fn send(mut self) -> Result<Self, Error> {
         let url = format!("{}/graphql", arweave_gateway());
-        let req = ureq::post(url)
-            .send_json(self.query.clone())?
-            .body_mut()
-            .read_to_string()?;
-        let res: Value = serde_json::from_str(&req)?;
+        let res: Value = post_json(&url, &self.query)?;
         self.server_resp = Some(res);
         Ok(self)
     }
@@ -195,7 +218,7 @@ impl OracleStakers {
         self.last_updates
             .clone()
             .ok_or(anyhow!("error while retrieving the message id"))?
-            .get(0)
+            .first()
             .ok_or(anyhow!("error while retrieving the message id"))
             .cloned()
     }
@@ -236,7 +259,23 @@ impl OracleStakers {
 
 #[cfg(test)]
 mod test {
-    use crate::gql::{OracleStakers, get_user_delegation_txid, get_user_last_delegation_txid};
+    use crate::delegation::delegation_era_start;
+    use crate::gql::{
+        ORACLES, Oracle, OracleStakers, get_user_delegation_txid, get_user_last_delegation_txid,
+    };
+
+    #[test]
+    fn registered_oracles_round_trip_tickers_test() {
+        for oracle in ORACLES {
+            let ticker = oracle.ticker().unwrap();
+            assert_eq!(Oracle::from_ticker(&ticker.to_uppercase()), oracle);
+            assert_eq!(oracle.metadata().unwrap().denomination, 18);
+        }
+        assert_eq!(Oracle::All.ticker(), None);
+        assert_eq!(Oracle::from_ticker("wbtc"), Oracle::Unknown);
+        assert!(Oracle::Unknown.metadata().is_err());
+    }
+
     #[test]
     fn test_single_oracle_usds_stakers() {
         let oracle = OracleStakers::new("steth").build().unwrap().send().unwrap();
@@ -256,7 +295,7 @@ mod test {
     #[test]
     fn test_get_user_delegation() {
         let address = "vZY2XY1RD9HIfWi8ift-1_DnHLDadZMWrufSh-_rKF0";
-        let last_id = get_user_last_delegation_txid(address).unwrap();
+        let last_id = get_user_last_delegation_txid(address, delegation_era_start()).unwrap();
         let delegation_ids = get_user_delegation_txid(&last_id[0]).unwrap();
         assert!(!delegation_ids.is_empty());
     }
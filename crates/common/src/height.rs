@@ -0,0 +1,73 @@
+//! block height conversions.
+//!
+//! heights are `u64` wherever they are computed or compared with the network
+//! tip (`get_network_height`, the explorer's `BlockStats`, ClickHouse `UInt64`
+//! columns). they are a `BlockHeight` (`u32`) where they are stored or
+//! scanned: `UInt32` columns, the mainnet / ao token / delegation mapping
+//! message metadata and the GraphQL block filters. widening is always
+//! `u64::from`; narrowing only goes through `to_block_height`, which fails
+//! instead of truncating.
+
+use anyhow::Error;
+use serde_json::Value;
+use std::fmt;
+
+pub type BlockHeight = u32;
+
+/// a height past `BlockHeight::MAX`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeightOutOfRange(pub u64);
+
+impl fmt::Display for HeightOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block height {} exceeds the stored range (max {})",
+            self.0,
+            BlockHeight::MAX
+        )
+    }
+}
+
+impl std::error::Error for HeightOutOfRange {}
+
+pub fn to_block_height(height: u64) -> Result<BlockHeight, HeightOutOfRange> {
+    BlockHeight::try_from(height).map_err(|_| HeightOutOfRange(height))
+}
+
+/// `block.height` of a GraphQL transaction node, 0 while it is unconfirmed
+pub fn node_block_height(node: &Value) -> Result<BlockHeight, Error> {
+    let height = node
+        .get("block")
+        .and_then(|v| v.get("height"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    Ok(to_block_height(height)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn heights_past_u32_are_rejected_test() {
+        assert_eq!(to_block_height(1_810_252), Ok(1_810_252));
+        assert_eq!(to_block_height(u64::from(u32::MAX)), Ok(u32::MAX));
+        assert_eq!(
+            to_block_height(u64::from(u32::MAX) + 1),
+            Err(HeightOutOfRange(4_294_967_296))
+        );
+    }
+
+    #[test]
+    fn node_block_height_test() {
+        assert_eq!(
+            node_block_height(&json!({"block": {"height": 1_600_000}})).unwrap(),
+            1_600_000
+        );
+        assert_eq!(node_block_height(&json!({"block": null})).unwrap(), 0);
+        let err = node_block_height(&json!({"block": {"height": 5_000_000_000u64}})).unwrap_err();
+        assert!(err.downcast_ref::<HeightOutOfRange>().is_some());
+    }
+}
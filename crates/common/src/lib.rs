@@ -1,9 +1,14 @@
+pub mod amount;
 pub mod ao_token;
+pub mod build_info;
+pub mod circuit;
 pub mod constants;
 pub mod delegation;
 pub mod env;
+pub mod evm;
 pub mod gateway;
 pub mod gql;
+pub mod height;
 pub mod mainnet;
 pub mod minting;
 pub mod projects;
@@ -9,12 +9,41 @@
 /// - type B follows Header-Case tags key format
 /// - type A start blockheight: 1_594_020 -- Jan 22 2025
 /// - type B start blockheight: 1_616_999 --  Feb 25 2025
+use crate::constants::arweave_gateway;
+use crate::gateway::{check_page_size, get_json, post_json};
+use crate::height::{BlockHeight, node_block_height};
 use anyhow::{Error, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
 const MAINNET_ARWEAVE_GATEWAY: &str = "https://permagate.io";
 
+/// per-block metrics of the indexed mainnet messages above a height, binds
+/// the height then a row limit. shared by the indexer, which rolls them into
+/// `ao_mainnet_explorer`, and the server's raw metrics route. the tags join
+/// fans a message out to one row per tag, repeated tags included, so the
+/// counts are of distinct messages
+pub const MAINNET_BLOCK_METRICS_QUERY: &str = "\
+    select \
+        toDateTime64(max(m.block_timestamp), 3) as ts, \
+        max(m.block_timestamp) as ts_unix, \
+        m.block_height as height, \
+        uniqExact(m.msg_id) as tx_count, \
+        uniqExactIf(m.msg_id, lowerUTF8(t.tag_key) = 'action' and lowerUTF8(t.tag_value) = 'eval') as eval_count, \
+        uniqExactIf(m.msg_id, lowerUTF8(t.tag_key) = 'action' and lowerUTF8(t.tag_value) = 'transfer') as transfer_count, \
+        uniqExactIf(m.msg_id, lowerUTF8(t.tag_key) = 'type' and lowerUTF8(t.tag_value) = 'process') as new_process_count, \
+        uniqExactIf(m.msg_id, lowerUTF8(t.tag_key) = 'type' and lowerUTF8(t.tag_value) = 'module') as new_module_count, \
+        uniqExact(m.owner) as active_users, \
+        uniqExactIf(t.tag_value, lowerUTF8(t.tag_key) in ('from-process','process','from-process-id','process-id')) as active_processes, \
+        arraySum(x -> x.2, groupUniqArray(tuple(m.msg_id, toUInt64OrZero(m.data_size)))) as total_data_size \
+    from ao_mainnet_messages m \
+    left join ao_mainnet_message_tags t \
+      on t.protocol = m.protocol and t.block_height = m.block_height and t.msg_id = m.msg_id \
+    where m.block_height > ? \
+    group by m.block_height \
+    order by m.block_height asc \
+    limit ?";
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub enum DataProtocol {
     A,
@@ -50,7 +79,7 @@ pub struct MainnetBlockMessagesMeta {
     pub msg_id: String,
     pub owner: String,
     pub recipient: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub block_timestamp: u64,
     pub bundled_in: String,
     pub data_size: String,
@@ -64,71 +93,26 @@ pub struct MainnetBlockMessagesPage {
     pub end_cursor: Option<String>,
 }
 
+/// one page of at most `page_size` (up to `GQL_MAX_PAGE_SIZE`) messages
 pub fn scan_arweave_block_for_msgs(
     data_protocol: DataProtocol,
-    blockheight: u32,
+    blockheight: BlockHeight,
     after: Option<&str>,
+    page_size: u32,
 ) -> Result<MainnetBlockMessagesPage, Error> {
-    let query_tags = data_protocol.tags();
-    let template = r#"
-
-query aoMainnet {
-    transactions(
-      sort: HEIGHT_ASC
-      first: 100
-      $afterclause
-        tags: [$dataprotocol_tags]
-        block: { min: $blockheight, max: $blockheight }
-    ) {
-        edges {
-            node {
-                id
-                recipient
-                tags {
-                    name
-                    value
-                }
-                owner {
-                    address
-                }
-                bundledIn {
-                    id
-                }
-                block {
-                    height
-                    timestamp
-                }
-                data {
-                    size
-                }
-            }
-        }
-        pageInfo {
-      hasNextPage
-    }
-    }
-}
-
-    "#;
-
-    let after_clause = after
-        .map(|cursor| format!("    after: \"{cursor}\"\n"))
-        .unwrap_or_default();
-    let query = template
-        .replace("$dataprotocol_tags", &query_tags)
-        .replace("$afterclause", &after_clause)
-        .replace("$blockheight", &blockheight.to_string());
+    let query = mainnet_msgs_query(
+        data_protocol,
+        blockheight,
+        after,
+        check_page_size(page_size)?,
+    );
 
     let body = json!({
         "query": query,
         "variables": {}
     });
 
-    let req = ureq::post(format!("{MAINNET_ARWEAVE_GATEWAY}/graphql"))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{MAINNET_ARWEAVE_GATEWAY}/graphql"), &body)?;
 
     let txs = res
         .get("data")
@@ -157,17 +141,11 @@ query aoMainnet {
         let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
             continue;
         };
-        let block_height = node
-            .get("block")
-            .and_then(|v| v.get("height"))
-            .and_then(|v| v.as_u64())
-            .map(|v| v as u32)
-            .unwrap_or(0);
+        let block_height = node_block_height(node)?;
         let block_timestamp = node
             .get("block")
             .and_then(|v| v.get("timestamp"))
             .and_then(|v| v.as_u64())
-            .map(|v| v)
             .unwrap_or(0);
         let tags = node
             .get("tags")
@@ -233,15 +211,70 @@ query aoMainnet {
     })
 }
 
+fn mainnet_msgs_query(
+    data_protocol: DataProtocol,
+    blockheight: BlockHeight,
+    after: Option<&str>,
+    page_size: u32,
+) -> String {
+    let template = r#"
+
+query aoMainnet {
+    transactions(
+      sort: HEIGHT_ASC
+      first: $pagesize
+      $afterclause
+        tags: [$dataprotocol_tags]
+        block: { min: $blockheight, max: $blockheight }
+    ) {
+        edges {
+            node {
+                id
+                recipient
+                tags {
+                    name
+                    value
+                }
+                owner {
+                    address
+                }
+                bundledIn {
+                    id
+                }
+                block {
+                    height
+                    timestamp
+                }
+                data {
+                    size
+                }
+            }
+        }
+        pageInfo {
+      hasNextPage
+    }
+    }
+}
+
+    "#;
+
+    let after_clause = after
+        .map(|cursor| format!("    after: \"{cursor}\"\n"))
+        .unwrap_or_default();
+    template
+        .replace("$dataprotocol_tags", &data_protocol.tags())
+        .replace("$pagesize", &page_size.to_string())
+        .replace("$afterclause", &after_clause)
+        .replace("$blockheight", &blockheight.to_string())
+}
+
 #[derive(Deserialize)]
 struct NetworkInfo {
     height: u64,
 }
 
 pub fn get_network_height() -> Result<u64, Error> {
-    let mut res = ureq::get("https://arweave.net/info").call()?;
-    let body = res.body_mut().read_to_string()?;
-    let info: NetworkInfo = serde_json::from_str(&body)?;
+    let info: NetworkInfo = get_json(&format!("{}/info", arweave_gateway()))?;
     Ok(info.height)
 }
 
@@ -249,13 +282,19 @@ pub fn get_network_height() -> Result<u64, Error> {
 mod tests {
     use crate::{
         constants::{DATA_PROTOCOL_A_START, DATA_PROTOCOL_B_START},
-        mainnet::{DataProtocol, scan_arweave_block_for_msgs},
+        gateway::{GQL_DEFAULT_PAGE_SIZE, GQL_MAX_PAGE_SIZE},
+        mainnet::{DataProtocol, mainnet_msgs_query, scan_arweave_block_for_msgs},
     };
 
     #[test]
     fn scan_protocol_a_genesis_test() {
-        let messages =
-            scan_arweave_block_for_msgs(DataProtocol::A, DATA_PROTOCOL_A_START, None).unwrap();
+        let messages = scan_arweave_block_for_msgs(
+            DataProtocol::A,
+            DATA_PROTOCOL_A_START,
+            None,
+            GQL_DEFAULT_PAGE_SIZE,
+        )
+        .unwrap();
         println!("{:?}", messages);
         assert_eq!(
             messages.mappings[0].msg_id,
@@ -266,8 +305,13 @@ mod tests {
 
     #[test]
     fn scan_protocol_b_genesis_test() {
-        let messages =
-            scan_arweave_block_for_msgs(DataProtocol::B, DATA_PROTOCOL_B_START, None).unwrap();
+        let messages = scan_arweave_block_for_msgs(
+            DataProtocol::B,
+            DATA_PROTOCOL_B_START,
+            None,
+            GQL_DEFAULT_PAGE_SIZE,
+        )
+        .unwrap();
         println!("{:?}", messages);
         assert_eq!(
             messages.mappings[0].msg_id,
@@ -279,13 +323,20 @@ mod tests {
     #[test]
     // simulates an messages-empty block
     fn scan_protocol_a_pre_genesis_test() {
-        let err = scan_arweave_block_for_msgs(DataProtocol::A, DATA_PROTOCOL_A_START - 1, None);
+        let err = scan_arweave_block_for_msgs(
+            DataProtocol::A,
+            DATA_PROTOCOL_A_START - 1,
+            None,
+            GQL_DEFAULT_PAGE_SIZE,
+        );
         assert!(err.is_err());
     }
 
     #[test]
     fn recipient_test() {
-        let messages = scan_arweave_block_for_msgs(DataProtocol::B, 1630347, None).unwrap();
+        let messages =
+            scan_arweave_block_for_msgs(DataProtocol::B, 1630347, None, GQL_DEFAULT_PAGE_SIZE)
+                .unwrap();
         println!("{:?}", messages);
         assert_eq!(
             messages.mappings[0].recipient,
@@ -293,4 +344,20 @@ mod tests {
         );
         assert!(!messages.has_next_page);
     }
+
+    #[test]
+    fn page_size_is_templated_test() {
+        let query = mainnet_msgs_query(DataProtocol::A, 1_600_000, Some("c1"), 250);
+        assert!(query.contains("first: 250\n"));
+        assert!(query.contains("after: \"c1\""));
+        assert!(!query.contains('$'));
+    }
+
+    #[test]
+    fn page_size_above_gateway_max_is_rejected_test() {
+        for size in [0, GQL_MAX_PAGE_SIZE + 1] {
+            let err = scan_arweave_block_for_msgs(DataProtocol::A, 1_600_000, None, size);
+            assert!(err.unwrap_err().to_string().contains("out of range"));
+        }
+    }
 }
@@ -1,4 +1,5 @@
-use crate::constants::{AO_AUTHORITY, arweave_gateway};
+use crate::constants::{ao_authority, arweave_gateway};
+use crate::gateway::post_json;
 use anyhow::{Error, anyhow};
 use serde_json::{Value, json};
 
@@ -40,7 +41,7 @@ pub fn get_flp_own_minting_report(flp_id: &str) -> Result<String, Error> {
     "#;
 
     let query = template
-        .replace("$addressvar", AO_AUTHORITY)
+        .replace("$addressvar", ao_authority())
         .replace("$flpidvar", flp_id);
 
     let body = json!({
@@ -48,11 +49,7 @@ pub fn get_flp_own_minting_report(flp_id: &str) -> Result<String, Error> {
         "variables": {}
     });
 
-    let req = ureq::post(format!("{}/graphql", arweave_gateway()))
-        .send_json(body)?
-        .body_mut()
-        .read_to_string()?;
-    let res: Value = serde_json::from_str(&req)?;
+    let res: Value = post_json(&format!("{}/graphql", arweave_gateway()), &body)?;
 
     let id = res
         .get("data")
@@ -70,14 +67,13 @@ pub fn get_flp_own_minting_report(flp_id: &str) -> Result<String, Error> {
 }
 
 #[cfg(test)]
-
 mod tests {
     use crate::minting::get_flp_own_minting_report;
     use crate::projects::LOAD_PID;
 
     #[test]
     fn get_latest_minting_report_test() {
-        let res = get_flp_own_minting_report(&LOAD_PID).unwrap();
+        let res = get_flp_own_minting_report(LOAD_PID).unwrap();
         println!("{res}");
         assert_eq!(res.len(), 43);
     }
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 // FLPs PIDs
 // here this PI_PID is set internally as same as token pid to refer
@@ -49,6 +51,13 @@ pub struct Project {
     pub denomination: u32, // todo! add more metadata
 }
 
+/// the name and ticker of a project, for labelling its pid
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProjectLabel {
+    pub name: String,
+    pub ticker: String,
+}
+
 macro_rules! project {
     ($fn_name:ident, $name:expr, $ticker:expr, $pid:expr, $token:expr, $denomination:expr) => {
         pub fn $fn_name() -> Project {
@@ -107,28 +116,31 @@ impl Project {
 }
 
 impl Project {
+    /// derived from `get_all()` so the FLP list stays single-sourced
     pub fn is_flp_project(pid: &str) -> bool {
-        matches!(
-            pid,
-            INTERNAL_PI_PID
-                | PI_LEGACY_PID
-                | PI_PID
-                | LOAD_PID
-                | APUS_PID
-                | BOTG_PID
-                | AOS_PID
-                | WNDR_PID
-                | ACTION_PID
-                | SMONEY_PID
-                | LQD_PID
-                | GAME_PID
-                | NAU_PID
-                | RELLA_PID
-                | ARIO_PID
-                | PIXL_PID
-                | VELA_PID
-                | INF_PID
-        )
+        static PIDS: OnceLock<HashSet<String>> = OnceLock::new();
+        PIDS.get_or_init(|| Project::get_all().into_iter().map(|p| p.pid).collect())
+            .contains(pid)
+    }
+
+    pub fn by_pid(pid: &str) -> Option<&'static Project> {
+        static BY_PID: OnceLock<HashMap<String, Project>> = OnceLock::new();
+        BY_PID
+            .get_or_init(|| {
+                Project::get_all()
+                    .into_iter()
+                    .map(|p| (p.pid.clone(), p))
+                    .collect()
+            })
+            .get(pid)
+    }
+
+    /// `None` for a pid that isn't an FLP
+    pub fn label_for(pid: &str) -> Option<ProjectLabel> {
+        Project::by_pid(pid).map(|p| ProjectLabel {
+            name: p.name.clone(),
+            ticker: p.ticker.clone(),
+        })
     }
 
     pub fn get_all() -> Vec<Project> {
@@ -154,3 +166,30 @@ impl Project {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_all_matches_flp_pids_test() {
+        let projects = Project::get_all();
+        let pids: HashSet<&str> = projects.iter().map(|p| p.pid.as_str()).collect();
+        assert_eq!(pids.len(), projects.len());
+        assert_eq!(projects.len(), 18);
+        assert!(projects.iter().all(|p| Project::is_flp_project(&p.pid)));
+        assert!(!Project::is_flp_project(LOAD_TOKEN));
+    }
+
+    #[test]
+    fn label_for_known_pids_test() {
+        assert_eq!(
+            Project::label_for(LOAD_PID),
+            Some(ProjectLabel {
+                name: Project::load().name,
+                ticker: Project::load().ticker,
+            })
+        );
+        assert_eq!(Project::label_for(LOAD_TOKEN), None);
+    }
+}
@@ -1,15 +1,27 @@
 use anyhow::{Result, anyhow};
+use common::{
+    constants::{arweave_gateway, explorer_graphql_url},
+    env::get_env_var,
+    gateway,
+    height::{BlockHeight, to_block_height},
+};
 pub mod update_stats_gap;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::{
     collections::{BTreeMap, HashSet},
+    fmt,
+    ops::RangeInclusive,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
     thread,
     time::Duration,
 };
 use update_stats_gap::LATEST_AGG_STATS_SET;
 
-const ENDPOINT: &str = "https://permagate.io/graphql";
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AoTx {
     pub id: String,
@@ -42,13 +54,34 @@ pub struct BlockStats {
     pub tx_count_rolling: u64,
     pub processes_rolling: u64,
     pub modules_rolling: u64,
+    /// per-block counts of the tracked `Action` tag values
+    #[serde(default)]
+    pub action_counts: BTreeMap<String, u64>,
 }
 
-pub fn fetch_ao_page(height: u32) -> Result<AoPage> {
+/// message kinds counted into `BlockStats::action_counts` by the stats indexer,
+/// beyond the fixed eval/transfer counters
+pub const TRACKED_ACTIONS: &[&str] = &[
+    "Cast",
+    "Credit-Notice",
+    "Debit-Notice",
+    "Mint",
+    "Burn",
+    "Spawned",
+];
+
+/// tags `AoTx::process` is read from, most specific first: the sending
+/// process of a pushed message, then the target process. `Pushed-For` (the id
+/// of the message a push was made for) and `Reference` (a per-sender sequence
+/// number) are left out on purpose, neither is a process id. the default for
+/// `EXPLORER_PROCESS_TAGS`.
+pub const PROCESS_TAGS: &[&str] = &["From-Process", "Process"];
+
+pub fn fetch_ao_page(height: BlockHeight) -> Result<AoPage> {
     fetch_ao_page_with_cursor(height, None)
 }
 
-fn fetch_ao_page_with_cursor(height: u32, cursor: Option<&str>) -> Result<AoPage> {
+fn fetch_raw_page(height: BlockHeight, cursor: Option<&str>) -> Result<RawPage> {
     let template = r#"
 query GetAoTxs {
   transactions(
@@ -85,48 +118,222 @@ tags: [
         "query": query,
         "variables": {}
     });
-    let mut res = ureq::post(ENDPOINT).send_json(body)?;
+    let mut res = gateway::post(explorer_graphql_url()).send_json(body)?;
     let res = res.body_mut().read_to_string()?;
     let res: GraphResponse = serde_json::from_str(&res)?;
     let data = res.data.ok_or_else(|| anyhow!("missing data"))?;
     let page = data.transactions;
-    let mut end_cursor = None;
-    let txs = page
-        .edges
-        .into_iter()
-        .map(|edge| {
-            end_cursor = Some(edge.cursor);
-            AoTx::from_node(edge.node)
-        })
-        .collect();
-    Ok(AoPage {
-        txs,
-        cursor: end_cursor,
+    let cursor = page.edges.last().map(|edge| edge.cursor.clone());
+    Ok(RawPage {
+        nodes: page.edges.into_iter().map(|edge| edge.node).collect(),
+        cursor,
         has_more: page.page_info.has_next_page,
     })
 }
 
-pub fn fetch_full_block(height: u32) -> Result<Vec<AoTx>> {
-    let mut cursor = None;
+fn fetch_ao_page_with_cursor(height: BlockHeight, cursor: Option<&str>) -> Result<AoPage> {
+    let page = fetch_raw_page(height, cursor)?;
+    Ok(AoPage {
+        txs: page.nodes.into_iter().map(AoTx::from_node).collect(),
+        cursor: page.cursor,
+        has_more: page.has_more,
+    })
+}
+
+/// a GraphQL page before its nodes are converted. the next cursor is known as
+/// soon as it arrives, so the following page can be requested right away.
+struct RawPage {
+    nodes: Vec<GraphNode>,
+    cursor: Option<String>,
+    has_more: bool,
+}
+
+/// pages fetched ahead of the one being converted in `fetch_full_block`,
+/// `EXPLORER_PAGE_PREFETCH` (default 1, 0 pages strictly sequentially)
+fn page_prefetch() -> usize {
+    static PREFETCH: OnceLock<usize> = OnceLock::new();
+    *PREFETCH.get_or_init(|| {
+        get_env_var("EXPLORER_PAGE_PREFETCH")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1)
+    })
+}
+
+/// `EXPLORER_PROCESS_TAGS`, a comma separated tag list overriding `PROCESS_TAGS`
+fn process_tags() -> &'static [String] {
+    static TAGS: OnceLock<Vec<String>> = OnceLock::new();
+    TAGS.get_or_init(|| {
+        parse_process_tags(&get_env_var("EXPLORER_PROCESS_TAGS").unwrap_or_default())
+    })
+}
+
+/// a blank list falls back to `PROCESS_TAGS`
+fn parse_process_tags(value: &str) -> Vec<String> {
+    let tags: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+    if tags.is_empty() {
+        PROCESS_TAGS.iter().map(|tag| tag.to_string()).collect()
+    } else {
+        tags
+    }
+}
+
+pub fn fetch_full_block(height: BlockHeight) -> Result<Vec<AoTx>> {
+    collect_pages(
+        page_prefetch(),
+        |cursor| fetch_raw_page(height, cursor),
+        AoTx::from_node,
+    )
+}
+
+/// walks the pages of a block from the first cursor on. with `prefetch > 0`
+/// the (sequential) page requests run on a helper thread up to `prefetch`
+/// pages ahead, overlapping the network with `convert`. txs keep page order
+/// and one repeated across pages is kept once.
+fn collect_pages<F, C>(prefetch: usize, mut fetch: F, mut convert: C) -> Result<Vec<AoTx>>
+where
+    F: FnMut(Option<&str>) -> Result<RawPage> + Send,
+    C: FnMut(GraphNode) -> AoTx,
+{
+    let mut seen = HashSet::new();
     let mut all = Vec::new();
-    loop {
-        let page = fetch_ao_page_with_cursor(height, cursor.as_deref())?;
-        let has_more = page.has_more;
-        cursor = page.cursor.clone();
-        all.extend(page.txs);
-        if !has_more || cursor.is_none() {
-            break;
+    let mut push = |page: RawPage| {
+        for node in page.nodes {
+            let tx = convert(node);
+            if seen.insert(tx.id.clone()) {
+                all.push(tx);
+            }
+        }
+    };
+    if prefetch == 0 {
+        let mut cursor = None;
+        loop {
+            let page = fetch(cursor.as_deref())?;
+            let next = next_cursor(&page);
+            push(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok(all),
+            }
         }
     }
+    thread::scope(|scope| -> Result<()> {
+        let (pages_tx, pages_rx) = mpsc::sync_channel(prefetch);
+        scope.spawn(move || {
+            let mut cursor = None;
+            loop {
+                let page = fetch(cursor.as_deref());
+                let next = page.as_ref().ok().and_then(next_cursor);
+                // a closed channel means the consumer bailed out on an error
+                if pages_tx.send(page).is_err() {
+                    return;
+                }
+                match next {
+                    Some(next) => cursor = Some(next),
+                    None => return,
+                }
+            }
+        });
+        for page in pages_rx {
+            push(page?);
+        }
+        Ok(())
+    })?;
     Ok(all)
 }
 
-pub fn aggregate_block_full(height: u32) -> Result<Vec<BlockStats>> {
+fn next_cursor(page: &RawPage) -> Option<String> {
+    page.cursor.clone().filter(|_| page.has_more)
+}
+
+/// the requested block is beyond the current network tip
+#[derive(Debug)]
+pub struct HeightAboveTip {
+    pub height: u64,
+    pub tip: u64,
+}
+
+impl fmt::Display for HeightAboveTip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} is above the network tip {}",
+            self.height, self.tip
+        )
+    }
+}
+
+impl std::error::Error for HeightAboveTip {}
+
+/// highest network tip seen so far. the tip only grows, so heights at or below
+/// it need no round trip; anything above refreshes it once from the gateway
+static KNOWN_TIP: AtomicU64 = AtomicU64::new(0);
+
+fn ensure_below_tip<T>(known: &AtomicU64, height: u64, fetch_tip: T) -> Result<()>
+where
+    T: FnOnce() -> Result<u64>,
+{
+    if height <= known.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let tip = fetch_tip()?;
+    known.fetch_max(tip, Ordering::Relaxed);
+    if height > tip {
+        return Err(HeightAboveTip { height, tip }.into());
+    }
+    Ok(())
+}
+
+fn validate_range(range: &RangeInclusive<BlockHeight>) -> Result<()> {
+    if range.start() > range.end() {
+        return Err(anyhow!(
+            "invalid block range: start {} is after end {}",
+            range.start(),
+            range.end()
+        ));
+    }
+    Ok(())
+}
+
+pub fn aggregate_block_full(height: BlockHeight) -> Result<Vec<BlockStats>> {
+    ensure_below_tip(&KNOWN_TIP, u64::from(height), current_network_height)?;
     let txs = fetch_full_block(height)?;
-    Ok(aggregate_block(&txs))
+    Ok(aggregate_block_with_actions(&txs, TRACKED_ACTIONS))
+}
+
+/// aggregates every block of `range` (inclusive), rolling counters running
+/// across the whole range
+pub fn aggregate_blocks(range: RangeInclusive<BlockHeight>) -> Result<Vec<BlockStats>> {
+    validate_range(&range)?;
+    ensure_below_tip(&KNOWN_TIP, u64::from(*range.end()), current_network_height)?;
+    let mut txs = Vec::new();
+    for height in range {
+        txs.extend(fetch_full_block(height)?);
+    }
+    Ok(aggregate_block_with_actions(&txs, TRACKED_ACTIONS))
 }
 
 pub fn aggregate_block(txs: &[AoTx]) -> Vec<BlockStats> {
+    aggregate_block_with_actions(txs, &[])
+}
+
+/// tag values match case-insensitively, like the `lowerUTF8` comparisons of
+/// the mainnet metrics queries, so both sources count the same txs
+fn is_tag(value: &Option<String>, expected: &str) -> bool {
+    value
+        .as_deref()
+        .is_some_and(|value| value.eq_ignore_ascii_case(expected))
+}
+
+/// `aggregate_block`, also counting the messages of each of `actions` (matched
+/// on the `Action` tag) into `action_counts`. actions absent from a block are
+/// left out of its map.
+pub fn aggregate_block_with_actions(txs: &[AoTx], actions: &[&str]) -> Vec<BlockStats> {
     let mut grouped: BTreeMap<u64, Vec<&AoTx>> = BTreeMap::new();
     for tx in txs {
         grouped.entry(tx.block_height).or_default().push(tx);
@@ -141,22 +348,26 @@ pub fn aggregate_block(txs: &[AoTx]) -> Vec<BlockStats> {
             .map(|t| t.block_timestamp.max(0) as u64)
             .unwrap_or(0);
         let tx_count = block.len() as u64;
-        let eval_count = block
-            .iter()
-            .filter(|t| t.action.as_deref() == Some("Eval"))
-            .count() as u64;
+        let eval_count = block.iter().filter(|t| is_tag(&t.action, "Eval")).count() as u64;
         let transfer_count = block
             .iter()
-            .filter(|t| t.action.as_deref() == Some("Transfer"))
+            .filter(|t| is_tag(&t.action, "Transfer"))
             .count() as u64;
         let new_process_count = block
             .iter()
-            .filter(|t| t.tx_type.as_deref() == Some("Process"))
+            .filter(|t| is_tag(&t.tx_type, "Process"))
             .count() as u64;
         let new_module_count = block
             .iter()
-            .filter(|t| t.tx_type.as_deref() == Some("Module"))
+            .filter(|t| is_tag(&t.tx_type, "Module"))
             .count() as u64;
+        // counted under the tracked spelling, whatever the tag's casing
+        let mut action_counts = BTreeMap::new();
+        for tx in &block {
+            if let Some(action) = actions.iter().find(|action| is_tag(&tx.action, action)) {
+                *action_counts.entry(action.to_string()).or_insert(0) += 1;
+            }
+        }
         let mut users = HashSet::new();
         let mut processes = HashSet::new();
         for tx in &block {
@@ -181,53 +392,208 @@ pub fn aggregate_block(txs: &[AoTx]) -> Vec<BlockStats> {
             tx_count_rolling: tx_roll,
             processes_rolling: proc_roll,
             modules_rolling: mod_roll,
+            action_counts,
         });
     }
     out
 }
 
+/// default delay between tip checks right after a new block
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// default ceiling the tip check delay backs off to while no block arrives
+pub const DEFAULT_MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// floor of the tip check delay, a zero one would spin on the gateway
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// tip polling once caught up: `min` after a poll that found new blocks,
+/// doubling on every poll that found none, up to `max`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PollBackoff {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl PollBackoff {
+    /// `min` below `MIN_POLL_INTERVAL` is raised to it, `max` below `min` to `min`
+    pub fn new(min: Duration, max: Duration) -> Self {
+        let min = min.max(MIN_POLL_INTERVAL);
+        Self {
+            min,
+            max: max.max(min),
+        }
+    }
+
+    /// always waits `interval`, at least `MIN_POLL_INTERVAL`
+    pub fn fixed(interval: Duration) -> Self {
+        Self::new(interval, interval)
+    }
+}
+
+impl Default for PollBackoff {
+    fn default() -> Self {
+        Self::new(DEFAULT_POLL_INTERVAL, DEFAULT_MAX_POLL_INTERVAL)
+    }
+}
+
+/// the wait before the next tip check
+struct PollState {
+    backoff: PollBackoff,
+    next: Duration,
+}
+
+impl PollState {
+    fn new(backoff: PollBackoff) -> Self {
+        Self {
+            backoff,
+            next: backoff.min,
+        }
+    }
+
+    fn new_block(&mut self) {
+        self.next = self.backoff.min;
+    }
+
+    /// the wait after a poll, growing the one after it
+    fn idle(&mut self) -> Duration {
+        let wait = self.next;
+        self.next = wait.saturating_mul(2).min(self.backoff.max);
+        wait
+    }
+}
+
 pub fn resume_stats_indexer<F>(handler: F) -> Result<()>
 where
     F: FnMut(&BlockStats) -> Result<()>,
 {
-    run_stats_indexer_from(LATEST_AGG_STATS_SET.clone(), handler)
+    run_stats_indexer_from(
+        LATEST_AGG_STATS_SET.clone(),
+        PollBackoff::default(),
+        handler,
+    )
+}
+
+/// indexes every block after `last` up to the tip, then waits per `poll`
+/// before checking the tip again. no sleep happens while behind the tip. a
+/// failing `handler` doesn't stop the loop: the same block is handed to it
+/// again, waiting `poll.min` and doubling up to `poll.max` while it keeps
+/// failing, so nothing past it is indexed until it's stored.
+/// neither does a failed tip fetch: the cycle is skipped like an empty poll.
+pub fn run_stats_indexer_from<F>(last: BlockStats, poll: PollBackoff, handler: F) -> Result<()>
+where
+    F: FnMut(&BlockStats) -> Result<()>,
+{
+    run_stats_indexer_with_idle(last, poll, handler, || Ok(()))
+}
+
+/// same as `run_stats_indexer_from`, calling `on_idle` each time the loop has
+/// caught up with the tip, right before it sleeps. an `on_idle` error is only
+/// logged.
+pub fn run_stats_indexer_with_idle<F, I>(
+    last: BlockStats,
+    poll: PollBackoff,
+    handler: F,
+    mut on_idle: I,
+) -> Result<()>
+where
+    F: FnMut(&BlockStats) -> Result<()>,
+    I: FnMut() -> Result<()>,
+{
+    tip_loop(
+        last,
+        poll,
+        current_network_height,
+        build_block_stats,
+        handler,
+        |interval| {
+            if let Err(err) = on_idle() {
+                eprintln!("explorer idle hook error: {err:?}");
+            }
+            thread::sleep(interval);
+            Ok(())
+        },
+    )
 }
 
-pub fn run_stats_indexer_from<F>(mut last: BlockStats, mut handler: F) -> Result<()>
+fn tip_loop<T, B, F, S>(
+    mut last: BlockStats,
+    poll: PollBackoff,
+    mut tip: T,
+    mut build: B,
+    mut handler: F,
+    mut sleep: S,
+) -> Result<()>
 where
+    T: FnMut() -> Result<u64>,
+    B: FnMut(u64, &BlockStats) -> Result<BlockStats>,
     F: FnMut(&BlockStats) -> Result<()>,
+    S: FnMut(Duration) -> Result<()>,
 {
     let mut height = last.height + 1;
+    let mut state = PollState::new(poll);
     loop {
-        let tip = current_network_height()?;
+        let tip = match tip() {
+            Ok(tip) => tip,
+            Err(err) => {
+                eprintln!("explorer tip fetch error, retrying: {err:?}");
+                sleep(state.idle())?;
+                continue;
+            }
+        };
+        if height <= tip {
+            state.new_block();
+        }
         while height <= tip {
-            let stats = build_block_stats(height, &last)?;
-            handler(&stats)?;
+            let stats = match build(height, &last) {
+                Ok(stats) => stats,
+                // the gateway's circuit is open: wait it out and rebuild the block
+                Err(err) => match gateway::circuit_retry_in(&err) {
+                    Some(retry_in) => {
+                        eprintln!("explorer block {height} not built, retrying: {err}");
+                        sleep(retry_in)?;
+                        continue;
+                    }
+                    None => return Err(err),
+                },
+            };
+            let mut retry = PollState::new(poll);
+            while let Err(err) = handler(&stats) {
+                eprintln!("explorer block {height} not stored, retrying: {err:?}");
+                sleep(retry.idle())?;
+            }
             last = stats;
             height += 1;
         }
-        thread::sleep(Duration::from_secs(10));
+        sleep(state.idle())?;
     }
 }
 
 impl AoTx {
     fn from_node(node: GraphNode) -> Self {
+        Self::from_node_with(node, process_tags())
+    }
+
+    /// `process` is the value of the first of `process_tags` (in list order,
+    /// not tag order) the node carries. tag names match case-insensitively.
+    fn from_node_with(node: GraphNode, process_tags: &[impl AsRef<str>]) -> Self {
         let mut tx_type = None;
         let mut action = None;
-        let mut process = None;
+        // (rank in process_tags, value)
+        let mut process: Option<(usize, String)> = None;
         for tag in node.tags {
-            match tag.name.as_str() {
-                "Type" => tx_type = Some(tag.value),
-                "Action" => action = Some(tag.value),
-                "From-Process" => process = Some(tag.value),
-                "Process" => {
-                    if process.is_none() {
-                        process = Some(tag.value);
-                    }
-                }
-                _ => {}
+            let name = tag.name.as_str();
+            if name.eq_ignore_ascii_case("Type") {
+                tx_type = Some(tag.value);
+            } else if name.eq_ignore_ascii_case("Action") {
+                action = Some(tag.value);
+            } else if let Some(rank) = process_tags
+                .iter()
+                .position(|t| t.as_ref().eq_ignore_ascii_case(name))
+                && process.as_ref().is_none_or(|(best, _)| rank < *best)
+            {
+                process = Some((rank, tag.value));
             }
         }
+        let process = process.map(|(_, value)| value);
         AoTx {
             id: node.id,
             block_height: node.block.height,
@@ -295,7 +661,7 @@ struct PageInfo {
 }
 
 fn build_block_stats(height: u64, last: &BlockStats) -> Result<BlockStats> {
-    let blocks = aggregate_block_full(height as u32)?;
+    let blocks = aggregate_block_full(to_block_height(height)?)?;
     if let Some(mut stats) = blocks.into_iter().find(|s| s.height == height) {
         finalize_block_stats(&mut stats, last)?;
         Ok(stats)
@@ -329,23 +695,45 @@ fn empty_block_stats(height: u64, timestamp: u64, last: &BlockStats) -> BlockSta
         tx_count_rolling: last.tx_count_rolling,
         processes_rolling: last.processes_rolling,
         modules_rolling: last.modules_rolling,
+        action_counts: BTreeMap::new(),
     }
 }
 
+/// the `n` most frequent actions of `counts`, ties broken by name
+pub fn top_actions(counts: &BTreeMap<String, u64>, n: usize) -> BTreeMap<String, u64> {
+    let mut ranked: Vec<(&String, &u64)> = counts.iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    ranked
+        .into_iter()
+        .take(n)
+        .map(|(action, count)| (action.clone(), *count))
+        .collect()
+}
+
+/// the gateway's tip, retrying transient failures with backoff. every
+/// successful fetch also raises `KNOWN_TIP`.
 fn current_network_height() -> Result<u64> {
     #[derive(Deserialize)]
     struct NetworkInfo {
         height: u64,
     }
-    let mut res = ureq::get("https://arweave.net/info").call()?;
-    let body = res.body_mut().read_to_string()?;
-    let info: NetworkInfo = serde_json::from_str(&body)?;
-    Ok(info.height)
+    let height = gateway::retry_with_backoff(
+        gateway::GATEWAY_MAX_RETRIES,
+        Duration::from_millis(gateway::GATEWAY_BASE_BACKOFF_MS),
+        || {
+            let mut res = gateway::get(&format!("{}/info", arweave_gateway())).call()?;
+            let body = res.body_mut().read_to_string()?;
+            let info: NetworkInfo = serde_json::from_str(&body)?;
+            Ok(info.height)
+        },
+    )?;
+    KNOWN_TIP.fetch_max(height, Ordering::Relaxed);
+    Ok(height)
 }
 
 fn fetch_block_timestamp(height: u64) -> Result<u64> {
-    let url = format!("https://arweave.net/block/height/{height}");
-    let mut res = ureq::get(&url).call()?;
+    let url = format!("{}/block/height/{height}", arweave_gateway());
+    let mut res = gateway::get(&url).call()?;
     let body = res.body_mut().read_to_string()?;
     let value: Value = serde_json::from_str(&body)?;
     Ok(value
@@ -361,6 +749,434 @@ fn fetch_block_timestamp(height: u64) -> Result<u64> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn tip_loop_uses_configured_interval() {
+        let start = BlockStats {
+            height: 10,
+            timestamp: 0,
+            tx_count: 0,
+            eval_count: 0,
+            transfer_count: 0,
+            new_process_count: 0,
+            new_module_count: 0,
+            active_users: 0,
+            active_processes: 0,
+            tx_count_rolling: 0,
+            processes_rolling: 0,
+            modules_rolling: 0,
+            action_counts: BTreeMap::new(),
+        };
+        let mut indexed = Vec::new();
+        let mut sleeps = Vec::new();
+        let res = tip_loop(
+            start,
+            PollBackoff::fixed(Duration::from_millis(1500)),
+            || Ok(13),
+            |height, last| {
+                let mut stats = last.clone();
+                stats.height = height;
+                Ok(stats)
+            },
+            |stats| {
+                indexed.push(stats.height);
+                Ok(())
+            },
+            |interval| {
+                sleeps.push(interval);
+                Err(anyhow!("stop"))
+            },
+        );
+        assert!(res.is_err());
+        // caught up from 11 to the tip without sleeping in between
+        assert_eq!(indexed, vec![11, 12, 13]);
+        assert_eq!(sleeps, vec![Duration::from_millis(1500)]);
+    }
+
+    #[test]
+    fn poll_backoff_never_goes_below_the_floor_test() {
+        let zero = PollBackoff::new(Duration::ZERO, Duration::ZERO);
+        assert_eq!((zero.min, zero.max), (MIN_POLL_INTERVAL, MIN_POLL_INTERVAL));
+        assert_eq!(PollBackoff::fixed(Duration::ZERO).min, MIN_POLL_INTERVAL);
+        let backoff = PollBackoff::new(Duration::from_millis(10), Duration::from_secs(30));
+        assert_eq!(
+            (backoff.min, backoff.max),
+            (MIN_POLL_INTERVAL, Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn poll_interval_backs_off_and_resets_on_a_block_test() {
+        let mut start = LATEST_AGG_STATS_SET.clone();
+        start.height = 10;
+        let mut tips = vec![10, 10, 10, 10, 11, 11].into_iter();
+        let mut sleeps = Vec::new();
+        let res = tip_loop(
+            start,
+            PollBackoff::new(Duration::from_secs(1), Duration::from_secs(4)),
+            || tips.next().ok_or_else(|| anyhow!("stop")),
+            |height, last| {
+                let mut stats = last.clone();
+                stats.height = height;
+                Ok(stats)
+            },
+            |_| Ok(()),
+            |interval| {
+                sleeps.push(interval.as_secs());
+                if sleeps.len() == 6 {
+                    return Err(anyhow!("stop"));
+                }
+                Ok(())
+            },
+        );
+        assert!(res.is_err());
+        // doubles up to the max on empty polls, back to the min after block 11
+        assert_eq!(sleeps, vec![1, 2, 4, 4, 1, 2]);
+    }
+
+    #[test]
+    fn failed_tip_fetch_skips_the_cycle_test() {
+        let mut start = LATEST_AGG_STATS_SET.clone();
+        start.height = 10;
+        let mut tips =
+            vec![Err(anyhow!("info timed out")), Err(anyhow!("502")), Ok(12)].into_iter();
+        let mut indexed = Vec::new();
+        let mut sleeps = Vec::new();
+        let res = tip_loop(
+            start,
+            PollBackoff::new(Duration::from_secs(1), Duration::from_secs(8)),
+            || tips.next().unwrap_or(Ok(12)),
+            |height, last| {
+                let mut stats = last.clone();
+                stats.height = height;
+                Ok(stats)
+            },
+            |stats| {
+                indexed.push(stats.height);
+                Ok(())
+            },
+            |interval| {
+                sleeps.push(interval.as_secs());
+                if sleeps.len() == 3 {
+                    return Err(anyhow!("stop"));
+                }
+                Ok(())
+            },
+        );
+        assert!(res.is_err());
+        // the loop outlived both failures, backing off like empty polls
+        assert_eq!(indexed, vec![11, 12]);
+        assert_eq!(sleeps, vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn open_circuit_retries_the_block_after_the_cooldown_test() {
+        let mut start = LATEST_AGG_STATS_SET.clone();
+        start.height = 10;
+        let mut built = Vec::new();
+        let mut sleeps = Vec::new();
+        let res = tip_loop(
+            start,
+            PollBackoff::fixed(Duration::from_secs(1)),
+            || Ok(11),
+            |height, last| {
+                built.push(height);
+                if built.len() == 1 {
+                    let rejected = gateway::GatewayUnavailable {
+                        status: 503,
+                        content_type: None,
+                        retry_in: Some(Duration::from_secs(7)),
+                    };
+                    return Err(ureq::Error::Other(Box::new(rejected)).into());
+                }
+                let mut stats = last.clone();
+                stats.height = height;
+                Ok(stats)
+            },
+            |_| Ok(()),
+            |interval| {
+                sleeps.push(interval.as_secs());
+                if sleeps.len() == 2 {
+                    return Err(anyhow!("stop"));
+                }
+                Ok(())
+            },
+        );
+        assert!(res.is_err());
+        assert_eq!(built, vec![11, 11]);
+        assert_eq!(sleeps, vec![7, 1]);
+    }
+
+    #[test]
+    fn failed_handler_retries_the_same_block_test() {
+        let mut start = LATEST_AGG_STATS_SET.clone();
+        start.height = 10;
+        let mut built = Vec::new();
+        let mut handled = Vec::new();
+        let mut failures = 3;
+        let mut sleeps = Vec::new();
+        let res = tip_loop(
+            start,
+            PollBackoff::new(Duration::from_secs(1), Duration::from_secs(2)),
+            || Ok(12),
+            |height, last| {
+                built.push(height);
+                let mut stats = last.clone();
+                stats.height = height;
+                Ok(stats)
+            },
+            |stats| {
+                handled.push(stats.height);
+                if stats.height == 11 && failures > 0 {
+                    failures -= 1;
+                    return Err(anyhow!("insert failed"));
+                }
+                Ok(())
+            },
+            |interval| {
+                sleeps.push(interval.as_secs());
+                if sleeps.len() > 3 {
+                    Err(anyhow!("stop"))
+                } else {
+                    Ok(())
+                }
+            },
+        );
+        assert!(res.is_err());
+        // block 11 is handed over again, not rebuilt and not skipped
+        assert_eq!(built, vec![11, 12]);
+        assert_eq!(handled, vec![11, 11, 11, 11, 12]);
+        // the retries back off like an idle tip, then the caught-up poll
+        assert_eq!(sleeps, vec![1, 2, 2, 1]);
+    }
+
+    #[test]
+    fn above_tip_height_is_rejected_test() {
+        let known = AtomicU64::new(0);
+        let err = ensure_below_tip(&known, 1_000_001, || Ok(1_000_000)).unwrap_err();
+        let above = err.downcast_ref::<HeightAboveTip>().unwrap();
+        assert_eq!((above.height, above.tip), (1_000_001, 1_000_000));
+        // the fetched tip is remembered, lower heights skip the round trip
+        ensure_below_tip(&known, 1_000_000, || Err(anyhow!("no fetch expected"))).unwrap();
+        ensure_below_tip(&known, 999_000, || Err(anyhow!("no fetch expected"))).unwrap();
+    }
+
+    #[test]
+    fn inverted_range_is_rejected_test() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let err = aggregate_blocks(1_810_252..=1_810_247).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("start 1810252 is after end 1810247")
+        );
+        assert!(validate_range(&(1_810_247..=1_810_247)).is_ok());
+    }
+
+    fn tx(height: u64, owner: &str, action: Option<&str>) -> AoTx {
+        AoTx {
+            id: format!("{owner}-{}", action.unwrap_or("none")),
+            block_height: height,
+            block_timestamp: 1_700_000_000,
+            owner: owner.to_string(),
+            tx_type: Some("Message".to_string()),
+            action: action.map(str::to_string),
+            process: None,
+        }
+    }
+
+    #[test]
+    fn mixed_action_block_counts_tracked_actions_test() {
+        let txs = vec![
+            tx(7, "a", Some("Eval")),
+            tx(7, "a", Some("Transfer")),
+            tx(7, "b", Some("Credit-Notice")),
+            tx(7, "c", Some("Credit-Notice")),
+            tx(7, "c", Some("Debit-Notice")),
+            tx(7, "d", Some("Cast")),
+            tx(7, "d", Some("Untracked")),
+            tx(7, "e", None),
+        ];
+        let stats =
+            aggregate_block_with_actions(&txs, &["Cast", "Credit-Notice", "Debit-Notice", "Mint"]);
+        let stats = &stats[0];
+        assert_eq!(
+            (stats.tx_count, stats.eval_count, stats.transfer_count),
+            (8, 1, 1)
+        );
+        let counts: Vec<(&str, u64)> = stats
+            .action_counts
+            .iter()
+            .map(|(action, count)| (action.as_str(), *count))
+            .collect();
+        assert_eq!(
+            counts,
+            [("Cast", 1), ("Credit-Notice", 2), ("Debit-Notice", 1)]
+        );
+        // no tracked set, no counts
+        assert!(aggregate_block(&txs)[0].action_counts.is_empty());
+
+        let top = top_actions(&stats.action_counts, 2);
+        assert_eq!(top.keys().collect::<Vec<_>>(), ["Cast", "Credit-Notice"]);
+    }
+
+    fn node(tags: &[(&str, &str)]) -> GraphNode {
+        GraphNode {
+            id: "tx".to_string(),
+            owner: Owner {
+                address: "owner".to_string(),
+            },
+            block: Block {
+                height: 7,
+                timestamp: Some(1_700_000_000),
+            },
+            tags: tags
+                .iter()
+                .map(|(name, value)| Tag {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn process_falls_back_through_process_tags_test() {
+        // `Pushed-For` holds a message id, not a process
+        let pushed = AoTx::from_node(node(&[("Type", "Message"), ("Pushed-For", "msg_p")]));
+        assert_eq!(pushed.process, None);
+        assert_eq!(pushed.tx_type.as_deref(), Some("Message"));
+        let target = AoTx::from_node(node(&[("Type", "Message"), ("Process", "pid_t")]));
+        assert_eq!(target.process.as_deref(), Some("pid_t"));
+
+        // list order wins over tag order
+        let both = AoTx::from_node(node(&[
+            ("Pushed-For", "pid_p"),
+            ("Process", "pid_t"),
+            ("From-Process", "pid_f"),
+        ]));
+        assert_eq!(both.process.as_deref(), Some("pid_f"));
+
+        let none = AoTx::from_node(node(&[("Reference", "12")]));
+        assert_eq!(none.process, None);
+        let custom = AoTx::from_node_with(node(&[("Reference", "12")]), &["Reference"]);
+        assert_eq!(custom.process.as_deref(), Some("12"));
+
+        // the fallback process counts as active
+        assert_eq!(aggregate_block(&[pushed, target])[0].active_processes, 1);
+    }
+
+    #[test]
+    fn process_tags_from_config_test() {
+        assert_eq!(parse_process_tags(""), PROCESS_TAGS);
+        assert_eq!(parse_process_tags(" , "), PROCESS_TAGS);
+        assert_eq!(
+            parse_process_tags("From-Process, Process,Pushed-For"),
+            ["From-Process", "Process", "Pushed-For"]
+        );
+    }
+
+    /// three stubbed pages; p3 repeats `b2` from p2
+    fn stub_page(cursor: Option<&str>) -> Result<RawPage> {
+        let (ids, next): (&[&str], _) = match cursor {
+            None => (&["a1", "a2"], Some("c1")),
+            Some("c1") => (&["b1", "b2"], Some("c2")),
+            Some("c2") => (&["b2", "c1"], None),
+            Some(other) => return Err(anyhow!("unexpected cursor {other}")),
+        };
+        Ok(RawPage {
+            nodes: ids
+                .iter()
+                .map(|id| GraphNode {
+                    id: id.to_string(),
+                    ..node(&[])
+                })
+                .collect(),
+            cursor: Some(next.unwrap_or("end").to_string()),
+            has_more: next.is_some(),
+        })
+    }
+
+    #[test]
+    fn prefetched_pages_keep_order_and_dedup_test() {
+        for prefetch in [0, 1, 3] {
+            let txs = collect_pages(prefetch, stub_page, AoTx::from_node).unwrap();
+            let ids: Vec<&str> = txs.iter().map(|tx| tx.id.as_str()).collect();
+            assert_eq!(ids, ["a1", "a2", "b1", "b2", "c1"], "prefetch {prefetch}");
+        }
+    }
+
+    #[test]
+    fn next_page_is_fetched_while_converting_test() {
+        // converting the first page waits until the fetch of the third one has
+        // started, which only happens if fetching runs ahead of conversion
+        let (started_tx, started_rx) = mpsc::channel();
+        let fetch = move |cursor: Option<&str>| {
+            if cursor == Some("c2") {
+                let _ = started_tx.send(());
+            }
+            stub_page(cursor)
+        };
+        let mut overlapped = None;
+        let convert = |node: GraphNode| {
+            if node.id == "a1" {
+                overlapped = Some(started_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+            }
+            AoTx::from_node(node)
+        };
+        let txs = collect_pages(2, fetch, convert).unwrap();
+        assert_eq!(txs.len(), 5);
+        assert_eq!(overlapped, Some(true));
+    }
+
+    #[test]
+    fn failed_page_fails_the_block_test() {
+        let fetch = |cursor: Option<&str>| match cursor {
+            Some("c1") => Err(anyhow!("gateway down")),
+            _ => stub_page(cursor),
+        };
+        for prefetch in [0, 1] {
+            let err = collect_pages(prefetch, fetch, AoTx::from_node).unwrap_err();
+            assert_eq!(err.to_string(), "gateway down");
+        }
+    }
+
+    #[test]
+    fn mixed_case_tags_are_counted_test() {
+        let typed = |tx_type: &str| AoTx {
+            tx_type: Some(tx_type.to_string()),
+            ..tx(9, "f", None)
+        };
+        let txs = vec![
+            tx(9, "a", Some("eval")),
+            tx(9, "a", Some("EVAL")),
+            tx(9, "b", Some("transfer")),
+            tx(9, "c", Some("credit-notice")),
+            tx(9, "c", Some("Credit-Notice")),
+            typed("process"),
+            typed("MODULE"),
+        ];
+        let stats = &aggregate_block_with_actions(&txs, &["Credit-Notice"])[0];
+        assert_eq!(
+            (
+                stats.eval_count,
+                stats.transfer_count,
+                stats.new_process_count,
+                stats.new_module_count
+            ),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(stats.action_counts["Credit-Notice"], 2);
+        assert_eq!(stats.action_counts.len(), 1);
+
+        let header_case = AoTx::from_node(node(&[
+            ("type", "Message"),
+            ("ACTION", "Eval"),
+            ("from-process", "pid_f"),
+        ]));
+        assert_eq!(header_case.tx_type.as_deref(), Some("Message"));
+        assert_eq!(header_case.action.as_deref(), Some("Eval"));
+        assert_eq!(header_case.process.as_deref(), Some("pid_f"));
+    }
+
     #[test]
     fn fetch_page_empty() {
         let block_number = 1_810_247_u32;
@@ -1,4 +1,5 @@
 use crate::BlockStats;
+use std::collections::BTreeMap;
 pub const ATLAS_AGG_STATS_START_BLOCK: u64 = 1802760; // Nov 26 2025 00:07:14 AM (GMT)
 
 // agg_stats last message:
@@ -33,4 +34,5 @@ pub const LATEST_AGG_STATS_SET: BlockStats = BlockStats {
     tx_count_rolling: 2771411066,
     processes_rolling: 540463,
     modules_rolling: 10157,
+    action_counts: BTreeMap::new(),
 };
@@ -1,50 +1,333 @@
-use crate::types::{DelegationMappingsRow, SetBalancesData};
-use anyhow::Error;
-use common::gateway::download_tx_data;
-use csv::{Reader, StringRecord};
-
-pub fn parse_flp_balances_setting_res(txid: &str) -> Result<Vec<SetBalancesData>, Error> {
-    let mut res: Vec<SetBalancesData> = Vec::new();
-    let data = download_tx_data(txid)?;
-    let str_data = String::from_utf8(data)?;
-    let mut rdr = Reader::from_reader(str_data.as_bytes());
-    // setting custom header given ao's flp Set-Balance dont have headers
-    rdr.set_headers(StringRecord::from(vec!["eoa", "amount", "ar_address"]));
-
-    for row in rdr.deserialize() {
-        let record: SetBalancesData = row?;
-        res.push(record);
+use crate::{
+    ensure_csv,
+    error::FlpError,
+    types::{DelegationMappingsRow, SetBalancesData},
+};
+use anyhow::{Context, Error};
+use common::gateway::{download_tx_data, open_tx_data};
+use csv::{ByteRecord, ByteRecordsIntoIter, ReaderBuilder};
+use std::io::{BufRead, BufReader, Read};
+
+/// ao's flp Set-Balance CSVs have no header: `eoa,amount,ar_address`
+const SET_BALANCES_COLUMNS: usize = 3;
+/// how many malformed rows are kept for logging
+const SKIPPED_SAMPLE_SIZE: usize = 5;
+
+pub fn parse_flp_balances_setting_res(txid: &str) -> Result<Vec<SetBalancesData>, FlpError> {
+    let data = download_tx_data(txid).map_err(FlpError::gateway)?;
+    ensure_csv(txid, &data)?;
+    let mut reader = BalancesReader::for_tx(txid, data.as_slice());
+    let res = reader.by_ref().collect::<Result<Vec<_>, _>>()?;
+    reader.skipped().log(txid);
+    Ok(res)
+}
+
+/// streaming variant of `parse_flp_balances_setting_res` for large oracles:
+/// rows are parsed as they are read off the gateway instead of loading the
+/// whole CSV first. only the first buffered read is checked for binary data.
+pub fn stream_flp_balances(txid: &str) -> Result<BalancesReader<Box<dyn Read + Send>>, FlpError> {
+    let mut data = BufReader::new(open_tx_data(txid).map_err(FlpError::gateway)?);
+    let head = data
+        .fill_buf()
+        .map_err(|err| FlpError::Gateway(err.into()))?;
+    ensure_csv(txid, head)?;
+    Ok(BalancesReader::for_tx(txid, Box::new(data)))
+}
+
+/// rows dropped from a Set-Balances or Delegation-Mappings CSV because their
+/// column count was off or they weren't valid UTF-8
+#[derive(Debug, Default, Clone)]
+pub struct SkippedRows {
+    pub count: usize,
+    /// the first few, as `line N: raw,row` (invalid UTF-8 shown as U+FFFD)
+    pub sample: Vec<String>,
+}
+
+impl SkippedRows {
+    pub fn log(&self, txid: &str) {
+        if self.count > 0 {
+            eprintln!(
+                "tx {txid}: skipped {} malformed rows, e.g. {:?}",
+                self.count, self.sample
+            );
+        }
+    }
+
+    fn push(&mut self, record: &ByteRecord) {
+        self.count += 1;
+        if self.sample.len() < SKIPPED_SAMPLE_SIZE {
+            let line = record.position().map_or(0, |pos| pos.line());
+            let raw = record
+                .iter()
+                .map(String::from_utf8_lossy)
+                .collect::<Vec<_>>()
+                .join(",");
+            self.sample.push(format!("line {line}: {raw}"));
+        }
+    }
+}
+
+/// yields Set-Balances rows, skipping (and counting) rows that don't have
+/// exactly `SET_BALANCES_COLUMNS` UTF-8 columns so one bad row doesn't fail
+/// the file.
+/// read errors are still returned: I/O ones (the body is read off the gateway)
+/// as `FlpError::Gateway`, the rest as `FlpError::Parse`.
+pub struct BalancesReader<R> {
+    records: ByteRecordsIntoIter<R>,
+    skipped: SkippedRows,
+    /// named in read errors when known
+    tx_id: Option<String>,
+}
+
+impl<R: Read> BalancesReader<R> {
+    pub fn new(reader: R) -> Self {
+        let records = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+            .into_byte_records();
+        Self {
+            records,
+            skipped: SkippedRows::default(),
+            tx_id: None,
+        }
+    }
+
+    /// a reader over the body of `txid`, whose read errors name the tx
+    pub fn for_tx(txid: &str, reader: R) -> Self {
+        Self {
+            tx_id: Some(txid.to_string()),
+            ..Self::new(reader)
+        }
+    }
+
+    pub fn skipped(&self) -> &SkippedRows {
+        &self.skipped
+    }
+}
+
+impl<R: Read> Iterator for BalancesReader<R> {
+    type Item = Result<SetBalancesData, FlpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(err) => {
+                    let kind = if err.is_io_error() {
+                        FlpError::Gateway
+                    } else {
+                        FlpError::Parse
+                    };
+                    let err = Error::from(err);
+                    return Some(Err(kind(match &self.tx_id {
+                        Some(txid) => err.context(format!("parsing set-balances for tx {txid}")),
+                        None => err,
+                    })));
+                }
+            };
+            let fields = (record.len() == SET_BALANCES_COLUMNS)
+                .then(|| {
+                    record
+                        .iter()
+                        .map(|field| std::str::from_utf8(field).ok())
+                        .collect::<Option<Vec<_>>>()
+                })
+                .flatten();
+            let Some([eoa, amount, ar_address]) = fields.as_deref() else {
+                self.skipped.push(&record);
+                continue;
+            };
+            return Some(Ok(SetBalancesData {
+                eoa: eoa.to_string(),
+                amount: amount.to_string(),
+                ar_address: ar_address.to_string(),
+            }));
+        }
     }
+}
+
+pub fn parse_delegation_mappings_res(txid: &str) -> Result<Vec<DelegationMappingsRow>, FlpError> {
+    let data = download_tx_data(txid).map_err(FlpError::gateway)?;
+    let (res, skipped) = delegation_mappings_from_slice(txid, &data)?;
+    skipped.log(txid);
     Ok(res)
 }
 
-pub fn parse_delegation_mappings_res(txid: &str) -> Result<Vec<DelegationMappingsRow>, Error> {
+/// parses a downloaded `Delegation-Mappings` CSV body of the `txid` message.
+/// rows that aren't valid UTF-8 are skipped and counted, a few invalid bytes
+/// in a text body only spoil their rows; any other malformed row fails it.
+pub fn delegation_mappings_from_slice(
+    txid: &str,
+    data: &[u8],
+) -> Result<(Vec<DelegationMappingsRow>, SkippedRows), FlpError> {
+    let context = || format!("parsing delegation mappings for tx {txid}");
     let mut res: Vec<DelegationMappingsRow> = Vec::new();
-    let data = download_tx_data(txid)?;
-    let str_data = String::from_utf8(data)?;
-    let mut rdr = Reader::from_reader(str_data.as_bytes());
-    // setting custom header given ao's msg Delegation-Mappings dont have headers
-    rdr.set_headers(StringRecord::from(vec!["walletFrom", "walletTo", "factor"]));
+    let mut skipped = SkippedRows::default();
+    ensure_csv(txid, data)?;
+    let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(data);
+    // custom header given ao's msg Delegation-Mappings dont have headers
+    let headers = ByteRecord::from(vec!["walletFrom", "walletTo", "factor"]);
 
-    for row in rdr.deserialize() {
-        let record: DelegationMappingsRow = row?;
-        res.push(record);
+    for record in rdr.byte_records() {
+        let record = record.with_context(context).map_err(FlpError::Parse)?;
+        if std::str::from_utf8(record.as_slice()).is_err() {
+            skipped.push(&record);
+            continue;
+        }
+        let row: DelegationMappingsRow = record
+            .deserialize(Some(&headers))
+            .with_context(context)
+            .map_err(FlpError::Parse)?;
+        res.push(row);
     }
-    Ok(res)
+    Ok((res, skipped))
 }
 
 #[cfg(test)]
-
 mod tests {
-    use crate::csv_parser::{parse_delegation_mappings_res, parse_flp_balances_setting_res};
+    use crate::csv_parser::{
+        BalancesReader, delegation_mappings_from_slice, parse_delegation_mappings_res,
+        parse_flp_balances_setting_res,
+    };
+    use crate::error::FlpError;
     use common::gql::OracleStakers;
+    use std::io::Read;
+
+    #[test]
+    fn balances_from_reader_streams_large_csv_test() {
+        let rows = 100_000;
+        let csv: String = (0..rows)
+            .map(|i| format!("0x{i:040x},{i}000000000000000000,ar_address_{i}\n"))
+            .collect();
+        let mut iter = BalancesReader::new(csv.as_bytes());
+        let mut chunks = 0;
+        let mut last = None;
+        loop {
+            let chunk: Vec<_> = iter.by_ref().take(1000).collect::<Result<_, _>>().unwrap();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks += 1;
+            last = chunk.last().cloned();
+        }
+        assert_eq!(chunks, rows / 1000);
+        let last = last.unwrap();
+        assert_eq!(last.ar_address, format!("ar_address_{}", rows - 1));
+        assert_eq!(last.amount, format!("{}000000000000000000", rows - 1));
+    }
+
+    #[test]
+    fn balances_reader_parses_valid_csv_test() {
+        let csv = "0xa,1,ar_a\n0xb,2,ar_b\n0xc,3,ar_c\n";
+        let mut reader = BalancesReader::new(csv.as_bytes());
+        let rows: Vec<_> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].eoa, "0xa");
+        assert_eq!(rows[2].amount, "3");
+        assert_eq!(rows[2].ar_address, "ar_c");
+        assert_eq!(reader.skipped().count, 0);
+    }
+
+    #[test]
+    fn balances_reader_skips_short_rows_test() {
+        let csv = "0xa,1,ar_a\n0xb,2\n0xc,3,ar_c\n";
+        let mut reader = BalancesReader::new(csv.as_bytes());
+        let rows: Vec<_> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].ar_address, "ar_c");
+        assert_eq!(reader.skipped().count, 1);
+        assert_eq!(reader.skipped().sample, vec!["line 2: 0xb,2"]);
+    }
+
+    #[test]
+    fn balances_reader_skips_extra_column_rows_test() {
+        let csv = "0xa,1,ar_a,extra\n0xb,2,ar_b\n";
+        let mut reader = BalancesReader::new(csv.as_bytes());
+        let rows: Vec<_> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].ar_address, "ar_b");
+        assert_eq!(reader.skipped().count, 1);
+        assert_eq!(reader.skipped().sample, vec!["line 1: 0xa,1,ar_a,extra"]);
+    }
+
+    #[test]
+    fn delegation_mappings_from_slice_test() {
+        let csv = "ar_a,pid_1,10000\nar_b,pid_2,5000\n";
+        let (rows, skipped) = delegation_mappings_from_slice("tx_abc", csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(skipped.count, 0);
+        assert_eq!(rows[1].wallet_from, "ar_b");
+        assert_eq!(rows[1].factor, 5000);
+        let err = delegation_mappings_from_slice("tx_abc", b"ar_a,pid_1,lots\n").unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("parsing delegation mappings for tx tx_abc"),
+            "{msg}"
+        );
+    }
+
+    #[test]
+    fn balances_reader_skips_invalid_utf8_rows_test() {
+        let csv: &[u8] = b"0xa,1,ar_a\n0xb,\xff,ar_b\n0xc,3,ar_c\n";
+        let mut reader = BalancesReader::for_tx("tx_abc", csv);
+        let rows: Vec<_> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].ar_address, "ar_c");
+        assert_eq!(reader.skipped().count, 1);
+        assert_eq!(reader.skipped().sample, vec!["line 2: 0xb,\u{fffd},ar_b"]);
+    }
+
+    #[test]
+    fn delegation_mappings_invalid_utf8_test() {
+        // stray bytes only spoil their row, which is skipped rather than stored mangled
+        let csv = b"ar_a,pid_1,10000\nar_\xe9,pid_2,5000\nar_c,pid_3,2500\n";
+        let (rows, skipped) = delegation_mappings_from_slice("tx_abc", csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].wallet_from, "ar_c");
+        assert_eq!(rows[1].factor, 2500);
+        assert_eq!(skipped.count, 1);
+        assert_eq!(skipped.sample, vec!["line 2: ar_\u{fffd},pid_2,5000"]);
+
+        let binary = b"\x1f\x8b\x08\0\0\0\0\0\0\x03\xed\xc1\x01\r\0\0\0";
+        let err = delegation_mappings_from_slice("tx_abc", binary).unwrap_err();
+        assert!(matches!(err, FlpError::Parse(_)));
+        assert!(
+            err.to_string().starts_with("tx tx_abc data is not a CSV"),
+            "{err}"
+        );
+    }
+
+    /// a body whose download breaks off after the first row
+    struct BrokenBody(bool);
+
+    impl Read for BrokenBody {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if std::mem::replace(&mut self.0, true) {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            let row = b"0xa,1,ar_a\n";
+            buf[..row.len()].copy_from_slice(row);
+            Ok(row.len())
+        }
+    }
+
+    #[test]
+    fn balances_io_error_is_a_gateway_error_test() {
+        let mut reader = BalancesReader::for_tx("tx_abc", BrokenBody(false));
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err, FlpError::Gateway(_)), "{err}");
+        let msg = format!("{err:#}");
+        assert!(msg.contains("parsing set-balances for tx tx_abc"), "{msg}");
+    }
 
     #[test]
     fn parse_flp_balances_setting_res_test() {
         let res =
             parse_flp_balances_setting_res("VkkgrjyRunadgj7p0j2_Wo8dC2F3H1WCmHgV9BL0i2Y").unwrap();
         println!("parse response: {:#?}", res);
-        assert!(res.len() > 0);
+        assert!(!res.is_empty());
     }
 
     #[test]
@@ -53,7 +336,7 @@ mod tests {
         let last_update = oracle.last_update().unwrap();
         let set_balances_parsed_data = parse_flp_balances_setting_res(&last_update).unwrap();
         println!("{:#?}", set_balances_parsed_data);
-        assert!(set_balances_parsed_data.len() > 0);
+        assert!(!set_balances_parsed_data.is_empty());
     }
 
     #[test]
@@ -0,0 +1,102 @@
+use common::gateway::TxNotFound;
+use std::fmt;
+
+/// failure kinds of the flp API, so library users can tell a gateway outage
+/// from a malformed tx body or a missing preference
+#[derive(Debug)]
+pub enum FlpError {
+    /// a gateway download or GraphQL query failed (network, HTTP status,
+    /// rate limit)
+    Gateway(anyhow::Error),
+    /// a tx body that isn't the expected CSV/JSON, naming the tx and quoting
+    /// the start of its body
+    Parse(anyhow::Error),
+    /// nothing to return, e.g. no delegation preferences for a wallet or a tx
+    /// the gateway doesn't know
+    NotFound(String),
+    /// well-formed input that can't be used, e.g. factors not summing to
+    /// `MAX_FACTOR`
+    Invalid(String),
+}
+
+impl FlpError {
+    /// a failed gateway call, `NotFound` when the gateway has no such tx
+    pub fn gateway(err: anyhow::Error) -> Self {
+        match err.downcast_ref::<TxNotFound>() {
+            Some(missing) => Self::NotFound(format!("tx {}", missing.txid)),
+            None => Self::Gateway(err),
+        }
+    }
+
+    /// back to `anyhow` for callers that classify errors by downcasting:
+    /// wrapped gateway and parse errors are unwrapped (so `RateLimited` or
+    /// `GatewayUnavailable` stay visible), the other kinds downcast to `FlpError`
+    pub fn into_anyhow(self) -> anyhow::Error {
+        match self {
+            Self::Gateway(err) | Self::Parse(err) => err,
+            other => anyhow::Error::new(other),
+        }
+    }
+}
+
+impl fmt::Display for FlpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gateway(err) | Self::Parse(err) => write!(f, "{err:#}"),
+            Self::NotFound(what) => write!(f, "no {what} found"),
+            Self::Invalid(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+// the wrapped error's chain is already part of the message, so no `source`
+impl std::error::Error for FlpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn into_anyhow_keeps_downcasts_test() {
+        let missing = anyhow::Error::new(TxNotFound {
+            txid: "abc".to_string(),
+        })
+        .context("downloading abc");
+        let err = FlpError::Gateway(missing).into_anyhow();
+        assert!(err.downcast_ref::<TxNotFound>().is_some());
+
+        let err = FlpError::NotFound("delegation preferences".to_string()).into_anyhow();
+        assert!(matches!(
+            err.downcast_ref::<FlpError>(),
+            Some(FlpError::NotFound(_))
+        ));
+        assert_eq!(err.to_string(), "no delegation preferences found");
+    }
+
+    #[test]
+    fn missing_tx_is_not_found_test() {
+        let missing = anyhow::Error::new(TxNotFound {
+            txid: "abc".to_string(),
+        })
+        .context("downloading abc");
+        let err = FlpError::gateway(missing);
+        assert!(matches!(&err, FlpError::NotFound(what) if what == "tx abc"));
+        assert_eq!(err.to_string(), "no tx abc found");
+
+        let err = FlpError::gateway(anyhow::anyhow!("connection reset"));
+        assert!(matches!(err, FlpError::Gateway(_)));
+    }
+
+    #[test]
+    fn wrapped_errors_display_their_chain_test() {
+        let err = Err::<(), _>(anyhow::anyhow!("EOF"))
+            .context("parsing delegation preferences for tx abc")
+            .map_err(FlpError::Parse)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parsing delegation preferences for tx abc: EOF"
+        );
+    }
+}
@@ -1,18 +1,43 @@
-use crate::types::OwnMintingReport;
-use anyhow::Error;
+use crate::{error::FlpError, snippet, types::OwnMintingReport};
+use anyhow::Context;
 use common::gateway::download_tx_data;
 
-pub fn parse_own_minting_report(txid: &str) -> Result<OwnMintingReport, Error> {
-    let tx_data = download_tx_data(txid)?;
-    let mut res: OwnMintingReport = serde_json::from_slice(&tx_data)?;
+pub fn parse_own_minting_report(txid: &str) -> Result<OwnMintingReport, FlpError> {
+    let tx_data = download_tx_data(txid).map_err(FlpError::gateway)?;
+    own_minting_report_from_slice(txid, &tx_data)
+}
+
+/// parses a downloaded `Own-Minting-Report` JSON body of the `txid` report
+pub fn own_minting_report_from_slice(
+    txid: &str,
+    data: &[u8],
+) -> Result<OwnMintingReport, FlpError> {
+    let mut res: OwnMintingReport = serde_json::from_slice(data)
+        .with_context(|| {
+            format!(
+                "parsing own minting report for tx {txid}, input: {}",
+                snippet(data)
+            )
+        })
+        .map_err(FlpError::Parse)?;
     res.report_id = Some(txid.to_string());
     Ok(res)
 }
 
 #[cfg(test)]
-
 mod tests {
-    use crate::json_parser::parse_own_minting_report;
+    use crate::json_parser::{own_minting_report_from_slice, parse_own_minting_report};
+
+    #[test]
+    fn own_minting_report_error_has_context_test() {
+        let err = own_minting_report_from_slice("tx_abc", br#"{"timestamp": "soon"}"#).unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("parsing own minting report for tx tx_abc"),
+            "{msg}"
+        );
+        assert!(msg.contains(r#"{"timestamp": "soon"}"#), "{msg}");
+    }
 
     #[test]
     fn parse_own_minting_report_test() {
@@ -1,4 +1,78 @@
+use anyhow::anyhow;
+use error::FlpError;
+
 pub mod csv_parser;
+pub mod error;
 pub mod json_parser;
 pub mod types;
 pub mod wallet;
+
+/// how much of an unparsable tx body is quoted in parse errors
+const SNIPPET_LEN: usize = 120;
+/// how much of a tx body is looked at to tell text from binary
+const SNIFF_LEN: usize = 512;
+
+/// the start of `data` (lossy utf-8) for parse error context
+pub(crate) fn snippet(data: &[u8]) -> String {
+    let head = String::from_utf8_lossy(&data[..data.len().min(SNIPPET_LEN)]).into_owned();
+    if data.len() > SNIPPET_LEN {
+        format!("{head}...")
+    } else {
+        head
+    }
+}
+
+/// whether the start of `data` is binary rather than text: more than one in
+/// ten of its characters are invalid UTF-8 or control characters other than
+/// tabs and line breaks
+pub(crate) fn looks_binary(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(SNIFF_LEN)]);
+    let (mut chars, mut odd) = (0, 0);
+    for c in head.chars() {
+        chars += 1;
+        if c == char::REPLACEMENT_CHARACTER || (c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+        {
+            odd += 1;
+        }
+    }
+    odd * 10 > chars
+}
+
+/// rejects a binary tx body (wrong tx id, unexpected data item) before it
+/// reaches a CSV parser, with an error naming the tx
+pub(crate) fn ensure_csv(txid: &str, data: &[u8]) -> Result<(), FlpError> {
+    if looks_binary(data) {
+        return Err(FlpError::Parse(anyhow!(
+            "tx {txid} data is not a CSV ({} bytes, starts with {:?})",
+            data.len(),
+            snippet(data)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_bodies_are_told_from_text_test() {
+        assert!(!looks_binary(b"0xa,1,ar_a\r\n0xb,2,ar_b\n"));
+        assert!(!looks_binary("ar_\u{e9},pid,10000\n".as_bytes()));
+        // a few stray bytes in otherwise readable text
+        assert!(!looks_binary(b"0xa,1,ar_a\n0xb,\xff,ar_b\n0xc,3,ar_c\n"));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0"));
+        assert!(looks_binary(&[
+            0xde, 0xad, 0xbe, 0xef, 0x00, 0x01, 0x02, 0x03
+        ]));
+        assert!(!looks_binary(b""));
+
+        let err = ensure_csv("tx_abc", b"\0\x01\x02\x03").unwrap_err();
+        assert!(matches!(err, FlpError::Parse(_)));
+        assert!(
+            err.to_string()
+                .starts_with("tx tx_abc data is not a CSV (4 bytes"),
+            "{err}"
+        );
+    }
+}
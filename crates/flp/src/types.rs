@@ -1,4 +1,5 @@
-use common::projects::INTERNAL_PI_PID;
+use crate::error::FlpError;
+use common::projects::{INTERNAL_PI_PID, Project, ProjectLabel};
 use serde::{Deserialize, Serialize};
 
 pub const MAX_FACTOR: u32 = 10000;
@@ -8,6 +9,10 @@ pub const MAX_FACTOR: u32 = 10000;
 pub struct WalletDelegations {
     pub wallet_to: String,
     pub factor: u32,
+    /// the FLP `wallet_to` points at, filled by `with_target_projects`;
+    /// omitted for any other target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_project: Option<ProjectLabel>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,16 +23,25 @@ pub struct DelegationMappingsRow {
     pub factor: u32,
 }
 
+/// a wallet's delegation preferences. two payload shapes are accepted:
+/// the legacy one (`wallet`, `lastUpdate`, `delegationPrefs`) and the newer
+/// one that adds `_key` and `totalFactor`. `normalized` fills in
+/// `total_factor` for legacy payloads.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct DelegationsRes {
     #[serde(rename = "_key")]
     pub key: Option<String>, // newer version
     pub last_update: Option<u64>,
-    pub total_factor: Option<u32>,
+    pub total_factor: Option<u32>, // newer version
     pub wallet: Option<String>,
     pub delegation_prefs: Vec<WalletDelegations>,
     pub delegation_msg_id: Option<String>,
+    /// block of the `delegation_msg_id` message, `None` while it's pending or
+    /// for the PI fallback
+    pub last_update_height: Option<u64>,
+    /// unix seconds
+    pub last_update_ts: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -50,18 +64,173 @@ pub struct OwnMintingReport {
 }
 
 impl DelegationsRes {
+    /// legacy payloads carry no `totalFactor`: it's the sum of the prefs
+    pub fn normalized(mut self) -> Self {
+        if self.total_factor.is_none() {
+            self.total_factor = Some(self.delegation_prefs.iter().map(|p| p.factor).sum());
+        }
+        self
+    }
+
+    /// labels each preference whose target is a known FLP, for responses.
+    /// other targets keep only their raw pid.
+    pub fn with_target_projects(mut self) -> Self {
+        for pref in &mut self.delegation_prefs {
+            pref.target_project = Project::label_for(&pref.wallet_to);
+        }
+        self
+    }
+
+    /// the protocol default: 100% to PI
     pub fn pi_default(address: &str) -> Self {
-        let preference = WalletDelegations {
-            wallet_to: INTERNAL_PI_PID.to_string(),
-            factor: MAX_FACTOR,
-        };
-        DelegationsRes {
+        Self::default_for(&[(INTERNAL_PI_PID.to_string(), MAX_FACTOR)], address)
+            .expect("pi default factors sum to MAX_FACTOR")
+    }
+
+    /// a fallback preference for a wallet without a delegation message. the
+    /// factors must sum to `MAX_FACTOR`; no targets means unstaked (factor 0).
+    pub fn default_for(targets: &[(String, u32)], address: &str) -> Result<Self, FlpError> {
+        // summed wide so oversized factors are reported instead of overflowing
+        let total_factor: u64 = targets.iter().map(|(_, factor)| u64::from(*factor)).sum();
+        if !targets.is_empty() && total_factor != u64::from(MAX_FACTOR) {
+            return Err(FlpError::Invalid(format!(
+                "default delegation factors sum to {total_factor}, expected {MAX_FACTOR}"
+            )));
+        }
+        if let Some((target, _)) = targets
+            .iter()
+            .find(|(target, factor)| target.is_empty() || *factor == 0)
+        {
+            return Err(FlpError::Invalid(format!(
+                "invalid default delegation target {target:?}"
+            )));
+        }
+        let delegation_prefs = targets
+            .iter()
+            .map(|(wallet_to, factor)| WalletDelegations {
+                wallet_to: wallet_to.clone(),
+                factor: *factor,
+                target_project: None,
+            })
+            .collect();
+        Ok(DelegationsRes {
             key: Some(format!("base_{address}")),
             last_update: None,
-            total_factor: Some(MAX_FACTOR),
+            // 0 or MAX_FACTOR once checked above
+            total_factor: Some(total_factor as u32),
             wallet: Some(address.to_string()),
-            delegation_prefs: vec![preference],
+            delegation_prefs,
             delegation_msg_id: Some("not found".to_string()),
-        }
+            last_update_height: None,
+            last_update_ts: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::FlpError;
+    use crate::types::{DelegationsRes, MAX_FACTOR};
+    use common::projects::{INTERNAL_PI_PID, LOAD_PID, Project};
+
+    /// a delegation payload from before `_key` and `totalFactor` existed
+    const LEGACY_DELEGATIONS: &str = r#"{
+        "wallet": "ar1",
+        "lastUpdate": 1733000000000,
+        "delegationPrefs": [
+            {"walletTo": "pid_a", "factor": 6000},
+            {"walletTo": "pid_b", "factor": 4000}
+        ]
+    }"#;
+
+    const CURRENT_DELEGATIONS: &str = r#"{
+        "_key": "base_ar1",
+        "wallet": "ar1",
+        "lastUpdate": 1764000000000,
+        "totalFactor": 7500,
+        "delegationPrefs": [{"walletTo": "pid_a", "factor": 7500}]
+    }"#;
+
+    #[test]
+    fn legacy_delegations_shape_parses_test() {
+        let res: DelegationsRes = serde_json::from_str(LEGACY_DELEGATIONS).unwrap();
+        let res = res.normalized();
+        assert_eq!(res.key, None);
+        assert_eq!(res.wallet.as_deref(), Some("ar1"));
+        assert_eq!(res.last_update, Some(1733000000000));
+        assert_eq!(res.delegation_prefs.len(), 2);
+        assert_eq!(res.delegation_prefs[1].wallet_to, "pid_b");
+        assert_eq!(res.delegation_prefs[1].factor, 4000);
+        assert_eq!(res.total_factor, Some(MAX_FACTOR));
+    }
+
+    #[test]
+    fn current_delegations_shape_parses_test() {
+        let res: DelegationsRes = serde_json::from_str(CURRENT_DELEGATIONS).unwrap();
+        let res = res.normalized();
+        assert_eq!(res.key.as_deref(), Some("base_ar1"));
+        assert_eq!(res.delegation_prefs.len(), 1);
+        assert_eq!(res.delegation_prefs[0].wallet_to, "pid_a");
+        // a stated totalFactor is kept as is
+        assert_eq!(res.total_factor, Some(7500));
+        assert_eq!(res.delegation_msg_id, None);
+    }
+
+    #[test]
+    fn default_for_factors_sum_to_max_test() {
+        let targets = vec![("pid_a".to_string(), 7500), ("pid_b".to_string(), 2500)];
+        let res = DelegationsRes::default_for(&targets, "ar1").unwrap();
+        let sum: u32 = res.delegation_prefs.iter().map(|p| p.factor).sum();
+        assert_eq!(sum, MAX_FACTOR);
+        assert_eq!(res.total_factor, Some(MAX_FACTOR));
+        assert_eq!(res.wallet.as_deref(), Some("ar1"));
+
+        let pi = DelegationsRes::pi_default("ar1");
+        assert_eq!(pi.delegation_prefs[0].wallet_to, INTERNAL_PI_PID);
+        assert_eq!(pi.total_factor, Some(MAX_FACTOR));
+
+        let short = vec![("pid_a".to_string(), 5000)];
+        assert!(matches!(
+            DelegationsRes::default_for(&short, "ar1"),
+            Err(FlpError::Invalid(_))
+        ));
+        // wraps around to exactly MAX_FACTOR in u32
+        let overflow = vec![
+            ("pid_a".to_string(), u32::MAX),
+            ("pid_b".to_string(), MAX_FACTOR + 1),
+        ];
+        assert!(matches!(
+            DelegationsRes::default_for(&overflow, "ar1"),
+            Err(FlpError::Invalid(_))
+        ));
+        let zero = vec![("pid_a".to_string(), MAX_FACTOR), ("pid_b".to_string(), 0)];
+        assert!(matches!(
+            DelegationsRes::default_for(&zero, "ar1"),
+            Err(FlpError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn default_for_without_targets_is_unstaked_test() {
+        let res = DelegationsRes::default_for(&[], "ar1").unwrap();
+        assert!(res.delegation_prefs.is_empty());
+        assert_eq!(res.total_factor, Some(0));
+    }
+
+    #[test]
+    fn flp_targets_are_labelled_test() {
+        let targets = vec![(LOAD_PID.to_string(), 7500), ("pid_b".to_string(), 2500)];
+        let res = DelegationsRes::default_for(&targets, "ar1")
+            .unwrap()
+            .with_target_projects();
+        let load = res.delegation_prefs[0].target_project.as_ref().unwrap();
+        assert_eq!(load.ticker, Project::load().ticker);
+        assert_eq!(res.delegation_prefs[1].target_project, None);
+
+        let value = serde_json::to_value(&res.delegation_prefs[1]).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"walletTo": "pid_b", "factor": 2500})
+        );
     }
 }
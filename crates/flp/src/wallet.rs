@@ -1,39 +1,72 @@
-use crate::types::{DelegationsRes, MAX_FACTOR};
-use anyhow::{Error, anyhow};
+use crate::{
+    error::FlpError,
+    snippet,
+    types::{DelegationsRes, MAX_FACTOR},
+};
+use anyhow::Context;
+use common::delegation::delegation_era_start;
 use common::gateway::download_tx_data;
-use common::gql::{get_user_delegation_txid, get_user_last_delegation_txid};
-use common::projects::INTERNAL_PI_PID;
+use common::gql::{get_user_delegation_txid, get_user_last_delegation_msgs};
 
 /// retrieves wallet delegation preferences by making two queries:
 /// 1- gets the last delegation message ID (msg sent from user addr to DELEGATION_PID)
 /// 2- extracts the actual delegation data from its `Pushed-For` tag
 /// (msg sent from AO_AUTHORITY to user address with From-Process & Pushed-For tags)
-pub fn get_wallet_delegations(address: &str) -> Result<DelegationsRes, Error> {
-    let last_ids = get_user_last_delegation_txid(address)?;
+/// the block of the delegation message comes with the first query, it's
+/// returned as `last_update_height`/`last_update_ts`. only messages of the
+/// current delegation process' era count, older ones fall back to PI.
+pub fn get_wallet_delegations(address: &str) -> Result<DelegationsRes, FlpError> {
+    let last_msgs = get_user_last_delegation_msgs(address, delegation_era_start())
+        .map_err(FlpError::gateway)?;
+    if last_msgs.is_empty() {
+        return Ok(DelegationsRes::pi_default(address));
+    }
     let mut fallback = None;
-    for last_delegation_txid in last_ids {
-        if last_delegation_txid == INTERNAL_PI_PID {
-            return Ok(DelegationsRes::pi_default(address));
-        }
-        let delegation_txid = get_user_delegation_txid(&last_delegation_txid)?;
-        let delegation_data = download_tx_data(&delegation_txid)?;
-        let mut res: DelegationsRes = serde_json::from_slice(&delegation_data)?;
-        let total_factor = res
-            .total_factor
-            .unwrap_or_else(|| res.delegation_prefs.iter().map(|p| p.factor).sum());
-        if total_factor >= MAX_FACTOR {
-            res.delegation_msg_id = Some(last_delegation_txid);
+    for last_msg in last_msgs {
+        let delegation_txid = get_user_delegation_txid(&last_msg.id).map_err(FlpError::gateway)?;
+        let delegation_data = download_tx_data(&delegation_txid).map_err(FlpError::gateway)?;
+        let mut res = delegations_from_slice(&delegation_txid, &delegation_data)?;
+        res.delegation_msg_id = Some(last_msg.id);
+        res.last_update_height = last_msg.height;
+        res.last_update_ts = last_msg.timestamp;
+        if res.total_factor.unwrap_or_default() >= MAX_FACTOR {
             return Ok(res);
         }
-        res.delegation_msg_id = Some(last_delegation_txid);
         fallback = Some(res);
     }
-    fallback.ok_or_else(|| anyhow!("error: no delegation preferences found"))
+    fallback.ok_or_else(|| FlpError::NotFound("delegation preferences".to_string()))
+}
+
+/// parses a downloaded delegation preferences JSON body (the `Pushed-For` tx),
+/// in either the legacy or the current shape
+pub fn delegations_from_slice(txid: &str, data: &[u8]) -> Result<DelegationsRes, FlpError> {
+    let res: DelegationsRes = serde_json::from_slice(data)
+        .with_context(|| {
+            format!(
+                "parsing delegation preferences for tx {txid}, input: {}",
+                snippet(data)
+            )
+        })
+        .map_err(FlpError::Parse)?;
+    Ok(res.normalized())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::wallet::get_wallet_delegations;
+    use crate::error::FlpError;
+    use crate::wallet::{delegations_from_slice, get_wallet_delegations};
+
+    #[test]
+    fn delegations_error_has_context_test() {
+        let err = delegations_from_slice("tx_abc", b"not json").unwrap_err();
+        assert!(matches!(err, FlpError::Parse(_)));
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("parsing delegation preferences for tx tx_abc"),
+            "{msg}"
+        );
+        assert!(msg.contains("input: not json"), "{msg}");
+    }
 
     #[test]
     fn get_wallet_delegations_pi_test() {
@@ -1,16 +1,26 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use clickhouse::{Client, Row};
-use explorer::BlockStats;
+use common::{
+    amount::TokenAmount, env::redact_url_credentials, height::BlockHeight,
+    mainnet::MAINNET_BLOCK_METRICS_QUERY,
+};
+use explorer::{BlockStats, top_actions};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::config::Config;
 
+/// actions kept per block in `atlas_explorer.action_counts`
+const TOP_ACTIONS: usize = 10;
+
 #[derive(Clone)]
 pub struct Clickhouse {
     client: Client,
     admin: Client,
     database: String,
+    url: String,
+    user: String,
 }
 
 impl Clickhouse {
@@ -24,9 +34,47 @@ impl Clickhouse {
             client,
             admin,
             database: config.clickhouse_database.clone(),
+            // only used in errors
+            url: redact_url_credentials(&config.clickhouse_url),
+            user: config.clickhouse_user.clone(),
         }
     }
 
+    /// clients connect lazily, so check the server and credentials up front
+    /// rather than failing halfway through startup. uses the admin client as
+    /// the database may not exist yet.
+    pub async fn verify(&self) -> Result<()> {
+        self.admin
+            .query("select 1")
+            .fetch_one::<u8>()
+            .await
+            .with_context(|| {
+                format!(
+                    "cannot connect to ClickHouse at {} as {}",
+                    self.url, self.user
+                )
+            })?;
+        Ok(())
+    }
+
+    /// creates/migrates the schema. `ReplacingMergeTree` collapses rows that
+    /// share the full `order by` key, so each key must identify one logical row:
+    /// - wallet_balances: one balance per (ticker, wallet) per ticker cycle `ts`,
+    ///   only written in the cycles where it changed
+    /// - wallet_delegations: one payload per wallet per cycle `ts`
+    /// - flp_positions: one position per (project, ticker, wallet) per cycle `ts`
+    /// - delegation_mappings: one factor per (wallet_from, wallet_to) per mapping tx
+    /// - atlas_explorer / ao_mainnet_explorer: one row per height, re-inserts overwrite
+    /// - ao_mainnet_messages / ao_token_messages: one row per msg_id (per protocol/token source)
+    /// - ao_mainnet_message_tags: one row per tag position (`tag_index`) on a message,
+    ///   so repeated tags survive
+    /// - ao_token_message_tags: identical (key, value) pairs on a message are
+    ///   duplicates anyway
+    /// - ao_mainnet_block_state: latest-wins per protocol
+    /// - ao_token_block_state: keeps each checkpoint, readers take the max
+    /// - oracle_evm_totals: one on-chain stake per ticker per EVM block
+    /// - oracle_evm_divergence: one AO vs EVM comparison per ticker per cycle `ts`
+    /// - flp_minting_reports: one row per Own-Minting-Report msg of a project
     pub async fn ensure(&self) -> Result<()> {
         let create_db = format!("create database if not exists {}", self.database);
         self.admin.query(&create_db).execute().await?;
@@ -34,23 +82,30 @@ impl Clickhouse {
             "create table if not exists oracle_snapshots(ts DateTime64(3), ticker String, tx_id String) engine=MergeTree order by (ticker, ts)",
             "create table if not exists wallet_balances(ts DateTime64(3), ticker String, wallet String, eoa String, amount String, tx_id String) engine=ReplacingMergeTree order by (ticker, wallet, ts)",
             "create table if not exists wallet_delegations(ts DateTime64(3), wallet String, payload String) engine=ReplacingMergeTree order by (wallet, ts)",
-            "create table if not exists flp_positions(ts DateTime64(3), ticker String, wallet String, eoa String, project String, factor UInt32, amount String) engine=ReplacingMergeTree order by (project, wallet, ts)",
+            "create table if not exists flp_positions(ts DateTime64(3), ticker String, wallet String, eoa String, project String, factor UInt32, amount String) engine=ReplacingMergeTree order by (project, ticker, wallet, ts)",
             "create table if not exists delegation_mappings(ts DateTime64(3), height UInt32, tx_id String, wallet_from String, wallet_to String, factor UInt32) engine=ReplacingMergeTree order by (height, tx_id, wallet_from, wallet_to)",
             "create table if not exists atlas_explorer(ts DateTime64(3), height UInt64, tx_count UInt64, eval_count UInt64, transfer_count UInt64, new_process_count UInt64, new_module_count UInt64, active_users UInt64, active_processes UInt64, tx_count_rolling UInt64, processes_rolling UInt64, modules_rolling UInt64) engine=ReplacingMergeTree order by height",
             "create table if not exists ao_mainnet_explorer(ts DateTime64(3), height UInt64, tx_count UInt64, eval_count UInt64, transfer_count UInt64, new_process_count UInt64, new_module_count UInt64, active_users UInt64, active_processes UInt64, tx_count_rolling UInt64, processes_rolling UInt64, modules_rolling UInt64) engine=ReplacingMergeTree order by height",
             "create table if not exists ao_mainnet_messages(ts DateTime64(3), protocol String, block_height UInt32, block_timestamp UInt64, msg_id String, owner String, recipient String, bundled_in String, data_size String) engine=ReplacingMergeTree order by (protocol, block_height, msg_id)",
-            "create table if not exists ao_mainnet_message_tags(ts DateTime64(3), protocol String, block_height UInt32, msg_id String, tag_key String, tag_value String) engine=ReplacingMergeTree order by (tag_key, tag_value, block_height, msg_id)",
+            "create table if not exists ao_mainnet_message_tags(ts DateTime64(3), protocol String, block_height UInt32, msg_id String, tag_key String, tag_value String, tag_index UInt16) engine=ReplacingMergeTree order by (tag_key, tag_value, block_height, msg_id, tag_index)",
             "create table if not exists ao_mainnet_block_state(protocol String, last_complete_height UInt32, last_cursor String, updated_at DateTime64(3)) engine=ReplacingMergeTree order by protocol",
             "create table if not exists ao_token_messages(ts DateTime64(3), token String, source String, block_height UInt32, block_timestamp UInt64, msg_id String, owner String, recipient String, bundled_in String, data_size String) engine=ReplacingMergeTree order by (token, source, block_height, msg_id)",
             "create table if not exists ao_token_message_tags(ts DateTime64(3), token String, source String, block_height UInt32, msg_id String, tag_key String, tag_value String) engine=ReplacingMergeTree order by (token, source, tag_key, tag_value, block_height, msg_id)",
             "create table if not exists ao_token_block_state(token String, last_complete_height UInt32, updated_at DateTime64(3)) engine=ReplacingMergeTree order by (token, updated_at)",
+            "create table if not exists oracle_evm_divergence(ts DateTime64(3), ticker String, oracle_tx_id String, evm_block UInt64, ao_total String, evm_total String, divergence Float64, tolerance Float64, diverged Bool) engine=ReplacingMergeTree order by (ticker, ts)",
+            "create table if not exists oracle_evm_totals(ts DateTime64(3), ticker String, staking_address String, token_address String, evm_block UInt64, total String) engine=ReplacingMergeTree order by (ticker, evm_block)",
+            "create table if not exists flp_minting_reports(ts DateTime64(3), project String, report_id String, distribution_tick UInt32, total_minted String, total_inflow String, ao_kept String, ao_exchanged_for_pi String) engine=ReplacingMergeTree order by (project, ts, report_id)",
         ];
         for stmt in stmts {
             self.client.query(stmt).execute().await?;
         }
         let alters = [
+            "alter table oracle_snapshots add column if not exists total String default ''",
             "alter table wallet_balances add column if not exists eoa String after wallet",
             "alter table wallet_balances add column if not exists ar_balance String after amount",
+            "alter table wallet_balances add column if not exists stale Bool default false",
+            "alter table atlas_explorer add column if not exists action_counts String",
+            "alter table wallet_delegations add column if not exists stale Bool default false",
             "alter table flp_positions add column if not exists eoa String after wallet",
             "alter table flp_positions add column if not exists ar_amount String after amount",
             "alter table flp_positions modify column project String",
@@ -58,10 +113,83 @@ impl Clickhouse {
             "alter table ao_token_messages add column if not exists token String default 'ao'",
             "alter table ao_token_message_tags add column if not exists token String default 'ao'",
             "alter table ao_token_block_state add column if not exists token String default 'ao'",
+            "alter table ao_mainnet_explorer add column if not exists total_data_size UInt64 default 0",
+            "alter table ao_mainnet_explorer add column if not exists data_size_rolling UInt64 default 0",
         ];
         for stmt in alters {
             self.client.query(stmt).execute().await?;
         }
+        self.migrate_flp_positions_key().await?;
+        self.migrate_mainnet_message_tags_key().await?;
+        // leftover of a rebuild that died before the swap
+        self.client
+            .query("drop table if exists ao_mainnet_explorer_tmp")
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// older deployments keyed flp_positions without `ticker`, so positions of
+    /// the same wallet/project for different tickers written in the same cycle
+    /// collapsed on merge. the sort key can't be changed in place: copy into a
+    /// correctly keyed table and swap. rows already merged away stay lost.
+    async fn migrate_flp_positions_key(&self) -> Result<()> {
+        let rows = self
+            .client
+            .query(
+                "select sorting_key from system.tables \
+                 where database = currentDatabase() and name = 'flp_positions'",
+            )
+            .fetch_all::<SortingKeyRow>()
+            .await?;
+        let Some(row) = rows.into_iter().next() else {
+            return Ok(());
+        };
+        if row.sorting_key.contains("ticker") {
+            return Ok(());
+        }
+        println!("migrating flp_positions sort key to (project, ticker, wallet, ts)");
+        let stmts = [
+            "drop table if exists flp_positions_v2",
+            "create table flp_positions_v2 as flp_positions engine=ReplacingMergeTree order by (project, ticker, wallet, ts)",
+            "insert into flp_positions_v2 select * from flp_positions",
+            "exchange tables flp_positions and flp_positions_v2",
+            "drop table flp_positions_v2",
+        ];
+        for stmt in stmts {
+            self.client.query(stmt).execute().await?;
+        }
+        Ok(())
+    }
+
+    /// older deployments keyed ao_mainnet_message_tags without a tag position,
+    /// so a message carrying the same tag twice kept only one. a column added in
+    /// the same alter can be appended to the sort key in place; existing rows
+    /// get index 0 and tags already merged away stay lost.
+    async fn migrate_mainnet_message_tags_key(&self) -> Result<()> {
+        let rows = self
+            .client
+            .query(
+                "select sorting_key from system.tables \
+                 where database = currentDatabase() and name = 'ao_mainnet_message_tags'",
+            )
+            .fetch_all::<SortingKeyRow>()
+            .await?;
+        let Some(row) = rows.into_iter().next() else {
+            return Ok(());
+        };
+        if row.sorting_key.contains("tag_index") {
+            return Ok(());
+        }
+        println!("migrating ao_mainnet_message_tags sort key to include tag_index");
+        self.client
+            .query(
+                "alter table ao_mainnet_message_tags \
+                 add column tag_index UInt16 default 0 after tag_value, \
+                 modify order by (tag_key, tag_value, block_height, msg_id, tag_index)",
+            )
+            .execute()
+            .await?;
         Ok(())
     }
 
@@ -69,6 +197,18 @@ impl Clickhouse {
         self.insert_rows("oracle_snapshots", rows).await
     }
 
+    pub async fn insert_evm_totals(&self, rows: &[OracleEvmTotalRow]) -> Result<()> {
+        self.insert_rows("oracle_evm_totals", rows).await
+    }
+
+    pub async fn insert_divergences(&self, rows: &[OracleDivergenceRow]) -> Result<()> {
+        self.insert_rows("oracle_evm_divergence", rows).await
+    }
+
+    pub async fn insert_minting_reports(&self, rows: &[FlpMintingReportRow]) -> Result<()> {
+        self.insert_rows("flp_minting_reports", rows).await
+    }
+
     pub async fn insert_balances(&self, rows: &[WalletBalanceRow]) -> Result<()> {
         self.insert_rows("wallet_balances", rows).await
     }
@@ -111,9 +251,34 @@ impl Clickhouse {
         self.insert_rows("ao_token_block_state", rows).await
     }
 
-    pub async fn truncate_mainnet_explorer(&self) -> Result<()> {
+    /// fresh, empty copy of `ao_mainnet_explorer` for a rebuild to fill
+    pub async fn create_mainnet_explorer_tmp(&self) -> Result<()> {
+        self.client
+            .query("drop table if exists ao_mainnet_explorer_tmp")
+            .execute()
+            .await?;
+        self.client
+            .query("create table ao_mainnet_explorer_tmp as ao_mainnet_explorer")
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn insert_mainnet_explorer_tmp_rows(
+        &self,
+        rows: &[MainnetExplorerRow],
+    ) -> Result<()> {
+        self.insert_rows("ao_mainnet_explorer_tmp", rows).await
+    }
+
+    /// atomically swaps the rebuilt tmp table in and drops the old data
+    pub async fn swap_mainnet_explorer_tmp(&self) -> Result<()> {
         self.client
-            .query("truncate table if exists ao_mainnet_explorer")
+            .query("exchange tables ao_mainnet_explorer and ao_mainnet_explorer_tmp")
+            .execute()
+            .await?;
+        self.client
+            .query("drop table if exists ao_mainnet_explorer_tmp")
             .execute()
             .await?;
         Ok(())
@@ -129,7 +294,8 @@ impl Clickhouse {
             .query(
                 "select ts, height, tx_count, eval_count, transfer_count, \
                  new_process_count, new_module_count, active_users, active_processes, \
-                 tx_count_rolling, processes_rolling, modules_rolling \
+                 tx_count_rolling, processes_rolling, modules_rolling, \
+                 total_data_size, data_size_rolling \
                  from ao_mainnet_explorer \
                  order by height desc \
                  limit 1",
@@ -141,31 +307,12 @@ impl Clickhouse {
 
     pub async fn fetch_mainnet_block_metrics(
         &self,
-        after_height: u32,
+        after_height: BlockHeight,
         limit: u64,
     ) -> Result<Vec<MainnetBlockMetricRow>> {
-        let query = "\
-            select \
-                toDateTime64(max(m.block_timestamp), 3) as ts, \
-                max(m.block_timestamp) as ts_unix, \
-                m.block_height as height, \
-                count() as tx_count, \
-                countIf(lowerUTF8(t.tag_key) = 'action' and lowerUTF8(t.tag_value) = 'eval') as eval_count, \
-                countIf(lowerUTF8(t.tag_key) = 'action' and lowerUTF8(t.tag_value) = 'transfer') as transfer_count, \
-                countIf(lowerUTF8(t.tag_key) = 'type' and lowerUTF8(t.tag_value) = 'process') as new_process_count, \
-                countIf(lowerUTF8(t.tag_key) = 'type' and lowerUTF8(t.tag_value) = 'module') as new_module_count, \
-                uniqExact(m.owner) as active_users, \
-                uniqExactIf(t.tag_value, lowerUTF8(t.tag_key) in ('from-process','process','from-process-id','process-id')) as active_processes \
-            from ao_mainnet_messages m \
-            left join ao_mainnet_message_tags t \
-              on t.protocol = m.protocol and t.block_height = m.block_height and t.msg_id = m.msg_id \
-            where m.block_height > ? \
-            group by m.block_height \
-            order by m.block_height asc \
-            limit ?";
         let rows = self
             .client
-            .query(query)
+            .query(MAINNET_BLOCK_METRICS_QUERY)
             .bind(after_height)
             .bind(limit)
             .fetch_all::<MainnetBlockMetricRow>()
@@ -226,6 +373,96 @@ impl Clickhouse {
         Ok(row.cnt > 0)
     }
 
+    pub async fn has_minting_report(&self, project: &str, report_id: &str) -> Result<bool> {
+        let row = self
+            .client
+            .query(
+                "select count() as cnt from flp_minting_reports \
+                 where project = ? and report_id = ? limit 1",
+            )
+            .bind(project)
+            .bind(report_id)
+            .fetch_one::<CountRow>()
+            .await?;
+        Ok(row.cnt > 0)
+    }
+
+    /// the newest oracle snapshot of `ticker` that recorded its summed
+    /// balances; snapshots stored before `total` existed have it empty
+    pub async fn latest_oracle_total(&self, ticker: &str) -> Result<Option<OracleTotalRow>> {
+        let rows = self
+            .client
+            .query(
+                "select tx_id, total from oracle_snapshots \
+                 where ticker = ? and total != '' \
+                 order by ts desc limit 1",
+            )
+            .bind(ticker)
+            .fetch_all::<OracleTotalRow>()
+            .await?;
+        Ok(rows.into_iter().next())
+    }
+
+    pub async fn latest_wallet_ar_balance(
+        &self,
+        ticker: &str,
+        wallet: &str,
+    ) -> Result<Option<String>> {
+        let rows = self
+            .client
+            .query(
+                "select ar_balance from wallet_balances \
+                 where ticker = ? and wallet = ? \
+                 order by ts desc \
+                 limit 1",
+            )
+            .bind(ticker)
+            .bind(wallet)
+            .fetch_all::<ArBalanceRow>()
+            .await?;
+        Ok(rows.into_iter().next().map(|row| row.ar_balance))
+    }
+
+    /// the newest stored balance of each of `wallets` for `ticker`
+    pub async fn latest_wallet_balances(
+        &self,
+        ticker: &str,
+        wallets: &[String],
+    ) -> Result<Vec<StoredBalanceRow>> {
+        if wallets.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows = self
+            .client
+            .query(
+                "select wallet, argMax(eoa, ts) as eoa, argMax(amount, ts) as amount, \
+                 argMax(ar_balance, ts) as ar_balance \
+                 from wallet_balances \
+                 where ticker = ? and wallet in ? \
+                 group by wallet",
+            )
+            .bind(ticker)
+            .bind(wallets)
+            .fetch_all::<StoredBalanceRow>()
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn latest_wallet_delegation(&self, wallet: &str) -> Result<Option<String>> {
+        let rows = self
+            .client
+            .query(
+                "select payload from wallet_delegations \
+                 where wallet = ? \
+                 order by ts desc \
+                 limit 1",
+            )
+            .bind(wallet)
+            .fetch_all::<DelegationPayloadRow>()
+            .await?;
+        Ok(rows.into_iter().next().map(|row| row.payload))
+    }
+
     pub async fn has_delegation_mapping(&self, tx_id: &str) -> Result<bool> {
         let query = "select count() as cnt from delegation_mappings where tx_id = ? limit 1";
         let row = self
@@ -237,6 +474,16 @@ impl Clickhouse {
         Ok(row.cnt > 0)
     }
 
+    /// height of the newest stored delegation mapping, `None` before the first
+    pub async fn latest_delegation_mapping_height(&self) -> Result<Option<BlockHeight>> {
+        let height = self
+            .client
+            .query("select max(height) from delegation_mappings")
+            .fetch_one::<BlockHeight>()
+            .await?;
+        Ok((height > 0).then_some(height))
+    }
+
     pub async fn latest_explorer_stats(&self) -> Result<Option<BlockStats>> {
         let rows = self
             .client
@@ -273,6 +520,54 @@ pub struct OracleSnapshotRow {
     pub ts: DateTime<Utc>,
     pub ticker: String,
     pub tx_id: String,
+    /// sum of the tx's balances
+    pub total: TokenAmount,
+}
+
+#[derive(Clone, Debug, Row, Deserialize)]
+pub struct OracleTotalRow {
+    pub tx_id: String,
+    pub total: TokenAmount,
+}
+
+#[derive(Clone, Debug, Row, Serialize)]
+pub struct OracleDivergenceRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+    pub ticker: String,
+    pub oracle_tx_id: String,
+    pub evm_block: u64,
+    pub ao_total: TokenAmount,
+    pub evm_total: TokenAmount,
+    pub divergence: f64,
+    pub tolerance: f64,
+    pub diverged: bool,
+}
+
+/// an FLP's `Own-Minting-Report`, `ts` being the report's own timestamp.
+/// amounts are kept as the report's raw strings.
+#[derive(Clone, Debug, Row, Serialize)]
+pub struct FlpMintingReportRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+    pub project: String,
+    pub report_id: String,
+    pub distribution_tick: u32,
+    pub total_minted: String,
+    pub total_inflow: String,
+    pub ao_kept: String,
+    pub ao_exchanged_for_pi: String,
+}
+
+#[derive(Clone, Debug, Row, Serialize)]
+pub struct OracleEvmTotalRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+    pub ticker: String,
+    pub staking_address: String,
+    pub token_address: String,
+    pub evm_block: u64,
+    pub total: TokenAmount,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
@@ -282,9 +577,10 @@ pub struct WalletBalanceRow {
     pub ticker: String,
     pub wallet: String,
     pub eoa: String,
-    pub amount: String,
-    pub ar_balance: String,
+    pub amount: TokenAmount,
+    pub ar_balance: TokenAmount,
     pub tx_id: String,
+    pub stale: bool,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
@@ -293,6 +589,7 @@ pub struct WalletDelegationRow {
     pub ts: DateTime<Utc>,
     pub wallet: String,
     pub payload: String,
+    pub stale: bool,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
@@ -304,15 +601,15 @@ pub struct FlpPositionRow {
     pub eoa: String,
     pub project: String,
     pub factor: u32,
-    pub amount: String,
-    pub ar_amount: String,
+    pub amount: TokenAmount,
+    pub ar_amount: TokenAmount,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
 pub struct DelegationMappingRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub ts: DateTime<Utc>,
-    pub height: u32,
+    pub height: BlockHeight,
     pub tx_id: String,
     pub wallet_from: String,
     pub wallet_to: String,
@@ -334,6 +631,8 @@ pub struct AtlasExplorerRow {
     pub tx_count_rolling: u64,
     pub processes_rolling: u64,
     pub modules_rolling: u64,
+    /// JSON object of the block's `TOP_ACTIONS` most frequent tracked actions
+    pub action_counts: String,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
@@ -341,7 +640,7 @@ pub struct MainnetMessageRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub ts: DateTime<Utc>,
     pub protocol: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub block_timestamp: u64,
     pub msg_id: String,
     pub owner: String,
@@ -355,10 +654,12 @@ pub struct MainnetMessageTagRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub ts: DateTime<Utc>,
     pub protocol: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub msg_id: String,
     pub tag_key: String,
     pub tag_value: String,
+    /// position of the tag on the message, keeps repeated tags apart
+    pub tag_index: u16,
 }
 
 #[derive(Clone, Debug, Row, Serialize)]
@@ -367,7 +668,7 @@ pub struct AoTokenMessageRow {
     pub ts: DateTime<Utc>,
     pub token: String,
     pub source: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub block_timestamp: u64,
     pub msg_id: String,
     pub owner: String,
@@ -382,7 +683,7 @@ pub struct AoTokenMessageTagRow {
     pub ts: DateTime<Utc>,
     pub token: String,
     pub source: String,
-    pub block_height: u32,
+    pub block_height: BlockHeight,
     pub msg_id: String,
     pub tag_key: String,
     pub tag_value: String,
@@ -391,7 +692,7 @@ pub struct AoTokenMessageTagRow {
 #[derive(Clone, Debug, Row, Serialize, Deserialize)]
 pub struct AoTokenBlockStateRow {
     pub token: String,
-    pub last_complete_height: u32,
+    pub last_complete_height: BlockHeight,
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub updated_at: DateTime<Utc>,
 }
@@ -401,7 +702,7 @@ pub struct MainnetBlockStateRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub updated_at: DateTime<Utc>,
     pub protocol: String,
-    pub last_complete_height: u32,
+    pub last_complete_height: BlockHeight,
     pub last_cursor: String,
 }
 
@@ -420,6 +721,8 @@ pub struct MainnetExplorerRow {
     pub tx_count_rolling: u64,
     pub processes_rolling: u64,
     pub modules_rolling: u64,
+    pub total_data_size: u64,
+    pub data_size_rolling: u64,
 }
 
 #[derive(Clone, Debug, Row, Serialize, Deserialize)]
@@ -427,7 +730,7 @@ pub struct MainnetBlockMetricRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     pub ts: DateTime<Utc>,
     pub ts_unix: u64,
-    pub height: u32,
+    pub height: BlockHeight,
     pub tx_count: u64,
     pub eval_count: u64,
     pub transfer_count: u64,
@@ -435,6 +738,8 @@ pub struct MainnetBlockMetricRow {
     pub new_module_count: u64,
     pub active_users: u64,
     pub active_processes: u64,
+    // deduplicated per message, the tags join fans rows out
+    pub total_data_size: u64,
 }
 
 impl AtlasExplorerRow {
@@ -454,6 +759,8 @@ impl AtlasExplorerRow {
             tx_count_rolling: stats.tx_count_rolling,
             processes_rolling: stats.processes_rolling,
             modules_rolling: stats.modules_rolling,
+            action_counts: serde_json::to_string(&top_actions(&stats.action_counts, TOP_ACTIONS))
+                .unwrap_or_default(),
         })
     }
 }
@@ -490,10 +797,53 @@ impl From<ExplorerSelectRow> for BlockStats {
             tx_count_rolling: row.tx_count_rolling,
             processes_rolling: row.processes_rolling,
             modules_rolling: row.modules_rolling,
+            action_counts: BTreeMap::new(),
         }
     }
 }
+#[derive(Debug, Row, serde::Deserialize)]
+struct SortingKeyRow {
+    sorting_key: String,
+}
+
 #[derive(Debug, Row, Serialize, serde::Deserialize)]
 struct CountRow {
     pub cnt: u64,
 }
+
+/// amounts stay raw strings, rows written before `ar_balance` existed hold ''
+#[derive(Debug, Row, serde::Deserialize)]
+pub struct StoredBalanceRow {
+    pub wallet: String,
+    pub eoa: String,
+    pub amount: String,
+    pub ar_balance: String,
+}
+
+#[derive(Debug, Row, serde::Deserialize)]
+struct ArBalanceRow {
+    ar_balance: String,
+}
+
+#[derive(Debug, Row, serde::Deserialize)]
+struct DelegationPayloadRow {
+    payload: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn verify_reports_url_and_user_test() {
+        let mut config = Config::load();
+        // nothing listens on port 1
+        config.clickhouse_url = "http://127.0.0.1:1".to_string();
+        config.clickhouse_user = "atlas_ro".to_string();
+        let err = Clickhouse::new(&config).verify().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot connect to ClickHouse at http://127.0.0.1:1 as atlas_ro"
+        );
+    }
+}
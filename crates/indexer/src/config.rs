@@ -1,7 +1,19 @@
-use common::env::get_env_var;
+use common::{
+    constants::{
+        DATA_PROTOCOL_A_START, DATA_PROTOCOL_B_START, arweave_gateway, explorer_graphql_url,
+    },
+    env::{clickhouse_database, clickhouse_url, get_env_var, redact_url_credentials},
+    gateway::{GQL_DEFAULT_PAGE_SIZE, check_page_size},
+    gql::configured_oracle_tickers,
+    height::BlockHeight,
+};
+use explorer::PollBackoff;
 use serde::Deserialize;
 use std::{fs, io::ErrorKind, time::Duration};
 
+/// 0.1% of the on-chain stake
+pub const DEFAULT_EVM_DIVERGENCE_TOLERANCE: f64 = 0.001;
+
 #[derive(Clone)]
 pub struct Config {
     pub clickhouse_url: String,
@@ -10,10 +22,55 @@ pub struct Config {
     pub clickhouse_database: String,
     pub interval: Duration,
     pub concurrency: usize,
+    pub balances_chunk_size: usize,
+    pub max_stakers: usize,
     pub tickers: Vec<String>,
+    pub mainnet_a_start: u32,
+    pub mainnet_b_start: u32,
+    pub explorer_poll: PollBackoff,
+    pub explorer_batch_size: usize,
+    pub explorer_flush_interval: Duration,
+    /// store mainnet message tags; off keeps only message-level metadata
+    pub index_message_tags: bool,
+    /// `first` of the mainnet and token scanners' GraphQL pages
+    pub gql_page_size: u32,
+    pub mainnet_pacing: MainnetPacing,
+    /// Ethereum JSON-RPC endpoint of the staking contracts cross-check
+    pub evm_rpc_url: Option<String>,
+    /// relative gap between the AO and EVM totals tolerated before warning
+    pub evm_divergence_tolerance: f64,
     pub indexers: IndexerConfig,
 }
 
+/// sleep between stored mainnet pages: `block_delay` near tip, none while
+/// more than `catch_up_blocks` behind it
+#[derive(Clone, Copy, Debug)]
+pub struct MainnetPacing {
+    pub block_delay: Duration,
+    pub catch_up_blocks: u64,
+}
+
+impl Default for MainnetPacing {
+    fn default() -> Self {
+        Self {
+            block_delay: Duration::from_secs(1),
+            catch_up_blocks: 50,
+        }
+    }
+}
+
+impl MainnetPacing {
+    pub fn delay(&self, height: BlockHeight, network_tip: u64) -> Duration {
+        if network_tip.saturating_sub(u64::from(height)) > self.catch_up_blocks {
+            Duration::ZERO
+        } else {
+            self.block_delay
+        }
+    }
+}
+
+/// which sub-indexers run. `flp` and `mainnet` are umbrellas over their finer
+/// flags, a component runs only when both its umbrella and its own flag are on.
 #[derive(Clone, Copy)]
 pub struct IndexerConfig {
     pub ao: bool,
@@ -21,6 +78,14 @@ pub struct IndexerConfig {
     pub explorer: bool,
     pub flp: bool,
     pub mainnet: bool,
+    pub mainnet_a: bool,
+    pub mainnet_b: bool,
+    pub oracles: bool,
+    pub delegation_mappings: bool,
+    /// off by default: needs `EVM_RPC_URL`
+    pub evm_verify: bool,
+    /// off by default: fetches one gateway report per project per cycle
+    pub minting_reports: bool,
 }
 
 impl Default for IndexerConfig {
@@ -31,6 +96,12 @@ impl Default for IndexerConfig {
             explorer: true,
             flp: true,
             mainnet: true,
+            mainnet_a: true,
+            mainnet_b: true,
+            oracles: true,
+            delegation_mappings: true,
+            evm_verify: false,
+            minting_reports: false,
         }
     }
 }
@@ -52,12 +123,10 @@ struct FileIndexersConfig {
 
 impl Config {
     pub fn load() -> Self {
-        let clickhouse_url =
-            get_env_var("CLICKHOUSE_URL").unwrap_or_else(|_| "http://localhost:8123".into());
+        let clickhouse_url = clickhouse_url();
         let clickhouse_user = get_env_var("CLICKHOUSE_USER").unwrap_or_else(|_| "default".into());
         let clickhouse_password = get_env_var("CLICKHOUSE_PASSWORD").unwrap_or_default();
-        let clickhouse_database =
-            get_env_var("CLICKHOUSE_DATABASE").unwrap_or_else(|_| "atlas_oracles".into());
+        let clickhouse_database = clickhouse_database();
         let interval = get_env_var("ORACLE_REFRESH_SECS")
             .ok()
             .and_then(|v| v.parse::<u64>().ok())
@@ -68,12 +137,64 @@ impl Config {
             .and_then(|v| v.parse::<usize>().ok())
             .filter(|v| *v > 0)
             .unwrap_or(16);
-        let tickers = get_env_var("ORACLE_TICKERS")
-            .unwrap_or_else(|_| "usds,dai,steth".into())
-            .split(',')
-            .map(|v| v.trim().to_ascii_lowercase())
-            .filter(|v| !v.is_empty())
-            .collect();
+        let balances_chunk_size = get_env_var("BALANCES_CHUNK_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(1000);
+        let max_stakers = get_env_var("MAX_STAKERS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(200_000);
+        let tickers = configured_oracle_tickers();
+        let mainnet_a_start = get_env_var("MAINNET_A_START")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .unwrap_or(DATA_PROTOCOL_A_START);
+        let mainnet_b_start = get_env_var("MAINNET_B_START")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .unwrap_or(DATA_PROTOCOL_B_START);
+        let explorer_poll_min = get_env_var("EXPLORER_POLL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(explorer::DEFAULT_POLL_INTERVAL);
+        let explorer_poll_max = get_env_var("EXPLORER_POLL_MAX_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(explorer::DEFAULT_MAX_POLL_INTERVAL);
+        let explorer_poll = PollBackoff::new(explorer_poll_min, explorer_poll_max);
+        let explorer_batch_size = get_env_var("EXPLORER_BATCH_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(50);
+        let explorer_flush_interval = get_env_var("EXPLORER_FLUSH_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_secs(5));
+        let index_message_tags = get_env_var("INDEX_MESSAGE_TAGS")
+            .map(|v| !(v.trim() == "0" || v.trim().eq_ignore_ascii_case("false")))
+            .unwrap_or(true);
+        let gql_page_size = parse_page_size(get_env_var("GQL_PAGE_SIZE").ok());
+        let defaults = MainnetPacing::default();
+        let mainnet_pacing = MainnetPacing {
+            block_delay: get_env_var("MAINNET_BLOCK_DELAY_MS")
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.block_delay),
+            catch_up_blocks: get_env_var("MAINNET_CATCH_UP_BLOCKS")
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(defaults.catch_up_blocks),
+        };
         let mut config = Config {
             clickhouse_url,
             clickhouse_user,
@@ -81,16 +202,80 @@ impl Config {
             clickhouse_database,
             interval,
             concurrency,
+            balances_chunk_size,
+            max_stakers,
             tickers,
+            mainnet_a_start,
+            mainnet_b_start,
+            explorer_poll,
+            explorer_batch_size,
+            explorer_flush_interval,
+            index_message_tags,
+            gql_page_size,
+            mainnet_pacing,
+            evm_rpc_url: get_env_var("EVM_RPC_URL")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            evm_divergence_tolerance: get_env_var("EVM_DIVERGENCE_TOLERANCE")
+                .ok()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or(DEFAULT_EVM_DIVERGENCE_TOLERANCE),
             indexers: IndexerConfig::default(),
         };
         if let Some(file_config) = FileConfig::load() {
             config.indexers.apply(file_config.indexers);
         }
+        config.indexers.apply_env(|key| get_env_var(key).ok());
         config
     }
 }
 
+impl Config {
+    /// the RPC endpoint when the EVM cross-check is on and has one
+    pub fn evm_verify_rpc(&self) -> Option<&str> {
+        self.evm_rpc_url
+            .as_deref()
+            .filter(|_| self.indexers.evm_verify())
+    }
+
+    /// endpoints and switches worth knowing when correlating stored data to
+    /// a run, without credentials
+    pub fn summary(&self) -> String {
+        format!(
+            "clickhouse={}/{} gateway={} graphql={} indexers={:?} tickers={:?} gql_page_size={}",
+            redact_url_credentials(&self.clickhouse_url),
+            self.clickhouse_database,
+            arweave_gateway(),
+            explorer_graphql_url(),
+            self.indexers.enabled(),
+            self.tickers,
+            self.gql_page_size,
+        )
+    }
+}
+
+/// `GQL_PAGE_SIZE`, falling back to the default when unset or beyond what
+/// the gateways accept
+fn parse_page_size(raw: Option<String>) -> u32 {
+    let Some(raw) = raw else {
+        return GQL_DEFAULT_PAGE_SIZE;
+    };
+    match raw
+        .trim()
+        .parse::<u32>()
+        .map_err(anyhow::Error::from)
+        .and_then(check_page_size)
+    {
+        Ok(size) => size,
+        Err(err) => {
+            eprintln!("ignoring GQL_PAGE_SIZE={raw:?}: {err}");
+            GQL_DEFAULT_PAGE_SIZE
+        }
+    }
+}
+
 impl IndexerConfig {
     fn apply(&mut self, file: FileIndexersConfig) {
         if let Some(value) = file.ao {
@@ -109,6 +294,74 @@ impl IndexerConfig {
             self.mainnet = value;
         }
     }
+
+    /// `ENABLE_*` env vars override atlas.toml
+    fn apply_env<F>(&mut self, lookup: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let flags = [
+            ("ENABLE_EXPLORER", &mut self.explorer),
+            ("ENABLE_MAINNET_A", &mut self.mainnet_a),
+            ("ENABLE_MAINNET_B", &mut self.mainnet_b),
+            ("ENABLE_ORACLES", &mut self.oracles),
+            ("ENABLE_DELEGATION_MAPPINGS", &mut self.delegation_mappings),
+            ("ENABLE_EVM_VERIFY", &mut self.evm_verify),
+            ("ENABLE_MINTING_REPORTS", &mut self.minting_reports),
+        ];
+        for (key, flag) in flags {
+            let Some(raw) = lookup(key) else {
+                continue;
+            };
+            match raw.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" => *flag = true,
+                "0" | "false" => *flag = false,
+                _ => eprintln!("ignoring {key}={raw:?}, expected 1/0 or true/false"),
+            }
+        }
+    }
+
+    pub fn mainnet_a(&self) -> bool {
+        self.mainnet && self.mainnet_a
+    }
+
+    pub fn mainnet_b(&self) -> bool {
+        self.mainnet && self.mainnet_b
+    }
+
+    pub fn oracles(&self) -> bool {
+        self.flp && self.oracles
+    }
+
+    pub fn delegation_mappings(&self) -> bool {
+        self.flp && self.delegation_mappings
+    }
+
+    pub fn evm_verify(&self) -> bool {
+        self.flp && self.evm_verify
+    }
+
+    pub fn minting_reports(&self) -> bool {
+        self.flp && self.minting_reports
+    }
+
+    /// names of the components that will run, for the startup log
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            ("explorer", self.explorer),
+            ("mainnet_a", self.mainnet_a()),
+            ("mainnet_b", self.mainnet_b()),
+            ("oracles", self.oracles()),
+            ("delegation_mappings", self.delegation_mappings()),
+            ("evm_verify", self.evm_verify()),
+            ("minting_reports", self.minting_reports()),
+            ("ao", self.ao),
+            ("pi", self.pi),
+        ]
+        .into_iter()
+        .filter_map(|(name, on)| on.then_some(name))
+        .collect()
+    }
 }
 
 impl FileConfig {
@@ -131,3 +384,54 @@ impl FileConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_flags_override_and_umbrellas_apply_test() {
+        let mut indexers = IndexerConfig::default();
+        indexers.apply_env(|key| match key {
+            "ENABLE_MAINNET_B" => Some("0".to_string()),
+            "ENABLE_DELEGATION_MAPPINGS" => Some("false".to_string()),
+            "ENABLE_EXPLORER" => Some("maybe".to_string()),
+            "ENABLE_EVM_VERIFY" => Some("1".to_string()),
+            "ENABLE_MINTING_REPORTS" => Some("1".to_string()),
+            _ => None,
+        });
+        assert!(indexers.explorer, "invalid values are ignored");
+        assert!(indexers.mainnet_a() && !indexers.mainnet_b());
+        assert!(indexers.oracles() && !indexers.delegation_mappings());
+        assert!(indexers.evm_verify() && !IndexerConfig::default().evm_verify());
+        assert!(indexers.minting_reports() && !IndexerConfig::default().minting_reports());
+
+        // atlas.toml `flp = false` turns off both flp components
+        indexers.flp = false;
+        assert!(!indexers.oracles() && !indexers.evm_verify());
+        assert_eq!(indexers.enabled(), ["explorer", "mainnet_a", "ao", "pi"]);
+    }
+
+    #[test]
+    fn page_size_is_validated_test() {
+        assert_eq!(parse_page_size(None), GQL_DEFAULT_PAGE_SIZE);
+        assert_eq!(parse_page_size(Some(" 500 ".into())), 500);
+        assert_eq!(parse_page_size(Some("1000".into())), 1000);
+        assert_eq!(parse_page_size(Some("1001".into())), GQL_DEFAULT_PAGE_SIZE);
+        assert_eq!(parse_page_size(Some("0".into())), GQL_DEFAULT_PAGE_SIZE);
+        assert_eq!(parse_page_size(Some("lots".into())), GQL_DEFAULT_PAGE_SIZE);
+    }
+
+    #[test]
+    fn catch_up_mode_skips_the_block_delay_test() {
+        let pacing = MainnetPacing {
+            block_delay: Duration::from_millis(750),
+            catch_up_blocks: 50,
+        };
+        assert_eq!(pacing.delay(1_000_000, 1_000_051), Duration::ZERO);
+        assert_eq!(pacing.delay(1_000_000, 1_000_050), pacing.block_delay);
+        assert_eq!(pacing.delay(1_000_000, 1_000_003), pacing.block_delay);
+        // a stale tip below the current height is treated as being at tip
+        assert_eq!(pacing.delay(1_000_000, 999_990), pacing.block_delay);
+    }
+}
@@ -1,44 +1,60 @@
-use anyhow::Result;
-use chrono::Utc;
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Utc};
 use common::{
+    amount::{STORED_DECIMALS, TokenAmount},
     ao_token::{
         AoTokenMessageMeta, AoTokenMessagesPage, AoTokenQuery, scan_arweave_block_for_token_msgs,
     },
-    constants::{
-        AO_TOKEN_PROCESS, AO_TOKEN_START, DATA_PROTOCOL_A_START, DATA_PROTOCOL_B_START,
-        PI_TOKEN_PROCESS, PI_TOKEN_START,
+    build_info::BuildInfo,
+    constants::{AO_TOKEN_PROCESS, AO_TOKEN_START, PI_TOKEN_PROCESS, PI_TOKEN_START},
+    delegation::{
+        DelegationMappingMeta, DelegationMappingsPage, get_all_delegation_mappings,
+        get_delegation_mappings,
     },
-    delegation::{DelegationMappingMeta, DelegationMappingsPage, get_delegation_mappings},
-    gateway::get_ar_balance,
-    gql::OracleStakers,
+    evm::oracle_stake,
+    gateway::{RateLimited, circuit_retry_in, get_ar_balance},
+    gql::{Oracle, OracleStakers},
+    height::{BlockHeight, to_block_height},
     mainnet::{
-        DataProtocol, MainnetBlockMessagesMeta, MainnetBlockMessagesPage, get_network_height,
+        DataProtocol, MainnetBlockMessagesMeta, MainnetBlockMessagesPage, Tag, get_network_height,
         scan_arweave_block_for_msgs,
     },
-    projects::Project,
+    minting::get_flp_own_minting_report,
+    projects::{INTERNAL_PI_PID, Project},
 };
 use flp::{
-    csv_parser::{parse_delegation_mappings_res, parse_flp_balances_setting_res},
-    types::{DelegationsRes, MAX_FACTOR, SetBalancesData},
+    csv_parser::{BalancesReader, parse_delegation_mappings_res, stream_flp_balances},
+    error::FlpError,
+    json_parser::parse_own_minting_report,
+    types::{DelegationsRes, MAX_FACTOR, OwnMintingReport, SetBalancesData, WalletDelegations},
     wallet::get_wallet_delegations,
 };
 use futures::{StreamExt, stream};
 use rust_decimal::{Decimal, prelude::FromPrimitive};
-use serde_json::to_string;
-use std::str::FromStr;
+use serde_json::{from_str, to_string};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::Read,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 use tokio::{
     runtime::Handle,
-    time::{Duration, sleep},
+    sync::{Mutex as AsyncMutex, OwnedMutexGuard},
+    time::{Duration, MissedTickBehavior, sleep},
 };
 
 use crate::{
     clickhouse::{
         AoTokenBlockStateRow, AoTokenMessageRow, AoTokenMessageTagRow, AtlasExplorerRow,
-        Clickhouse, DelegationMappingRow, FlpPositionRow, MainnetBlockStateRow, MainnetExplorerRow,
-        MainnetMessageRow, MainnetMessageTagRow, OracleSnapshotRow, WalletBalanceRow,
-        WalletDelegationRow,
+        Clickhouse, DelegationMappingRow, FlpMintingReportRow, FlpPositionRow,
+        MainnetBlockMetricRow, MainnetBlockStateRow, MainnetExplorerRow, MainnetMessageRow,
+        MainnetMessageTagRow, OracleDivergenceRow, OracleEvmTotalRow, OracleSnapshotRow,
+        StoredBalanceRow, WalletBalanceRow, WalletDelegationRow,
     },
-    config::Config,
+    config::{Config, MainnetPacing},
 };
 // use explorer;
 
@@ -48,72 +64,198 @@ const ARWEAVE_TIP_SAFE_GAP: u64 = 3;
 struct TokenConfig {
     label: &'static str,
     process_id: &'static str,
-    start_height: u32,
+    start_height: BlockHeight,
+    page_size: u32,
+}
+
+/// one async lock per oracle ticker, so overlapping cycles can't both pass
+/// the `has_oracle` check for a tx before either stored its snapshot
+#[derive(Default)]
+struct TickerLocks {
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl TickerLocks {
+    async fn lock(&self, ticker: &str) -> OwnedMutexGuard<()> {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(ticker.to_string())
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
 }
 
 pub struct Indexer {
     config: Config,
     clickhouse: Clickhouse,
+    ticker_locks: TickerLocks,
 }
 
 impl Indexer {
     pub fn new(config: Config, clickhouse: Clickhouse) -> Self {
-        Indexer { config, clickhouse }
+        Indexer {
+            config,
+            clickhouse,
+            ticker_locks: TickerLocks::default(),
+        }
     }
 
     pub async fn run(&self) -> Result<()> {
         self.clickhouse.ensure().await?;
+        println!("{}", BuildInfo::new("atlas-indexer"));
+        println!("config: {}", self.config.summary());
         // self.reindex_mainnet_gap(1_821_500).await?;
         if self.config.indexers.explorer {
             self.spawn_explorer_bridge().await?;
         }
-        if self.config.indexers.mainnet {
+        if self.config.indexers.mainnet_a() || self.config.indexers.mainnet_b() {
             self.spawn_mainnet_indexer().await?;
         }
-        if self.config.indexers.explorer {
+        // the mainnet explorer metrics come from the tags table: rebuilding it
+        // without tags would replace the stored rollup with zeroed counts
+        if self.config.indexers.explorer && self.config.index_message_tags {
             self.rebuild_mainnet_explorer().await?;
             self.spawn_mainnet_explorer_tail().await?;
+        } else if self.config.indexers.explorer {
+            println!("mainnet explorer disabled, INDEX_MESSAGE_TAGS is off");
         }
         if self.config.indexers.ao || self.config.indexers.pi {
             self.spawn_ao_token_indexer().await?;
         }
         // self.spawn_backfill();
-        if self.config.indexers.flp {
+        if self.config.indexers.evm_verify() && self.config.evm_verify_rpc().is_none() {
+            println!("evm verify disabled, EVM_RPC_URL is not set");
+        }
+        let cycle = self.config.indexers.oracles()
+            || self.config.indexers.delegation_mappings()
+            || self.config.indexers.minting_reports()
+            || self.config.evm_verify_rpc().is_some();
+        if self.config.indexers.oracles() {
             println!("indexer ready with tickers {:?}", self.config.tickers);
         } else {
             println!("indexer ready");
         }
-        if self.config.indexers.flp {
-            if let Err(err) = self.run_once().await {
-                eprintln!("index cycle error: {err:?}");
-            }
-            let mut interval = tokio::time::interval(self.config.interval);
-            loop {
-                println!("waiting {:?}", self.config.interval);
-                interval.tick().await;
-                println!("starting new cycle");
+        if cycle {
+            run_cycles(self.config.interval, || async {
                 if let Err(err) = self.run_once().await {
                     eprintln!("index cycle error: {err:?}");
                 }
-            }
+            })
+            .await;
         }
         futures::future::pending::<()>().await;
         Ok(())
     }
 
     async fn run_once(&self) -> Result<()> {
-        if self.config.indexers.flp {
-            if let Err(err) = self.index_delegation_mappings().await {
-                eprintln!("delegation mapping error: {err:?}");
-            }
+        if self.config.indexers.delegation_mappings()
+            && let Err(err) = self.index_delegation_mappings().await
+        {
+            eprintln!("delegation mapping error: {err:?}");
         }
-        if self.config.indexers.flp {
+        if self.config.indexers.oracles() {
             for ticker in &self.config.tickers {
                 if let Err(err) = self.index_ticker(ticker).await {
                     eprintln!("ticker {ticker} error: {err:?}");
                 }
             }
         }
+        if let Some(rpc_url) = self.config.evm_verify_rpc() {
+            for ticker in &self.config.tickers {
+                if let Err(err) = self.index_evm_total(rpc_url, ticker).await {
+                    eprintln!("ticker {ticker} evm total error: {err:?}");
+                }
+            }
+        }
+        if self.config.indexers.minting_reports() {
+            // the internal PI target is not an FLP process and mints nothing
+            for project in Project::get_all()
+                .into_iter()
+                .filter(|project| project.pid != INTERNAL_PI_PID)
+            {
+                if let Err(err) = self.index_minting_report(&project.pid).await {
+                    eprintln!("project {} minting report error: {err:?}", project.pid);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// stores the project's latest Own-Minting-Report unless it already is
+    async fn index_minting_report(&self, project: &str) -> Result<()> {
+        let report_id = latest_minting_report(project.to_string()).await?;
+        if self
+            .clickhouse
+            .has_minting_report(project, &report_id)
+            .await?
+        {
+            return Ok(());
+        }
+        let report = load_minting_report(report_id).await?;
+        let row = minting_report_row(project, report)?;
+        println!(
+            "project {project}: minting report {} tick {} minted {}",
+            row.report_id, row.distribution_tick, row.total_minted
+        );
+        self.clickhouse.insert_minting_reports(&[row]).await
+    }
+
+    /// stores the stake the ticker's staking contract holds on chain and how
+    /// far the balances its oracle last reported on AO are from it. an
+    /// unreachable RPC fails before anything is compared.
+    async fn index_evm_total(&self, rpc_url: &str, ticker: &str) -> Result<()> {
+        let metadata = Oracle::from_ticker(ticker).metadata()?;
+        let rpc_url = rpc_url.to_string();
+        let oracle = metadata.clone();
+        let stake = tokio::task::spawn_blocking(move || oracle_stake(&rpc_url, &oracle)).await??;
+        self.clickhouse
+            .insert_evm_totals(&[OracleEvmTotalRow {
+                ts: Utc::now(),
+                ticker: ticker.to_string(),
+                staking_address: metadata.evm_address,
+                token_address: metadata.evm_token_address,
+                evm_block: stake.block,
+                total: stake.total,
+            }])
+            .await?;
+        println!(
+            "ticker {ticker}: evm stake {} at block {}",
+            stake.total, stake.block
+        );
+        let Some(reported) = self.clickhouse.latest_oracle_total(ticker).await? else {
+            println!("ticker {ticker}: no oracle total stored yet, skipping divergence check");
+            return Ok(());
+        };
+        let tolerance = self.config.evm_divergence_tolerance;
+        let divergence = divergence(reported.total, stake.total);
+        let diverged = divergence > tolerance;
+        if diverged {
+            eprintln!(
+                "ticker {ticker}: oracle tx {} reports {} staked but the evm contract holds {} at block {} ({:.4}% apart, tolerance {:.4}%)",
+                reported.tx_id,
+                reported.total,
+                stake.total,
+                stake.block,
+                divergence * 100.0,
+                tolerance * 100.0
+            );
+        }
+        self.clickhouse
+            .insert_divergences(&[OracleDivergenceRow {
+                ts: Utc::now(),
+                ticker: ticker.to_string(),
+                oracle_tx_id: reported.tx_id,
+                evm_block: stake.block,
+                ao_total: reported.total,
+                evm_total: stake.total,
+                divergence,
+                tolerance,
+                diverged,
+            }])
+            .await?;
         Ok(())
     }
 
@@ -125,15 +267,38 @@ impl Indexer {
             .unwrap_or_else(|| explorer::update_stats_gap::LATEST_AGG_STATS_SET.clone());
         let clickhouse = self.clickhouse.clone();
         let handle = Handle::current();
+        let poll = self.config.explorer_poll;
+        let batcher = RefCell::new(RowBatcher::new(
+            self.config.explorer_batch_size,
+            self.config.explorer_flush_interval,
+        ));
         std::thread::spawn(move || {
-            if let Err(err) = explorer::run_stats_indexer_from(start, |stats| {
-                let row = match AtlasExplorerRow::from_block_stats(stats) {
-                    Some(row) => row,
-                    None => return Ok(()),
-                };
-                let rows = [row];
-                handle.block_on(async { clickhouse.insert_explorer_stats(&rows).await })
-            }) {
+            // the resume point is the latest stored row, so it only moves once a
+            // batch is actually written
+            let insert = |rows: &[AtlasExplorerRow]| {
+                retry_insert(INSERT_MAX_RETRIES, INSERT_BASE_BACKOFF, || {
+                    handle.block_on(clickhouse.insert_explorer_stats(rows))
+                })
+            };
+            let res = explorer::run_stats_indexer_with_idle(
+                start,
+                poll,
+                |stats| {
+                    let Some(row) = AtlasExplorerRow::from_block_stats(stats) else {
+                        return Ok(());
+                    };
+                    store_block_row(&mut batcher.borrow_mut(), row, |row| row.height, insert)
+                },
+                // caught up with the tip: don't hold blocks back
+                || {
+                    let mut batcher = batcher.borrow_mut();
+                    match batcher.take() {
+                        Some(rows) => flush_batch(&mut batcher, rows, insert),
+                        None => Ok(()),
+                    }
+                },
+            );
+            if let Err(err) = res {
                 eprintln!("atlas explorer indexer error: {err:?}");
             }
         });
@@ -148,13 +313,44 @@ impl Indexer {
     // }
 
     async fn spawn_mainnet_indexer(&self) -> Result<()> {
-        for (protocol, start) in [
-            (DataProtocol::A, DATA_PROTOCOL_A_START),
-            (DataProtocol::B, DATA_PROTOCOL_B_START),
-        ] {
+        let starts: Vec<(DataProtocol, BlockHeight)> = [
+            (
+                DataProtocol::A,
+                self.config.mainnet_a_start,
+                self.config.indexers.mainnet_a(),
+            ),
+            (
+                DataProtocol::B,
+                self.config.mainnet_b_start,
+                self.config.indexers.mainnet_b(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(protocol, start, enabled)| enabled.then_some((protocol, start)))
+        .collect();
+        match fetch_network_height().await {
+            Ok(tip) => {
+                for &(protocol, start) in &starts {
+                    if u64::from(start) > tip {
+                        bail!(
+                            "mainnet protocol {} start height {start} is above network tip {tip}",
+                            protocol_label(protocol)
+                        );
+                    }
+                }
+            }
+            Err(err) => eprintln!("mainnet start height validation skipped, tip error {err:?}"),
+        }
+        let index_tags = self.config.index_message_tags;
+        let page_size = self.config.gql_page_size;
+        let pacing = self.config.mainnet_pacing;
+        for (protocol, start) in starts {
             let clickhouse = self.clickhouse.clone();
             tokio::spawn(async move {
-                if let Err(err) = run_mainnet_worker(clickhouse, protocol, start).await {
+                if let Err(err) =
+                    run_mainnet_worker(clickhouse, protocol, start, index_tags, page_size, pacing)
+                        .await
+                {
                     eprintln!(
                         "mainnet indexer error protocol={} start={} err={err:?}",
                         protocol_label(protocol),
@@ -173,6 +369,7 @@ impl Indexer {
                 label: "ao",
                 process_id: AO_TOKEN_PROCESS,
                 start_height: AO_TOKEN_START,
+                page_size: self.config.gql_page_size,
             });
         }
         if self.config.indexers.pi {
@@ -180,6 +377,7 @@ impl Indexer {
                 label: "pi",
                 process_id: PI_TOKEN_PROCESS,
                 start_height: PI_TOKEN_START,
+                page_size: self.config.gql_page_size,
             });
         }
         for token in tokens {
@@ -198,43 +396,17 @@ impl Indexer {
 
     async fn rebuild_mainnet_explorer(&self) -> Result<()> {
         println!("rebuilding ao mainnet explorer table from scratch");
-        self.clickhouse.truncate_mainnet_explorer().await?;
-        let mut last_height: u32 = 0;
-        let mut tx_roll: u64 = 0;
-        let mut proc_roll: u64 = 0;
-        let mut mod_roll: u64 = 0;
-        loop {
-            let metrics = self
-                .clickhouse
-                .fetch_mainnet_block_metrics(last_height, 512)
-                .await?;
-            if metrics.is_empty() {
-                break;
-            }
-            let mut rows = Vec::with_capacity(metrics.len());
-            for metric in metrics {
-                last_height = metric.height;
-                tx_roll += metric.tx_count;
-                proc_roll += metric.new_process_count;
-                mod_roll += metric.new_module_count;
-                rows.push(MainnetExplorerRow {
-                    ts: metric.ts,
-                    height: metric.height as u64,
-                    tx_count: metric.tx_count,
-                    eval_count: metric.eval_count,
-                    transfer_count: metric.transfer_count,
-                    new_process_count: metric.new_process_count,
-                    new_module_count: metric.new_module_count,
-                    active_users: metric.active_users,
-                    active_processes: metric.active_processes,
-                    tx_count_rolling: tx_roll,
-                    processes_rolling: proc_roll,
-                    modules_rolling: mod_roll,
-                });
-            }
-            self.clickhouse.insert_mainnet_explorer_rows(&rows).await?;
-            println!("mainnet explorer indexed up to height {last_height}");
-        }
+        // readers keep the previous table until the rebuilt one is swapped in
+        self.clickhouse.create_mainnet_explorer_tmp().await?;
+        let clickhouse = &self.clickhouse;
+        rebuild_explorer_pages(
+            |after_height| {
+                clickhouse.fetch_mainnet_block_metrics(after_height, MAINNET_EXPLORER_PAGE)
+            },
+            |rows| async move { clickhouse.insert_mainnet_explorer_tmp_rows(&rows).await },
+        )
+        .await?;
+        self.clickhouse.swap_mainnet_explorer_tmp().await?;
         println!("ao mainnet explorer rebuild complete");
         Ok(())
     }
@@ -259,25 +431,104 @@ impl Indexer {
     //     });
     // }
 
+    /// held from the `has_oracle` check until the snapshot is stored
     async fn index_ticker(&self, ticker: &str) -> Result<()> {
+        let _guard = self.ticker_locks.lock(ticker).await;
         let now = Utc::now();
         let ticker_owned = ticker.to_string();
-        let (tx_id, balances) = load_balances(ticker_owned.clone()).await?;
+        let tx_id = latest_balances_tx(ticker_owned.clone()).await?;
         if self.clickhouse.has_oracle(&ticker_owned, &tx_id).await? {
             println!("ticker {ticker}: tx {tx_id} already processed, skipping");
             return Ok(());
         }
         println!("ticker {ticker}: loading balances");
-        println!("ticker {ticker}: balances {}", balances.len());
+        self.index_oracle_tx(ticker, &tx_id, now, || open_balances(tx_id.clone()))
+            .await?;
+        Ok(())
+    }
+
+    /// stores the balances, delegations and positions of one Set-Balances tx,
+    /// then the oracle snapshot marking it processed. a tx without any staker
+    /// row (data not propagated yet, or nothing parseable) is not recorded, so
+    /// the next cycle retries it instead of storing a phantom empty cycle.
+    /// returns whether the snapshot was stored.
+    ///
+    /// `open` is called twice: a counting pass refuses a tx over `max_stakers`
+    /// before anything is stored, then the CSV is re-opened and indexed one
+    /// chunk at a time, so only a chunk is ever held in memory.
+    async fn index_oracle_tx<F, Fut>(
+        &self,
+        ticker: &str,
+        tx_id: &str,
+        now: DateTime<Utc>,
+        open: F,
+    ) -> Result<bool>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<BalancesStream>>,
+    {
+        let (counted, stakers) = count_balances(open().await?, self.config.max_stakers).await?;
+        if stakers > self.config.max_stakers {
+            bail!(
+                "ticker {ticker}: tx {tx_id} has more than {} stakers, refusing to index it",
+                self.config.max_stakers
+            );
+        }
+        if stakers == 0 {
+            counted.skipped().log(tx_id);
+            eprintln!(
+                "ticker {ticker}: tx {tx_id} has no balances (empty or not propagated yet), retrying next cycle"
+            );
+            return Ok(false);
+        }
+        let mut balances = open().await?;
+        let mut total = TokenAmount::ZERO;
+        let (mut balances_count, mut delegations_count, mut positions_count) = (0, 0, 0);
+        loop {
+            let (rest, chunk) =
+                next_balances_chunk(balances, self.config.balances_chunk_size).await?;
+            balances = rest;
+            if chunk.is_empty() {
+                break;
+            }
+            total += chunk
+                .iter()
+                .filter_map(|entry| normalize_amount(&entry.amount, ticker).ok())
+                .sum();
+            let (b, d, p) = self.index_balances_chunk(ticker, tx_id, now, chunk).await?;
+            balances_count += b;
+            delegations_count += d;
+            positions_count += p;
+        }
+        balances.skipped().log(tx_id);
+        println!("ticker {ticker}: balances {stakers}");
+        // marks the tx as processed, so only once every chunk is stored
         self.clickhouse
             .insert_oracles(&[OracleSnapshotRow {
                 ts: now,
-                ticker: ticker_owned.clone(),
-                tx_id: tx_id.clone(),
+                ticker: ticker.to_string(),
+                tx_id: tx_id.to_string(),
+                total,
             }])
             .await?;
+        println!(
+            "ticker {ticker}: stored balances {balances_count} delegations {delegations_count} positions {positions_count}"
+        );
+        Ok(true)
+    }
 
-        let pairs: Vec<(SetBalancesData, Option<DelegationsRes>, Decimal)> =
+    /// resolves delegations and AR balances for a chunk of stakers and stores
+    /// their rows. returns the (balances, delegations, positions) counts.
+    async fn index_balances_chunk(
+        &self,
+        ticker: &str,
+        tx_id: &str,
+        now: DateTime<Utc>,
+        balances: Vec<SetBalancesData>,
+    ) -> Result<(usize, usize, usize)> {
+        let ticker_owned = ticker.to_string();
+        let tx_id = tx_id.to_string();
+        let pairs: Vec<(SetBalancesData, Option<DelegationsRes>, Option<Decimal>)> =
             stream::iter(balances.into_iter().map(|entry| async move {
                 let delegation = load_delegations(entry.ar_address.clone()).await;
                 let ar_balance = load_ar_balance(entry.ar_address.clone()).await;
@@ -294,78 +545,108 @@ impl Indexer {
         let mut position_rows = Vec::new();
 
         for (entry, delegation, ar_balance) in pairs {
-            let Some(delegation) = delegation else {
+            // on fetch failure carry the wallet's last indexed value forward
+            // (flagged as stale) instead of writing sentinel data
+            let previous_delegation = match delegation {
+                Some(_) => None,
+                None => self
+                    .clickhouse
+                    .latest_wallet_delegation(&entry.ar_address)
+                    .await?
+                    .and_then(|payload| from_str::<DelegationsRes>(&payload).ok()),
+            };
+            let Some((delegation, delegation_stale)) =
+                carry_forward(delegation, previous_delegation)
+            else {
                 continue;
             };
-            let Some(amount_dec) = normalize_amount(&entry.amount, &ticker_owned) else {
+            let previous_ar_balance = match ar_balance {
+                Some(_) => None,
+                None => self
+                    .clickhouse
+                    .latest_wallet_ar_balance(&ticker_owned, &entry.ar_address)
+                    .await?
+                    .and_then(|value| Decimal::from_str(&value).ok()),
+            };
+            let Some((ar_balance, ar_balance_stale)) =
+                carry_forward(ar_balance, previous_ar_balance)
+            else {
                 continue;
             };
-            let amount_str = amount_dec.to_string();
-            let ar_balance_str = ar_balance.to_string();
-            balance_rows.push(WalletBalanceRow {
-                ts: now,
-                ticker: ticker_owned.clone(),
-                wallet: entry.ar_address.clone(),
-                eoa: entry.eoa.clone(),
-                amount: amount_str.clone(),
-                ar_balance: ar_balance_str.clone(),
-                tx_id: tx_id.clone(),
-            });
-            delegation_rows.push(WalletDelegationRow {
-                ts: now,
-                wallet: entry.ar_address.clone(),
-                payload: to_string(&delegation)?,
-            });
-            for pref in delegation.delegation_prefs {
-                if Project::is_flp_project(&pref.wallet_to) {
-                    let delegated = delegated_amount(&amount_dec, pref.factor);
-                    let delegated_ar = delegated_amount(&ar_balance, pref.factor);
-                    // if the delegator had interacted with the FLP Bridge, have no more staked LSTs
-                    // but still delegating AR, track them
-                    if delegated.is_zero() && delegated_ar.is_zero() {
-                        continue;
-                    }
-                    position_rows.push(FlpPositionRow {
-                        ts: now,
-                        ticker: ticker_owned.clone(),
-                        wallet: entry.ar_address.clone(),
-                        eoa: entry.eoa.clone(),
-                        project: pref.wallet_to,
-                        factor: pref.factor,
-                        amount: delegated.to_string(),
-                        ar_amount: delegated_ar.to_string(),
-                    });
+            let amount = match normalize_amount(&entry.amount, &ticker_owned) {
+                Ok(amount) => amount,
+                Err(err) => {
+                    eprintln!(
+                        "ticker {ticker}: skipping wallet {}: {err}",
+                        entry.ar_address
+                    );
+                    continue;
                 }
-            }
+            };
+            let rows = staker_rows(
+                &ticker_owned,
+                &tx_id,
+                now,
+                entry,
+                amount,
+                (delegation, delegation_stale),
+                (ar_balance, ar_balance_stale),
+            )?;
+            balance_rows.push(rows.balance);
+            delegation_rows.push(rows.delegation);
+            position_rows.extend(rows.positions);
         }
 
+        let wallets: Vec<String> = balance_rows.iter().map(|row| row.wallet.clone()).collect();
+        let previous = self
+            .clickhouse
+            .latest_wallet_balances(&ticker_owned, &wallets)
+            .await?;
+        let balance_rows = changed_balances(balance_rows, previous);
         self.clickhouse.insert_balances(&balance_rows).await?;
         self.clickhouse.insert_delegations(&delegation_rows).await?;
         self.clickhouse.insert_positions(&position_rows).await?;
-        println!(
-            "ticker {ticker}: stored balances {} delegations {} positions {}",
+        Ok((
             balance_rows.len(),
             delegation_rows.len(),
-            position_rows.len()
-        );
-        Ok(())
+            position_rows.len(),
+        ))
     }
 
     async fn index_delegation_mappings(&self) -> Result<()> {
         let page = fetch_latest_mapping_page(1).await?;
-        let Some(meta) = page.mappings.into_iter().next() else {
+        let Some(latest) = page.mappings.into_iter().next() else {
             return Ok(());
         };
-        if self.clickhouse.has_delegation_mapping(&meta.tx_id).await? {
+        if self
+            .clickhouse
+            .has_delegation_mapping(&latest.tx_id)
+            .await?
+        {
             return Ok(());
         }
-        println!(
-            "forward delegation mapping tx {} height {}",
-            meta.tx_id, meta.height
-        );
-        if let Err(err) = self.store_delegation_mapping(&meta).await {
-            eprintln!("forward delegation mapping tx {} error {err:?}", meta.tx_id);
-        } else {
+        // more than one mapping can land between two runs, so page through
+        // everything above the newest stored one. an empty table only takes
+        // the latest, history is the backfill's job
+        let pending = match self.clickhouse.latest_delegation_mapping_height().await? {
+            Some(stored) if stored < latest.height => {
+                fetch_mappings_between(stored + 1, latest.height).await?
+            }
+            _ => vec![latest],
+        };
+        // oldest first, so the stored heights keep increasing
+        for meta in pending.iter().rev() {
+            if self.clickhouse.has_delegation_mapping(&meta.tx_id).await? {
+                continue;
+            }
+            println!(
+                "forward delegation mapping tx {} height {}",
+                meta.tx_id, meta.height
+            );
+            if let Err(err) = self.store_delegation_mapping(meta).await {
+                eprintln!("forward delegation mapping tx {} error {err:?}", meta.tx_id);
+                continue;
+            }
             println!(
                 "forward delegation mapping stored tx {} height {}",
                 meta.tx_id, meta.height
@@ -381,30 +662,360 @@ impl Indexer {
     }
 }
 
-fn normalize_amount(amount: &str, ticker: &str) -> Option<Decimal> {
-    let amt = Decimal::from_str(amount).ok()?;
-    Some((amt / ticker_scale(ticker)).normalize())
+/// buffers rows so catch-up writes one insert per batch instead of one per
+/// block. a batch is handed out once it is full or its oldest row has waited
+/// `max_wait`.
+struct RowBatcher<T> {
+    rows: Vec<T>,
+    batch_size: usize,
+    max_wait: Duration,
+    oldest: Option<Instant>,
 }
 
-// all 3 oracles tokens are 18 decimals
-fn ticker_scale(ticker: &str) -> Decimal {
-    let key = ticker.to_ascii_lowercase();
-    match key.as_str() {
-        "usds" | "dai" | "steth" => Decimal::from_str("1000000000000000000").unwrap(),
-        _ => Decimal::ONE,
+impl<T> RowBatcher<T> {
+    fn new(batch_size: usize, max_wait: Duration) -> Self {
+        Self {
+            rows: Vec::with_capacity(batch_size),
+            batch_size: batch_size.max(1),
+            max_wait,
+            oldest: None,
+        }
+    }
+
+    /// buffers `row` and returns the batch to flush, if it is due
+    fn push(&mut self, row: T) -> Option<Vec<T>> {
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+        self.rows.push(row);
+        if self.rows.len() >= self.batch_size || oldest.elapsed() >= self.max_wait {
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    /// drains whatever is buffered
+    fn take(&mut self) -> Option<Vec<T>> {
+        self.oldest = None;
+        if self.rows.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.rows))
+        }
+    }
+
+    /// puts a batch that failed to insert back in front of the buffer
+    fn restore(&mut self, mut rows: Vec<T>) {
+        rows.append(&mut self.rows);
+        self.rows = rows;
+        self.oldest = Some(Instant::now());
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.rows.last()
     }
 }
 
-fn delegated_amount(amount: &Decimal, factor: u32) -> Decimal {
-    (amount * Decimal::from(factor) / Decimal::from(MAX_FACTOR)).normalize()
+/// retries of a failed ClickHouse insert before giving up on the batch
+const INSERT_MAX_RETRIES: u32 = 3;
+const INSERT_BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// runs `insert` until it succeeds or `max_retries` retries are exhausted. the
+/// delay doubles after each failed attempt.
+fn retry_insert<F>(max_retries: u32, base_delay: Duration, mut insert: F) -> Result<()>
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut attempt = 0;
+    loop {
+        match insert() {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt < max_retries => {
+                eprintln!("insert attempt {} failed: {err:?}", attempt + 1);
+                std::thread::sleep(base_delay * 2u32.pow(attempt));
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
 }
 
-async fn load_balances(ticker: String) -> Result<(String, Vec<SetBalancesData>)> {
-    tokio::task::spawn_blocking(move || -> Result<(String, Vec<SetBalancesData>)> {
+/// buffers the row of one block and inserts the batch once it is due. the
+/// stats loop hands a block over again when this fails, and its row is then
+/// still buffered: the retry only re-runs the insert.
+fn store_block_row<T>(
+    batcher: &mut RowBatcher<T>,
+    row: T,
+    height: impl Fn(&T) -> u64,
+    insert: impl FnMut(&[T]) -> Result<()>,
+) -> Result<()> {
+    let due = if batcher
+        .last()
+        .is_some_and(|last| height(last) >= height(&row))
+    {
+        batcher.take()
+    } else {
+        batcher.push(row)
+    };
+    match due {
+        Some(rows) => flush_batch(batcher, rows, insert),
+        None => Ok(()),
+    }
+}
+
+/// inserts `rows`, keeping them buffered if that fails so no block is lost
+fn flush_batch<T>(
+    batcher: &mut RowBatcher<T>,
+    rows: Vec<T>,
+    mut insert: impl FnMut(&[T]) -> Result<()>,
+) -> Result<()> {
+    if let Err(err) = insert(&rows) {
+        batcher.restore(rows);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// keeps the rows whose eoa, amount or AR balance differ from the wallet's
+/// last stored balance, so `wallet_balances` only records real changes. the
+/// oracle `tx_id` is new every cycle and doesn't count as a change.
+fn changed_balances(
+    rows: Vec<WalletBalanceRow>,
+    previous: Vec<StoredBalanceRow>,
+) -> Vec<WalletBalanceRow> {
+    let previous: HashMap<String, StoredBalanceRow> = previous
+        .into_iter()
+        .map(|row| (row.wallet.clone(), row))
+        .collect();
+    let same = |stored: &str, current: &TokenAmount| {
+        Decimal::from_str(stored).is_ok_and(|stored| stored == current.value())
+    };
+    rows.into_iter()
+        .filter(|row| match previous.get(&row.wallet) {
+            Some(stored) => {
+                stored.eoa != row.eoa
+                    || !same(&stored.amount, &row.amount)
+                    || !same(&stored.ar_balance, &row.ar_balance)
+            }
+            None => true,
+        })
+        .collect()
+}
+
+/// what one staker of a Set-Balances tx stores
+struct StakerRows {
+    balance: WalletBalanceRow,
+    delegation: WalletDelegationRow,
+    positions: Vec<FlpPositionRow>,
+}
+
+/// builds a staker's rows from its resolved delegation and AR balance, each
+/// paired with whether it was carried forward after a failed fetch
+fn staker_rows(
+    ticker: &str,
+    tx_id: &str,
+    now: DateTime<Utc>,
+    entry: SetBalancesData,
+    amount: TokenAmount,
+    (delegation, delegation_stale): (DelegationsRes, bool),
+    (ar_balance, ar_balance_stale): (Decimal, bool),
+) -> Result<StakerRows> {
+    let balance = WalletBalanceRow {
+        ts: now,
+        ticker: ticker.to_string(),
+        wallet: entry.ar_address.clone(),
+        eoa: entry.eoa.clone(),
+        amount,
+        ar_balance: TokenAmount::new(ar_balance),
+        tx_id: tx_id.to_string(),
+        stale: ar_balance_stale,
+    };
+    let delegation_row = WalletDelegationRow {
+        ts: now,
+        wallet: entry.ar_address.clone(),
+        payload: to_string(&delegation)?,
+        stale: delegation_stale,
+    };
+    let mut positions = Vec::new();
+    let base = allocation_base(&delegation.delegation_prefs);
+    for pref in delegation.delegation_prefs {
+        if Project::is_flp_project(&pref.wallet_to) {
+            if pref.factor > MAX_FACTOR {
+                eprintln!(
+                    "ticker {ticker}: wallet {} factor {} to {} exceeds {MAX_FACTOR}, clamping",
+                    entry.ar_address, pref.factor, pref.wallet_to
+                );
+            }
+            let factor = pref.factor.min(MAX_FACTOR);
+            let delegated = delegated_amount(&amount.value(), factor, base);
+            let delegated_ar = delegated_amount(&ar_balance, factor, base);
+            // if the delegator had interacted with the FLP Bridge, have no more staked LSTs
+            // but still delegating AR, track them
+            if delegated.is_zero() && delegated_ar.is_zero() {
+                continue;
+            }
+            positions.push(FlpPositionRow {
+                ts: now,
+                ticker: ticker.to_string(),
+                wallet: entry.ar_address.clone(),
+                eoa: entry.eoa.clone(),
+                project: pref.wallet_to,
+                factor,
+                amount: TokenAmount::new(delegated),
+                ar_amount: TokenAmount::new(delegated_ar),
+            });
+        }
+    }
+    Ok(StakerRows {
+        balance,
+        delegation: delegation_row,
+        positions,
+    })
+}
+
+/// picks the freshly fetched value, falling back to the previously indexed one.
+/// the returned flag is true when the value was carried forward.
+fn carry_forward<T>(fetched: Option<T>, previous: Option<T>) -> Option<(T, bool)> {
+    match (fetched, previous) {
+        (Some(value), _) => Some((value, false)),
+        (None, Some(value)) => Some((value, true)),
+        (None, None) => None,
+    }
+}
+
+/// gap between the AO reported and on-chain totals relative to the latter.
+/// with nothing staked on chain any reported stake is entirely divergent.
+fn divergence(reported: TokenAmount, on_chain: TokenAmount) -> f64 {
+    let gap = (reported - on_chain).value().abs();
+    if on_chain.is_zero() {
+        return if gap.is_zero() { 0.0 } else { 1.0 };
+    }
+    TokenAmount::new(gap / on_chain.value().abs()).to_f64()
+}
+
+/// oracle CSV amounts are raw base units
+fn normalize_amount(amount: &str, ticker: &str) -> Result<TokenAmount> {
+    TokenAmount::from_base_units(amount, ticker_decimals(ticker))
+}
+
+// the registered oracle's denomination, 0 for unknown tickers
+fn ticker_decimals(ticker: &str) -> u32 {
+    Oracle::from_ticker(ticker)
+        .metadata()
+        .map_or(0, |metadata| metadata.denomination)
+}
+
+/// denominator used to allocate a wallet's balance across its prefs.
+///
+/// policy: factors are out of `MAX_FACTOR`. when the (clamped) factors of a
+/// wallet sum above `MAX_FACTOR`, every pref is scaled down proportionally by
+/// dividing by the sum instead, so the allocations add up to the balance.
+/// positions store the clamped factor, not the on-chain one.
+fn allocation_base(prefs: &[WalletDelegations]) -> u64 {
+    let total: u64 = prefs
+        .iter()
+        .map(|pref| u64::from(pref.factor.min(MAX_FACTOR)))
+        .sum();
+    total.max(u64::from(MAX_FACTOR))
+}
+
+/// share of `amount` delegated at `factor` out of `base`, never exceeding `amount` itself.
+/// rounded to `STORED_DECIMALS` so the server's decimal sums see the exact value
+fn delegated_amount(amount: &Decimal, factor: u32, base: u64) -> Decimal {
+    let factor = Decimal::from(factor.min(MAX_FACTOR));
+    let base = Decimal::from(base.max(u64::from(MAX_FACTOR)));
+    // for amounts close to Decimal::MAX, divide first to stay in range
+    let delegated = match amount.checked_mul(factor) {
+        Some(product) => product / base,
+        None => amount / base * factor,
+    };
+    delegated.min(*amount).round_dp(STORED_DECIMALS).normalize()
+}
+
+type BalancesStream = BalancesReader<Box<dyn Read + Send>>;
+
+async fn latest_balances_tx(ticker: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || -> Result<String> {
         let oracle = OracleStakers::new(&ticker).build()?.send()?;
-        let tx_id = oracle.clone().last_update()?;
-        let data = parse_flp_balances_setting_res(&tx_id)?;
-        Ok((tx_id, data))
+        oracle.last_update()
+    })
+    .await?
+}
+
+async fn latest_minting_report(project: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || get_flp_own_minting_report(&project)).await?
+}
+
+async fn load_minting_report(report_id: String) -> Result<OwnMintingReport> {
+    tokio::task::spawn_blocking(move || {
+        parse_own_minting_report(&report_id).map_err(FlpError::into_anyhow)
+    })
+    .await?
+}
+
+fn minting_report_row(project: &str, report: OwnMintingReport) -> Result<FlpMintingReportRow> {
+    let report_id = report
+        .report_id
+        .ok_or_else(|| anyhow!("minting report of {project} has no id"))?;
+    let ts = i64::try_from(report.timestamp)
+        .ok()
+        .and_then(DateTime::from_timestamp_millis)
+        .ok_or_else(|| {
+            anyhow!(
+                "minting report {report_id} has an invalid timestamp {}",
+                report.timestamp
+            )
+        })?;
+    Ok(FlpMintingReportRow {
+        ts,
+        project: project.to_string(),
+        report_id,
+        distribution_tick: report.distribution_tick,
+        total_minted: report.total_minted,
+        total_inflow: report.total_inflow,
+        ao_kept: report.ao_kept,
+        ao_exchanged_for_pi: report.ao_exchanged_for_pi,
+    })
+}
+
+async fn open_balances(tx_id: String) -> Result<BalancesStream> {
+    tokio::task::spawn_blocking(move || -> Result<BalancesStream> {
+        stream_flp_balances(&tx_id).map_err(FlpError::into_anyhow)
+    })
+    .await?
+}
+
+/// counts the rows of the (blocking) CSV stream, stopping once past `limit`,
+/// handing the stream back
+async fn count_balances(
+    mut balances: BalancesStream,
+    limit: usize,
+) -> Result<(BalancesStream, usize)> {
+    tokio::task::spawn_blocking(move || {
+        let mut count = 0;
+        while count <= limit {
+            match balances.next() {
+                Some(row) => row.map_err(FlpError::into_anyhow)?,
+                None => break,
+            };
+            count += 1;
+        }
+        Ok((balances, count))
+    })
+    .await?
+}
+
+/// reads up to `size` rows off the (blocking) CSV stream, handing the stream back
+async fn next_balances_chunk(
+    mut balances: BalancesStream,
+    size: usize,
+) -> Result<(BalancesStream, Vec<SetBalancesData>)> {
+    tokio::task::spawn_blocking(move || {
+        let chunk = balances
+            .by_ref()
+            .take(size)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(FlpError::into_anyhow)?;
+        Ok((balances, chunk))
     })
     .await?
 }
@@ -414,16 +1025,20 @@ async fn load_delegations(address: String) -> Option<DelegationsRes> {
     match tokio::task::spawn_blocking(move || get_wallet_delegations(&address)).await {
         Ok(Ok(data)) => Some(data),
         _ => {
-            eprintln!("delegation lookup failed for {fallback}, skipping");
+            eprintln!("delegation lookup failed for {fallback}");
             None
         }
     }
 }
 
-async fn load_ar_balance(address: String) -> Decimal {
+async fn load_ar_balance(address: String) -> Option<Decimal> {
+    let fallback = address.clone();
     match tokio::task::spawn_blocking(move || get_ar_balance(&address)).await {
-        Ok(Ok(value)) => Decimal::from_f64(value).unwrap_or(Decimal::ZERO),
-        _ => Decimal::ZERO,
+        Ok(Ok(value)) => Decimal::from_f64(value),
+        _ => {
+            eprintln!("ar balance lookup failed for {fallback}");
+            None
+        }
     }
 }
 
@@ -431,6 +1046,13 @@ async fn fetch_latest_mapping_page(limit: u32) -> Result<DelegationMappingsPage>
     tokio::task::spawn_blocking(move || get_delegation_mappings(Some(limit), None)).await?
 }
 
+async fn fetch_mappings_between(
+    min_height: BlockHeight,
+    max_height: BlockHeight,
+) -> Result<Vec<DelegationMappingMeta>> {
+    tokio::task::spawn_blocking(move || get_all_delegation_mappings(min_height, max_height)).await?
+}
+
 async fn build_mapping_rows(meta: &DelegationMappingMeta) -> Result<Vec<DelegationMappingRow>> {
     let tx_id = meta.tx_id.clone();
     let height = meta.height;
@@ -438,7 +1060,8 @@ async fn build_mapping_rows(meta: &DelegationMappingMeta) -> Result<Vec<Delegati
         let fetch_id = tx_id.clone();
         move || parse_delegation_mappings_res(&fetch_id)
     })
-    .await??;
+    .await?
+    .map_err(FlpError::into_anyhow)?;
     let ts = Utc::now();
     Ok(csv_rows
         .into_iter()
@@ -453,16 +1076,51 @@ async fn build_mapping_rows(meta: &DelegationMappingMeta) -> Result<Vec<Delegati
         .collect())
 }
 
+/// runs `cycle` every `period`, one at a time. a cycle that overruns its
+/// period is logged and followed right away by a single next cycle, the
+/// other ticks it missed are skipped rather than fired back to back. cycles
+/// are timed on tokio's clock, the same one the interval ticks on
+async fn run_cycles<F, Fut>(period: Duration, mut cycle: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        println!("starting new cycle");
+        let started = tokio::time::Instant::now();
+        cycle().await;
+        let elapsed = started.elapsed();
+        if elapsed > period {
+            eprintln!(
+                "index cycle took {elapsed:?}, longer than its {period:?} interval, consider raising ORACLE_REFRESH_SECS"
+            );
+        } else {
+            println!("waiting {:?}", period - elapsed);
+        }
+    }
+}
+
+/// with `index_tags` off only message rows are stored, `ao_mainnet_message_tags`
+/// isn't written. `pacing` spaces stored pages near tip.
 async fn run_mainnet_worker(
     clickhouse: Clickhouse,
     protocol: DataProtocol,
-    start: u32,
+    start: BlockHeight,
+    index_tags: bool,
+    page_size: u32,
+    pacing: MainnetPacing,
 ) -> Result<()> {
     let protocol_name = protocol_label(protocol).to_string();
     let mut height = start;
     let mut cursor = None;
-    if let Some(state) = clickhouse.fetch_mainnet_block_state(&protocol_name).await? {
-        height = state.last_complete_height.max(start);
+    // a configured start above the persisted state wins, dropping the stale cursor
+    if let Some(state) = clickhouse.fetch_mainnet_block_state(&protocol_name).await?
+        && state.last_complete_height >= start
+    {
+        height = state.last_complete_height;
         if !state.last_cursor.is_empty() {
             cursor = Some(state.last_cursor);
         } else {
@@ -470,23 +1128,23 @@ async fn run_mainnet_worker(
         }
     }
     println!("mainnet protocol {protocol_name} starting at height {height}");
-    let mut network_tip = fetch_network_height().await.unwrap_or(height as u64);
+    let mut network_tip = fetch_network_height().await.unwrap_or(u64::from(height));
     loop {
-        while height as u64 + ARWEAVE_TIP_SAFE_GAP > network_tip {
+        while u64::from(height) + ARWEAVE_TIP_SAFE_GAP > network_tip {
             match fetch_network_height().await {
                 Ok(latest) => network_tip = latest,
                 Err(err) => {
                     eprintln!("mainnet tip fetch error protocol={protocol_name} err={err:?}");
                 }
             }
-            if height as u64 + ARWEAVE_TIP_SAFE_GAP > network_tip {
+            if u64::from(height) + ARWEAVE_TIP_SAFE_GAP > network_tip {
                 println!(
                     "mainnet protocol {protocol_name} waiting, height {height} exceeds tip {network_tip} with gap {ARWEAVE_TIP_SAFE_GAP}"
                 );
                 sleep(Duration::from_secs(60)).await;
             }
         }
-        let page = match fetch_mainnet_page(protocol, height, cursor.clone()).await {
+        let page = match fetch_mainnet_page(protocol, height, cursor.clone(), page_size).await {
             Ok(page) => page,
             Err(err) => {
                 if is_empty_block_error(&err) {
@@ -504,11 +1162,8 @@ async fn run_mainnet_worker(
                     eprintln!(
                         "mainnet fetch error protocol={protocol_name} height={height} err={err:?}"
                     );
-                    let delay = if is_rate_limit_error(&err) {
-                        Duration::from_secs(5)
-                    } else {
-                        Duration::from_secs(1)
-                    };
+                    let delay = rate_limit_backoff(&err, Duration::from_secs(5))
+                        .unwrap_or(Duration::from_secs(1));
                     sleep(delay).await;
                 }
                 continue;
@@ -528,7 +1183,15 @@ async fn run_mainnet_worker(
                 data_size,
                 tags,
             } = meta;
-            let msg_id_for_tags = msg_id.clone();
+            if index_tags {
+                tag_rows.extend(mainnet_tag_rows(
+                    ts,
+                    &protocol_name,
+                    block_height,
+                    &msg_id,
+                    tags,
+                ));
+            }
             message_rows.push(MainnetMessageRow {
                 ts,
                 protocol: protocol_name.clone(),
@@ -540,16 +1203,6 @@ async fn run_mainnet_worker(
                 bundled_in,
                 data_size,
             });
-            for tag in tags {
-                tag_rows.push(MainnetMessageTagRow {
-                    ts,
-                    protocol: protocol_name.clone(),
-                    block_height,
-                    msg_id: msg_id_for_tags.clone(),
-                    tag_key: tag.key,
-                    tag_value: tag.value,
-                });
-            }
         }
         clickhouse.insert_mainnet_messages(&message_rows).await?;
         clickhouse.insert_mainnet_message_tags(&tag_rows).await?;
@@ -574,7 +1227,7 @@ async fn run_mainnet_worker(
         if cursor.is_none() {
             height = height.saturating_add(1);
         }
-        sleep(Duration::from_secs(1)).await;
+        sleep(pacing.delay(height, network_tip)).await;
     }
 }
 
@@ -587,16 +1240,16 @@ async fn run_token_worker(clickhouse: Clickhouse, token: TokenConfig) -> Result<
             .saturating_add(1);
     }
     println!("token indexer {} starting at height {height}", token.label);
-    let mut network_tip = fetch_network_height().await.unwrap_or(height as u64);
+    let mut network_tip = fetch_network_height().await.unwrap_or(u64::from(height));
     loop {
-        while height as u64 + ARWEAVE_TIP_SAFE_GAP > network_tip {
+        while u64::from(height) + ARWEAVE_TIP_SAFE_GAP > network_tip {
             match fetch_network_height().await {
                 Ok(latest) => network_tip = latest,
                 Err(err) => {
                     eprintln!("token {} tip fetch error err={err:?}", token.label);
                 }
             }
-            if height as u64 + ARWEAVE_TIP_SAFE_GAP > network_tip {
+            if u64::from(height) + ARWEAVE_TIP_SAFE_GAP > network_tip {
                 println!(
                     "token {} waiting, height {height} exceeds tip {network_tip} with gap {ARWEAVE_TIP_SAFE_GAP}",
                     token.label
@@ -616,16 +1269,12 @@ async fn run_token_worker(clickhouse: Clickhouse, token: TokenConfig) -> Result<
         {
             Ok(count) => count,
             Err(err) => {
-                if is_rate_limit_error(&err)
-                    || is_timeout_error(&err)
-                    || is_retryable_http_error(&err)
-                    || is_not_found_error(&err)
-                {
+                if let Some(delay) = token_retry_delay(&err) {
                     eprintln!(
                         "token {} transfer query error height={height} err={err:?}",
                         token.label
                     );
-                    sleep(Duration::from_secs(300)).await;
+                    sleep(delay).await;
                     continue;
                 }
                 return Err(err);
@@ -637,16 +1286,12 @@ async fn run_token_worker(clickhouse: Clickhouse, token: TokenConfig) -> Result<
             {
                 Ok(count) => count,
                 Err(err) => {
-                    if is_rate_limit_error(&err)
-                        || is_timeout_error(&err)
-                        || is_retryable_http_error(&err)
-                        || is_not_found_error(&err)
-                    {
+                    if let Some(delay) = token_retry_delay(&err) {
                         eprintln!(
                             "token {} process query error height={height} err={err:?}",
                             token.label
                         );
-                        sleep(Duration::from_secs(300)).await;
+                        sleep(delay).await;
                         continue;
                     }
                     return Err(err);
@@ -670,11 +1315,12 @@ async fn run_token_worker(clickhouse: Clickhouse, token: TokenConfig) -> Result<
 
 pub async fn fetch_mainnet_page(
     protocol: DataProtocol,
-    height: u32,
+    height: BlockHeight,
     cursor: Option<String>,
+    page_size: u32,
 ) -> Result<MainnetBlockMessagesPage> {
     tokio::task::spawn_blocking(move || {
-        scan_arweave_block_for_msgs(protocol, height, cursor.as_deref())
+        scan_arweave_block_for_msgs(protocol, height, cursor.as_deref(), page_size)
     })
     .await?
 }
@@ -682,17 +1328,18 @@ pub async fn fetch_mainnet_page(
 pub async fn fetch_ao_token_page(
     process_id: &'static str,
     query: AoTokenQuery,
-    height: u32,
+    height: BlockHeight,
     cursor: Option<String>,
+    page_size: u32,
 ) -> Result<AoTokenMessagesPage> {
     tokio::task::spawn_blocking(move || {
-        scan_arweave_block_for_token_msgs(process_id, query, height, cursor.as_deref())
+        scan_arweave_block_for_token_msgs(process_id, query, height, cursor.as_deref(), page_size)
     })
     .await?
 }
 
 pub async fn fetch_network_height() -> Result<u64> {
-    tokio::task::spawn_blocking(|| get_network_height()).await?
+    tokio::task::spawn_blocking(get_network_height).await?
 }
 
 pub fn protocol_label(protocol: DataProtocol) -> &'static str {
@@ -708,7 +1355,29 @@ pub fn is_empty_block_error(err: &anyhow::Error) -> bool {
 }
 
 fn is_rate_limit_error(err: &anyhow::Error) -> bool {
-    err.to_string().contains("http status: 429")
+    err.downcast_ref::<RateLimited>().is_some() || err.to_string().contains("http status: 429")
+}
+
+/// the wait after a gateway rate limit: its `Retry-After` (capped) when it sent
+/// one, else `default`. `None` when `err` isn't a rate limit.
+fn rate_limit_backoff(err: &anyhow::Error, default: Duration) -> Option<Duration> {
+    if let Some(limited) = err.downcast_ref::<RateLimited>() {
+        return Some(limited.backoff(default));
+    }
+    is_rate_limit_error(err).then_some(default)
+}
+
+/// token scanner errors worth waiting out, and for how long. calls the
+/// gateway circuit rejected wait for its cooldown.
+fn token_retry_delay(err: &anyhow::Error) -> Option<Duration> {
+    let default = Duration::from_secs(300);
+    if let Some(retry_in) = circuit_retry_in(err) {
+        return Some(retry_in);
+    }
+    rate_limit_backoff(err, default).or_else(|| {
+        (is_timeout_error(err) || is_retryable_http_error(err) || is_not_found_error(err))
+            .then_some(default)
+    })
 }
 
 fn is_timeout_error(err: &anyhow::Error) -> bool {
@@ -736,13 +1405,20 @@ async fn ingest_token_query(
     clickhouse: &Clickhouse,
     token: TokenConfig,
     query: AoTokenQuery,
-    height: u32,
+    height: BlockHeight,
     source: &str,
 ) -> Result<usize> {
     let mut cursor = None;
     let mut total = 0usize;
     loop {
-        let page = fetch_ao_token_page(token.process_id, query, height, cursor.clone()).await?;
+        let page = fetch_ao_token_page(
+            token.process_id,
+            query,
+            height,
+            cursor.clone(),
+            token.page_size,
+        )
+        .await?;
         let ts = Utc::now();
         let mut message_rows = Vec::with_capacity(page.mappings.len());
         let mut tag_rows = Vec::new();
@@ -800,39 +1476,625 @@ async fn ingest_token_query(
 
 async fn run_mainnet_explorer_tail(clickhouse: Clickhouse) -> Result<()> {
     let last_row = clickhouse.latest_mainnet_explorer_row().await?;
-    let mut last_height = last_row.as_ref().map(|r| r.height as u32).unwrap_or(0);
-    let mut tx_roll = last_row.as_ref().map(|r| r.tx_count_rolling).unwrap_or(0);
-    let mut proc_roll = last_row.as_ref().map(|r| r.processes_rolling).unwrap_or(0);
-    let mut mod_roll = last_row.as_ref().map(|r| r.modules_rolling).unwrap_or(0);
+    let mut last_height = match &last_row {
+        Some(row) => to_block_height(row.height)?,
+        None => 0,
+    };
+    let mut totals = MainnetRollingTotals::from_row(last_row.as_ref());
     loop {
         let metrics = clickhouse
-            .fetch_mainnet_block_metrics(last_height, 512)
+            .fetch_mainnet_block_metrics(last_height, MAINNET_EXPLORER_PAGE)
             .await?;
-        if metrics.is_empty() {
+        let Some(last) = metrics.last() else {
             sleep(Duration::from_secs(120)).await;
             continue;
+        };
+        last_height = last.height;
+        let rows = totals.rows(metrics);
+        clickhouse.insert_mainnet_explorer_rows(&rows).await?;
+    }
+}
+
+/// one row per tag, numbered in message order so repeated tags stay distinct
+fn mainnet_tag_rows(
+    ts: DateTime<Utc>,
+    protocol: &str,
+    block_height: BlockHeight,
+    msg_id: &str,
+    tags: Vec<Tag>,
+) -> Vec<MainnetMessageTagRow> {
+    tags.into_iter()
+        .enumerate()
+        .map(|(index, tag)| MainnetMessageTagRow {
+            ts,
+            protocol: protocol.to_string(),
+            block_height,
+            msg_id: msg_id.to_string(),
+            tag_key: tag.key,
+            tag_value: tag.value,
+            tag_index: index as u16,
+        })
+        .collect()
+}
+
+/// block metrics pages read per `fetch_mainnet_block_metrics` call
+const MAINNET_EXPLORER_PAGE: u64 = 512;
+
+/// running totals carried from one mainnet explorer row to the next
+#[derive(Default)]
+struct MainnetRollingTotals {
+    tx_count: u64,
+    processes: u64,
+    modules: u64,
+    data_size: u64,
+}
+
+impl MainnetRollingTotals {
+    fn from_row(row: Option<&MainnetExplorerRow>) -> Self {
+        row.map_or_else(Self::default, |row| Self {
+            tx_count: row.tx_count_rolling,
+            processes: row.processes_rolling,
+            modules: row.modules_rolling,
+            data_size: row.data_size_rolling,
+        })
+    }
+
+    /// explorer rows for height-ordered `metrics`, advancing the totals
+    fn rows(&mut self, metrics: Vec<MainnetBlockMetricRow>) -> Vec<MainnetExplorerRow> {
+        metrics
+            .into_iter()
+            .map(|metric| {
+                self.tx_count += metric.tx_count;
+                self.processes += metric.new_process_count;
+                self.modules += metric.new_module_count;
+                self.data_size += metric.total_data_size;
+                MainnetExplorerRow {
+                    ts: metric.ts,
+                    height: u64::from(metric.height),
+                    tx_count: metric.tx_count,
+                    eval_count: metric.eval_count,
+                    transfer_count: metric.transfer_count,
+                    new_process_count: metric.new_process_count,
+                    new_module_count: metric.new_module_count,
+                    active_users: metric.active_users,
+                    active_processes: metric.active_processes,
+                    tx_count_rolling: self.tx_count,
+                    processes_rolling: self.processes,
+                    modules_rolling: self.modules,
+                    total_data_size: metric.total_data_size,
+                    data_size_rolling: self.data_size,
+                }
+            })
+            .collect()
+    }
+}
+
+/// pages through every block metric from height 0, fetching page N+1 while
+/// page N is inserted. only the rolling totals are sequential, and they are
+/// computed here between the two, so the overlap can't reorder them.
+async fn rebuild_explorer_pages<F, FFut, I, IFut>(mut fetch: F, mut insert: I) -> Result<()>
+where
+    F: FnMut(BlockHeight) -> FFut,
+    FFut: Future<Output = Result<Vec<MainnetBlockMetricRow>>>,
+    I: FnMut(Vec<MainnetExplorerRow>) -> IFut,
+    IFut: Future<Output = Result<()>>,
+{
+    let mut totals = MainnetRollingTotals::default();
+    let mut metrics = fetch(0).await?;
+    while let Some(last) = metrics.last() {
+        let last_height = last.height;
+        let rows = totals.rows(metrics);
+        let (next, ()) = tokio::try_join!(fetch(last_height), insert(rows))?;
+        println!("mainnet explorer indexed up to height {last_height}");
+        metrics = next;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::projects::{APUS_PID, LOAD_PID};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const MAX_BASE: u64 = MAX_FACTOR as u64;
+
+    fn block_metric(height: u32) -> MainnetBlockMetricRow {
+        MainnetBlockMetricRow {
+            ts: DateTime::from_timestamp(height as i64 * 120, 0).unwrap(),
+            ts_unix: height as u64 * 120,
+            height,
+            tx_count: height as u64 % 7,
+            eval_count: 0,
+            transfer_count: 0,
+            new_process_count: height as u64 % 3,
+            new_module_count: height.is_multiple_of(50) as u64,
+            active_users: 1,
+            active_processes: 1,
+            total_data_size: height as u64 * 10,
         }
-        let mut rows = Vec::with_capacity(metrics.len());
-        for metric in metrics {
-            last_height = metric.height;
-            tx_roll += metric.tx_count;
-            proc_roll += metric.new_process_count;
-            mod_roll += metric.new_module_count;
-            rows.push(MainnetExplorerRow {
-                ts: metric.ts,
-                height: metric.height as u64,
-                tx_count: metric.tx_count,
-                eval_count: metric.eval_count,
-                transfer_count: metric.transfer_count,
-                new_process_count: metric.new_process_count,
-                new_module_count: metric.new_module_count,
-                active_users: metric.active_users,
-                active_processes: metric.active_processes,
-                tx_count_rolling: tx_roll,
-                processes_rolling: proc_roll,
-                modules_rolling: mod_roll,
-            });
+    }
+
+    #[test]
+    fn mainnet_tag_rows_keep_repeated_tags_test() {
+        let tag = |key: &str, value: &str| Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let tags = vec![
+            tag("Action", "Transfer"),
+            tag("Pushed-For", "msg_a"),
+            tag("Pushed-For", "msg_a"),
+        ];
+        let rows = mainnet_tag_rows(Utc::now(), "A", 10, "msg_1", tags);
+        assert_eq!(rows.len(), 3);
+        // the table's sort key: rows sharing it collapse on merge
+        let keys: std::collections::HashSet<_> = rows
+            .iter()
+            .map(|r| {
+                (
+                    &r.tag_key,
+                    &r.tag_value,
+                    r.block_height,
+                    &r.msg_id,
+                    r.tag_index,
+                )
+            })
+            .collect();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(rows[2].tag_index, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn overrunning_cycle_is_not_overlapped_test() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let origin = tokio::time::Instant::now();
+        let cycles = run_cycles(Duration::from_secs(10), || {
+            let (running, started) = (running.clone(), started.clone());
+            async move {
+                assert_eq!(running.fetch_add(1, Ordering::SeqCst), 0, "cycles overlap");
+                started.lock().unwrap().push(origin.elapsed().as_secs());
+                sleep(Duration::from_secs(25)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+        let _ = tokio::time::timeout(Duration::from_secs(65), cycles).await;
+        // the ticks missed during a cycle collapse into one start at its end
+        assert_eq!(*started.lock().unwrap(), [0, 25, 50]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_cycles_index_a_ticker_once_test() {
+        let locks = Arc::new(TickerLocks::default());
+        let stored = Arc::new(Mutex::new(Vec::<&str>::new()));
+        // stands in for index_ticker: check, a slow load, then the insert
+        let index = |ticker: &'static str| {
+            let (locks, stored) = (locks.clone(), stored.clone());
+            async move {
+                let _guard = locks.lock(ticker).await;
+                if stored.lock().unwrap().contains(&ticker) {
+                    return false;
+                }
+                sleep(Duration::from_millis(20)).await;
+                stored.lock().unwrap().push(ticker);
+                true
+            }
+        };
+        let (first, second, other) = tokio::join!(index("usds"), index("usds"), index("steth"));
+        assert!(first ^ second, "exactly one cycle stores the tx");
+        assert!(other, "other tickers are not blocked");
+        assert_eq!(stored.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn pipelined_rebuild_matches_serial_rolling_totals_test() {
+        let metrics: Vec<_> = (1..=1300).map(block_metric).collect();
+        let serial = MainnetRollingTotals::default().rows(metrics.clone());
+
+        let inserted = RefCell::new(Vec::new());
+        rebuild_explorer_pages(
+            |after_height| {
+                let page: Vec<_> = metrics
+                    .iter()
+                    .filter(|m| m.height > after_height)
+                    .take(MAINNET_EXPLORER_PAGE as usize)
+                    .cloned()
+                    .collect();
+                async move { Ok(page) }
+            },
+            |rows| {
+                inserted.borrow_mut().push(rows);
+                async { Ok(()) }
+            },
+        )
+        .await
+        .unwrap();
+
+        let inserted = inserted.into_inner();
+        assert_eq!(inserted.len(), 3);
+        let pipelined: Vec<_> = inserted.into_iter().flatten().collect();
+        let rolling = |rows: &[MainnetExplorerRow]| -> Vec<_> {
+            rows.iter()
+                .map(|r| {
+                    (
+                        r.height,
+                        r.tx_count_rolling,
+                        r.processes_rolling,
+                        r.modules_rolling,
+                        r.data_size_rolling,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(rolling(&pipelined), rolling(&serial));
+    }
+
+    #[test]
+    fn row_batcher_flushes_full_batch_as_one_insert_test() {
+        let mut batcher = RowBatcher::new(50, Duration::from_secs(3600));
+        let mut inserts = Vec::new();
+        for height in 0..50u64 {
+            if let Some(rows) = batcher.push(height) {
+                inserts.push(rows);
+            }
         }
-        clickhouse.insert_mainnet_explorer_rows(&rows).await?;
+        assert_eq!(inserts.len(), 1);
+        assert_eq!(inserts[0], (0..50).collect::<Vec<_>>());
+        assert!(batcher.take().is_none());
+    }
+
+    #[test]
+    fn row_batcher_flushes_stale_and_idle_rows_test() {
+        let mut stale = RowBatcher::new(50, Duration::ZERO);
+        assert_eq!(stale.push(1), Some(vec![1]));
+
+        let mut idle = RowBatcher::new(50, Duration::from_secs(3600));
+        assert_eq!(idle.push(1), None);
+        assert_eq!(idle.push(2), None);
+        assert_eq!(idle.take(), Some(vec![1, 2]));
+        assert!(idle.take().is_none());
+    }
+
+    #[test]
+    fn transient_insert_failure_keeps_the_block_test() {
+        let mut batcher = RowBatcher::new(2, Duration::from_secs(3600));
+        let mut inserted: Vec<Vec<u64>> = Vec::new();
+        let mut failures = 1;
+        let mut insert = |rows: &[u64]| {
+            retry_insert(2, Duration::ZERO, || {
+                if failures > 0 {
+                    failures -= 1;
+                    bail!("connection reset");
+                }
+                inserted.push(rows.to_vec());
+                Ok(())
+            })
+        };
+        store_block_row(&mut batcher, 1, |h| *h, &mut insert).unwrap();
+        store_block_row(&mut batcher, 2, |h| *h, &mut insert).unwrap();
+        assert_eq!(inserted, vec![vec![1, 2]]);
+        assert!(batcher.take().is_none());
+    }
+
+    #[test]
+    fn failed_insert_is_retried_with_the_same_block_test() {
+        let mut batcher = RowBatcher::new(2, Duration::from_secs(3600));
+        let mut inserted: Vec<Vec<u64>> = Vec::new();
+        store_block_row(&mut batcher, 1, |h| *h, |_| Ok(())).unwrap();
+        let res = store_block_row(&mut batcher, 2, |h| *h, |_| bail!("clickhouse unavailable"));
+        assert!(res.is_err());
+        // the loop hands block 2 over again: it is still buffered, not duplicated
+        store_block_row(
+            &mut batcher,
+            2,
+            |h| *h,
+            |rows| {
+                inserted.push(rows.to_vec());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(inserted, vec![vec![1, 2]]);
+        assert!(batcher.take().is_none());
+    }
+
+    #[test]
+    fn failed_delegation_fetch_carries_forward_test() {
+        let previous: DelegationsRes = from_str(
+            r#"{"delegationPrefs":[{"walletTo":"Qz3n2P-EiWNoWsvk7gKLtrV9ChvSXQ5HJPgPklWEgQ0","factor":10000}],"totalFactor":10000,"lastUpdate":1,"wallet":"wallet"}"#,
+        )
+        .unwrap();
+        let payload = to_string(&previous).unwrap();
+
+        let fetched: Option<DelegationsRes> = None;
+        let previous = from_str::<DelegationsRes>(&payload).ok();
+        let (delegation, stale) = carry_forward(fetched, previous).unwrap();
+
+        assert!(stale);
+        assert_eq!(to_string(&delegation).unwrap(), payload);
+    }
+
+    #[test]
+    fn failed_delegation_fetch_stores_the_previous_prefs_as_stale_test() {
+        let previous = format!(
+            r#"{{"delegationPrefs":[{{"walletTo":"{LOAD_PID}","factor":5000}}],"totalFactor":5000,"lastUpdate":1,"wallet":"wallet"}}"#
+        );
+        let entry = SetBalancesData {
+            eoa: "0xeoa".to_string(),
+            amount: "2000000000000000000".to_string(),
+            ar_address: "wallet".to_string(),
+        };
+        let amount = normalize_amount(&entry.amount, "usds").unwrap();
+        // the delegation lookup failed, the AR balance one did not
+        let delegation = carry_forward(None, from_str::<DelegationsRes>(&previous).ok()).unwrap();
+        let ar_balance = carry_forward(Some(Decimal::TEN), None).unwrap();
+
+        let rows = staker_rows(
+            "usds",
+            "tx_1",
+            Utc::now(),
+            entry,
+            amount,
+            delegation,
+            ar_balance,
+        )
+        .unwrap();
+
+        assert!(rows.delegation.stale);
+        assert_eq!(
+            from_str::<DelegationsRes>(&rows.delegation.payload)
+                .unwrap()
+                .delegation_prefs[0]
+                .wallet_to,
+            LOAD_PID
+        );
+        assert!(!rows.balance.stale);
+        assert_eq!(rows.positions.len(), 1);
+        assert_eq!(rows.positions[0].project, LOAD_PID);
+        assert_eq!(rows.positions[0].amount.to_string(), "1");
+        assert_eq!(rows.positions[0].ar_amount.to_string(), "5");
+    }
+
+    #[test]
+    fn divergence_is_relative_to_the_on_chain_stake_test() {
+        let amount = |v: &str| v.parse::<TokenAmount>().unwrap();
+        assert_eq!(divergence(amount("1000"), amount("1000")), 0.0);
+        assert_eq!(divergence(amount("999"), amount("1000")), 0.001);
+        assert_eq!(divergence(amount("1100"), amount("1000")), 0.1);
+        assert_eq!(divergence(amount("0"), amount("0")), 0.0);
+        assert_eq!(divergence(amount("5"), amount("0")), 1.0);
+    }
+
+    #[test]
+    fn delegated_amount_full_factor_test() {
+        let amount = Decimal::from_str("1234.5678").unwrap();
+        assert_eq!(delegated_amount(&amount, MAX_FACTOR, MAX_BASE), amount);
+        assert_eq!(
+            delegated_amount(&amount, MAX_FACTOR / 2, MAX_BASE),
+            Decimal::from_str("617.2839").unwrap()
+        );
+    }
+
+    #[test]
+    fn delegated_amount_clamps_factor_test() {
+        let amount = Decimal::from(100);
+        assert_eq!(delegated_amount(&amount, MAX_FACTOR * 3, MAX_BASE), amount);
+        assert_eq!(delegated_amount(&amount, u32::MAX, MAX_BASE), amount);
+    }
+
+    #[test]
+    fn delegated_amount_huge_amount_test() {
+        assert_eq!(
+            delegated_amount(&Decimal::MAX, MAX_FACTOR, MAX_BASE),
+            Decimal::MAX
+        );
+        let half = delegated_amount(&Decimal::MAX, MAX_FACTOR / 2, MAX_BASE);
+        assert!(half > Decimal::ZERO && half < Decimal::MAX);
+    }
+
+    #[test]
+    fn delegated_amount_is_rounded_to_stored_decimals_test() {
+        // 1 * 7000 / 21000 has 28 decimals unrounded, more than the server sums at
+        let third = delegated_amount(&Decimal::ONE, 7000, 21000);
+        assert_eq!(third.scale(), STORED_DECIMALS);
+        assert_eq!(third.to_string(), "0.333333333333333333");
+    }
+
+    #[test]
+    fn oversubscribed_factors_are_normalized_test() {
+        let prefs = vec![
+            WalletDelegations {
+                wallet_to: LOAD_PID.to_string(),
+                factor: 7000,
+                target_project: None,
+            },
+            WalletDelegations {
+                wallet_to: APUS_PID.to_string(),
+                factor: 7000,
+                target_project: None,
+            },
+        ];
+        let base = allocation_base(&prefs);
+        assert_eq!(base, 14000);
+        let amount = Decimal::from(1000);
+        let split: Vec<Decimal> = prefs
+            .iter()
+            .map(|pref| delegated_amount(&amount, pref.factor, base))
+            .collect();
+        assert_eq!(split, vec![Decimal::from(500), Decimal::from(500)]);
+        assert_eq!(split.iter().sum::<Decimal>(), amount);
+    }
+
+    #[test]
+    fn fresh_fetch_is_not_stale_test() {
+        let (balance, stale) = carry_forward(Some(Decimal::ONE), Some(Decimal::TWO)).unwrap();
+        assert!(!stale);
+        assert_eq!(balance, Decimal::ONE);
+        assert!(carry_forward::<Decimal>(None, None).is_none());
+    }
+
+    fn balance_row(wallet: &str, amount: &str, ar_balance: &str, tx_id: &str) -> WalletBalanceRow {
+        WalletBalanceRow {
+            ts: Utc::now(),
+            ticker: "usds".to_string(),
+            wallet: wallet.to_string(),
+            eoa: format!("0x{wallet}"),
+            amount: TokenAmount::new(Decimal::from_str(amount).unwrap()),
+            ar_balance: TokenAmount::new(Decimal::from_str(ar_balance).unwrap()),
+            tx_id: tx_id.to_string(),
+            stale: false,
+        }
+    }
+
+    fn stored(row: &WalletBalanceRow) -> StoredBalanceRow {
+        StoredBalanceRow {
+            wallet: row.wallet.clone(),
+            eoa: row.eoa.clone(),
+            amount: row.amount.to_string(),
+            ar_balance: row.ar_balance.to_string(),
+        }
+    }
+
+    async fn csv_balances(csv: &str) -> Result<BalancesStream> {
+        Ok(BalancesReader::new(Box::new(std::io::Cursor::new(
+            csv.as_bytes().to_vec(),
+        ))))
+    }
+
+    #[tokio::test]
+    async fn count_balances_stops_past_the_limit_test() {
+        let csv = "0xa,1,ar_a\n0xb,2,ar_b\n0xc,3,ar_c\n";
+        let (_, count) = count_balances(csv_balances(csv).await.unwrap(), 10)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        let (mut rest, count) = count_balances(csv_balances(csv).await.unwrap(), 1)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        // the third row was never read
+        assert!(rest.next().is_some());
+    }
+
+    #[tokio::test]
+    async fn empty_set_balances_is_not_recorded_test() {
+        let mut config = Config::load();
+        // any write would fail: nothing listens on port 1
+        config.clickhouse_url = "http://127.0.0.1:1".to_string();
+        let indexer = Indexer::new(config.clone(), Clickhouse::new(&config));
+        for csv in ["", "\n\n", "not,enough\n"] {
+            let stored = indexer
+                .index_oracle_tx("usds", "tx_empty", Utc::now(), || csv_balances(csv))
+                .await
+                .unwrap();
+            assert!(!stored, "{csv:?} recorded a snapshot");
+        }
+    }
+
+    #[tokio::test]
+    async fn over_limit_tx_stores_nothing_test() {
+        let mut config = Config::load();
+        // any write would fail: nothing listens on port 1
+        config.clickhouse_url = "http://127.0.0.1:1".to_string();
+        config.max_stakers = 2;
+        config.balances_chunk_size = 1;
+        let indexer = Indexer::new(config.clone(), Clickhouse::new(&config));
+        let csv = "0xa,1,ar_a\n0xb,2,ar_b\n0xc,3,ar_c\n";
+        // refused before the first chunk is looked up or written
+        let err = indexer
+            .index_oracle_tx("usds", "tx_big", Utc::now(), || csv_balances(csv))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("more than 2 stakers"), "{err}");
+    }
+
+    #[test]
+    fn minting_report_row_uses_the_report_timestamp_test() {
+        let report = OwnMintingReport {
+            distribution_tick: 42,
+            total_minted: "1000".to_string(),
+            total_inflow: "900".to_string(),
+            timestamp: 1_764_976_437_232,
+            ao_kept: "600".to_string(),
+            ao_exchanged_for_pi: "400".to_string(),
+            report_id: Some("report_1".to_string()),
+        };
+        let row = minting_report_row("pid_a", report.clone()).unwrap();
+        assert_eq!(row.ts.timestamp_millis(), 1_764_976_437_232);
+        assert_eq!(
+            (row.project.as_str(), row.report_id.as_str()),
+            ("pid_a", "report_1")
+        );
+        assert_eq!(row.total_minted, "1000");
+        let unnamed = OwnMintingReport {
+            report_id: None,
+            ..report
+        };
+        assert!(minting_report_row("pid_a", unnamed).is_err());
+    }
+
+    #[test]
+    fn rate_limit_backoff_honours_retry_after_test() {
+        let err: anyhow::Error = RateLimited {
+            retry_after: Some(Duration::from_secs(30)),
+        }
+        .into();
+        assert_eq!(
+            rate_limit_backoff(&err, Duration::from_secs(5)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(token_retry_delay(&err), Some(Duration::from_secs(30)));
+        // status errors from other call paths carry no hint
+        let err = anyhow::anyhow!("http status: 429");
+        assert_eq!(
+            rate_limit_backoff(&err, Duration::from_secs(5)),
+            Some(Duration::from_secs(5))
+        );
+        let err = anyhow::anyhow!("invalid cursor");
+        assert_eq!(rate_limit_backoff(&err, Duration::from_secs(5)), None);
+        assert_eq!(token_retry_delay(&err), None);
+    }
+
+    #[test]
+    fn open_circuit_does_not_end_the_token_worker_test() {
+        // what the breaker middleware hands back through ureq
+        let rejected = common::gateway::GatewayUnavailable {
+            status: 503,
+            content_type: None,
+            retry_in: Some(Duration::from_secs(12)),
+        };
+        let err: anyhow::Error = ureq::Error::Other(Box::new(rejected)).into();
+        assert_eq!(token_retry_delay(&err), Some(Duration::from_secs(12)));
+    }
+
+    #[test]
+    fn unchanged_balance_is_not_rewritten_test() {
+        // first cycle: nothing stored yet, every wallet is written
+        let first = vec![
+            balance_row("w1", "100", "1.5", "tx1"),
+            balance_row("w2", "50", "0", "tx1"),
+        ];
+        let written = changed_balances(first.clone(), Vec::new());
+        assert_eq!(written.len(), 2);
+
+        // second cycle: w1 unchanged (new tx, same values), w2's AR balance moved
+        let previous = written.iter().map(stored).collect();
+        let second = vec![
+            balance_row("w1", "100.0", "1.50", "tx2"),
+            balance_row("w2", "50", "2", "tx2"),
+            balance_row("w3", "7", "0", "tx2"),
+        ];
+        let written = changed_balances(second, previous);
+        let wallets: Vec<&str> = written.iter().map(|row| row.wallet.as_str()).collect();
+        assert_eq!(wallets, ["w2", "w3"]);
+    }
+
+    #[test]
+    fn legacy_empty_ar_balance_counts_as_changed_test() {
+        let row = balance_row("w1", "100", "1.5", "tx2");
+        let mut previous = stored(&row);
+        previous.ar_balance = String::new();
+        assert_eq!(changed_balances(vec![row], vec![previous]).len(), 1);
     }
 }
@@ -11,6 +11,8 @@ use indexer::Indexer;
 async fn main() -> Result<()> {
     let config = Config::load();
     let clickhouse = clickhouse::Clickhouse::new(&config);
+    // fail fast on a wrong URL or credentials, before any worker spawns
+    clickhouse.verify().await?;
     let indexer = Indexer::new(config, clickhouse);
     indexer.run().await
 }
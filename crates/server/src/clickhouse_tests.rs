@@ -0,0 +1,782 @@
+//! round-trip tests of the `AtlasIndexerClient` SQL against a real ClickHouse.
+//! they only run when `CLICKHOUSE_TEST_URL` points at a disposable instance, e.g.
+//! `docker run -d -p 8123:8123 clickhouse/clickhouse-server` and
+//! `CLICKHOUSE_TEST_URL=http://localhost:8123 cargo test -p server clickhouse_tests`.
+//! every test works in its own throwaway database, dropped at the end.
+
+use crate::indexer::AtlasIndexerClient;
+use common::env::get_env_var;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const T1: &str = "2025-01-01 00:00:00.000";
+const T2: &str = "2025-01-02 00:00:00.000";
+
+struct TestDb {
+    admin: clickhouse::Client,
+    raw: clickhouse::Client,
+    database: String,
+    client: AtlasIndexerClient,
+}
+
+impl TestDb {
+    /// `None` when no test instance is configured
+    async fn setup(name: &str) -> Option<Self> {
+        let Ok(url) = get_env_var("CLICKHOUSE_TEST_URL") else {
+            eprintln!("CLICKHOUSE_TEST_URL not set, skipping {name}");
+            return None;
+        };
+        let user = get_env_var("CLICKHOUSE_TEST_USER").unwrap_or_else(|_| "default".into());
+        let password = get_env_var("CLICKHOUSE_TEST_PASSWORD").unwrap_or_default();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let database = format!("atlas_test_{name}_{nanos}");
+        let client =
+            AtlasIndexerClient::connect(&url, &user, &password, &database, Duration::from_secs(30))
+                .await
+                .expect("connect to test clickhouse");
+        let admin = clickhouse::Client::default()
+            .with_url(&url)
+            .with_user(&user)
+            .with_password(&password);
+        let raw = admin.clone().with_database(&database);
+        Some(Self {
+            admin,
+            raw,
+            database,
+            client,
+        })
+    }
+
+    async fn exec(&self, sql: &str) {
+        self.raw.query(sql).execute().await.expect(sql);
+    }
+
+    async fn teardown(self) {
+        let drop_db = format!("drop database if exists {}", self.database);
+        self.admin.query(&drop_db).execute().await.unwrap();
+    }
+}
+
+/// two cycles (T1, T2) of positions for `pid_a` and `pid_b`; w1 delegates to both
+async fn seed_positions(db: &TestDb) {
+    db.exec(&format!(
+        "insert into oracle_snapshots (ts, ticker, tx_id) values \
+         ('{T1}', 'usds', 'tx_u1'), ('{T1}', 'dai', 'tx_d1'), ('{T2}', 'usds', 'tx_u2')"
+    ))
+    .await;
+    db.exec(&format!(
+        "insert into flp_positions (ts, ticker, wallet, eoa, project, factor, amount, ar_amount) values \
+         ('{T1}', 'usds', 'w1', '0x1', 'pid_a', 10000, '100', '1'), \
+         ('{T1}', 'usds', 'w2', '0x2', 'pid_a', 10000, '50', '0.5'), \
+         ('{T1}', 'dai', 'w1', '0x1', 'pid_a', 10000, '10', '0.1'), \
+         ('{T2}', 'usds', 'w1', '0x1', 'pid_a', 5000, '200', '2'), \
+         ('{T2}', 'usds', 'w2', '0x2', 'pid_a', 10000, '20', '0.2'), \
+         ('{T2}', 'usds', 'w1', '0x1', 'pid_b', 5000, '5', '0.05'), \
+         ('{T1}', 'usds', 'w3', '0x3', 'pid_b', 10000, '7', '0.07')"
+    ))
+    .await;
+}
+
+#[tokio::test]
+async fn latest_project_snapshot_round_trip_test() {
+    let Some(db) = TestDb::setup("snapshot").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    let snapshot = db
+        .client
+        .latest_project_snapshot("pid_a", None)
+        .await
+        .unwrap();
+    // usds comes from T2, dai only has T1
+    assert_eq!(snapshot.delegators.len(), 3);
+    let at = |ts: &str| {
+        chrono::NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S%.3f")
+            .unwrap()
+            .and_utc()
+    };
+    assert_eq!((snapshot.ts, snapshot.oldest_ts), (at(T2), at(T1)));
+    assert_eq!(snapshot.totals.len(), 2);
+    let dai = &snapshot.totals[0];
+    assert_eq!(
+        (
+            dai.ticker.as_str(),
+            dai.amount.to_string(),
+            dai.delegators_count
+        ),
+        ("dai", "10".to_string(), 1)
+    );
+    let usds = &snapshot.totals[1];
+    assert_eq!(
+        (
+            usds.ticker.as_str(),
+            usds.amount.to_string(),
+            usds.delegators_count
+        ),
+        ("usds", "220".to_string(), 2)
+    );
+    // dust filter: only usds w1 (200) stays, totals still count everyone
+    let filtered = db
+        .client
+        .latest_project_snapshot("pid_a", Some("50".parse().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(filtered.delegators.len(), 1);
+    assert_eq!(filtered.delegators[0].amount.to_string(), "200");
+    assert_eq!(filtered.totals[1].amount.to_string(), "220");
+    let count = |min: Option<&str>| {
+        db.client
+            .count_project_delegators("pid_a", min.map(|m| m.parse().unwrap()))
+    };
+    // same unit as the lists above: one per wallet and ticker
+    assert_eq!(count(None).await.unwrap(), 3);
+    assert_eq!(count(Some("50")).await.unwrap(), 1);
+    assert!(
+        db.client
+            .latest_project_snapshot("pid_missing", None)
+            .await
+            .is_err()
+    );
+    let missing = db
+        .client
+        .count_project_delegators("pid_missing", None)
+        .await
+        .unwrap_err();
+    // a 404, like the list
+    assert!(matches!(
+        missing.downcast_ref::<flp::error::FlpError>(),
+        Some(flp::error::FlpError::NotFound(_))
+    ));
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn project_totals_agree_across_sum_paths_test() {
+    let Some(db) = TestDb::setup("sum_paths").await else {
+        return;
+    };
+    // thirds as the indexer stores them, rounded to 18 decimals
+    db.exec(&format!(
+        "insert into flp_positions (ts, ticker, wallet, eoa, project, factor, amount, ar_amount) values \
+         ('{T1}', 'usds', 'w1', '0x1', 'pid_a', 3333, '0.333333333333333333', '0'), \
+         ('{T1}', 'usds', 'w2', '0x2', 'pid_a', 3333, '0.333333333333333333', '0'), \
+         ('{T1}', 'usds', 'w3', '0x3', 'pid_a', 3333, '0.333333333333333333', '0')"
+    ))
+    .await;
+    let rust_sum = db
+        .client
+        .latest_project_snapshot("pid_a", None)
+        .await
+        .unwrap()
+        .totals[0]
+        .amount;
+    let sql_sum = db.client.all_project_snapshots().await.unwrap()[0].totals[0].amount;
+    assert_eq!(rust_sum.to_string(), "0.999999999999999999");
+    assert_eq!(sql_sum, rust_sum);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn multi_project_delegators_round_trip_test() {
+    let Some(db) = TestDb::setup("multi").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    let page = db
+        .client
+        .multi_project_delegators(2, 10, 0, true)
+        .await
+        .unwrap();
+    assert_eq!(page.total, Some(1));
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(
+        db.client.count_multi_project_delegators(2).await.unwrap(),
+        1
+    );
+    // every wallet delegates to at least one FLP
+    assert_eq!(
+        db.client.count_multi_project_delegators(1).await.unwrap(),
+        3
+    );
+    let all = db
+        .client
+        .multi_project_delegators(1, 2, 1, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        all.items
+            .iter()
+            .map(|d| d.wallet.as_str())
+            .collect::<Vec<_>>(),
+        vec!["w2", "w3"]
+    );
+    assert!(
+        db.client
+            .multi_project_delegators(3, 10, 0, false)
+            .await
+            .is_err()
+    );
+    let w1 = &page.items[0];
+    assert_eq!(w1.wallet, "w1");
+    assert_eq!(w1.project_count, 2);
+    let mut projects = w1.projects.clone();
+    projects.sort();
+    assert_eq!(projects, vec!["pid_a", "pid_b"]);
+    assert!(!w1.projects_truncated);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn multi_project_delegator_lists_every_project_test() {
+    let Some(db) = TestDb::setup("multi_many").await else {
+        return;
+    };
+    let values = (0..300)
+        .map(|i| format!("('{T1}', 'usds', 'w1', '0x1', 'pid_{i}', 10000, '1', '0.01')"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    db.exec(&format!(
+        "insert into flp_positions (ts, ticker, wallet, eoa, project, factor, amount, ar_amount) values {values}"
+    ))
+    .await;
+    let page = db
+        .client
+        .multi_project_delegators(2, 10, 0, false)
+        .await
+        .unwrap();
+    let w1 = &page.items[0];
+    assert_eq!(w1.project_count, 300);
+    assert_eq!(w1.projects.len(), 300);
+    assert!(!w1.projects_truncated);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn project_cycle_totals_round_trip_test() {
+    let Some(db) = TestDb::setup("cycles").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    let page = db
+        .client
+        .project_cycle_totals("pid_a", None, 10, 0, true)
+        .await
+        .unwrap();
+    // T1 carries both the usds and the dai snapshot
+    assert_eq!(page.total, Some(2));
+    assert_eq!(page.items[0].tx_ids["usds"], "tx_u2");
+    assert_eq!(page.items[0].totals["usds"].to_string(), "220");
+    assert_eq!(page.items[0].totals.len(), 1);
+    let t1 = &page.items[1];
+    assert_eq!(t1.tx_ids["usds"], "tx_u1");
+    assert_eq!(t1.tx_ids["dai"], "tx_d1");
+    assert_eq!(t1.totals["usds"].to_string(), "150");
+    assert_eq!(t1.totals["dai"].to_string(), "10");
+
+    let dai = db
+        .client
+        .project_cycle_totals("pid_a", Some("dai"), 10, 0, true)
+        .await
+        .unwrap();
+    assert_eq!(dai.total, Some(1));
+    assert_eq!(dai.items[0].tx_ids["dai"], "tx_d1");
+    assert_eq!(dai.items[0].totals.len(), 1);
+    assert_eq!(dai.items[0].totals["dai"].to_string(), "10");
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn delegation_mappings_round_trip_test() {
+    let Some(db) = TestDb::setup("mappings").await else {
+        return;
+    };
+    db.exec(
+        "insert into delegation_mappings (ts, height, tx_id, wallet_from, wallet_to, factor) values \
+         ('2025-01-01 00:00:00.000', 100, 'tx_a', 'ar1', 'pid_a', 10000), \
+         ('2025-01-02 00:00:00.000', 200, 'tx_b', 'ar1', 'pid_a', 5000), \
+         ('2025-01-02 00:00:00.000', 200, 'tx_b', 'ar1', 'pid_b', 5000), \
+         ('2025-01-02 00:00:00.000', 200, 'tx_b', 'ar2', 'pid_a', 10000)",
+    )
+    .await;
+    let latest = db.client.latest_delegation_snapshot().await.unwrap();
+    assert_eq!((latest.height, latest.tx_id.as_str()), (200, "tx_b"));
+    assert_eq!(latest.preferences_count, 3);
+    assert_eq!(latest.wallets_count, 2);
+
+    let split = db.client.delegation_split_distribution().await.unwrap();
+    assert_eq!(split.height, 200);
+    assert_eq!(
+        (
+            split.one_target,
+            split.two_targets,
+            split.three_plus_targets
+        ),
+        (1, 1, 0)
+    );
+
+    let at = db
+        .client
+        .wallet_delegation_at("ar1", 150)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((at.height, at.preferences.len()), (100, 1));
+    assert_eq!(
+        db.client
+            .count_wallet_delegation_mappings("ar1")
+            .await
+            .unwrap(),
+        2
+    );
+    assert!(
+        db.client
+            .wallet_delegation_at("ar1", 50)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn explorer_block_time_round_trip_test() {
+    let Some(db) = TestDb::setup("block_time").await else {
+        return;
+    };
+    // 10s, a missing timestamp, an interpolated repeat, then 30s over 3 blocks
+    db.exec(
+        "insert into atlas_explorer (ts, height) values \
+         ('2025-01-01 00:00:00.000', 1), ('2025-01-01 00:00:10.000', 2), \
+         (toDateTime64(0, 3), 3), ('2025-01-01 00:00:10.000', 4), \
+         ('2025-01-01 00:00:40.000', 5)",
+    )
+    .await;
+    let stats = db.client.explorer_block_time(Some(1), None).await.unwrap();
+    assert_eq!((stats.from_height, stats.to_height), (1, 5));
+    assert_eq!(stats.intervals, 2);
+    assert_eq!(stats.avg_secs, Some(10.0));
+    assert_eq!(stats.p95_secs, Some(10.0));
+
+    let empty = db
+        .client
+        .explorer_block_time(Some(100), Some(200))
+        .await
+        .unwrap();
+    assert_eq!(empty.intervals, 0);
+    assert_eq!(empty.avg_secs, None);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn explorer_block_round_trip_test() {
+    let Some(db) = TestDb::setup("explorer_block").await else {
+        return;
+    };
+    db.exec(
+        "insert into atlas_explorer (ts, height, tx_count, tx_count_rolling) values \
+         ('2025-01-01 00:00:00.000', 10, 3, 3), ('2025-01-01 00:02:00.000', 11, 2, 5)",
+    )
+    .await;
+    let block = db.client.explorer_block(11).await.unwrap().unwrap();
+    assert_eq!(
+        (block.height, block.tx_count, block.tx_count_rolling),
+        (11, 2, 5)
+    );
+    assert!(db.client.explorer_block(12).await.unwrap().is_none());
+    assert_eq!(block.total_data_size, None);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn mainnet_explorer_data_size_round_trip_test() {
+    let Some(db) = TestDb::setup("mainnet_data_size").await else {
+        return;
+    };
+    // the indexer owns this table, so the server schema doesn't create it
+    db.exec(
+        "create table ao_mainnet_explorer(ts DateTime64(3), height UInt64, tx_count UInt64, \
+         eval_count UInt64, transfer_count UInt64, new_process_count UInt64, \
+         new_module_count UInt64, active_users UInt64, active_processes UInt64, \
+         tx_count_rolling UInt64, processes_rolling UInt64, modules_rolling UInt64, \
+         total_data_size UInt64, data_size_rolling UInt64) \
+         engine=ReplacingMergeTree order by height",
+    )
+    .await;
+    db.exec(
+        "insert into ao_mainnet_explorer (ts, height, total_data_size, data_size_rolling) values \
+         ('2025-01-01 00:00:00.000', 10, 100, 100), ('2025-01-01 00:02:00.000', 11, 50, 150)",
+    )
+    .await;
+    let blocks = db.client.mainnet_explorer_blocks(10).await.unwrap();
+    let sizes: Vec<_> = blocks
+        .iter()
+        .map(|block| (block.height, block.total_data_size, block.data_size_rolling))
+        .collect();
+    assert_eq!(
+        sizes,
+        [(11, Some(50), Some(150)), (10, Some(100), Some(100))]
+    );
+
+    let day = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let stats = db.client.mainnet_daily_explorer_stats(day).await.unwrap();
+    assert_eq!(
+        (stats.data_size, stats.data_size_roll),
+        (Some(150), Some(150))
+    );
+    let days = db.client.mainnet_recent_explorer_days(7).await.unwrap();
+    assert_eq!(days[0].data_size, Some(150));
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn mainnet_tag_queries_count_messages_once_test() {
+    let Some(db) = TestDb::setup("block_metrics").await else {
+        return;
+    };
+    // the indexer owns these tables, so the server schema doesn't create them
+    db.exec(
+        "create table ao_mainnet_messages(ts DateTime64(3), protocol String, \
+         block_height UInt32, block_timestamp UInt64, msg_id String, owner String, \
+         recipient String, bundled_in String, data_size String) \
+         engine=ReplacingMergeTree order by (protocol, block_height, msg_id)",
+    )
+    .await;
+    db.exec(
+        "create table ao_mainnet_message_tags(ts DateTime64(3), protocol String, \
+         block_height UInt32, msg_id String, tag_key String, tag_value String, tag_index UInt16) \
+         engine=ReplacingMergeTree order by (tag_key, tag_value, block_height, msg_id, tag_index)",
+    )
+    .await;
+    db.exec(&format!(
+        "insert into ao_mainnet_messages values \
+         ('{T1}', 'A', 10, 1735689600, 'msg_1', 'owner_1', '', '', '100'), \
+         ('{T1}', 'A', 10, 1735689600, 'msg_2', 'owner_2', '', '', '20')"
+    ))
+    .await;
+    // msg_1 repeats its Action tag
+    db.exec(&format!(
+        "insert into ao_mainnet_message_tags values \
+         ('{T1}', 'A', 10, 'msg_1', 'Action', 'Transfer', 0), \
+         ('{T1}', 'A', 10, 'msg_1', 'Action', 'Transfer', 1), \
+         ('{T1}', 'A', 10, 'msg_1', 'From-Process', 'pid_1', 2), \
+         ('{T1}', 'A', 10, 'msg_2', 'Action', 'Eval', 0)"
+    ))
+    .await;
+    let metrics = db.client.mainnet_block_metrics(0, 10).await.unwrap();
+    assert_eq!(metrics.len(), 1);
+    let block = &metrics[0];
+    assert_eq!(
+        (block.tx_count, block.transfer_count, block.eval_count),
+        (2, 1, 1)
+    );
+    assert_eq!((block.active_processes, block.total_data_size), (1, 120));
+
+    let transfers = db
+        .client
+        .mainnet_messages_by_tag(None, &["Action".to_string()], "Transfer", 10)
+        .await
+        .unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].msg_id, "msg_1");
+    assert_eq!(transfers[0].tags.len(), 3);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn indexed_oracle_tickers_round_trip_test() {
+    let Some(db) = TestDb::setup("oracle_tickers").await else {
+        return;
+    };
+    assert!(db.client.indexed_oracle_tickers().await.unwrap().is_empty());
+    seed_positions(&db).await;
+    assert_eq!(
+        db.client.indexed_oracle_tickers().await.unwrap(),
+        vec!["dai", "usds"]
+    );
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn oracle_value_daily_round_trip_test() {
+    let Some(db) = TestDb::setup("oracle_daily").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    let day = |d: &str| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+    let days = db
+        .client
+        .oracle_value_daily("usds", day("2024-12-31"), day("2025-01-04"))
+        .await
+        .unwrap();
+    let series: Vec<_> = days
+        .iter()
+        .map(|d| {
+            (
+                d.day.as_str(),
+                d.total.map(|t| t.to_string()),
+                d.carried_forward,
+            )
+        })
+        .collect();
+    assert_eq!(
+        series,
+        vec![
+            ("2024-12-31", None, true),
+            ("2025-01-01", Some("157".to_string()), false),
+            ("2025-01-02", Some("225".to_string()), false),
+            ("2025-01-03", Some("225".to_string()), true),
+            ("2025-01-04", Some("225".to_string()), true),
+        ]
+    );
+
+    // the value before `from` is carried into the range
+    let later = db
+        .client
+        .oracle_value_daily("usds", day("2025-01-05"), day("2025-01-05"))
+        .await
+        .unwrap();
+    assert_eq!(later[0].total.map(|t| t.to_string()), Some("225".into()));
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn zero_balance_delegators_round_trip_test() {
+    let Some(db) = TestDb::setup("zero_balance").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    // balances are only written on change: w1 was empty at T1 but funded at T2,
+    // w2 emptied its AR wallet at T1 and has no T2 row
+    db.exec(&format!(
+        "insert into wallet_balances (ts, ticker, wallet, eoa, amount, ar_balance, tx_id) values \
+         ('{T1}', 'usds', 'w1', '0x1', '100', '0', 'tx_u1'), \
+         ('{T2}', 'usds', 'w1', '0x1', '200', '3.5', 'tx_u2'), \
+         ('{T1}', 'usds', 'w2', '0x2', '50', '0', 'tx_u1')"
+    ))
+    .await;
+    let rows = db.client.zero_balance_delegators("pid_a").await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        (rows[0].wallet.as_str(), rows[0].ticker.as_str()),
+        ("w2", "usds")
+    );
+    assert_eq!(rows[0].amount.to_string(), "20");
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn oracle_balance_deltas_round_trip_test() {
+    let Some(db) = TestDb::setup("oracle_deltas").await else {
+        return;
+    };
+    assert!(
+        db.client
+            .oracle_balance_deltas("usds")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    seed_positions(&db).await;
+    // usds cycles T1 and T2: w1 grows (row written at T2), w2 stays unchanged
+    // (no T2 balance row), w3 leaves and w4 joins
+    db.exec(&format!(
+        "insert into wallet_balances (ts, ticker, wallet, eoa, amount, ar_balance, tx_id) values \
+         ('{T1}', 'usds', 'w1', '0x1', '100', '1', 'tx_u1'), \
+         ('{T2}', 'usds', 'w1', '0x1', '250.5', '1', 'tx_u2'), \
+         ('{T1}', 'usds', 'w2', '0x2', '50', '1', 'tx_u1'), \
+         ('{T1}', 'usds', 'w3', '0x3', '7', '1', 'tx_u1'), \
+         ('{T2}', 'usds', 'w4', '0x4', '1', '1', 'tx_u2')"
+    ))
+    .await;
+    db.exec(&format!(
+        "insert into wallet_delegations (ts, wallet, payload) values \
+         ('{T1}', 'w1', '{{}}'), ('{T1}', 'w2', '{{}}'), ('{T1}', 'w3', '{{}}'), \
+         ('{T2}', 'w1', '{{}}'), ('{T2}', 'w2', '{{}}'), ('{T2}', 'w4', '{{}}')"
+    ))
+    .await;
+    let deltas = db
+        .client
+        .oracle_balance_deltas("usds")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(deltas.previous_ts.is_some());
+    assert_eq!((deltas.entries, deltas.exits, deltas.changed), (1, 1, 1));
+    let summary: Vec<(&str, String)> = deltas
+        .deltas
+        .iter()
+        .map(|d| (d.wallet.as_str(), d.delta.to_string()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("w1", "150.5".to_string()),
+            ("w3", "-7".to_string()),
+            ("w4", "1".to_string())
+        ]
+    );
+
+    // dai has a single cycle: every wallet is an entry
+    let dai = db
+        .client
+        .oracle_balance_deltas("dai")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(dai.previous_ts, None);
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn token_transfer_volume_daily_round_trip_test() {
+    let Some(db) = TestDb::setup("transfer_volume").await else {
+        return;
+    };
+    // 2025-01-01 and 2025-01-02 block timestamps
+    db.exec(&format!(
+        "insert into ao_token_messages (ts, token, source, block_height, block_timestamp, msg_id) values \
+         ('{T1}', 'ao', 'A', 1, 1735689600, 'm1'), ('{T1}', 'ao', 'A', 1, 1735689600, 'm2'), \
+         ('{T1}', 'ao', 'A', 2, 1735776000, 'm3'), ('{T1}', 'ao', 'A', 2, 1735776000, 'm4'), \
+         ('{T1}', 'pi', 'A', 2, 1735776000, 'm5')"
+    ))
+    .await;
+    db.exec(&format!(
+        "insert into ao_token_message_tags (ts, token, source, block_height, msg_id, tag_key, tag_value) values \
+         ('{T1}', 'ao', 'A', 1, 'm1', 'Action', 'Credit-Notice'), ('{T1}', 'ao', 'A', 1, 'm1', 'Quantity', '1500000000000'), \
+         ('{T1}', 'ao', 'A', 1, 'm2', 'Action', 'Debit-Notice'), ('{T1}', 'ao', 'A', 1, 'm2', 'Quantity', '1500000000000'), \
+         ('{T1}', 'ao', 'A', 2, 'm3', 'Action', 'Credit-Notice'), ('{T1}', 'ao', 'A', 2, 'm3', 'Quantity', '2000000000000'), \
+         ('{T1}', 'ao', 'A', 2, 'm4', 'Action', 'Credit-Notice'), \
+         ('{T1}', 'pi', 'A', 2, 'm5', 'Action', 'Credit-Notice'), ('{T1}', 'pi', 'A', 2, 'm5', 'Quantity', '9000000000000')"
+    ))
+    .await;
+    let day = |d: &str| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+    let days = db
+        .client
+        .token_transfer_volume_daily("ao", day("2025-01-01"), day("2025-01-03"))
+        .await
+        .unwrap();
+    let series: Vec<_> = days
+        .iter()
+        .map(|d| {
+            (
+                d.day.as_str(),
+                d.transfers,
+                d.missing_quantity,
+                d.volume.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        series,
+        vec![
+            ("2025-01-01", 1, 0, "1.5"),
+            ("2025-01-02", 2, 1, "2"),
+            ("2025-01-03", 0, 0, "0"),
+        ]
+    );
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn latest_delegation_mappings_csv_round_trip_test() {
+    let Some(db) = TestDb::setup("delegation_csv").await else {
+        return;
+    };
+    db.exec(&format!(
+        "insert into delegation_mappings (ts, height, tx_id, wallet_from, wallet_to, factor) values \
+         ('{T1}', 10, 'old', 'ar_a', 'pid_1', 10000), \
+         ('{T2}', 20, 'new', 'ar_b', 'pid_1', 2500), ('{T2}', 20, 'new', 'ar_b', 'pid_2', 7500), \
+         ('{T2}', 20, 'new', 'ar_a', 'pid_2', 10000)"
+    ))
+    .await;
+    let (latest, chunks) = db.client.latest_delegation_mappings_csv().await.unwrap();
+    assert_eq!((latest.height, latest.tx_id.as_str()), (20, "new"));
+    let csv = futures::TryStreamExt::try_concat(chunks).await.unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "wallet_from,wallet_to,factor\nar_a,pid_2,10000\nar_b,pid_1,2500\nar_b,pid_2,7500\n"
+    );
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn oracle_divergence_round_trip_test() {
+    let Some(db) = TestDb::setup("divergence").await else {
+        return;
+    };
+    db.exec(&format!(
+        "insert into oracle_evm_divergence \
+         (ts, ticker, oracle_tx_id, evm_block, ao_total, evm_total, divergence, tolerance, diverged) values \
+         ('{T1}', 'usds', 'tx_u1', 21000000, '1000', '1000', 0, 0.001, false), \
+         ('{T2}', 'usds', 'tx_u2', 21007200, '1100', '1000', 0.1, 0.001, true), \
+         ('{T2}', 'dai', 'tx_d1', 21007200, '10', '10', 0, 0.001, false)"
+    ))
+    .await;
+    let checks = db.client.oracle_divergence("usds", 10).await.unwrap();
+    assert_eq!(checks.len(), 2);
+    assert_eq!(checks[0].oracle_tx_id, "tx_u2");
+    assert!(checks[0].diverged);
+    assert_eq!(checks[0].ao_total.to_string(), "1100");
+    assert_eq!(checks[0].evm_block, 21_007_200);
+    assert!(!checks[1].diverged);
+
+    let latest = db.client.oracle_divergence("usds", 1).await.unwrap();
+    assert_eq!(latest.len(), 1);
+    assert!(
+        db.client
+            .oracle_divergence("steth", 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    db.teardown().await;
+}
+
+#[tokio::test]
+async fn project_mint_vs_delegation_round_trip_test() {
+    let Some(db) = TestDb::setup("mint_vs_delegation").await else {
+        return;
+    };
+    seed_positions(&db).await;
+    db.exec(&format!(
+        "insert into flp_minting_reports \
+         (ts, project, report_id, distribution_tick, total_minted, total_inflow, ao_kept, ao_exchanged_for_pi) values \
+         ('{T1}', 'pid_a', 'r1', 1, '1000', '10', '5', '5'), \
+         ('2025-01-02 01:00:00.000', 'pid_a', 'r2', 2, '1100', '11', '6', '5'), \
+         ('{T1}', 'pid_b', 'r3', 1, '70', '1', '1', '0')"
+    ))
+    .await;
+    let series = db
+        .client
+        .project_mint_vs_delegation("pid_a", 10)
+        .await
+        .unwrap();
+    assert_eq!(series.len(), 2);
+    // the newer report sits an hour after T2; dai has no cycle within a day of it
+    assert_eq!(series[0].report_id, "r2");
+    assert_eq!(series[0].total_minted, "1100");
+    assert_eq!(series[0].cycle_gap_secs, Some(3600));
+    assert_eq!(series[0].delegated_total.len(), 1);
+    assert_eq!(series[0].delegated_total["usds"].to_string(), "220");
+    assert_eq!(series[1].report_id, "r1");
+    assert_eq!(series[1].cycle_gap_secs, Some(0));
+    assert_eq!(series[1].delegated_total["usds"].to_string(), "150");
+    assert_eq!(series[1].delegated_total["dai"].to_string(), "10");
+
+    let latest = db
+        .client
+        .project_mint_vs_delegation("pid_a", 1)
+        .await
+        .unwrap();
+    assert_eq!(latest.len(), 1);
+    assert!(
+        db.client
+            .project_mint_vs_delegation("pid_c", 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    db.teardown().await;
+}
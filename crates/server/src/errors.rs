@@ -1,30 +1,239 @@
+use crate::indexer::UnsupportedSortKey;
 use axum::{
     Json,
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use serde_json::json;
+use common::{
+    gateway::{RateLimited, TxNotFound, gateway_unavailable},
+    height::HeightOutOfRange,
+};
+use explorer::HeightAboveTip;
+use flp::error::FlpError;
+use serde_json::{Value, json};
+
+/// stable, machine-readable `error_code` of an error response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotFound,
+    BadRequest,
+    MethodNotAllowed,
+    Unprocessable,
+    GatewayUnavailable,
+    Internal,
+}
+
+impl ErrorCode {
+    /// every code, for the OpenAPI description
+    #[cfg(feature = "openapi")]
+    pub const ALL: [ErrorCode; 6] = [
+        ErrorCode::NotFound,
+        ErrorCode::BadRequest,
+        ErrorCode::MethodNotAllowed,
+        ErrorCode::Unprocessable,
+        ErrorCode::GatewayUnavailable,
+        ErrorCode::Internal,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::BadRequest => "bad_request",
+            ErrorCode::MethodNotAllowed => "method_not_allowed",
+            ErrorCode::Unprocessable => "unprocessable",
+            ErrorCode::GatewayUnavailable => "gateway_unavailable",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::Unprocessable => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::GatewayUnavailable => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub struct ServerError {
+    code: ErrorCode,
+    err: anyhow::Error,
+    /// extra fields of the response body, e.g. the unmatched `path`
+    context: Vec<(&'static str, String)>,
+}
 
-pub struct ServerError(anyhow::Error);
+impl ServerError {
+    pub fn new(code: ErrorCode, msg: impl Into<String>) -> Self {
+        Self {
+            code,
+            err: anyhow::anyhow!(msg.into()),
+            context: Vec::new(),
+        }
+    }
+
+    pub fn not_found(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, msg)
+    }
+
+    pub fn bad_request(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::BadRequest, msg)
+    }
+
+    pub fn unprocessable(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Unprocessable, msg)
+    }
+
+    pub fn method_not_allowed(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::MethodNotAllowed, msg)
+    }
+
+    pub fn with_context(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.context.push((key, value.into()));
+        self
+    }
+
+    /// the JSON body of the error response
+    pub fn body(&self) -> Value {
+        let mut body = json!({
+            "error": self.err.to_string(),
+            "error_code": self.code.as_str(),
+        });
+        for (key, value) in &self.context {
+            body[*key] = value.as_str().into();
+        }
+        body
+    }
+}
 
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
-        eprintln!("server error: {:?}", self.0);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": self.0.to_string()
-            })),
-        )
-            .into_response()
+        let status = self.code.status();
+        if status.is_server_error() {
+            eprintln!("server error: {:?}", self.err);
+        }
+        (status, Json(self.body())).into_response()
     }
 }
 
+/// gateway failures, out-of-range heights and unknown sort keys keep their
+/// meaning, anything else is internal. flp errors are unwrapped first so the
+/// gateway errors they carry are classified too.
 impl<E> From<E> for ServerError
 where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into())
+        let err = match err.into().downcast::<FlpError>() {
+            Ok(err) => err.into_anyhow(),
+            Err(err) => err,
+        };
+        let code = if err.downcast_ref::<TxNotFound>().is_some()
+            || matches!(err.downcast_ref::<FlpError>(), Some(FlpError::NotFound(_)))
+        {
+            ErrorCode::NotFound
+        } else if gateway_unavailable(&err).is_some() || err.downcast_ref::<RateLimited>().is_some()
+        {
+            ErrorCode::GatewayUnavailable
+        } else if err.downcast_ref::<HeightAboveTip>().is_some()
+            || err.downcast_ref::<HeightOutOfRange>().is_some()
+            || err.downcast_ref::<UnsupportedSortKey>().is_some()
+        {
+            ErrorCode::BadRequest
+        } else {
+            ErrorCode::Internal
+        };
+        Self {
+            code,
+            err,
+            context: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use common::gateway::GatewayUnavailable;
+
+    async fn body(err: ServerError) -> (StatusCode, Value) {
+        let res = err.into_response();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn error_body_carries_code_and_status_test() {
+        let (status, value) = body(ServerError::bad_request("invalid day")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(value["error_code"], "bad_request");
+        assert_eq!(value["error"], "invalid day");
+
+        let (status, value) = body(ServerError::not_found("block 12 not indexed yet")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(value["error_code"], "not_found");
+
+        let (status, value) = body(anyhow::anyhow!("query timed out").into()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(value["error_code"], "internal");
+    }
+
+    #[test]
+    fn gateway_errors_keep_their_code_test() {
+        let unavailable: ServerError = anyhow::Error::new(GatewayUnavailable {
+            status: 503,
+            content_type: Some("text/html".to_string()),
+            retry_in: None,
+        })
+        .into();
+        assert_eq!(unavailable.code, ErrorCode::GatewayUnavailable);
+        assert_eq!(unavailable.code.status(), StatusCode::BAD_GATEWAY);
+
+        // rejected by the circuit breaker inside ureq
+        let rejected: ServerError =
+            anyhow::Error::new(ureq::Error::Other(Box::new(GatewayUnavailable {
+                status: 503,
+                content_type: None,
+                retry_in: Some(std::time::Duration::from_secs(30)),
+            })))
+            .into();
+        assert_eq!(rejected.code, ErrorCode::GatewayUnavailable);
+
+        let missing: ServerError = anyhow::Error::new(TxNotFound {
+            txid: "abc".to_string(),
+        })
+        .context("downloading abc")
+        .into();
+        assert_eq!(missing.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn flp_errors_are_classified_by_kind_test() {
+        let missing = anyhow::Error::new(TxNotFound {
+            txid: "abc".to_string(),
+        });
+        let err: ServerError = FlpError::Gateway(missing).into();
+        assert_eq!(err.code, ErrorCode::NotFound);
+
+        let err: ServerError = FlpError::NotFound("delegation preferences".to_string()).into();
+        assert_eq!(err.code, ErrorCode::NotFound);
+
+        let err: ServerError = FlpError::Parse(anyhow::anyhow!("bad csv")).into();
+        assert_eq!(err.code, ErrorCode::Internal);
+        assert_eq!(err.err.to_string(), "bad csv");
+    }
+
+    #[test]
+    fn unsupported_sort_key_is_a_bad_request_test() {
+        let err: ServerError = anyhow::Error::new(UnsupportedSortKey {
+            key: "wallet".to_string(),
+            allowed: &["height"],
+        })
+        .into();
+        assert_eq!(err.code, ErrorCode::BadRequest);
     }
 }
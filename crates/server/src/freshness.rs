@@ -0,0 +1,214 @@
+//! data freshness response headers, so caches can reason about staleness
+//! without parsing bodies: explorer routes carry the newest indexed height of
+//! their rollup, snapshot routes the `ts` of the snapshot they serve. snapshot
+//! bodies also carry their age, flagged `stale` past the caller's
+//! `max_age_secs`, so an indexer outage doesn't pass for live data.
+
+use crate::errors::ServerError;
+use crate::indexer::AtlasIndexerClient;
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+pub const INDEXED_HEIGHT_HEADER: HeaderName = HeaderName::from_static("x-atlas-indexed-height");
+pub const SNAPSHOT_TS_HEADER: HeaderName = HeaderName::from_static("x-atlas-snapshot-ts");
+
+/// how long a looked up height is reused before asking ClickHouse again
+const HEIGHT_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Rollup {
+    Legacy,
+    Mainnet,
+}
+
+fn rollup_for(path: &str) -> Option<Rollup> {
+    if path.starts_with("/explorer/") {
+        Some(Rollup::Legacy)
+    } else if path.starts_with("/mainnet/explorer/") {
+        Some(Rollup::Mainnet)
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct HeightCache {
+    entries: HashMap<Rollup, (Instant, u64)>,
+}
+
+impl HeightCache {
+    fn get(&self, rollup: Rollup, now: Instant) -> Option<u64> {
+        self.entries
+            .get(&rollup)
+            .filter(|(at, _)| now.duration_since(*at) < HEIGHT_TTL)
+            .map(|(_, height)| *height)
+    }
+
+    fn put(&mut self, rollup: Rollup, now: Instant, height: u64) {
+        self.entries.insert(rollup, (now, height));
+    }
+}
+
+static HEIGHTS: LazyLock<Mutex<HeightCache>> = LazyLock::new(Mutex::default);
+
+async fn indexed_height(rollup: Rollup) -> Option<u64> {
+    if let Some(height) = HEIGHTS.lock().ok()?.get(rollup, Instant::now()) {
+        return Some(height);
+    }
+    let client = AtlasIndexerClient::new().await.ok()?;
+    let height = match rollup {
+        Rollup::Legacy => client.explorer_tip().await,
+        Rollup::Mainnet => client.mainnet_explorer_tip().await,
+    }
+    .ok()?;
+    HEIGHTS.lock().ok()?.put(rollup, Instant::now(), height);
+    Some(height)
+}
+
+/// adds `X-Atlas-Indexed-Height` to successful explorer responses. a failed
+/// height lookup only drops the header, never the response.
+pub async fn indexed_height_header(req: Request, next: Next) -> Response {
+    let rollup = rollup_for(req.uri().path());
+    let mut res = next.run(req).await;
+    if let Some(rollup) = rollup
+        && res.status().is_success()
+        && let Some(height) = indexed_height(rollup).await
+    {
+        res.headers_mut()
+            .insert(INDEXED_HEIGHT_HEADER, HeaderValue::from(height));
+    }
+    res
+}
+
+/// `X-Atlas-Snapshot-Ts` for a snapshot taken at `ts`
+pub fn snapshot_ts_headers(ts: DateTime<Utc>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&ts.to_rfc3339_opts(SecondsFormat::Millis, true)) {
+        headers.insert(SNAPSHOT_TS_HEADER, value);
+    }
+    headers
+}
+
+/// how old the served snapshot is. without a snapshot `age_secs` is null and
+/// any threshold counts as exceeded.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotAge {
+    pub age_secs: Option<u64>,
+    pub stale: bool,
+}
+
+impl SnapshotAge {
+    pub fn new(ts: Option<DateTime<Utc>>, now: DateTime<Utc>, max_age_secs: Option<u64>) -> Self {
+        // a snapshot stamped ahead of our clock is as fresh as it gets
+        let age_secs = ts.map(|ts| (now - ts).num_seconds().max(0) as u64);
+        let stale = match (age_secs, max_age_secs) {
+            (_, None) => false,
+            (Some(age), Some(max)) => age > max,
+            (None, Some(_)) => true,
+        };
+        Self { age_secs, stale }
+    }
+}
+
+/// `data` with its `SnapshotAge` fields next to its own
+#[derive(Serialize)]
+pub struct Aged<T> {
+    #[serde(flatten)]
+    pub data: T,
+    #[serde(flatten)]
+    pub age: SnapshotAge,
+}
+
+/// the optional `max_age_secs` query param
+pub fn parse_max_age(value: Option<&String>) -> Result<Option<u64>, ServerError> {
+    let Some(raw) = value.map(|v| v.trim()).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    raw.parse::<u64>()
+        .map(Some)
+        .map_err(|_| ServerError::bad_request(format!("invalid max_age_secs: {raw}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explorer_paths_map_to_their_rollup_test() {
+        assert_eq!(rollup_for("/explorer/blocks"), Some(Rollup::Legacy));
+        assert_eq!(rollup_for("/mainnet/explorer/day"), Some(Rollup::Mainnet));
+        assert_eq!(rollup_for("/mainnet/messages/recent"), None);
+        assert_eq!(rollup_for("/flp/snapshots"), None);
+    }
+
+    #[test]
+    fn cached_heights_expire_test() {
+        let mut cache = HeightCache::default();
+        let start = Instant::now();
+        cache.put(Rollup::Mainnet, start, 1_700_000);
+        assert_eq!(cache.get(Rollup::Mainnet, start), Some(1_700_000));
+        assert_eq!(cache.get(Rollup::Legacy, start), None);
+        assert_eq!(cache.get(Rollup::Mainnet, start + HEIGHT_TTL), None);
+    }
+
+    #[test]
+    fn snapshot_ts_is_rfc3339_millis_test() {
+        let ts = DateTime::from_timestamp_millis(1_735_689_600_250).unwrap();
+        let headers = snapshot_ts_headers(ts);
+        assert_eq!(headers[SNAPSHOT_TS_HEADER], "2025-01-01T00:00:00.250Z");
+    }
+
+    #[test]
+    fn snapshot_age_flags_stale_past_max_age_test() {
+        let now = DateTime::from_timestamp(1_735_689_600, 0).unwrap();
+        let ts = now - chrono::Duration::hours(2);
+        assert_eq!(
+            SnapshotAge::new(Some(ts), now, None),
+            SnapshotAge {
+                age_secs: Some(7200),
+                stale: false,
+            }
+        );
+        assert!(SnapshotAge::new(Some(ts), now, Some(3600)).stale);
+        assert!(!SnapshotAge::new(Some(ts), now, Some(7200)).stale);
+        // clock skew, and nothing indexed at all
+        let ahead = SnapshotAge::new(Some(now + chrono::Duration::seconds(5)), now, Some(0));
+        assert_eq!(ahead.age_secs, Some(0));
+        assert!(!ahead.stale);
+        assert!(SnapshotAge::new(None, now, Some(3600)).stale);
+        assert!(!SnapshotAge::new(None, now, None).stale);
+    }
+
+    #[test]
+    fn aged_body_keeps_data_fields_test() {
+        let body = serde_json::to_value(Aged {
+            data: serde_json::json!({ "project": "pid_a" }),
+            age: SnapshotAge {
+                age_secs: Some(60),
+                stale: true,
+            },
+        })
+        .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "project": "pid_a", "age_secs": 60, "stale": true })
+        );
+        assert!(matches!(
+            parse_max_age(Some(&"600".to_string())),
+            Ok(Some(600))
+        ));
+        assert!(matches!(parse_max_age(None), Ok(None)));
+        assert!(parse_max_age(Some(&"-1".to_string())).is_err());
+    }
+}
@@ -2,18 +2,28 @@ use anyhow::{Error, anyhow};
 use chrono::{DateTime, NaiveDate, Utc};
 use clickhouse::Row;
 use common::{
-    constants::{AO_TOKEN_START, DATA_PROTOCOL_A_START, DATA_PROTOCOL_B_START, PI_TOKEN_START},
-    env::get_env_var,
-    mainnet::get_network_height,
+    amount::{STORED_DECIMALS, TokenAmount},
+    constants::{
+        AO_TOKEN_START, DATA_PROTOCOL_A_START, DATA_PROTOCOL_B_START, PI_TOKEN_START, ao_authority,
+    },
+    env::{clickhouse_database, clickhouse_url, get_env_var},
+    height::{BlockHeight, to_block_height},
+    mainnet::{MAINNET_BLOCK_METRICS_QUERY, get_network_height},
+    projects::{Project, ProjectLabel},
 };
+use flp::{error::FlpError, types::MAX_FACTOR};
+use futures::{Stream, stream};
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct AtlasIndexerClient {
     client: clickhouse::Client,
+    query_timeout: Duration,
 }
 
+#[derive(Clone)]
 enum BindValue {
     Str(String),
     U64(u64),
@@ -30,23 +40,193 @@ impl BindValue {
     }
 }
 
+impl From<&str> for BindValue {
+    fn from(val: &str) -> Self {
+        BindValue::Str(val.to_string())
+    }
+}
+
+impl From<u64> for BindValue {
+    fn from(val: u64) -> Self {
+        BindValue::U64(val)
+    }
+}
+
+impl From<u32> for BindValue {
+    fn from(val: u32) -> Self {
+        BindValue::U32(val)
+    }
+}
+
+/// a sort key outside the query's whitelist
+#[derive(Debug)]
+pub struct UnsupportedSortKey {
+    pub key: String,
+    pub allowed: &'static [&'static str],
+}
+
+impl std::fmt::Display for UnsupportedSortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unsupported sort key {:?} (expected one of {})",
+            self.key,
+            self.allowed.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedSortKey {}
+
+/// the optional joins, filters and sort terms of a query. SQL text only
+/// comes from `&'static str` fragments and whitelisted columns, every value
+/// goes through a bind.
+#[derive(Default)]
+struct QueryParts {
+    joins: Vec<&'static str>,
+    join_binds: Vec<BindValue>,
+    filters: Vec<&'static str>,
+    filter_binds: Vec<BindValue>,
+    order: Vec<String>,
+}
+
+impl QueryParts {
+    /// `clause` carries one `?` per value in `binds`
+    fn join(&mut self, clause: &'static str, binds: impl IntoIterator<Item = BindValue>) {
+        self.joins.push(clause);
+        self.join_binds.extend(binds);
+    }
+
+    /// `clause` carries a single `?`, bound to `value`
+    fn filter(&mut self, clause: &'static str, value: impl Into<BindValue>) {
+        self.filters.push(clause);
+        self.filter_binds.push(value.into());
+    }
+
+    /// sorts by the `table` column `key` if it is one of `allowed`
+    fn order_by(
+        &mut self,
+        table: &'static str,
+        allowed: &'static [&'static str],
+        key: &str,
+        order: SortOrder,
+    ) -> Result<(), UnsupportedSortKey> {
+        let Some(column) = allowed.iter().find(|column| **column == key) else {
+            return Err(UnsupportedSortKey {
+                key: key.to_string(),
+                allowed,
+            });
+        };
+        self.order
+            .push(format!("{table}.{column} {}", order.as_sql()));
+        Ok(())
+    }
+
+    fn join_sql(&self) -> String {
+        self.joins.iter().map(|join| format!(" {join}")).collect()
+    }
+
+    /// the filters continuing a `where` the query already has
+    fn and_sql(&self) -> String {
+        self.filters
+            .iter()
+            .map(|clause| format!(" and {clause}"))
+            .collect()
+    }
+
+    fn where_sql(&self) -> String {
+        if self.filters.is_empty() {
+            String::new()
+        } else {
+            format!(" where {}", self.filters.join(" and "))
+        }
+    }
+
+    fn order_sql(&self) -> String {
+        if self.order.is_empty() {
+            String::new()
+        } else {
+            format!(" order by {}", self.order.join(", "))
+        }
+    }
+
+    /// binds the join values, then the filter values, in the order they were
+    /// added. the parts may be bound to several queries (rows and count).
+    fn bind(&self, mut query: clickhouse::query::Query) -> clickhouse::query::Query {
+        for value in self.join_binds.iter().chain(&self.filter_binds) {
+            query = value.clone().apply(query);
+        }
+        query
+    }
+}
+
 impl AtlasIndexerClient {
     pub async fn new() -> Result<Self, Error> {
-        let url = get_env_var("CLICKHOUSE_URL").unwrap_or_else(|_| "http://localhost:8123".into());
+        let url = clickhouse_url();
         let user = get_env_var("CLICKHOUSE_USER").unwrap_or_else(|_| "default".into());
         let password = get_env_var("CLICKHOUSE_PASSWORD").unwrap_or_default();
-        let database =
-            get_env_var("CLICKHOUSE_DATABASE").unwrap_or_else(|_| "atlas_oracles".into());
+        let database = clickhouse_database();
+        let query_timeout = get_env_var("CLICKHOUSE_QUERY_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30));
+        Self::connect(&url, &user, &password, &database, query_timeout).await
+    }
+
+    /// connects to an explicit instance/database and ensures its schema
+    pub async fn connect(
+        url: &str,
+        user: &str,
+        password: &str,
+        database: &str,
+        query_timeout: Duration,
+    ) -> Result<Self, Error> {
         let admin = clickhouse::Client::default()
-            .with_url(&url)
-            .with_user(&user)
-            .with_password(&password);
-        let client = admin.clone().with_database(&database);
-        ensure_schema(&admin, &client, &database).await?;
-        Ok(Self { client })
+            .with_url(url)
+            .with_user(user)
+            .with_password(password);
+        let client = admin.clone().with_database(database);
+        ensure_schema(&admin, &client, database).await?;
+        Ok(Self {
+            client,
+            query_timeout,
+        })
+    }
+
+    /// tags an expensive query with `max_execution_time` so ClickHouse aborts it
+    /// server-side even if the http client went away
+    fn bounded(&self, query: clickhouse::query::Query) -> clickhouse::query::Query {
+        query.with_option(
+            "max_execution_time",
+            self.query_timeout.as_secs().to_string(),
+        )
+    }
+
+    /// client-side guard on top of `bounded`, with a small grace period so the
+    /// server-side abort surfaces first
+    async fn with_timeout<T>(
+        &self,
+        fut: impl Future<Output = Result<T, clickhouse::error::Error>>,
+    ) -> Result<T, Error> {
+        let limit = self.query_timeout + Duration::from_secs(2);
+        match tokio::time::timeout(limit, fut).await {
+            Ok(res) => Ok(res?),
+            Err(_) => Err(anyhow!(
+                "query timed out after {}s",
+                self.query_timeout.as_secs()
+            )),
+        }
     }
 
-    pub async fn latest_project_snapshot(&self, project: &str) -> Result<ProjectSnapshot, Error> {
+    /// the project's latest positions per ticker. `min_amount` drops smaller
+    /// positions from `delegators`, `totals` always cover every position.
+    pub async fn latest_project_snapshot(
+        &self,
+        project: &str,
+        min_amount: Option<TokenAmount>,
+    ) -> Result<ProjectSnapshot, Error> {
         let query = "\
             with latest as (\
                 select ticker, max(ts) as ts \
@@ -60,24 +240,28 @@ impl AtlasIndexerClient {
             where p.project = ? \
             order by p.ticker, p.amount desc";
         let rows = self
-            .client
-            .query(query)
-            .bind(project)
-            .bind(project)
-            .fetch_all::<FlpPositionRow>()
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(project)
+                    .bind(project)
+                    .fetch_all::<FlpPositionRow>(),
+            )
             .await?;
         if rows.is_empty() {
-            return Err(anyhow!("no delegations found for project {project}"));
+            return Err(project_not_found(project));
         }
+        // every ticker's rows share its latest cycle ts
         let ts = rows.iter().map(|row| row.ts).max().unwrap();
+        let oldest_ts = rows.iter().map(|row| row.ts).min().unwrap();
         let totals = aggregate_totals(&rows);
         let delegators = rows
             .into_iter()
+            .filter(|row| meets_min_amount(row.amount, min_amount))
             .map(|row| Delegator {
                 wallet: row.wallet,
                 eoa: row.eoa,
                 ticker: row.ticker,
-                factor: row.factor,
+                factor: Factor(row.factor),
                 amount: row.amount,
                 ar_amount: row.ar_amount,
             })
@@ -85,11 +269,278 @@ impl AtlasIndexerClient {
         Ok(ProjectSnapshot {
             project: project.to_string(),
             ts,
+            oldest_ts,
             totals,
             delegators,
         })
     }
 
+    /// how many delegators `latest_project_snapshot` lists for the same
+    /// arguments (one per wallet and ticker), reading only their amounts
+    pub async fn count_project_delegators(
+        &self,
+        project: &str,
+        min_amount: Option<TokenAmount>,
+    ) -> Result<u64, Error> {
+        let query = "\
+            with latest as (\
+                select ticker, max(ts) as ts \
+                from flp_positions \
+                where project = ? \
+                group by ticker\
+            ) \
+            select p.amount \
+            from flp_positions p \
+            inner join latest l on p.ticker = l.ticker and p.ts = l.ts \
+            where p.project = ?";
+        let amounts = self
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(project)
+                    .bind(project)
+                    .fetch_all::<PositionAmountRow>(),
+            )
+            .await?;
+        if amounts.is_empty() {
+            return Err(project_not_found(project));
+        }
+        Ok(amounts
+            .into_iter()
+            .filter(|row| meets_min_amount(row.amount, min_amount))
+            .count() as u64)
+    }
+
+    /// latest snapshot of every FLP without the delegator lists, in one query
+    pub async fn all_project_snapshots(&self) -> Result<Vec<ProjectSnapshotSummary>, Error> {
+        let query = format!(
+            "\
+            with latest as (\
+                select project, ticker, max(ts) as ts \
+                from flp_positions \
+                group by project, ticker\
+            ), per_ticker as (\
+                select toString(p.project) as project, p.ticker as ticker, max(p.ts) as ticker_ts, \
+                toString({amount_sum}) as amount, \
+                toString({ar_amount_sum}) as ar_amount, \
+                toUInt32(count()) as ticker_delegators, \
+                groupUniqArray(p.wallet) as wallets \
+                from flp_positions p \
+                inner join latest l on p.project = l.project and p.ticker = l.ticker and p.ts = l.ts \
+                group by project, p.ticker\
+            ) \
+            select project, max(ticker_ts) as ts, \
+            arraySort(x -> x.1, groupArray(tuple(ticker, amount, ticker_delegators, ar_amount))) as totals, \
+            toUInt32(length(arrayDistinct(arrayFlatten(groupArray(wallets))))) as delegators_count \
+            from per_ticker \
+            group by project \
+            order by project",
+            amount_sum = decimal_sum("p.amount"),
+            ar_amount_sum = decimal_sum("p.ar_amount"),
+        );
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(&query))
+                    .fetch_all::<ProjectSnapshotSummaryRow>(),
+            )
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// wallets with a position in the project's latest snapshot whose AR balance
+    /// as of that cycle is 0 (possibly bridged out). balance rows are only
+    /// written on change, so the newest one at or before the snapshot counts.
+    /// balances that don't parse (e.g. rows written before `ar_balance` existed)
+    /// are skipped.
+    pub async fn zero_balance_delegators(
+        &self,
+        project: &str,
+    ) -> Result<Vec<ZeroBalanceDelegator>, Error> {
+        let query = "\
+            with latest as (\
+                select ticker, max(ts) as ts \
+                from flp_positions \
+                where project = ? \
+                group by ticker\
+            ) \
+            select p.wallet as wallet, p.eoa as eoa, p.ticker as ticker, p.factor as factor, \
+            p.amount as amount, b.stale as ar_balance_stale \
+            from flp_positions p \
+            inner join latest l on p.ticker = l.ticker and p.ts = l.ts \
+            asof inner join wallet_balances b on b.ticker = p.ticker and b.wallet = p.wallet and b.ts <= p.ts \
+            where p.project = ? \
+            and toDecimal128OrNull(b.ar_balance, 18) = 0 \
+            and toDecimal128OrZero(p.amount, 18) > 0 \
+            order by p.ticker, p.wallet";
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(project)
+                    .bind(project)
+                    .fetch_all::<ZeroBalanceDelegatorRow>(),
+            )
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// per-ticker delegation concentration at the project's latest snapshot
+    pub async fn project_concentration(
+        &self,
+        project: &str,
+    ) -> Result<ProjectConcentration, Error> {
+        let snapshot = self.latest_project_snapshot(project, None).await?;
+        let mut amounts: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        for delegator in &snapshot.delegators {
+            amounts
+                .entry(delegator.ticker.clone())
+                .or_default()
+                .push(delegator.amount.to_f64());
+        }
+        let tickers = amounts
+            .into_iter()
+            .map(|(ticker, amounts)| concentration(ticker, amounts))
+            .collect();
+        Ok(ProjectConcentration {
+            project: snapshot.project,
+            ts: snapshot.ts,
+            tickers,
+        })
+    }
+
+    /// per-wallet movement of a project's `ticker` delegations between its two
+    /// latest oracle snapshots. with a single snapshot every wallet is `new`.
+    pub async fn project_position_deltas(
+        &self,
+        project: &str,
+        ticker: &str,
+    ) -> Result<ProjectPositionDeltas, Error> {
+        let query = "\
+            select ts, ticker, wallet, eoa, toString(project) as project, factor, amount, ar_amount \
+            from flp_positions \
+            where project = ? and ticker = ? and ts in (\
+                select distinct ts from flp_positions \
+                where project = ? and ticker = ? \
+                order by ts desc \
+                limit 2\
+            )";
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(project)
+                    .bind(ticker)
+                    .bind(project)
+                    .bind(ticker)
+                    .fetch_all::<FlpPositionRow>(),
+            )
+            .await?;
+        let ts = match rows.iter().map(|row| row.ts).max() {
+            Some(ts) => ts,
+            None => {
+                return Err(anyhow!(
+                    "no {ticker} delegations found for project {project}"
+                ));
+            }
+        };
+        let (current, previous): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| row.ts == ts);
+        let previous_ts = previous.first().map(|row| row.ts);
+        Ok(ProjectPositionDeltas {
+            project: project.to_string(),
+            ticker: ticker.to_string(),
+            ts,
+            previous_ts,
+            deltas: position_deltas(wallet_amounts(previous), wallet_amounts(current)),
+        })
+    }
+
+    /// the ticker's latest AO vs EVM stake comparisons, newest first. empty
+    /// unless the indexer runs with `ENABLE_EVM_VERIFY`.
+    pub async fn oracle_divergence(
+        &self,
+        ticker: &str,
+        limit: u64,
+    ) -> Result<Vec<OracleDivergence>, Error> {
+        let query = "\
+            select ts, ticker, oracle_tx_id, evm_block, ao_total, evm_total, divergence, tolerance, diverged \
+            from oracle_evm_divergence \
+            where ticker = ? \
+            order by ts desc \
+            limit ?";
+        self.with_timeout(
+            self.bounded(self.client.query(query))
+                .bind(ticker)
+                .bind(limit)
+                .fetch_all::<OracleDivergence>(),
+        )
+        .await
+    }
+
+    /// tickers with at least one indexed oracle snapshot
+    pub async fn indexed_oracle_tickers(&self) -> Result<Vec<String>, Error> {
+        self.with_timeout(
+            self.bounded(
+                self.client
+                    .query("select distinct ticker from oracle_snapshots order by ticker"),
+            )
+            .fetch_all::<String>(),
+        )
+        .await
+    }
+
+    /// per-wallet Set-Balances changes between the ticker's two latest oracle
+    /// cycles. `wallet_balances` only stores changed balances, so a cycle's
+    /// wallets are the ones with a `wallet_delegations` row at its `ts`, each
+    /// with its newest balance at or before it. `None` without any cycle.
+    pub async fn oracle_balance_deltas(
+        &self,
+        ticker: &str,
+    ) -> Result<Option<OracleBalanceDeltas>, Error> {
+        let cycles = self
+            .with_timeout(
+                self.bounded(self.client.query(
+                    "select ts from oracle_snapshots where ticker = ? order by ts desc limit 2",
+                ))
+                .bind(ticker)
+                .fetch_all::<CycleTsRow>(),
+            )
+            .await?;
+        let Some(ts) = cycles.first().map(|cycle| cycle.ts) else {
+            return Ok(None);
+        };
+        let previous_ts = cycles.get(1).map(|cycle| cycle.ts);
+        let query = "\
+            select d.ts as ts, d.wallet as wallet, b.eoa as eoa, b.amount as amount \
+            from (\
+                select distinct ts, wallet from wallet_delegations \
+                where ts in (select ts from oracle_snapshots where ticker = ? order by ts desc limit 2)\
+            ) d \
+            asof inner join (\
+                select ts, wallet, eoa, amount from wallet_balances where ticker = ?\
+            ) b on b.wallet = d.wallet and b.ts <= d.ts";
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(ticker)
+                    .bind(ticker)
+                    .fetch_all::<CycleBalanceRow>(),
+            )
+            .await?;
+        let (current, previous): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| row.ts == ts);
+        let deltas = position_deltas(wallet_amounts(previous), wallet_amounts(current));
+        let count = |kind| deltas.iter().filter(|delta| delta.kind == kind).count();
+        let (entries, exits) = (
+            count(PositionDeltaKind::New),
+            count(PositionDeltaKind::Removed),
+        );
+        Ok(Some(OracleBalanceDeltas {
+            ticker: ticker.to_string(),
+            ts,
+            previous_ts,
+            entries,
+            exits,
+            changed: deltas.len() - entries - exits,
+            deltas,
+        }))
+    }
+
     pub async fn wallet_identity_history(&self, wallet: &str) -> Result<Vec<IdentityLink>, Error> {
         let rows = self
             .client
@@ -102,7 +553,9 @@ impl AtlasIndexerClient {
             .bind(wallet)
             .fetch_all::<IdentityRow>()
             .await?;
-        Ok(rows.into_iter().map(|row| row.into()).collect())
+        Ok(dedup_identity_links(
+            rows.into_iter().map(|row| row.into()).collect(),
+        ))
     }
 
     pub async fn eoa_identity_history(&self, eoa: &str) -> Result<Vec<IdentityLink>, Error> {
@@ -117,27 +570,54 @@ impl AtlasIndexerClient {
             .bind(eoa)
             .fetch_all::<IdentityRow>()
             .await?;
-        Ok(rows.into_iter().map(|row| row.into()).collect())
+        Ok(dedup_identity_links(
+            rows.into_iter().map(|row| row.into()).collect(),
+        ))
+    }
+
+    /// latest-wins `(wallet, eoa)` link for either an Arweave address or an EOA
+    pub async fn current_identity(&self, address: &str) -> Result<Option<IdentityLink>, Error> {
+        let rows = self
+            .client
+            .query(
+                "select wallet, eoa, ts \
+                 from wallet_balances \
+                 where wallet = ? or eoa = ? \
+                 order by ts desc \
+                 limit 1",
+            )
+            .bind(address)
+            .bind(address)
+            .fetch_all::<IdentityRow>()
+            .await?;
+        Ok(rows.into_iter().next().map(|row| row.into()))
     }
 
     pub async fn oracle_snapshot_feed(
         &self,
         ticker: &str,
         limit: u64,
+        order: SortOrder,
     ) -> Result<Vec<OracleSnapshot>, Error> {
+        // the latest `limit` snapshots, returned in the requested direction
+        let sql = format!(
+            "select ts, ticker, tx_id, toString(total_dec) as total, delegators from (\
+                select o.ts as ts, o.ticker as ticker, o.tx_id as tx_id, {} as total_dec, uniqExact(p.wallet) as delegators \
+                from oracle_snapshots o \
+                left join flp_positions p \
+                  on p.ticker = o.ticker and p.ts = o.ts \
+                where o.ticker = ? \
+                group by o.ts, o.ticker, o.tx_id \
+                having total_dec > 0 \
+                order by o.ts desc \
+                limit ?\
+             ) order by ts {}",
+            decimal_sum("p.amount"),
+            order.as_sql()
+        );
         let rows = self
             .client
-            .query(
-                "select o.ts, o.ticker, o.tx_id, toFloat64(sum(toDecimal128(if(length(p.amount) = 0, '0', p.amount), 18))) as total, uniqExact(p.wallet) as delegators \
-                 from oracle_snapshots o \
-                 left join flp_positions p \
-                   on p.ticker = o.ticker and p.ts = o.ts \
-                 where o.ticker = ? \
-                 group by o.ts, o.ticker, o.tx_id \
-                 having total > 0 \
-                 order by o.ts desc \
-                 limit ?",
-           )
+            .query(&sql)
             .bind(ticker)
             .bind(limit)
             .fetch_all::<OracleSnapshot>()
@@ -148,48 +628,163 @@ impl AtlasIndexerClient {
         Ok(rows)
     }
 
+    /// one total per UTC day in `[from, to]`: the day's latest snapshot total, else
+    /// the last one before it carried forward. `total` is null before the
+    /// ticker's first snapshot.
+    pub async fn oracle_value_daily(
+        &self,
+        ticker: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<OracleDailyValue>, Error> {
+        // snapshots before `from` collapse into the day before it, so the
+        // range opens with the value carried in
+        let sql = format!(
+            "select day, total, carried_forward from (\
+                select d.day as date, toString(d.day) as day, \
+                toString(last_value(s.total) over (order by d.day rows between unbounded preceding and current row)) as total, \
+                toBool(isNull(s.total)) as carried_forward \
+                from (\
+                    select toDate(?) - 1 + number as day \
+                    from numbers(toUInt64(dateDiff('day', toDate(?), toDate(?)) + 2))\
+                ) d \
+                left join (\
+                    select if(toDate(ts, 'UTC') < toDate(?), toDate(?) - 1, toDate(ts, 'UTC')) as day, \
+                    toNullable(argMax(total_dec, ts)) as total \
+                    from (\
+                        select o.ts as ts, {} as total_dec \
+                        from oracle_snapshots o \
+                        left join flp_positions p on p.ticker = o.ticker and p.ts = o.ts \
+                        where o.ticker = ? and toDate(o.ts, 'UTC') <= toDate(?) \
+                        group by o.ts \
+                        having total_dec > 0\
+                    ) \
+                    group by day\
+                ) s on s.day = d.day\
+             ) \
+             where date >= toDate(?) \
+             order by date",
+            decimal_sum("p.amount"),
+        );
+        let (from, to) = (from.to_string(), to.to_string());
+        let rows = self
+            .with_timeout(
+                self.client
+                    .query(&sql)
+                    .bind(&from)
+                    .bind(&from)
+                    .bind(&to)
+                    .bind(&from)
+                    .bind(&from)
+                    .bind(ticker)
+                    .bind(&to)
+                    .bind(&from)
+                    .fetch_all::<OracleDailyValue>(),
+            )
+            .await?;
+        Ok(rows)
+    }
+
     pub async fn wallet_delegation_mappings(
         &self,
         wallet: &str,
+        order: SortOrder,
     ) -> Result<Vec<DelegationMappingHistory>, Error> {
+        let sql = format!(
+            "select ts, height, tx_id, wallet_from, wallet_to, factor \
+             from delegation_mappings \
+             where wallet_from = ? \
+             order by height {}",
+            order.as_sql()
+        );
+        let rows = self
+            .client
+            .query(&sql)
+            .bind(wallet)
+            .fetch_all::<DelegationMappingRow>()
+            .await?;
+        if rows.is_empty() {
+            return Err(anyhow!("no delegation mappings found for wallet {wallet}"));
+        }
+        let mut out = group_delegation_mappings(rows);
+        match order {
+            SortOrder::Asc => out.sort_by_key(|r| r.height),
+            SortOrder::Desc => out.sort_by_key(|r| std::cmp::Reverse(r.height)),
+        }
+        Ok(out)
+    }
+
+    /// number of delegation mapping snapshots (txs) recorded for the wallet
+    pub async fn count_wallet_delegation_mappings(&self, wallet: &str) -> Result<u64, Error> {
+        self.count(
+            "select uniqExact(height, tx_id) as cnt \
+             from delegation_mappings \
+             where wallet_from = ?",
+            &[wallet],
+        )
+        .await
+    }
+
+    /// the wallet's delegation mapping snapshot in effect at `height`, i.e. the
+    /// newest group with `height <= H`
+    pub async fn wallet_delegation_at(
+        &self,
+        wallet: &str,
+        height: BlockHeight,
+    ) -> Result<Option<DelegationMappingHistory>, Error> {
         let rows = self
             .client
             .query(
                 "select ts, height, tx_id, wallet_from, wallet_to, factor \
                  from delegation_mappings \
-                 where wallet_from = ? \
-                 order by height desc",
+                 where wallet_from = ? and height <= ?",
             )
             .bind(wallet)
+            .bind(height)
+            .fetch_all::<DelegationMappingRow>()
+            .await?;
+        Ok(latest_delegation_mapping(group_delegation_mappings(rows)))
+    }
+
+    pub async fn delegation_mapping_by_tx(
+        &self,
+        tx_id: &str,
+    ) -> Result<Vec<DelegationMappingHistory>, Error> {
+        let rows = self
+            .client
+            .query(
+                "select ts, height, tx_id, wallet_from, wallet_to, factor \
+                 from delegation_mappings \
+                 where tx_id = ? \
+                 order by wallet_from",
+            )
+            .bind(tx_id)
             .fetch_all::<DelegationMappingRow>()
             .await?;
-        if rows.is_empty() {
-            return Err(anyhow!("no delegation mappings found for wallet {wallet}"));
-        }
         let mut map = BTreeMap::new();
         for row in rows {
-            let key = (row.height, row.tx_id.clone());
-            let entry = map.entry(key).or_insert_with(|| DelegationMappingHistory {
-                ts: row.ts,
-                height: row.height,
-                tx_id: row.tx_id.clone(),
-                wallet: row.wallet_from.clone(),
-                preferences: Vec::new(),
-            });
-            entry.preferences.push(DelegationPreference {
-                wallet_to: row.wallet_to,
-                factor: row.factor,
-            });
+            let entry =
+                map.entry(row.wallet_from.clone())
+                    .or_insert_with(|| DelegationMappingHistory {
+                        ts: row.ts,
+                        height: row.height,
+                        tx_id: row.tx_id.clone(),
+                        wallet: row.wallet_from.clone(),
+                        preferences: Vec::new(),
+                    });
+            entry
+                .preferences
+                .push(DelegationPreference::new(row.wallet_to, row.factor));
         }
-        let mut out: Vec<_> = map.into_values().collect();
-        out.sort_by(|a, b| b.height.cmp(&a.height));
-        Ok(out)
+        Ok(map.into_values().collect())
     }
 
     pub async fn latest_delegation_heights(
         &self,
         limit: u64,
-    ) -> Result<Vec<DelegationHeight>, Error> {
+        offset: u64,
+        with_total: bool,
+    ) -> Result<Page<DelegationHeight>, Error> {
         let rows = self
             .client
             .query(
@@ -197,50 +792,151 @@ impl AtlasIndexerClient {
                  from delegation_mappings \
                  group by height, tx_id \
                  order by height desc \
-                 limit ?",
+                 limit ? offset ?",
             )
             .bind(limit)
+            .bind(offset)
             .fetch_all::<DelegationHeightRow>()
             .await?;
-        if rows.is_empty() {
+        if rows.is_empty() && offset == 0 {
             return Err(anyhow!("no delegation mappings indexed yet"));
         }
-        Ok(rows
+        let total = if with_total {
+            Some(
+                self.count(
+                    "select count() as cnt from (\
+                        select height, tx_id from delegation_mappings group by height, tx_id\
+                     )",
+                    &[],
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+        let items = rows
             .into_iter()
             .map(|row| DelegationHeight {
                 height: row.height,
                 tx_id: row.tx_id,
             })
-            .collect())
+            .collect();
+        Ok(Page::new(items, limit, offset, total))
     }
 
-    pub async fn multi_project_delegators(&self, limit: u64) -> Result<Vec<MultiDelegator>, Error> {
+    /// wallets delegating to at least `min_projects` distinct FLPs
+    pub async fn multi_project_delegators(
+        &self,
+        min_projects: u64,
+        limit: u64,
+        offset: u64,
+        with_total: bool,
+    ) -> Result<Page<MultiDelegator>, Error> {
         let rows = self
             .client
-            .query(
+            .query(&format!(
                 "select wallet, any(eoa) as eoa, countDistinct(project) as project_count, \
-                 groupUniqArray(project) as projects \
+                 groupUniqArray({MULTI_DELEGATOR_MAX_PROJECTS})(project) as projects \
                  from flp_positions \
                  group by wallet \
-                 having project_count >= 2 \
-                 order by project_count desc \
-                 limit ?",
-            )
+                 having project_count >= ? \
+                 order by project_count desc, wallet \
+                 limit ? offset ?",
+            ))
+            .bind(min_projects)
             .bind(limit)
+            .bind(offset)
             .fetch_all::<MultiDelegatorRow>()
             .await?;
-        if rows.is_empty() {
+        if rows.is_empty() && offset == 0 {
             return Err(anyhow!("no multi project delegators found"));
         }
-        Ok(rows
-            .into_iter()
-            .map(|row| MultiDelegator {
-                wallet: row.wallet,
-                eoa: row.eoa,
-                project_count: row.project_count,
-                projects: row.projects,
-            })
-            .collect())
+        let total = if with_total {
+            Some(self.count_multi_project_delegators(min_projects).await?)
+        } else {
+            None
+        };
+        let items = rows.into_iter().map(MultiDelegator::from).collect();
+        Ok(Page::new(items, limit, offset, total))
+    }
+
+    /// number of wallets delegating to at least `min_projects` FLPs
+    pub async fn count_multi_project_delegators(&self, min_projects: u64) -> Result<u64, Error> {
+        let query = self
+            .bounded(self.client.query(
+                "select count() as cnt from (\
+                    select wallet from flp_positions \
+                    group by wallet \
+                    having countDistinct(project) >= ?\
+                 )",
+            ))
+            .bind(min_projects);
+        let row = self.with_timeout(query.fetch_one::<CountRow>()).await?;
+        Ok(row.cnt)
+    }
+
+    pub async fn all_projects_totals(&self) -> Result<Vec<ProjectTotals>, Error> {
+        let latest = "\
+            with latest as (\
+                select project, ticker, max(ts) as ts \
+                from flp_positions \
+                group by project, ticker\
+            ) ";
+        let ticker_rows = self
+            .client
+            .query(&format!(
+                "{latest}\
+                 select toString(p.project) as project, p.ticker, \
+                 toString({}) as amount, \
+                 toString({}) as ar_amount, \
+                 toUInt32(count()) as delegators_count \
+                 from flp_positions p \
+                 inner join latest l on p.project = l.project and p.ticker = l.ticker and p.ts = l.ts \
+                 group by project, p.ticker \
+                 order by project, p.ticker",
+                decimal_sum("p.amount"),
+                decimal_sum("p.ar_amount"),
+            ))
+            .fetch_all::<ProjectTickerTotalRow>()
+            .await?;
+        let count_rows = self
+            .client
+            .query(&format!(
+                "{latest}\
+                 select toString(p.project) as project, toUInt32(uniqExact(p.wallet)) as delegators_count \
+                 from flp_positions p \
+                 inner join latest l on p.project = l.project and p.ticker = l.ticker and p.ts = l.ts \
+                 group by project"
+            ))
+            .fetch_all::<ProjectDelegatorsRow>()
+            .await?;
+        let mut map: BTreeMap<String, ProjectTotals> = BTreeMap::new();
+        for row in count_rows {
+            map.insert(
+                row.project.clone(),
+                ProjectTotals {
+                    project: row.project,
+                    delegators_count: row.delegators_count,
+                    totals: Vec::new(),
+                },
+            );
+        }
+        for row in ticker_rows {
+            let entry = map
+                .entry(row.project.clone())
+                .or_insert_with(|| ProjectTotals {
+                    project: row.project.clone(),
+                    delegators_count: 0,
+                    totals: Vec::new(),
+                });
+            entry.totals.push(ProjectTotal {
+                ticker: row.ticker,
+                amount: row.amount,
+                delegators_count: row.delegators_count,
+                ar_amount: row.ar_amount,
+            });
+        }
+        Ok(map.into_values().collect())
     }
 
     pub async fn project_cycle_totals(
@@ -248,34 +944,234 @@ impl AtlasIndexerClient {
         project: &str,
         ticker: Option<&str>,
         limit: u64,
-    ) -> Result<Vec<ProjectCycleTotal>, Error> {
-        let ticker_clause = if ticker.is_some() {
-            " and p.ticker = ?"
+        offset: u64,
+        with_total: bool,
+    ) -> Result<Page<ProjectCycleTotal>, Error> {
+        let mut parts = QueryParts::default();
+        if let Some(ticker) = ticker {
+            parts.filter("p.ticker = ?", ticker);
+        }
+        let filters = parts.and_sql();
+        // one total per ticker found in the cycle, so new oracle tokens show
+        // up without a schema change. each ticker has its own snapshot tx, so
+        // a cycle is keyed by its ts alone
+        let query_str = format!(
+            "select ts, \
+             arraySort(x -> x.1, groupArray(tuple(ticker, tx_id))) as tx_ids, \
+             arraySort(x -> x.1, groupArray(tuple(ticker, total))) as totals from (\
+                select o.tx_id as tx_id, p.ts as ts, p.ticker as ticker, toString({}) as total \
+                from flp_positions p \
+                inner join oracle_snapshots o on o.ticker = p.ticker and o.ts = p.ts \
+                where p.project = ?{filters} \
+                group by o.tx_id, p.ts, p.ticker\
+             ) \
+             group by ts \
+             order by ts desc \
+             limit ? offset ?",
+            decimal_sum("p.amount"),
+        );
+        let query = parts.bind(self.bounded(self.client.query(&query_str).bind(project)));
+        let rows = self
+            .with_timeout(
+                query
+                    .bind(limit)
+                    .bind(offset)
+                    .fetch_all::<ProjectCycleTotalRow>(),
+            )
+            .await?;
+        let rows: Vec<ProjectCycleTotal> = rows.into_iter().map(|row| row.into()).collect();
+        if rows.is_empty() && offset == 0 {
+            return Err(anyhow!("no cycle totals found for project {project}"));
+        }
+        let total = if with_total {
+            let count_sql = format!(
+                "select uniqExact(p.ts) as cnt \
+                 from flp_positions p \
+                 inner join oracle_snapshots o on o.ticker = p.ticker and o.ts = p.ts \
+                 where p.project = ?{filters}"
+            );
+            let query = parts.bind(self.bounded(self.client.query(&count_sql).bind(project)));
+            Some(self.with_timeout(query.fetch_one::<CountRow>()).await?.cnt)
         } else {
-            ""
+            None
+        };
+        Ok(Page::new(rows, limit, offset, total))
+    }
+
+    /// the project's latest `limit` stored minting reports, newest first, each
+    /// with the delegated totals of the cycles nearest to it
+    pub async fn project_mint_vs_delegation(
+        &self,
+        project: &str,
+        limit: u64,
+    ) -> Result<Vec<MintVsDelegation>, Error> {
+        let reports = self
+            .with_timeout(
+                self.bounded(self.client.query(
+                    "select ts, report_id, distribution_tick, total_minted \
+                     from flp_minting_reports final \
+                     where project = ? \
+                     order by ts desc \
+                     limit ?",
+                ))
+                .bind(project)
+                .bind(limit)
+                .fetch_all::<MintingReportRow>(),
+            )
+            .await?;
+        let (Some(newest), Some(oldest)) = (reports.first(), reports.last()) else {
+            return Ok(Vec::new());
         };
+        let gap = MINT_CYCLE_MAX_GAP.num_milliseconds();
         let query_str = format!(
-            "select o.tx_id, p.ts, \
-             sumIf(toFloat64(p.amount), p.ticker = 'usds') as usds_total, \
-             sumIf(toFloat64(p.amount), p.ticker = 'dai') as dai_total, \
-             sumIf(toFloat64(p.amount), p.ticker = 'steth') as steth_total \
+            "select p.ticker as ticker, p.ts as ts, toString({}) as total \
              from flp_positions p \
-             inner join oracle_snapshots o on o.ticker = p.ticker and o.ts = p.ts \
-             where p.project = ?{ticker_clause} \
-             group by o.tx_id, p.ts \
-             order by p.ts desc \
-             limit ?",
+             where p.project = ? \
+             and p.ts between fromUnixTimestamp64Milli(toInt64(?)) and fromUnixTimestamp64Milli(toInt64(?)) \
+             group by p.ticker, p.ts \
+             order by p.ts",
+            decimal_sum("p.amount"),
         );
-        let mut query = self.client.query(&query_str);
-        query = query.bind(project);
-        if let Some(t) = ticker {
-            query = query.bind(t);
-        }
-        let rows = query.bind(limit).fetch_all::<ProjectCycleTotal>().await?;
-        if rows.is_empty() {
-            return Err(anyhow!("no cycle totals found for project {project}"));
+        let cycles = self
+            .with_timeout(
+                self.bounded(self.client.query(&query_str))
+                    .bind(project)
+                    .bind(oldest.ts.timestamp_millis() - gap)
+                    .bind(newest.ts.timestamp_millis() + gap)
+                    .fetch_all::<CycleTickerTotalRow>(),
+            )
+            .await?;
+        Ok(align_mint_with_cycles(reports, &cycles))
+    }
+
+    /// the newest mapping snapshot as `wallet_from,wallet_to,factor` CSV, in
+    /// chunks of `CSV_CHUNK_ROWS` rows read off a ClickHouse cursor so large
+    /// snapshots are never held in memory whole
+    pub async fn latest_delegation_mappings_csv(
+        &self,
+    ) -> Result<
+        (
+            DelegationHeight,
+            impl Stream<Item = Result<Vec<u8>, Error>> + Send + 'static,
+        ),
+        Error,
+    > {
+        let latest = self
+            .latest_delegation_heights(1, 0, false)
+            .await?
+            .items
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no delegation mappings indexed yet"))?;
+        // not `bounded`: the export runs as long as the client keeps reading,
+        // `final` drops rows a re-indexed tx stored twice
+        let cursor = self
+            .client
+            .query(
+                "select wallet_from, wallet_to, factor \
+                 from delegation_mappings final \
+                 where tx_id = ? \
+                 order by wallet_from, wallet_to",
+            )
+            .bind(&latest.tx_id)
+            .fetch::<DelegationMappingCsvRow>()?;
+        let chunks = stream::try_unfold(Some((cursor, true)), |state| async move {
+            let Some((mut cursor, header)) = state else {
+                return Ok(None);
+            };
+            let mut rows = Vec::with_capacity(CSV_CHUNK_ROWS);
+            while rows.len() < CSV_CHUNK_ROWS
+                && let Some(row) = cursor.next().await?
+            {
+                rows.push(row);
+            }
+            if rows.is_empty() && !header {
+                return Ok(None);
+            }
+            let chunk = delegation_mappings_csv(&rows, header)?;
+            let next = (rows.len() == CSV_CHUNK_ROWS).then_some((cursor, false));
+            Ok::<_, Error>(Some((chunk, next)))
+        });
+        Ok((latest, chunks))
+    }
+
+    /// newest indexed `Delegation-Mappings` tx with its row and wallet counts
+    pub async fn latest_delegation_snapshot(&self) -> Result<DelegationSnapshot, Error> {
+        let latest = self
+            .latest_delegation_heights(1, 0, false)
+            .await?
+            .items
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no delegation mappings indexed yet"))?;
+        let preferences_count = self
+            .count(
+                "select count() as cnt from delegation_mappings where tx_id = ?",
+                &[&latest.tx_id],
+            )
+            .await?;
+        let wallets_count = self
+            .count(
+                "select uniqExact(wallet_from) as cnt from delegation_mappings where tx_id = ?",
+                &[&latest.tx_id],
+            )
+            .await?;
+        Ok(DelegationSnapshot {
+            height: latest.height,
+            tx_id: latest.tx_id,
+            preferences_count,
+            wallets_count,
+        })
+    }
+
+    /// how wallets split their delegation in the newest mapping tx: wallets
+    /// counted by number of distinct targets (with a non-zero factor), 3 and
+    /// more grouped
+    pub async fn delegation_split_distribution(
+        &self,
+    ) -> Result<DelegationSplitDistribution, Error> {
+        let latest = self
+            .latest_delegation_heights(1, 0, false)
+            .await?
+            .items
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no delegation mappings indexed yet"))?;
+        let query = "\
+            select count() as wallets_count, \
+            countIf(targets = 1) as one_target, \
+            countIf(targets = 2) as two_targets, \
+            countIf(targets >= 3) as three_plus_targets \
+            from ( \
+                select wallet_from, uniqExact(wallet_to) as targets \
+                from delegation_mappings \
+                where tx_id = ? and factor > 0 \
+                group by wallet_from \
+            )";
+        let row = self
+            .with_timeout(
+                self.bounded(self.client.query(query))
+                    .bind(&latest.tx_id)
+                    .fetch_one::<DelegationSplitRow>(),
+            )
+            .await?;
+        Ok(DelegationSplitDistribution {
+            height: latest.height,
+            tx_id: latest.tx_id,
+            wallets_count: row.wallets_count,
+            one_target: row.one_target,
+            two_targets: row.two_targets,
+            three_plus_targets: row.three_plus_targets,
+        })
+    }
+
+    async fn count(&self, sql: &str, binds: &[&str]) -> Result<u64, Error> {
+        let mut query = self.bounded(self.client.query(sql));
+        for bind in binds {
+            query = query.bind(*bind);
         }
-        Ok(rows)
+        let row = self.with_timeout(query.fetch_one::<CountRow>()).await?;
+        Ok(row.cnt)
     }
 
     pub async fn recent_mainnet_messages(
@@ -312,7 +1208,7 @@ impl AtlasIndexerClient {
     pub async fn block_mainnet_messages(
         &self,
         protocol: Option<&str>,
-        height: u32,
+        height: BlockHeight,
         limit: u64,
     ) -> Result<Vec<MainnetMessage>, Error> {
         let mut clauses = vec!["m.block_height = ?".to_string()];
@@ -351,8 +1247,7 @@ impl AtlasIndexerClient {
         if tag_keys.is_empty() {
             return Ok(Vec::new());
         }
-        let placeholders = std::iter::repeat("?")
-            .take(tag_keys.len())
+        let placeholders = std::iter::repeat_n("?", tag_keys.len())
             .collect::<Vec<_>>()
             .join(", ");
         let protocol_clause = if protocol.is_some() {
@@ -360,17 +1255,19 @@ impl AtlasIndexerClient {
         } else {
             ""
         };
+        // a semi-join, so a message repeating the matched tag isn't fanned out
         let sql = format!(
             "select \
                 m.protocol, m.block_height, m.block_timestamp, m.msg_id, m.owner, m.recipient, \
                 m.bundled_in, m.data_size, m.ts, \
                 arrayFilter(x -> x.1 != '', groupArray(tuple(ifNull(t.tag_key, ''), ifNull(t.tag_value, '')))) as tags \
              from ao_mainnet_messages m \
-             inner join ao_mainnet_message_tags filter \
-               on filter.protocol = m.protocol and filter.block_height = m.block_height and filter.msg_id = m.msg_id \
              left join ao_mainnet_message_tags t \
                on t.protocol = m.protocol and t.block_height = m.block_height and t.msg_id = m.msg_id \
-             where filter.tag_key in ({placeholders}) and filter.tag_value = ?{protocol_clause} \
+             where (m.protocol, m.block_height, m.msg_id) in ( \
+                select protocol, block_height, msg_id from ao_mainnet_message_tags \
+                where tag_key in ({placeholders}) and tag_value = ? \
+             ){protocol_clause} \
              group by m.protocol, m.block_height, m.block_timestamp, m.msg_id, m.owner, m.recipient, m.bundled_in, m.data_size, m.ts \
              order by m.block_height desc, m.msg_id desc \
              limit ?"
@@ -387,6 +1284,48 @@ impl AtlasIndexerClient {
         Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 
+    /// most active processes over the last `last_n_blocks` indexed blocks. each
+    /// message is attributed to a single process (From-Process over Process)
+    pub async fn top_processes(
+        &self,
+        protocol: Option<&str>,
+        last_n_blocks: u32,
+        limit: u64,
+    ) -> Result<Vec<TopProcess>, Error> {
+        let protocol_clause = if protocol.is_some() {
+            " and protocol = ?"
+        } else {
+            ""
+        };
+        // both protocols share the arweave tip, so the window is anchored on the
+        // highest indexed block overall
+        let sql = format!(
+            "select process_id, uniqExact(protocol, msg_id) as message_count \
+             from ( \
+                select protocol, msg_id, \
+                    argMin(tag_value, if(lowerUTF8(tag_key) = 'from-process', 0, 1)) as process_id \
+                from ao_mainnet_message_tags \
+                where lowerUTF8(tag_key) in ('from-process', 'process') \
+                  and toInt64(block_height) > (select toInt64(max(block_height)) from ao_mainnet_messages) - ?{protocol_clause} \
+                group by protocol, msg_id \
+             ) \
+             where process_id != '' and process_id not in ? \
+             group by process_id \
+             order by message_count desc \
+             limit ?"
+        );
+        let mut query = self.client.query(&sql).bind(last_n_blocks);
+        if let Some(p) = protocol {
+            query = query.bind(p);
+        }
+        let rows = query
+            .bind(system_process_ids())
+            .bind(limit)
+            .fetch_all::<TopProcess>()
+            .await?;
+        Ok(rows)
+    }
+
     pub async fn mainnet_indexing_info(&self) -> Result<Vec<MainnetProtocolInfo>, Error> {
         let message_rows = self
             .client
@@ -424,13 +1363,151 @@ impl AtlasIndexerClient {
             .collect())
     }
 
+    /// the indexed stats of one legacy network block, `None` if not indexed yet
+    pub async fn explorer_block(&self, height: u64) -> Result<Option<ExplorerBlock>, Error> {
+        self.explorer_block_at("atlas_explorer", "height = ?", height)
+            .await
+    }
+
+    /// the indexed stats of one mainnet block, `None` if not indexed yet
+    pub async fn mainnet_explorer_block(
+        &self,
+        height: u64,
+    ) -> Result<Option<ExplorerBlock>, Error> {
+        self.explorer_block_at("ao_mainnet_explorer", "height = ?", height)
+            .await
+    }
+
+    /// computes a legacy network block's stats from the gateway, for heights the
+    /// rollup hasn't reached. rolling counters continue from the nearest lower
+    /// indexed block (or start at 0). `None` when the block has no ao txs.
+    pub async fn live_explorer_block(&self, height: u64) -> Result<Option<ExplorerBlock>, Error> {
+        let block_height = to_block_height(height)?;
+        let blocks =
+            tokio::task::spawn_blocking(move || explorer::aggregate_block_full(block_height))
+                .await??;
+        let Some(stats) = blocks.into_iter().find(|stats| stats.height == height) else {
+            return Ok(None);
+        };
+        let previous = self
+            .explorer_block_at("atlas_explorer", "height < ?", height)
+            .await?;
+        let (tx_roll, proc_roll, mod_roll) = previous.map_or((0, 0, 0), |block| {
+            (
+                block.tx_count_rolling,
+                block.processes_rolling,
+                block.modules_rolling,
+            )
+        });
+        let ts = DateTime::<Utc>::from_timestamp(stats.timestamp as i64, 0)
+            .ok_or_else(|| anyhow!("invalid timestamp for block {height}"))?;
+        Ok(Some(ExplorerBlock {
+            ts,
+            height,
+            tx_count: stats.tx_count,
+            eval_count: stats.eval_count,
+            transfer_count: stats.transfer_count,
+            new_process_count: stats.new_process_count,
+            new_module_count: stats.new_module_count,
+            active_users: stats.active_users,
+            active_processes: stats.active_processes,
+            tx_count_rolling: tx_roll + stats.tx_count,
+            processes_rolling: proc_roll + stats.new_process_count,
+            modules_rolling: mod_roll + stats.new_module_count,
+            total_data_size: None,
+            data_size_rolling: None,
+        }))
+    }
+
+    /// per-block diffs between the two explorer rollups, `atlas_explorer` (the
+    /// GraphQL bridge) and `ao_mainnet_explorer` (the indexed messages), over
+    /// `[from, to]`. `to` defaults to the lower of the two tips so both sources
+    /// cover the range, `from` to `RECONCILE_DEFAULT_WINDOW` blocks before it;
+    /// the window is clamped to `BLOCK_TIME_MAX_WINDOW` blocks.
+    pub async fn compare_explorer_sources(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<ExplorerReconciliation, Error> {
+        let to = match to {
+            Some(to) => to,
+            None => self
+                .explorer_tip()
+                .await?
+                .min(self.mainnet_explorer_tip().await?),
+        };
+        let from = from
+            .unwrap_or_else(|| to.saturating_sub(RECONCILE_DEFAULT_WINDOW - 1))
+            .max(to.saturating_sub(BLOCK_TIME_MAX_WINDOW - 1));
+        let legacy = self
+            .explorer_blocks_between("atlas_explorer", from, to)
+            .await?;
+        let mainnet = self
+            .explorer_blocks_between("ao_mainnet_explorer", from, to)
+            .await?;
+        Ok(reconcile_explorer_blocks(from, to, legacy, mainnet))
+    }
+
+    async fn explorer_blocks_between(
+        &self,
+        table: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<ExplorerBlock>, Error> {
+        let data_size = explorer_data_size_columns(table);
+        let sql = format!(
+            "select ts, height, tx_count, eval_count, transfer_count, \
+             new_process_count, new_module_count, active_users, active_processes, \
+             tx_count_rolling, processes_rolling, modules_rolling, {data_size} \
+             from {table} final \
+             where height between ? and ? \
+             order by height"
+        );
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(&sql))
+                    .bind(from)
+                    .bind(to)
+                    .fetch_all::<ExplorerBlockRow>(),
+            )
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    async fn explorer_block_at(
+        &self,
+        table: &str,
+        condition: &str,
+        height: u64,
+    ) -> Result<Option<ExplorerBlock>, Error> {
+        let data_size = explorer_data_size_columns(table);
+        let sql = format!(
+            "select ts, height, tx_count, eval_count, transfer_count, \
+             new_process_count, new_module_count, active_users, active_processes, \
+             tx_count_rolling, processes_rolling, modules_rolling, {data_size} \
+             from {table} \
+             where {condition} \
+             order by height desc \
+             limit 1"
+        );
+        let rows = self
+            .client
+            .query(&sql)
+            .bind(height)
+            .fetch_all::<ExplorerBlockRow>()
+            .await?;
+        Ok(rows.into_iter().next().map(|row| row.into()))
+    }
+
     pub async fn mainnet_explorer_blocks(&self, limit: u64) -> Result<Vec<ExplorerBlock>, Error> {
         let rows = self
             .client
             .query(
                 "select ts, height, tx_count, eval_count, transfer_count, \
                  new_process_count, new_module_count, active_users, active_processes, \
-                 tx_count_rolling, processes_rolling, modules_rolling \
+                 tx_count_rolling, processes_rolling, modules_rolling, \
+                 toNullable(total_data_size) as total_data_size, \
+                 toNullable(data_size_rolling) as data_size_rolling \
                  from ao_mainnet_explorer \
                  order by height desc \
                  limit ?",
@@ -441,6 +1518,132 @@ impl AtlasIndexerClient {
         Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 
+    /// rollup rows with `height > since`, ascending; the incremental-fetch
+    /// counterpart of `mainnet_explorer_blocks`
+    pub async fn explorer_blocks_since(
+        &self,
+        since: u64,
+        limit: u64,
+    ) -> Result<Vec<ExplorerBlock>, Error> {
+        let rows = self
+            .client
+            .query(
+                "select ts, height, tx_count, eval_count, transfer_count, \
+                 new_process_count, new_module_count, active_users, active_processes, \
+                 tx_count_rolling, processes_rolling, modules_rolling, \
+                 toNullable(total_data_size) as total_data_size, \
+                 toNullable(data_size_rolling) as data_size_rolling \
+                 from ao_mainnet_explorer \
+                 where height > ? \
+                 order by height asc \
+                 limit ?",
+            )
+            .bind(since)
+            .bind(limit)
+            .fetch_all::<ExplorerBlockRow>()
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// per-block activity series for `[from_height, to_height]`, ascending
+    pub async fn explorer_activity(
+        &self,
+        from_height: u64,
+        to_height: u64,
+        limit: u64,
+    ) -> Result<Vec<ExplorerActivity>, Error> {
+        let rows = self
+            .client
+            .query(
+                "select ts, height, tx_count, active_users, active_processes, \
+                 eval_count, transfer_count \
+                 from ao_mainnet_explorer \
+                 where height >= ? and height <= ? \
+                 order by height asc \
+                 limit ?",
+            )
+            .bind(from_height)
+            .bind(to_height)
+            .bind(limit)
+            .fetch_all::<ExplorerActivityRow>()
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| ExplorerActivity {
+                height: row.height,
+                ts: row.ts,
+                tx_count: row.tx_count,
+                active_users: row.active_users,
+                active_processes: row.active_processes,
+                eval_count: row.eval_count,
+                transfer_count: row.transfer_count,
+            })
+            .collect())
+    }
+
+    /// per-block metrics computed straight from the raw message tables, same
+    /// computation the indexer uses to rebuild `ao_mainnet_explorer`
+    pub async fn mainnet_block_metrics(
+        &self,
+        after_height: BlockHeight,
+        limit: u64,
+    ) -> Result<Vec<MainnetBlockMetric>, Error> {
+        let rows = self
+            .client
+            .query(MAINNET_BLOCK_METRICS_QUERY)
+            .bind(after_height)
+            .bind(limit)
+            .fetch_all::<MainnetBlockMetric>()
+            .await?;
+        Ok(rows)
+    }
+
+    /// true daily unique owners over `[from, to]` (inclusive, UTC days); days without
+    /// indexed messages are reported as zero
+    pub async fn daily_active_users(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyActiveUsers>, Error> {
+        let start = from.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+        let end = to
+            .succ_opt()
+            .unwrap_or(to)
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+        let rows = self
+            .client
+            .query(
+                "select toString(toDate(toDateTime(block_timestamp, 'UTC'))) as day, \
+                 uniqExact(owner) as active_users \
+                 from ao_mainnet_messages \
+                 where block_timestamp >= ? and block_timestamp < ? \
+                 group by day \
+                 order by day",
+            )
+            .bind(start)
+            .bind(end)
+            .fetch_all::<DailyActiveUsers>()
+            .await?;
+        let indexed: BTreeMap<String, u64> = rows
+            .into_iter()
+            .map(|row| (row.day, row.active_users))
+            .collect();
+        Ok(from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| {
+                let day = day.to_string();
+                DailyActiveUsers {
+                    active_users: indexed.get(&day).copied().unwrap_or(0),
+                    day,
+                }
+            })
+            .collect())
+    }
+
     pub async fn mainnet_daily_explorer_stats(
         &self,
         day: NaiveDate,
@@ -462,7 +1665,9 @@ impl AtlasIndexerClient {
                  sum(active_users) as active_users, sum(active_processes) as active_processes, \
                  max(tx_count_rolling) as txs_roll, \
                  max(processes_rolling) as processes_roll, \
-                 max(modules_rolling) as modules_roll \
+                 max(modules_rolling) as modules_roll, \
+                 toNullable(sum(total_data_size)) as data_size, \
+                 toNullable(max(data_size_rolling)) as data_size_roll \
                  from ao_mainnet_explorer \
                  where toUnixTimestamp(ts) >= ? and toUnixTimestamp(ts) < ?",
             )
@@ -482,6 +1687,8 @@ impl AtlasIndexerClient {
             txs_roll: 0,
             processes_roll: 0,
             modules_roll: 0,
+            data_size: None,
+            data_size_roll: None,
         });
         Ok(ExplorerDayStats {
             day,
@@ -496,6 +1703,8 @@ impl AtlasIndexerClient {
             txs_roll: stats.txs_roll,
             processes_roll: stats.processes_roll,
             modules_roll: stats.modules_roll,
+            data_size: stats.data_size,
+            data_size_roll: stats.data_size_roll,
         })
     }
 
@@ -513,7 +1722,9 @@ impl AtlasIndexerClient {
                  sum(active_users) as active_users, sum(active_processes) as active_processes, \
                  max(tx_count_rolling) as txs_roll, \
                  max(processes_rolling) as processes_roll, \
-                 max(modules_rolling) as modules_roll \
+                 max(modules_rolling) as modules_roll, \
+                 toNullable(sum(total_data_size)) as data_size, \
+                 toNullable(max(data_size_rolling)) as data_size_roll \
                  from ao_mainnet_explorer \
                  group by day_ts \
                  order by day_ts desc \
@@ -538,6 +1749,8 @@ impl AtlasIndexerClient {
                     txs_roll: row.txs_roll,
                     processes_roll: row.processes_roll,
                     modules_roll: row.modules_roll,
+                    data_size: row.data_size,
+                    data_size_roll: row.data_size_roll,
                 })
             })
             .collect())
@@ -586,7 +1799,7 @@ impl AtlasIndexerClient {
             None => (None, None),
         };
         let block_lag = match (arweave_tip, last_processed_height) {
-            (Some(tip), Some(processed)) if tip >= processed as u64 => Some(tip - processed as u64),
+            (Some(tip), Some(processed)) => tip.checked_sub(u64::from(processed)),
             _ => None,
         };
         let max_block_height = if stats.max_block_height == 0 {
@@ -723,38 +1936,209 @@ impl AtlasIndexerClient {
             group by recipient.tag_value \
             order by total_quantity desc \
             limit ?";
-        let spenders = self
+        let spenders = self
+            .client
+            .query(top_spenders_sql)
+            .bind(token)
+            .bind(limit)
+            .fetch_all::<AoTokenSumRow>()
+            .await?;
+        let receivers = self
+            .client
+            .query(top_receivers_sql)
+            .bind(token)
+            .bind(limit)
+            .fetch_all::<AoTokenSumRow>()
+            .await?;
+        Ok(AoTokenRichlist {
+            top_spenders: spenders
+                .into_iter()
+                .map(|row| AoTokenQuantityRank {
+                    address: row.address,
+                    total_quantity: format_quantity_human(row.total_quantity),
+                })
+                .collect(),
+            top_receivers: receivers
+                .into_iter()
+                .map(|row| AoTokenQuantityRank {
+                    address: row.address,
+                    total_quantity: format_quantity_human(row.total_quantity),
+                })
+                .collect(),
+        })
+    }
+
+    /// net balance of `address` from indexed Credit-Notices (inbound minus
+    /// outbound). approximation: mints/burns without transfer notices are ignored.
+    pub async fn ao_token_balance(
+        &self,
+        token: &str,
+        address: &str,
+    ) -> Result<AoTokenBalance, Error> {
+        let inbound_sql = "\
+            select sum(toUInt128OrZero(qty.tag_value)) as total_quantity \
+            from ao_token_messages m \
+            inner join ao_token_message_tags qty \
+              on qty.token = m.token and qty.source = m.source and qty.block_height = m.block_height \
+             and qty.msg_id = m.msg_id \
+            inner join ao_token_message_tags action \
+              on action.token = m.token and action.source = m.source and action.block_height = m.block_height \
+             and action.msg_id = m.msg_id \
+            where m.token = ? \
+              and m.recipient = ? \
+              and qty.tag_key = 'Quantity' \
+              and action.tag_key = 'Action' \
+              and action.tag_value = 'Credit-Notice'";
+        let outbound_sql = "\
+            select sum(toUInt128OrZero(qty.tag_value)) as total_quantity \
+            from ao_token_message_tags sender \
+            inner join ao_token_message_tags qty \
+              on qty.token = sender.token and qty.source = sender.source and qty.block_height = sender.block_height \
+             and qty.msg_id = sender.msg_id \
+            inner join ao_token_message_tags action \
+              on action.token = sender.token and action.source = sender.source and action.block_height = sender.block_height \
+             and action.msg_id = sender.msg_id \
+            where sender.token = ? \
+              and sender.tag_key = 'Sender' \
+              and sender.tag_value = ? \
+              and qty.tag_key = 'Quantity' \
+              and action.tag_key = 'Action' \
+              and action.tag_value = 'Credit-Notice'";
+        let inbound = self
+            .client
+            .query(inbound_sql)
+            .bind(token)
+            .bind(address)
+            .fetch_one::<AoTokenQuantityRow>()
+            .await?
+            .total_quantity;
+        let outbound = self
+            .client
+            .query(outbound_sql)
+            .bind(token)
+            .bind(address)
+            .fetch_one::<AoTokenQuantityRow>()
+            .await?
+            .total_quantity;
+        let balance = if inbound >= outbound {
+            format_quantity_human(inbound - outbound)
+        } else {
+            format!("-{}", format_quantity_human(outbound - inbound))
+        };
+        Ok(AoTokenBalance {
+            address: address.to_string(),
+            inbound: format_quantity_human(inbound),
+            outbound: format_quantity_human(outbound),
+            balance,
+        })
+    }
+
+    /// addresses ranked by their net Credit-Notice balance, same approximation
+    /// as `ao_token_balance`
+    pub async fn ao_token_top_holders(
+        &self,
+        token: &str,
+        limit: u64,
+    ) -> Result<Vec<AoTokenQuantityRank>, Error> {
+        let sql = "\
+            select address, toUInt128(sum(delta)) as total_quantity \
+            from ( \
+                select m.recipient as address, toInt256(toUInt128OrZero(qty.tag_value)) as delta \
+                from ao_token_messages m \
+                inner join ao_token_message_tags qty \
+                  on qty.token = m.token and qty.source = m.source and qty.block_height = m.block_height \
+                 and qty.msg_id = m.msg_id \
+                inner join ao_token_message_tags action \
+                  on action.token = m.token and action.source = m.source and action.block_height = m.block_height \
+                 and action.msg_id = m.msg_id \
+                where m.token = ? \
+                  and qty.tag_key = 'Quantity' \
+                  and action.tag_key = 'Action' \
+                  and action.tag_value = 'Credit-Notice' \
+                union all \
+                select sender.tag_value as address, -toInt256(toUInt128OrZero(qty.tag_value)) as delta \
+                from ao_token_message_tags sender \
+                inner join ao_token_message_tags qty \
+                  on qty.token = sender.token and qty.source = sender.source and qty.block_height = sender.block_height \
+                 and qty.msg_id = sender.msg_id \
+                inner join ao_token_message_tags action \
+                  on action.token = sender.token and action.source = sender.source and action.block_height = sender.block_height \
+                 and action.msg_id = sender.msg_id \
+                where sender.token = ? \
+                  and sender.tag_key = 'Sender' \
+                  and qty.tag_key = 'Quantity' \
+                  and action.tag_key = 'Action' \
+                  and action.tag_value = 'Credit-Notice' \
+            ) \
+            group by address \
+            having sum(delta) > 0 \
+            order by total_quantity desc \
+            limit ?";
+        let rows = self
             .client
-            .query(top_spenders_sql)
+            .query(sql)
             .bind(token)
-            .bind(limit)
-            .fetch_all::<AoTokenSumRow>()
-            .await?;
-        let receivers = self
-            .client
-            .query(top_receivers_sql)
             .bind(token)
             .bind(limit)
             .fetch_all::<AoTokenSumRow>()
             .await?;
-        Ok(AoTokenRichlist {
-            top_spenders: spenders
-                .into_iter()
-                .map(|row| AoTokenQuantityRank {
-                    address: row.address,
-                    total_quantity: format_quantity_human(row.total_quantity),
-                })
-                .collect(),
-            top_receivers: receivers
-                .into_iter()
-                .map(|row| AoTokenQuantityRank {
-                    address: row.address,
-                    total_quantity: format_quantity_human(row.total_quantity),
-                })
-                .collect(),
-        })
+        Ok(rows
+            .into_iter()
+            .map(|row| AoTokenQuantityRank {
+                address: row.address,
+                total_quantity: format_quantity_human(row.total_quantity),
+            })
+            .collect())
+    }
+
+    /// per-day count and summed `Quantity` of the token's transfers (one
+    /// Credit-Notice each) between `from` and `to`, by UTC day of the block timestamp. a
+    /// transfer without a numeric `Quantity` is counted but adds no volume.
+    pub async fn token_transfer_volume_daily(
+        &self,
+        token: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TokenTransferVolumeDay>, Error> {
+        let start = from.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+        let end = to
+            .succ_opt()
+            .unwrap_or(to)
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+        let sql = "\
+            select toString(toDate(toDateTime(m.block_timestamp, 'UTC'))) as day, \
+                   count() as transfers, \
+                   countIf(isNull(t.quantity)) as missing_quantity, \
+                   sum(ifNull(t.quantity, toUInt128(0))) as volume \
+            from ao_token_messages m final \
+            inner join ( \
+                select source, block_height, msg_id, \
+                       anyIf(toUInt128OrNull(tag_value), tag_key = 'Quantity') as quantity \
+                from ao_token_message_tags final \
+                where token = ? \
+                group by source, block_height, msg_id \
+                having countIf(tag_key = 'Action' and tag_value = 'Credit-Notice') > 0 \
+            ) t on t.source = m.source and t.block_height = m.block_height and t.msg_id = m.msg_id \
+            where m.token = ? and m.block_timestamp >= ? and m.block_timestamp < ? \
+            group by day \
+            order by day";
+        let rows = self
+            .with_timeout(
+                self.bounded(self.client.query(sql))
+                    .bind(token)
+                    .bind(token)
+                    .bind(start)
+                    .bind(end)
+                    .fetch_all::<TransferVolumeRow>(),
+            )
+            .await?;
+        Ok(transfer_volume_days(from, to, rows))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn ao_token_messages(
         &self,
         token: &str,
@@ -764,103 +2148,94 @@ impl AtlasIndexerClient {
         max_qty: Option<&str>,
         from_ts: Option<u64>,
         to_ts: Option<u64>,
-        block_min: Option<u32>,
-        block_max: Option<u32>,
+        block_min: Option<BlockHeight>,
+        block_max: Option<BlockHeight>,
         recipient: Option<&str>,
         sender: Option<&str>,
+        sort: Option<&str>,
         order: Option<&str>,
         limit: u64,
         offset: u64,
     ) -> Result<Vec<AoTokenMessage>, Error> {
-        let mut joins = Vec::new();
-        let mut where_clauses = Vec::new();
-        let mut join_binds: Vec<BindValue> = Vec::new();
-        let mut where_binds: Vec<BindValue> = Vec::new();
-
-        where_clauses.push("m.token = ?");
-        where_binds.push(BindValue::Str(token.to_string()));
+        const SORT_KEYS: &[&str] = &["block_height", "block_timestamp", "msg_id"];
+        let mut parts = QueryParts::default();
+        parts.filter("m.token = ?", token);
 
         if let Some(val) = action {
-            joins.push(
+            parts.join(
                 "inner join ao_token_message_tags action_filter \
                  on action_filter.token = m.token and action_filter.source = m.source and action_filter.block_height = m.block_height \
                  and action_filter.msg_id = m.msg_id and action_filter.tag_key = 'Action' \
-                 and lowerUTF8(action_filter.tag_value) = lowerUTF8(?)"
-                    .to_string(),
+                 and lowerUTF8(action_filter.tag_value) = lowerUTF8(?)",
+                [val.into()],
             );
-            join_binds.push(BindValue::Str(val.to_string()));
         }
         if let Some(val) = recipient {
-            joins.push(
+            parts.join(
                 "inner join ao_token_message_tags recipient_filter \
                  on recipient_filter.token = m.token and recipient_filter.source = m.source and recipient_filter.block_height = m.block_height \
                  and recipient_filter.msg_id = m.msg_id and recipient_filter.tag_key = 'Recipient' \
-                 and recipient_filter.tag_value = ?"
-                    .to_string(),
+                 and recipient_filter.tag_value = ?",
+                [val.into()],
             );
-            join_binds.push(BindValue::Str(val.to_string()));
         }
         if let Some(val) = sender {
-            joins.push(
+            parts.join(
                 "inner join ao_token_message_tags sender_filter \
                  on sender_filter.token = m.token and sender_filter.source = m.source and sender_filter.block_height = m.block_height \
                  and sender_filter.msg_id = m.msg_id and sender_filter.tag_key = 'Sender' \
-                 and sender_filter.tag_value = ?"
-                    .to_string(),
+                 and sender_filter.tag_value = ?",
+                [val.into()],
             );
-            join_binds.push(BindValue::Str(val.to_string()));
         }
         if min_qty.is_some() || max_qty.is_some() {
-            joins.push(
+            parts.join(
                 "inner join ao_token_message_tags qty_filter \
                  on qty_filter.token = m.token and qty_filter.source = m.source and qty_filter.block_height = m.block_height \
-                 and qty_filter.msg_id = m.msg_id and qty_filter.tag_key = 'Quantity'"
-                    .to_string(),
+                 and qty_filter.msg_id = m.msg_id and qty_filter.tag_key = 'Quantity'",
+                [],
             );
         }
         if let Some(val) = min_qty {
-            where_clauses.push("toUInt128OrZero(qty_filter.tag_value) >= toUInt128OrZero(?)");
-            where_binds.push(BindValue::Str(val.to_string()));
+            parts.filter(
+                "toUInt128OrZero(qty_filter.tag_value) >= toUInt128OrZero(?)",
+                val,
+            );
         }
         if let Some(val) = max_qty {
-            where_clauses.push("toUInt128OrZero(qty_filter.tag_value) <= toUInt128OrZero(?)");
-            where_binds.push(BindValue::Str(val.to_string()));
+            parts.filter(
+                "toUInt128OrZero(qty_filter.tag_value) <= toUInt128OrZero(?)",
+                val,
+            );
         }
         if let Some(val) = source {
-            where_clauses.push("m.source = ?");
-            where_binds.push(BindValue::Str(val.to_string()));
+            parts.filter("m.source = ?", val);
         }
         if let Some(val) = from_ts {
-            where_clauses.push("m.block_timestamp >= ?");
-            where_binds.push(BindValue::U64(val));
+            parts.filter("m.block_timestamp >= ?", val);
         }
         if let Some(val) = to_ts {
-            where_clauses.push("m.block_timestamp <= ?");
-            where_binds.push(BindValue::U64(val));
+            parts.filter("m.block_timestamp <= ?", val);
         }
         if let Some(val) = block_min {
-            where_clauses.push("m.block_height >= ?");
-            where_binds.push(BindValue::U32(val));
+            parts.filter("m.block_height >= ?", val);
         }
         if let Some(val) = block_max {
-            where_clauses.push("m.block_height <= ?");
-            where_binds.push(BindValue::U32(val));
+            parts.filter("m.block_height <= ?", val);
         }
-
-        let join_clause = if joins.is_empty() {
-            String::new()
-        } else {
-            format!("\n{}", joins.join("\n"))
-        };
-        let where_clause = if where_clauses.is_empty() {
-            String::new()
-        } else {
-            format!("\nwhere {}", where_clauses.join(" and "))
-        };
-        let order_dir = match order {
-            Some("asc") => "asc",
-            _ => "desc",
+        let order = match order {
+            Some("asc") => SortOrder::Asc,
+            _ => SortOrder::Desc,
         };
+        // `msg_id` breaks ties, so pages don't overlap
+        let sort = sort.unwrap_or("block_height");
+        parts.order_by("m", SORT_KEYS, sort, order)?;
+        if sort != "msg_id" {
+            parts.order_by("m", SORT_KEYS, "msg_id", order)?;
+        }
+
+        let (join_clause, where_clause, order_clause) =
+            (parts.join_sql(), parts.where_sql(), parts.order_sql());
         let sql = format!(
             "select \
                 m.source, m.block_height, m.block_timestamp, m.msg_id, m.owner, m.recipient, \
@@ -868,17 +2243,14 @@ impl AtlasIndexerClient {
                 arrayFilter(x -> x.1 != '', groupArray(tuple(ifNull(t.tag_key, ''), ifNull(t.tag_value, '')))) as tags \
              from ao_token_messages m \
              left join ao_token_message_tags t \
-               on t.token = m.token and t.source = m.source and t.block_height = m.block_height and t.msg_id = m.msg_id \
-             {join_clause} \
+               on t.token = m.token and t.source = m.source and t.block_height = m.block_height and t.msg_id = m.msg_id\
+             {join_clause}\
              {where_clause} \
-             group by m.source, m.block_height, m.block_timestamp, m.msg_id, m.owner, m.recipient, m.bundled_in, m.data_size, m.ts \
-             order by m.block_height {order_dir}, m.msg_id {order_dir} \
+             group by m.source, m.block_height, m.block_timestamp, m.msg_id, m.owner, m.recipient, m.bundled_in, m.data_size, m.ts\
+             {order_clause} \
              limit ? offset ?"
         );
-        let mut query = self.client.query(&sql);
-        for bind in join_binds.into_iter().chain(where_binds.into_iter()) {
-            query = bind.apply(query);
-        }
+        let query = parts.bind(self.client.query(&sql));
         let rows = query
             .bind(limit)
             .bind(offset)
@@ -954,23 +2326,126 @@ impl AtlasIndexerClient {
         Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 
-    pub async fn latest_explorer_blocks(&self, limit: u64) -> Result<Vec<ExplorerBlock>, Error> {
+    pub async fn latest_explorer_blocks(
+        &self,
+        limit: u64,
+        order: SortOrder,
+    ) -> Result<Vec<ExplorerBlock>, Error> {
+        // the latest `limit` blocks, returned in the requested direction
+        let sql = format!(
+            "select * from (\
+                select ts, height, tx_count, eval_count, transfer_count, \
+                new_process_count, new_module_count, active_users, active_processes, \
+                tx_count_rolling, processes_rolling, modules_rolling, {} \
+                from atlas_explorer \
+                order by height desc \
+                limit ?\
+             ) order by height {}",
+            explorer_data_size_columns("atlas_explorer"),
+            order.as_sql()
+        );
         let rows = self
             .client
-            .query(
-                "select ts, height, tx_count, eval_count, transfer_count, \
-                 new_process_count, new_module_count, active_users, active_processes, \
-                 tx_count_rolling, processes_rolling, modules_rolling \
-                 from atlas_explorer \
-                 order by height desc \
-                 limit ?",
-            )
+            .query(&sql)
             .bind(limit)
             .fetch_all::<ExplorerBlockRow>()
             .await?;
         Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 
+    /// average and p50/p95 seconds between legacy network blocks in `[from, to]`
+    pub async fn explorer_block_time(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<BlockTimeStats, Error> {
+        self.block_time_stats("atlas_explorer", from, to).await
+    }
+
+    /// average and p50/p95 seconds between mainnet blocks in `[from, to]`
+    pub async fn mainnet_explorer_block_time(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<BlockTimeStats, Error> {
+        self.block_time_stats("ao_mainnet_explorer", from, to).await
+    }
+
+    /// newest indexed legacy explorer height (0 when empty)
+    pub async fn explorer_tip(&self) -> Result<u64, Error> {
+        self.max_height("atlas_explorer").await
+    }
+
+    /// newest indexed mainnet explorer height (0 when empty)
+    pub async fn mainnet_explorer_tip(&self) -> Result<u64, Error> {
+        self.max_height("ao_mainnet_explorer").await
+    }
+
+    async fn max_height(&self, table: &str) -> Result<u64, Error> {
+        let sql = format!("select max(height) from {table}");
+        self.with_timeout(self.client.query(&sql).fetch_one::<u64>())
+            .await
+    }
+
+    /// `to` defaults to the newest indexed height and `from` to
+    /// `BLOCK_TIME_DEFAULT_WINDOW` blocks before it; the window is clamped to
+    /// `BLOCK_TIME_MAX_WINDOW` blocks ending at `to`. blocks without a timestamp
+    /// (0) are dropped and blocks repeating an earlier timestamp are treated as
+    /// interpolated (only the first is kept), so a gap is spread over the
+    /// heights it spans.
+    async fn block_time_stats(
+        &self,
+        table: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<BlockTimeStats, Error> {
+        let to = match to {
+            Some(to) => to,
+            None => self.max_height(table).await?,
+        };
+        let from = from
+            .unwrap_or_else(|| to.saturating_sub(BLOCK_TIME_DEFAULT_WINDOW - 1))
+            .max(to.saturating_sub(BLOCK_TIME_MAX_WINDOW - 1));
+        let sql = format!(
+            "select count() as intervals, avg(dt) as avg_secs, \
+             quantileExact(0.5)(dt) as p50_secs, quantileExact(0.95)(dt) as p95_secs \
+             from (\
+                select (ms - prev_ms) / 1000 / (height - prev_height) as dt \
+                from (\
+                    select height, ms, \
+                    lagInFrame(height) over w as prev_height, \
+                    lagInFrame(ms) over w as prev_ms \
+                    from (\
+                        select min(height) as height, toUnixTimestamp64Milli(ts) as ms \
+                        from {table} \
+                        where height between ? and ? and ts > toDateTime64(0, 3) \
+                        group by ts\
+                    ) \
+                    window w as (order by height rows between 1 preceding and current row)\
+                ) \
+                where prev_ms > 0 and ms > prev_ms\
+             )"
+        );
+        let row = self
+            .with_timeout(
+                self.client
+                    .query(&sql)
+                    .bind(from)
+                    .bind(to)
+                    .fetch_one::<BlockTimeRow>(),
+            )
+            .await?;
+        let stat = |value: f64| (row.intervals > 0).then_some(value);
+        Ok(BlockTimeStats {
+            from_height: from,
+            to_height: to,
+            intervals: row.intervals,
+            avg_secs: stat(row.avg_secs),
+            p50_secs: stat(row.p50_secs),
+            p95_secs: stat(row.p95_secs),
+        })
+    }
+
     pub async fn daily_explorer_stats(&self, day: NaiveDate) -> Result<ExplorerDayStats, Error> {
         let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
         let end = day
@@ -989,7 +2464,9 @@ impl AtlasIndexerClient {
                  sum(active_users) as active_users, sum(active_processes) as active_processes, \
                  max(tx_count_rolling) as txs_roll, \
                  max(processes_rolling) as processes_roll, \
-                 max(modules_rolling) as modules_roll \
+                 max(modules_rolling) as modules_roll, \
+                 cast(null, 'Nullable(UInt64)') as data_size, \
+                 cast(null, 'Nullable(UInt64)') as data_size_roll \
                  from atlas_explorer \
                  where toUnixTimestamp(ts) >= ? and toUnixTimestamp(ts) < ?",
             )
@@ -1009,6 +2486,8 @@ impl AtlasIndexerClient {
             txs_roll: 0,
             processes_roll: 0,
             modules_roll: 0,
+            data_size: None,
+            data_size_roll: None,
         });
         Ok(ExplorerDayStats {
             day,
@@ -1023,6 +2502,8 @@ impl AtlasIndexerClient {
             txs_roll: stats.txs_roll,
             processes_roll: stats.processes_roll,
             modules_roll: stats.modules_roll,
+            data_size: stats.data_size,
+            data_size_roll: stats.data_size_roll,
         })
     }
 
@@ -1037,7 +2518,9 @@ impl AtlasIndexerClient {
                  sum(active_users) as active_users, sum(active_processes) as active_processes, \
                  max(tx_count_rolling) as txs_roll, \
                  max(processes_rolling) as processes_roll, \
-                 max(modules_rolling) as modules_roll \
+                 max(modules_rolling) as modules_roll, \
+                 cast(null, 'Nullable(UInt64)') as data_size, \
+                 cast(null, 'Nullable(UInt64)') as data_size_roll \
                  from atlas_explorer \
                  group by day_ts \
                  order by day_ts desc \
@@ -1062,6 +2545,8 @@ impl AtlasIndexerClient {
                     txs_roll: row.txs_roll,
                     processes_roll: row.processes_roll,
                     modules_roll: row.modules_roll,
+                    data_size: row.data_size,
+                    data_size_roll: row.data_size_roll,
                 })
             })
             .collect())
@@ -1079,12 +2564,14 @@ async fn ensure_schema(
         "create table if not exists oracle_snapshots(ts DateTime64(3), ticker String, tx_id String) engine=MergeTree order by (ticker, ts)",
         "create table if not exists wallet_balances(ts DateTime64(3), ticker String, wallet String, eoa String, amount String, tx_id String) engine=ReplacingMergeTree order by (ticker, wallet, ts)",
         "create table if not exists wallet_delegations(ts DateTime64(3), wallet String, payload String) engine=ReplacingMergeTree order by (wallet, ts)",
-        "create table if not exists flp_positions(ts DateTime64(3), ticker String, wallet String, eoa String, project String, factor UInt32, amount String) engine=ReplacingMergeTree order by (project, wallet, ts)",
+        "create table if not exists flp_positions(ts DateTime64(3), ticker String, wallet String, eoa String, project String, factor UInt32, amount String) engine=ReplacingMergeTree order by (project, ticker, wallet, ts)",
         "create table if not exists delegation_mappings(ts DateTime64(3), height UInt32, tx_id String, wallet_from String, wallet_to String, factor UInt32) engine=ReplacingMergeTree order by (height, tx_id, wallet_from, wallet_to)",
         "create table if not exists atlas_explorer(ts DateTime64(3), height UInt64, tx_count UInt64, eval_count UInt64, transfer_count UInt64, new_process_count UInt64, new_module_count UInt64, active_users UInt64, active_processes UInt64, tx_count_rolling UInt64, processes_rolling UInt64, modules_rolling UInt64) engine=ReplacingMergeTree order by height",
         "create table if not exists ao_token_messages(ts DateTime64(3), token String, source String, block_height UInt32, block_timestamp UInt64, msg_id String, owner String, recipient String, bundled_in String, data_size String) engine=ReplacingMergeTree order by (token, source, block_height, msg_id)",
         "create table if not exists ao_token_message_tags(ts DateTime64(3), token String, source String, block_height UInt32, msg_id String, tag_key String, tag_value String) engine=ReplacingMergeTree order by (token, source, tag_key, tag_value, block_height, msg_id)",
         "create table if not exists ao_token_block_state(token String, last_complete_height UInt32, updated_at DateTime64(3)) engine=ReplacingMergeTree order by (token, updated_at)",
+        "create table if not exists oracle_evm_divergence(ts DateTime64(3), ticker String, oracle_tx_id String, evm_block UInt64, ao_total String, evm_total String, divergence Float64, tolerance Float64, diverged Bool) engine=ReplacingMergeTree order by (ticker, ts)",
+        "create table if not exists flp_minting_reports(ts DateTime64(3), project String, report_id String, distribution_tick UInt32, total_minted String, total_inflow String, ao_kept String, ao_exchanged_for_pi String) engine=ReplacingMergeTree order by (project, ts, report_id)",
     ];
     for stmt in stmts {
         client.query(stmt).execute().await?;
@@ -1092,6 +2579,8 @@ async fn ensure_schema(
     let alters = [
         "alter table wallet_balances add column if not exists eoa String after wallet",
         "alter table wallet_balances add column if not exists ar_balance String after amount",
+        "alter table wallet_balances add column if not exists stale Bool default false",
+        "alter table atlas_explorer add column if not exists action_counts String",
         "alter table flp_positions add column if not exists eoa String after wallet",
         "alter table flp_positions add column if not exists ar_amount String after amount",
         "alter table flp_positions modify column project String",
@@ -1106,22 +2595,38 @@ async fn ensure_schema(
     Ok(())
 }
 
+fn project_not_found(project: &str) -> Error {
+    FlpError::NotFound(format!("delegations for project {project}")).into()
+}
+
+/// the `min_amount` dust filter of a project's delegator list
+fn meets_min_amount(amount: TokenAmount, min_amount: Option<TokenAmount>) -> bool {
+    min_amount.is_none_or(|min| amount >= min)
+}
+
 fn aggregate_totals(rows: &[FlpPositionRow]) -> Vec<ProjectTotal> {
     let mut map = BTreeMap::new();
     for row in rows {
         let entry = map.entry(row.ticker.clone()).or_insert(ProjectTotal {
             ticker: row.ticker.clone(),
-            amount: 0.0,
-            ar_amount: 0.0,
+            amount: TokenAmount::ZERO,
+            ar_amount: TokenAmount::ZERO,
             delegators_count: 0,
         });
-        entry.amount += row.amount.parse::<f64>().unwrap_or(0.0);
-        entry.ar_amount += row.ar_amount.parse::<f64>().unwrap_or(0.0);
+        entry.amount += row.amount;
+        entry.ar_amount += row.ar_amount;
         entry.delegators_count += 1;
     }
     map.into_values().collect()
 }
 
+/// exact ClickHouse sum of a `String` amount column; empty values count as
+/// zero. the indexer rounds stored amounts to `STORED_DECIMALS`, the scale used
+/// here, so this agrees with the Rust side's `TokenAmount` sums.
+fn decimal_sum(column: &str) -> String {
+    format!("sum(toDecimal128(if({column} = '', '0', {column}), {STORED_DECIMALS}))")
+}
+
 #[derive(Row, serde::Deserialize)]
 struct FlpPositionRow {
     #[allow(dead_code)]
@@ -1133,8 +2638,294 @@ struct FlpPositionRow {
     #[allow(dead_code)]
     project: String,
     factor: u32,
-    amount: String,
-    ar_amount: String,
+    amount: TokenAmount,
+    ar_amount: TokenAmount,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct PositionAmountRow {
+    amount: TokenAmount,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct CycleTsRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct CycleBalanceRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    wallet: String,
+    eoa: String,
+    amount: TokenAmount,
+}
+
+/// number of largest delegators whose combined share is reported
+const CONCENTRATION_TOP_N: usize = 10;
+/// blocks covered by the block-time stats when no `from` height is given
+pub const BLOCK_TIME_DEFAULT_WINDOW: u64 = 1000;
+pub const BLOCK_TIME_MAX_WINDOW: u64 = 10_000;
+
+/// blocks compared by `/explorer/reconcile` when no `from` height is given
+pub const RECONCILE_DEFAULT_WINDOW: u64 = 1000;
+
+/// pairs the two rollups' blocks by height. rolling counters aren't compared,
+/// they depend on where each rollup started.
+fn reconcile_explorer_blocks(
+    from_height: u64,
+    to_height: u64,
+    legacy: Vec<ExplorerBlock>,
+    mainnet: Vec<ExplorerBlock>,
+) -> ExplorerReconciliation {
+    let (legacy_blocks, mainnet_blocks) = (legacy.len(), mainnet.len());
+    let mut pairs: BTreeMap<u64, (Option<ExplorerBlock>, Option<ExplorerBlock>)> = BTreeMap::new();
+    for block in legacy {
+        let height = block.height;
+        pairs.entry(height).or_default().0 = Some(block);
+    }
+    for block in mainnet {
+        let height = block.height;
+        pairs.entry(height).or_default().1 = Some(block);
+    }
+    let mut matching = 0;
+    let mut diffs = Vec::new();
+    for (height, pair) in pairs {
+        let diff = match pair {
+            (Some(legacy), Some(mainnet)) => {
+                let fields = explorer_field_diffs(&legacy, &mainnet);
+                if fields.is_empty() {
+                    matching += 1;
+                    continue;
+                }
+                ExplorerBlockDiff {
+                    height,
+                    missing_from: None,
+                    fields,
+                }
+            }
+            (Some(_), None) => ExplorerBlockDiff {
+                height,
+                missing_from: Some(ExplorerSource::Mainnet),
+                fields: BTreeMap::new(),
+            },
+            (None, _) => ExplorerBlockDiff {
+                height,
+                missing_from: Some(ExplorerSource::Legacy),
+                fields: BTreeMap::new(),
+            },
+        };
+        diffs.push(diff);
+    }
+    ExplorerReconciliation {
+        from_height,
+        to_height,
+        legacy_blocks,
+        mainnet_blocks,
+        matching,
+        diffs,
+    }
+}
+
+/// the data size columns of an explorer rollup table; `atlas_explorer` doesn't
+/// track them, so they read as null there
+fn explorer_data_size_columns(table: &str) -> &'static str {
+    if table == "ao_mainnet_explorer" {
+        "toNullable(total_data_size) as total_data_size, \
+         toNullable(data_size_rolling) as data_size_rolling"
+    } else {
+        "cast(null, 'Nullable(UInt64)') as total_data_size, \
+         cast(null, 'Nullable(UInt64)') as data_size_rolling"
+    }
+}
+
+fn explorer_field_diffs(
+    legacy: &ExplorerBlock,
+    mainnet: &ExplorerBlock,
+) -> BTreeMap<&'static str, FieldDiff> {
+    [
+        ("tx_count", legacy.tx_count, mainnet.tx_count),
+        ("eval_count", legacy.eval_count, mainnet.eval_count),
+        (
+            "transfer_count",
+            legacy.transfer_count,
+            mainnet.transfer_count,
+        ),
+        (
+            "new_process_count",
+            legacy.new_process_count,
+            mainnet.new_process_count,
+        ),
+        (
+            "new_module_count",
+            legacy.new_module_count,
+            mainnet.new_module_count,
+        ),
+        ("active_users", legacy.active_users, mainnet.active_users),
+        (
+            "active_processes",
+            legacy.active_processes,
+            mainnet.active_processes,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, legacy, mainnet)| legacy != mainnet)
+    .map(|(field, legacy, mainnet)| {
+        (
+            field,
+            FieldDiff {
+                legacy,
+                mainnet,
+                delta: mainnet as i64 - legacy as i64,
+            },
+        )
+    })
+    .collect()
+}
+
+/// a wallet's amount in one snapshot, what `position_deltas` compares
+struct WalletAmount {
+    wallet: String,
+    eoa: String,
+    amount: TokenAmount,
+}
+
+impl From<FlpPositionRow> for WalletAmount {
+    fn from(row: FlpPositionRow) -> Self {
+        Self {
+            wallet: row.wallet,
+            eoa: row.eoa,
+            amount: row.amount,
+        }
+    }
+}
+
+impl From<CycleBalanceRow> for WalletAmount {
+    fn from(row: CycleBalanceRow) -> Self {
+        Self {
+            wallet: row.wallet,
+            eoa: row.eoa,
+            amount: row.amount,
+        }
+    }
+}
+
+fn wallet_amounts<R: Into<WalletAmount>>(rows: Vec<R>) -> Vec<WalletAmount> {
+    rows.into_iter().map(Into::into).collect()
+}
+
+/// diff two snapshots of the same project/ticker by wallet; unchanged wallets are left out
+fn position_deltas(previous: Vec<WalletAmount>, current: Vec<WalletAmount>) -> Vec<PositionDelta> {
+    let mut before: BTreeMap<String, WalletAmount> = previous
+        .into_iter()
+        .map(|row| (row.wallet.clone(), row))
+        .collect();
+    let mut deltas = Vec::new();
+    for row in current {
+        let previous_amount = before.remove(&row.wallet).map(|prev| prev.amount);
+        let (kind, previous_amount) = match previous_amount {
+            None => (PositionDeltaKind::New, TokenAmount::ZERO),
+            Some(prev) if row.amount > prev => (PositionDeltaKind::Increased, prev),
+            Some(prev) if row.amount < prev => (PositionDeltaKind::Decreased, prev),
+            Some(_) => continue,
+        };
+        deltas.push(PositionDelta {
+            wallet: row.wallet,
+            eoa: row.eoa,
+            kind,
+            previous_amount,
+            current_amount: row.amount,
+            delta: row.amount - previous_amount,
+        });
+    }
+    deltas.extend(before.into_values().map(|row| PositionDelta {
+        wallet: row.wallet,
+        eoa: row.eoa,
+        kind: PositionDeltaKind::Removed,
+        previous_amount: row.amount,
+        current_amount: TokenAmount::ZERO,
+        delta: TokenAmount::ZERO - row.amount,
+    }));
+    deltas.sort_by(|a, b| {
+        b.delta
+            .value()
+            .abs()
+            .cmp(&a.delta.value().abs())
+            .then_with(|| a.wallet.cmp(&b.wallet))
+    });
+    deltas
+}
+
+/// top-N share and Gini coefficient over the delegated amounts of one ticker.
+/// both are 0 when nothing is delegated; a single delegator has a Gini of 0
+/// and a top-N share of 1.
+fn concentration(ticker: String, mut amounts: Vec<f64>) -> TickerConcentration {
+    amounts.sort_by(|a, b| b.total_cmp(a));
+    let n = amounts.len();
+    let total: f64 = amounts.iter().sum();
+    let (top_share, gini) = if total > 0.0 {
+        let top: f64 = amounts.iter().take(CONCENTRATION_TOP_N).sum();
+        // amounts are desc, so the ascending rank of the i-th entry is n - i
+        let weighted: f64 = amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| (n - i) as f64 * amount)
+            .sum();
+        let gini = 2.0 * weighted / (n as f64 * total) - (n as f64 + 1.0) / n as f64;
+        (top / total, gini.max(0.0))
+    } else {
+        (0.0, 0.0)
+    };
+    TickerConcentration {
+        ticker,
+        delegators_count: n as u32,
+        total_amount: total,
+        top_n: CONCENTRATION_TOP_N as u32,
+        top_n_share: top_share,
+        gini,
+    }
+}
+
+/// groups flat mapping rows into one snapshot per (height, tx_id), ordered by
+/// height asc
+fn group_delegation_mappings(rows: Vec<DelegationMappingRow>) -> Vec<DelegationMappingHistory> {
+    let mut map = BTreeMap::new();
+    for row in rows {
+        let key = (row.height, row.tx_id.clone());
+        let entry = map.entry(key).or_insert_with(|| DelegationMappingHistory {
+            ts: row.ts,
+            height: row.height,
+            tx_id: row.tx_id.clone(),
+            wallet: row.wallet_from.clone(),
+            preferences: Vec::new(),
+        });
+        entry
+            .preferences
+            .push(DelegationPreference::new(row.wallet_to, row.factor));
+    }
+    map.into_values().collect()
+}
+
+/// picks the newest snapshot; several txs in the same block are ordered by ts
+fn latest_delegation_mapping(
+    groups: Vec<DelegationMappingHistory>,
+) -> Option<DelegationMappingHistory> {
+    groups.into_iter().max_by_key(|g| (g.height, g.ts))
+}
+
+/// collapses runs of identical links (rows are written every cycle) so the
+/// history only shows actual changes. expects `ts desc` input and keeps the
+/// oldest row of each run, i.e. when the link started.
+fn dedup_identity_links(links: Vec<IdentityLink>) -> Vec<IdentityLink> {
+    let mut out: Vec<IdentityLink> = Vec::new();
+    for link in links {
+        match out.last_mut() {
+            Some(last) if last.wallet == link.wallet && last.eoa == link.eoa => *last = link,
+            _ => out.push(link),
+        }
+    }
+    out
 }
 
 #[derive(Row, serde::Deserialize)]
@@ -1155,20 +2946,204 @@ impl From<IdentityRow> for IdentityLink {
     }
 }
 
-#[derive(Serialize)]
-pub struct ProjectSnapshot {
-    pub project: String,
-    pub ts: DateTime<Utc>,
-    pub totals: Vec<ProjectTotal>,
-    pub delegators: Vec<Delegator>,
+/// whitelisted sort direction, the only way user input reaches an ORDER BY
+#[derive(Clone, Copy, Default)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+/// envelope for paginated list endpoints. `total` is only computed on demand
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_offset: Option<u64>,
+    pub total: Option<u64>,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, limit: u64, offset: u64, total: Option<u64>) -> Self {
+        let next = offset + items.len() as u64;
+        let has_more = match total {
+            Some(total) => next < total,
+            None => items.len() as u64 == limit,
+        };
+        Self {
+            next_offset: has_more.then_some(next),
+            items,
+            total,
+        }
+    }
+}
+
+#[derive(Row, serde::Deserialize)]
+struct CountRow {
+    cnt: u64,
+}
+
+#[derive(Serialize)]
+pub struct ProjectSnapshot {
+    pub project: String,
+    pub ts: DateTime<Utc>,
+    /// latest cycle of the ticker indexed longest ago, what the snapshot's
+    /// freshness is judged by
+    #[serde(skip)]
+    pub oldest_ts: DateTime<Utc>,
+    pub totals: Vec<ProjectTotal>,
+    pub delegators: Vec<Delegator>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProjectSnapshotSummary {
+    pub project: String,
+    pub ts: DateTime<Utc>,
+    pub delegators_count: u32,
+    pub totals: Vec<ProjectTotal>,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ProjectSnapshotSummaryRow {
+    project: String,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    totals: Vec<(String, TokenAmount, u32, TokenAmount)>,
+    delegators_count: u32,
+}
+
+impl From<ProjectSnapshotSummaryRow> for ProjectSnapshotSummary {
+    fn from(row: ProjectSnapshotSummaryRow) -> Self {
+        Self {
+            project: row.project,
+            ts: row.ts,
+            delegators_count: row.delegators_count,
+            totals: row
+                .totals
+                .into_iter()
+                .map(
+                    |(ticker, amount, delegators_count, ar_amount)| ProjectTotal {
+                        ticker,
+                        amount,
+                        delegators_count,
+                        ar_amount,
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProjectConcentration {
+    pub project: String,
+    pub ts: DateTime<Utc>,
+    pub tickers: Vec<TickerConcentration>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TickerConcentration {
+    pub ticker: String,
+    pub delegators_count: u32,
+    pub total_amount: f64,
+    pub top_n: u32,
+    pub top_n_share: f64,
+    pub gini: f64,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ZeroBalanceDelegatorRow {
+    wallet: String,
+    eoa: String,
+    ticker: String,
+    factor: u32,
+    amount: TokenAmount,
+    ar_balance_stale: bool,
+}
+
+impl From<ZeroBalanceDelegatorRow> for ZeroBalanceDelegator {
+    fn from(row: ZeroBalanceDelegatorRow) -> Self {
+        ZeroBalanceDelegator {
+            wallet: row.wallet,
+            eoa: row.eoa,
+            ticker: row.ticker,
+            factor: Factor(row.factor),
+            amount: row.amount,
+            ar_balance_stale: row.ar_balance_stale,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ZeroBalanceDelegator {
+    pub wallet: String,
+    pub eoa: String,
+    pub ticker: String,
+    #[serde(flatten)]
+    pub factor: Factor,
+    pub amount: TokenAmount,
+    /// the zero balance was carried forward from an earlier cycle
+    pub ar_balance_stale: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProjectPositionDeltas {
+    pub project: String,
+    pub ticker: String,
+    pub ts: DateTime<Utc>,
+    /// `None` when the project has a single snapshot for the ticker
+    pub previous_ts: Option<DateTime<Utc>>,
+    pub deltas: Vec<PositionDelta>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OracleBalanceDeltas {
+    pub ticker: String,
+    pub ts: DateTime<Utc>,
+    /// `None` when the ticker has a single indexed cycle
+    pub previous_ts: Option<DateTime<Utc>>,
+    /// wallets new to the latest cycle
+    pub entries: usize,
+    /// wallets gone from the latest cycle
+    pub exits: usize,
+    /// wallets in both whose amount changed
+    pub changed: usize,
+    pub deltas: Vec<PositionDelta>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionDeltaKind {
+    New,
+    Removed,
+    Increased,
+    Decreased,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PositionDelta {
+    pub wallet: String,
+    pub eoa: String,
+    pub kind: PositionDeltaKind,
+    pub previous_amount: TokenAmount,
+    pub current_amount: TokenAmount,
+    pub delta: TokenAmount,
 }
 
 #[derive(Serialize, Clone)]
 pub struct ProjectTotal {
     pub ticker: String,
-    pub amount: f64,
+    pub amount: TokenAmount,
     pub delegators_count: u32,
-    pub ar_amount: f64,
+    pub ar_amount: TokenAmount,
 }
 
 #[derive(Serialize, Clone)]
@@ -1176,9 +3151,10 @@ pub struct Delegator {
     pub wallet: String,
     pub eoa: String,
     pub ticker: String,
-    pub factor: u32,
-    pub amount: String,
-    pub ar_amount: String,
+    #[serde(flatten)]
+    pub factor: Factor,
+    pub amount: TokenAmount,
+    pub ar_amount: TokenAmount,
 }
 
 #[derive(Serialize, Clone)]
@@ -1195,15 +3171,40 @@ pub struct OracleSnapshot {
     pub ts: DateTime<Utc>,
     pub ticker: String,
     pub tx_id: String,
-    pub total: f64,
+    pub total: TokenAmount,
     pub delegators: u64,
 }
 
+/// one cycle's comparison of the oracle's summed balances with the stake
+/// its EVM contract held
+#[derive(Row, Serialize, serde::Deserialize, Clone)]
+pub struct OracleDivergence {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+    pub ticker: String,
+    pub oracle_tx_id: String,
+    pub evm_block: u64,
+    pub ao_total: TokenAmount,
+    pub evm_total: TokenAmount,
+    /// |ao_total - evm_total| / evm_total
+    pub divergence: f64,
+    pub tolerance: f64,
+    pub diverged: bool,
+}
+
+#[derive(Row, Serialize, serde::Deserialize, Clone)]
+pub struct OracleDailyValue {
+    pub day: String,
+    pub total: Option<TokenAmount>,
+    /// no snapshot on this day, `total` comes from an earlier one
+    pub carried_forward: bool,
+}
+
 #[derive(Row, serde::Deserialize)]
 struct DelegationMappingRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     ts: DateTime<Utc>,
-    height: u32,
+    height: BlockHeight,
     tx_id: String,
     wallet_from: String,
     wallet_to: String,
@@ -1213,7 +3214,7 @@ struct DelegationMappingRow {
 #[derive(Serialize, Clone)]
 pub struct DelegationMappingHistory {
     pub ts: DateTime<Utc>,
-    pub height: u32,
+    pub height: BlockHeight,
     pub tx_id: String,
     pub wallet: String,
     pub preferences: Vec<DelegationPreference>,
@@ -1222,19 +3223,130 @@ pub struct DelegationMappingHistory {
 #[derive(Serialize, Clone)]
 pub struct DelegationPreference {
     pub wallet_to: String,
-    pub factor: u32,
+    #[serde(flatten)]
+    pub factor: Factor,
+    /// the FLP `wallet_to` points at, null for any other target
+    pub target_project: Option<ProjectLabel>,
+}
+
+impl DelegationPreference {
+    fn new(wallet_to: String, factor: u32) -> Self {
+        Self {
+            target_project: Project::label_for(&wallet_to),
+            wallet_to,
+            factor: Factor(factor),
+        }
+    }
+}
+
+/// a delegation factor in basis points (`MAX_FACTOR` = 100%). flattened into
+/// its parent as the raw `factor` plus the `percent` it stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Factor(pub u32);
+
+impl Factor {
+    pub fn percent(self) -> f64 {
+        self.0 as f64 * 100.0 / MAX_FACTOR as f64
+    }
+}
+
+impl Serialize for Factor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("factor", &self.0)?;
+        map.serialize_entry("percent", &self.percent())?;
+        map.end()
+    }
 }
 
 #[derive(Row, serde::Deserialize)]
 struct DelegationHeightRow {
-    height: u32,
+    height: BlockHeight,
     tx_id: String,
 }
 
 #[derive(Serialize, Clone)]
 pub struct DelegationHeight {
-    pub height: u32,
+    pub height: BlockHeight,
+    pub tx_id: String,
+}
+
+/// rows per chunk of the streamed delegation mappings CSV
+const CSV_CHUNK_ROWS: usize = 1000;
+
+/// one row of the on-chain `Delegation-Mappings` CSV
+#[derive(Row, Serialize, serde::Deserialize)]
+struct DelegationMappingCsvRow {
+    wallet_from: String,
+    wallet_to: String,
+    factor: u32,
+}
+
+/// encodes `rows` as CSV, led by the `wallet_from,wallet_to,factor` header
+/// when `header` is set
+fn delegation_mappings_csv(
+    rows: &[DelegationMappingCsvRow],
+    header: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    if header {
+        writer.write_record(["wallet_from", "wallet_to", "factor"])?;
+    }
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.into_inner().map_err(|err| anyhow!(err.to_string()))
+}
+
+#[derive(Serialize, Clone)]
+pub struct DelegationSnapshot {
+    pub height: BlockHeight,
+    pub tx_id: String,
+    pub preferences_count: u64,
+    pub wallets_count: u64,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct DelegationSplitRow {
+    wallets_count: u64,
+    one_target: u64,
+    two_targets: u64,
+    three_plus_targets: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DelegationSplitDistribution {
+    pub height: BlockHeight,
     pub tx_id: String,
+    pub wallets_count: u64,
+    pub one_target: u64,
+    pub two_targets: u64,
+    pub three_plus_targets: u64,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ProjectTickerTotalRow {
+    project: String,
+    ticker: String,
+    amount: TokenAmount,
+    ar_amount: TokenAmount,
+    delegators_count: u32,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ProjectDelegatorsRow {
+    project: String,
+    delegators_count: u32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProjectTotals {
+    pub project: String,
+    pub delegators_count: u32,
+    pub totals: Vec<ProjectTotal>,
 }
 
 #[derive(Row, serde::Deserialize)]
@@ -1251,16 +3363,161 @@ pub struct MultiDelegator {
     pub eoa: String,
     pub project_count: u64,
     pub projects: Vec<String>,
+    /// `projects` lists fewer FLPs than `project_count`
+    pub projects_truncated: bool,
 }
 
-#[derive(Row, serde::Deserialize, Serialize, Clone)]
+/// explicit cap of the `projects` array, far above the number of FLPs so a
+/// truncated list only shows up as `projects_truncated`
+const MULTI_DELEGATOR_MAX_PROJECTS: u64 = 10_000;
+
+impl From<MultiDelegatorRow> for MultiDelegator {
+    fn from(row: MultiDelegatorRow) -> Self {
+        let projects_truncated = row.projects.len() as u64 != row.project_count;
+        if projects_truncated {
+            eprintln!(
+                "multi project delegator {}: {} projects listed out of {}",
+                row.wallet,
+                row.projects.len(),
+                row.project_count
+            );
+        }
+        Self {
+            wallet: row.wallet,
+            eoa: row.eoa,
+            project_count: row.project_count,
+            projects: row.projects,
+            projects_truncated,
+        }
+    }
+}
+
+/// cycles further than this from a minting report aren't matched to it
+const MINT_CYCLE_MAX_GAP: chrono::TimeDelta = chrono::TimeDelta::days(1);
+
+#[derive(Row, serde::Deserialize)]
+struct MintingReportRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    report_id: String,
+    distribution_tick: u32,
+    total_minted: String,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct CycleTickerTotalRow {
+    ticker: String,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    total: TokenAmount,
+}
+
+/// a minting report next to what was delegated to the project around it.
+/// tickers are different tokens, so the delegated totals stay per ticker.
+#[derive(Serialize, Clone)]
+pub struct MintVsDelegation {
+    pub ts: DateTime<Utc>,
+    pub report_id: String,
+    pub distribution_tick: u32,
+    /// raw amount, as reported
+    pub total_minted: String,
+    /// ticker -> delegated total of the ticker's cycle nearest to `ts`
+    pub delegated_total: BTreeMap<String, TokenAmount>,
+    /// widest distance between `ts` and a matched cycle, null without any
+    pub cycle_gap_secs: Option<u64>,
+}
+
+/// pairs each report with, per ticker, the cycle closest in time to it,
+/// ignoring cycles more than `MINT_CYCLE_MAX_GAP` away. `cycles` is sorted
+/// by `ts`.
+fn align_mint_with_cycles(
+    reports: Vec<MintingReportRow>,
+    cycles: &[CycleTickerTotalRow],
+) -> Vec<MintVsDelegation> {
+    reports
+        .into_iter()
+        .map(|report| {
+            let mut nearest: BTreeMap<&str, (chrono::TimeDelta, &CycleTickerTotalRow)> =
+                BTreeMap::new();
+            for cycle in cycles {
+                let gap = (cycle.ts - report.ts).abs();
+                if gap > MINT_CYCLE_MAX_GAP {
+                    continue;
+                }
+                // on a tie the earlier cycle wins
+                let closer = nearest
+                    .get(cycle.ticker.as_str())
+                    .is_none_or(|(best, _)| gap < *best);
+                if closer {
+                    nearest.insert(&cycle.ticker, (gap, cycle));
+                }
+            }
+            MintVsDelegation {
+                ts: report.ts,
+                report_id: report.report_id,
+                distribution_tick: report.distribution_tick,
+                total_minted: report.total_minted,
+                cycle_gap_secs: nearest
+                    .values()
+                    .map(|(gap, _)| gap.num_seconds() as u64)
+                    .max(),
+                delegated_total: nearest
+                    .into_iter()
+                    .map(|(ticker, (_, cycle))| (ticker.to_string(), cycle.total))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Clone)]
 pub struct ProjectCycleTotal {
-    pub tx_id: String,
+    pub ts: DateTime<Utc>,
+    /// ticker -> oracle snapshot tx of the cycle
+    pub tx_ids: BTreeMap<String, String>,
+    /// ticker -> exact delegated total of the cycle
+    pub totals: BTreeMap<String, TokenAmount>,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ProjectCycleTotalRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    tx_ids: Vec<(String, String)>,
+    totals: Vec<(String, TokenAmount)>,
+}
+
+impl From<ProjectCycleTotalRow> for ProjectCycleTotal {
+    fn from(row: ProjectCycleTotalRow) -> Self {
+        Self {
+            ts: row.ts,
+            tx_ids: row.tx_ids.into_iter().collect(),
+            totals: row.totals.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ExplorerActivity {
+    pub height: u64,
     pub ts: DateTime<Utc>,
-    pub usds_total: f64,
-    pub dai_total: f64,
-    pub steth_total: f64,
+    pub tx_count: u64,
+    pub active_users: u64,
+    pub active_processes: u64,
+    pub eval_count: u64,
+    pub transfer_count: u64,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct ExplorerActivityRow {
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+    height: u64,
+    tx_count: u64,
+    active_users: u64,
+    active_processes: u64,
+    eval_count: u64,
+    transfer_count: u64,
 }
 
 #[derive(Row, serde::Deserialize)]
@@ -1278,6 +3535,8 @@ struct ExplorerBlockRow {
     tx_count_rolling: u64,
     processes_rolling: u64,
     modules_rolling: u64,
+    total_data_size: Option<u64>,
+    data_size_rolling: Option<u64>,
 }
 
 impl From<ExplorerBlockRow> for ExplorerBlock {
@@ -1295,6 +3554,8 @@ impl From<ExplorerBlockRow> for ExplorerBlock {
             tx_count_rolling: row.tx_count_rolling,
             processes_rolling: row.processes_rolling,
             modules_rolling: row.modules_rolling,
+            total_data_size: row.total_data_size,
+            data_size_rolling: row.data_size_rolling,
         }
     }
 }
@@ -1314,6 +3575,48 @@ pub struct ExplorerBlock {
     pub tx_count_rolling: u64,
     pub processes_rolling: u64,
     pub modules_rolling: u64,
+    /// message data bytes in the block, mainnet rollup only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_data_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_size_rolling: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorerSource {
+    /// `atlas_explorer`
+    Legacy,
+    /// `ao_mainnet_explorer`
+    Mainnet,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub legacy: u64,
+    pub mainnet: u64,
+    /// `mainnet - legacy`
+    pub delta: i64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ExplorerBlockDiff {
+    pub height: u64,
+    /// set when only one rollup has the block
+    pub missing_from: Option<ExplorerSource>,
+    /// the stats that differ, empty when `missing_from` is set
+    pub fields: BTreeMap<&'static str, FieldDiff>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ExplorerReconciliation {
+    pub from_height: u64,
+    pub to_height: u64,
+    pub legacy_blocks: usize,
+    pub mainnet_blocks: usize,
+    /// blocks present in both with identical stats
+    pub matching: usize,
+    pub diffs: Vec<ExplorerBlockDiff>,
 }
 
 #[derive(Row, serde::Deserialize)]
@@ -1329,6 +3632,28 @@ struct ExplorerDayAggRow {
     txs_roll: u64,
     processes_roll: u64,
     modules_roll: u64,
+    data_size: Option<u64>,
+    data_size_roll: Option<u64>,
+}
+
+#[derive(Row, serde::Deserialize)]
+struct BlockTimeRow {
+    intervals: u64,
+    avg_secs: f64,
+    p50_secs: f64,
+    p95_secs: f64,
+}
+
+/// inter-block intervals in seconds; the stats are `None` when the window has
+/// fewer than two timestamped blocks
+#[derive(Serialize, Clone)]
+pub struct BlockTimeStats {
+    pub from_height: u64,
+    pub to_height: u64,
+    pub intervals: u64,
+    pub avg_secs: Option<f64>,
+    pub p50_secs: Option<f64>,
+    pub p95_secs: Option<f64>,
 }
 
 #[derive(Serialize, Clone)]
@@ -1345,6 +3670,11 @@ pub struct ExplorerDayStats {
     pub txs_roll: u64,
     pub processes_roll: u64,
     pub modules_roll: u64,
+    /// message data bytes over the day's blocks, mainnet rollup only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_size_roll: Option<u64>,
 }
 
 #[derive(Row, serde::Deserialize)]
@@ -1483,6 +3813,7 @@ struct AoTokenStatsRow {
 
 #[derive(Row, serde::Deserialize)]
 struct AoTokenStateRow {
+    #[allow(dead_code)]
     token: String,
     last_complete_height: u32,
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
@@ -1536,6 +3867,19 @@ struct AoTokenSumRow {
     total_quantity: u128,
 }
 
+#[derive(Row, serde::Deserialize)]
+struct AoTokenQuantityRow {
+    total_quantity: u128,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AoTokenBalance {
+    pub address: String,
+    pub inbound: String,
+    pub outbound: String,
+    pub balance: String,
+}
+
 #[derive(Serialize, Clone)]
 pub struct AoTokenQuantityRank {
     pub address: String,
@@ -1548,6 +3892,29 @@ pub struct AoTokenRichlist {
     pub top_receivers: Vec<AoTokenQuantityRank>,
 }
 
+/// one entry per day of `[from, to]`, zeroed where nothing was indexed
+fn transfer_volume_days(
+    from: NaiveDate,
+    to: NaiveDate,
+    rows: Vec<TransferVolumeRow>,
+) -> Vec<TokenTransferVolumeDay> {
+    let mut indexed: BTreeMap<String, TransferVolumeRow> =
+        rows.into_iter().map(|row| (row.day.clone(), row)).collect();
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| {
+            let day = day.to_string();
+            let row = indexed.remove(&day);
+            TokenTransferVolumeDay {
+                transfers: row.as_ref().map_or(0, |row| row.transfers),
+                missing_quantity: row.as_ref().map_or(0, |row| row.missing_quantity),
+                volume: format_quantity_human(row.map_or(0, |row| row.volume)),
+                day,
+            }
+        })
+        .collect()
+}
+
 fn format_quantity_human(value: u128) -> String {
     let scale: u128 = 1_000_000_000_000;
     let whole = value / scale;
@@ -1577,6 +3944,62 @@ impl From<MainnetProgressRow> for MainnetProtocolInfo {
     }
 }
 
+#[derive(Row, serde::Deserialize)]
+struct TransferVolumeRow {
+    day: String,
+    transfers: u64,
+    missing_quantity: u64,
+    volume: u128,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TokenTransferVolumeDay {
+    pub day: String,
+    pub transfers: u64,
+    /// transfers without a numeric `Quantity`, included in `transfers` only
+    pub missing_quantity: u64,
+    /// summed `Quantity` in whole tokens
+    pub volume: String,
+}
+
+#[derive(Row, serde::Deserialize, Serialize, Clone)]
+pub struct DailyActiveUsers {
+    pub day: String,
+    pub active_users: u64,
+}
+
+#[derive(Row, serde::Deserialize, Serialize, Clone)]
+pub struct TopProcess {
+    pub process_id: String,
+    pub message_count: u64,
+}
+
+// authority-level ids that show up in process tags but aren't user processes
+fn system_process_ids() -> Vec<&'static str> {
+    vec![ao_authority()]
+}
+
+#[derive(Row, serde::Deserialize, Serialize, Clone)]
+pub struct MainnetBlockMetric {
+    // same instant as `ts_unix`, only read to match the shared query's columns
+    #[allow(dead_code)]
+    #[serde(
+        skip_serializing,
+        with = "clickhouse::serde::chrono::datetime64::millis"
+    )]
+    pub ts: DateTime<Utc>,
+    pub ts_unix: u64,
+    pub height: BlockHeight,
+    pub tx_count: u64,
+    pub eval_count: u64,
+    pub transfer_count: u64,
+    pub new_process_count: u64,
+    pub new_module_count: u64,
+    pub active_users: u64,
+    pub active_processes: u64,
+    pub total_data_size: u64,
+}
+
 #[derive(Serialize, Clone)]
 pub struct MainnetProtocolInfo {
     pub protocol: String,
@@ -1620,6 +4043,8 @@ struct ExplorerRecentDayRow {
     txs_roll: u64,
     processes_roll: u64,
     modules_roll: u64,
+    data_size: Option<u64>,
+    data_size_roll: Option<u64>,
 }
 #[derive(Row, serde::Deserialize)]
 struct MainnetStateRow {
@@ -1629,3 +4054,366 @@ struct MainnetStateRow {
     #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
     updated_at: DateTime<Utc>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minting_reports_match_the_nearest_cycle_per_ticker_test() {
+        let at = |secs: i64| DateTime::from_timestamp(1_750_000_000 + secs, 0).unwrap();
+        let cycle = |ticker: &str, secs: i64, total: &str| CycleTickerTotalRow {
+            ticker: ticker.to_string(),
+            ts: at(secs),
+            total: total.parse().unwrap(),
+        };
+        let report = |id: &str, secs: i64| MintingReportRow {
+            ts: at(secs),
+            report_id: id.to_string(),
+            distribution_tick: 1,
+            total_minted: "1000".to_string(),
+        };
+        // usds cycles every 300s, dai ones on their own schedule
+        let cycles = [
+            cycle("usds", 0, "10"),
+            cycle("dai", 100, "1"),
+            cycle("usds", 300, "20"),
+            cycle("usds", 600, "30"),
+            cycle("dai", 5000, "2"),
+        ];
+        let rows =
+            align_mint_with_cycles(vec![report("r2", 4 * 86_400), report("r1", 420)], &cycles);
+        // r1 sits 120s after the 300s usds cycle, closer than the 600s one
+        let r1 = &rows[1];
+        assert_eq!(r1.delegated_total["usds"].to_string(), "20");
+        assert_eq!(r1.delegated_total["dai"].to_string(), "1");
+        assert_eq!(r1.cycle_gap_secs, Some(320));
+        // nothing within a day of r2
+        assert!(rows[0].delegated_total.is_empty());
+        assert_eq!(rows[0].cycle_gap_secs, None);
+    }
+
+    fn link(wallet: &str, eoa: &str, ts: i64) -> IdentityLink {
+        IdentityLink {
+            wallet: wallet.to_string(),
+            eoa: eoa.to_string(),
+            ts: DateTime::from_timestamp(ts, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn multi_delegator_flags_truncated_projects_test() {
+        let row = |listed: u64, count: u64| MultiDelegatorRow {
+            wallet: "w1".to_string(),
+            eoa: "0x1".to_string(),
+            project_count: count,
+            projects: (0..listed).map(|i| format!("pid_{i}")).collect(),
+        };
+        let full = MultiDelegator::from(row(250, 250));
+        assert_eq!(full.projects.len(), 250);
+        assert!(!full.projects_truncated);
+        assert!(MultiDelegator::from(row(100, 250)).projects_truncated);
+    }
+
+    #[test]
+    fn dedup_identity_links_keeps_changes_only_test() {
+        let links = vec![
+            link("ar1", "0xb", 50),
+            link("ar1", "0xb", 40),
+            link("ar1", "0xa", 30),
+            link("ar1", "0xa", 20),
+            link("ar1", "0xa", 10),
+        ];
+        let deduped = dedup_identity_links(links);
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].eoa, "0xb");
+        assert_eq!(deduped[0].ts.timestamp(), 40);
+        assert_eq!(deduped[1].eoa, "0xa");
+        assert_eq!(deduped[1].ts.timestamp(), 10);
+    }
+
+    fn position(ticker: &str, amount: &str) -> FlpPositionRow {
+        FlpPositionRow {
+            ts: DateTime::from_timestamp(0, 0).unwrap(),
+            ticker: ticker.to_string(),
+            wallet: "ar1".to_string(),
+            eoa: "0x1".to_string(),
+            project: "pid".to_string(),
+            factor: 10000,
+            amount: amount.parse().unwrap(),
+            ar_amount: TokenAmount::ZERO,
+        }
+    }
+
+    fn wallet_position(wallet: &str, amount: &str) -> WalletAmount {
+        FlpPositionRow {
+            wallet: wallet.to_string(),
+            ..position("usds", amount)
+        }
+        .into()
+    }
+
+    fn explorer_block(height: u64, tx_count: u64, active_users: u64) -> ExplorerBlock {
+        ExplorerBlock {
+            ts: DateTime::from_timestamp(0, 0).unwrap(),
+            height,
+            tx_count,
+            eval_count: 0,
+            transfer_count: 0,
+            new_process_count: 0,
+            new_module_count: 0,
+            active_users,
+            active_processes: 0,
+            tx_count_rolling: 0,
+            processes_rolling: 0,
+            modules_rolling: 0,
+            total_data_size: None,
+            data_size_rolling: None,
+        }
+    }
+
+    #[test]
+    fn non_whitelisted_sort_key_is_rejected_test() {
+        const KEYS: &[&str] = &["height", "ts"];
+        let mut parts = QueryParts::default();
+        let err = parts
+            .order_by(
+                "e",
+                KEYS,
+                "height; drop table atlas_explorer",
+                SortOrder::Asc,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported sort key \"height; drop table atlas_explorer\" (expected one of height, ts)"
+        );
+        assert_eq!(parts.order_sql(), "");
+
+        parts.order_by("e", KEYS, "ts", SortOrder::Asc).unwrap();
+        parts
+            .order_by("e", KEYS, "height", SortOrder::Desc)
+            .unwrap();
+        parts.filter("ticker = ?", "usds");
+        assert_eq!(parts.order_sql(), " order by e.ts asc, e.height desc");
+        assert_eq!(parts.where_sql(), " where ticker = ?");
+        assert_eq!(parts.and_sql(), " and ticker = ?");
+    }
+
+    #[test]
+    fn transfer_volume_days_fill_the_range_test() {
+        let day = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let rows = vec![TransferVolumeRow {
+            day: "2025-01-02".into(),
+            transfers: 3,
+            missing_quantity: 1,
+            volume: 2_500_000_000_000,
+        }];
+        let days = transfer_volume_days(day("2025-01-01"), day("2025-01-03"), rows);
+        let series: Vec<_> = days
+            .iter()
+            .map(|d| {
+                (
+                    d.day.as_str(),
+                    d.transfers,
+                    d.missing_quantity,
+                    d.volume.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            series,
+            vec![
+                ("2025-01-01", 0, 0, "0"),
+                ("2025-01-02", 3, 1, "2.5"),
+                ("2025-01-03", 0, 0, "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reconcile_explorer_blocks_reports_diffs_test() {
+        let legacy = vec![
+            explorer_block(10, 5, 2),
+            explorer_block(11, 7, 3),
+            explorer_block(12, 1, 1),
+        ];
+        let mut mainnet = vec![
+            explorer_block(10, 5, 2),
+            explorer_block(11, 9, 3),
+            explorer_block(13, 4, 1),
+        ];
+        // rolling counters are not compared
+        mainnet[0].tx_count_rolling = 1000;
+        let res = reconcile_explorer_blocks(10, 13, legacy, mainnet);
+        assert_eq!((res.legacy_blocks, res.mainnet_blocks), (3, 3));
+        assert_eq!(res.matching, 1);
+        let heights: Vec<_> = res
+            .diffs
+            .iter()
+            .map(|d| (d.height, d.missing_from))
+            .collect();
+        assert_eq!(
+            heights,
+            [
+                (11, None),
+                (12, Some(ExplorerSource::Mainnet)),
+                (13, Some(ExplorerSource::Legacy))
+            ]
+        );
+        let fields = &res.diffs[0].fields;
+        assert_eq!(fields.len(), 1);
+        assert_eq!(
+            fields["tx_count"],
+            FieldDiff {
+                legacy: 7,
+                mainnet: 9,
+                delta: 2
+            }
+        );
+    }
+
+    #[test]
+    fn position_deltas_between_snapshots_test() {
+        let previous = vec![
+            wallet_position("ar1", "10"),
+            wallet_position("ar2", "5"),
+            wallet_position("ar3", "7"),
+        ];
+        let current = vec![
+            wallet_position("ar1", "12.5"),
+            wallet_position("ar2", "5"),
+            wallet_position("ar4", "1"),
+        ];
+        let deltas = position_deltas(previous, current);
+        let summary: Vec<_> = deltas
+            .iter()
+            .map(|d| (d.wallet.as_str(), d.kind, d.delta.to_string()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("ar3", PositionDeltaKind::Removed, "-7".to_string()),
+                ("ar1", PositionDeltaKind::Increased, "2.5".to_string()),
+                ("ar4", PositionDeltaKind::New, "1".to_string()),
+            ]
+        );
+        assert_eq!(deltas[1].previous_amount.to_string(), "10");
+        assert_eq!(deltas[1].current_amount.to_string(), "12.5");
+    }
+
+    #[test]
+    fn position_deltas_single_snapshot_test() {
+        let current = vec![wallet_position("ar1", "3"), wallet_position("ar2", "4")];
+        let deltas = position_deltas(Vec::new(), current);
+        assert_eq!(deltas.len(), 2);
+        assert!(deltas.iter().all(|d| d.kind == PositionDeltaKind::New));
+        assert!(deltas.iter().all(|d| d.previous_amount.is_zero()));
+    }
+
+    #[test]
+    fn delegation_mappings_csv_has_one_header_test() {
+        let row = |from: &str, to: &str, factor| DelegationMappingCsvRow {
+            wallet_from: from.to_string(),
+            wallet_to: to.to_string(),
+            factor,
+        };
+        let first = delegation_mappings_csv(&[row("ar_a", "pid_1", 10000)], true).unwrap();
+        let rest = delegation_mappings_csv(&[row("ar_b", "pid_1", 2500)], false).unwrap();
+        let csv = String::from_utf8([first, rest].concat()).unwrap();
+        assert_eq!(
+            csv,
+            "wallet_from,wallet_to,factor\nar_a,pid_1,10000\nar_b,pid_1,2500\n"
+        );
+        let empty = delegation_mappings_csv(&[], true).unwrap();
+        assert_eq!(empty, b"wallet_from,wallet_to,factor\n");
+    }
+
+    #[test]
+    fn factor_serializes_with_percent_test() {
+        let preference = DelegationPreference::new("pid".to_string(), 2550);
+        let value = serde_json::to_value(&preference).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "wallet_to": "pid",
+                "factor": 2550,
+                "percent": 25.5,
+                "target_project": null
+            })
+        );
+        assert_eq!(Factor(MAX_FACTOR).percent(), 100.0);
+    }
+
+    #[test]
+    fn aggregate_totals_sums_exactly_test() {
+        let amounts = ["0.1", "0.2", "1000000000.000000000000000001"];
+        let float_sum: f64 = amounts.iter().map(|v| v.parse::<f64>().unwrap()).sum();
+        let rows: Vec<_> = amounts.iter().map(|v| position("usds", v)).collect();
+        let totals = aggregate_totals(&rows);
+        assert_eq!(
+            totals[0].amount.to_string(),
+            "1000000000.300000000000000001"
+        );
+        assert_eq!(totals[0].delegators_count, 3);
+        assert_ne!(float_sum.to_string(), totals[0].amount.to_string());
+    }
+
+    #[test]
+    fn concentration_of_equal_amounts_test() {
+        let res = concentration("usds".to_string(), vec![5.0; 20]);
+        assert_eq!(res.delegators_count, 20);
+        assert_eq!(res.total_amount, 100.0);
+        assert!((res.top_n_share - 0.5).abs() < 1e-9);
+        assert!(res.gini.abs() < 1e-9);
+    }
+
+    #[test]
+    fn concentration_with_few_delegators_test() {
+        let res = concentration("steth".to_string(), vec![0.0, 0.0, 30.0]);
+        assert_eq!(res.top_n_share, 1.0);
+        // one holder out of three: (n - 1) / n
+        assert!((res.gini - 2.0 / 3.0).abs() < 1e-9);
+
+        let single = concentration("dai".to_string(), vec![7.0]);
+        assert_eq!(single.top_n_share, 1.0);
+        assert_eq!(single.gini, 0.0);
+
+        let empty = concentration("dai".to_string(), vec![0.0]);
+        assert_eq!(empty.top_n_share, 0.0);
+        assert_eq!(empty.gini, 0.0);
+    }
+
+    fn mapping_row(
+        height: BlockHeight,
+        tx_id: &str,
+        ts: i64,
+        wallet_to: &str,
+    ) -> DelegationMappingRow {
+        DelegationMappingRow {
+            ts: DateTime::from_timestamp(ts, 0).unwrap(),
+            height,
+            tx_id: tx_id.to_string(),
+            wallet_from: "ar1".to_string(),
+            wallet_to: wallet_to.to_string(),
+            factor: 5000,
+        }
+    }
+
+    #[test]
+    fn latest_delegation_mapping_picks_newest_group_test() {
+        let rows = vec![
+            mapping_row(100, "tx_a", 10, "pid1"),
+            mapping_row(100, "tx_a", 10, "pid2"),
+            mapping_row(200, "tx_c", 30, "pid3"),
+            mapping_row(200, "tx_b", 20, "pid1"),
+        ];
+        let groups = group_delegation_mappings(rows);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].preferences.len(), 2);
+        let latest = latest_delegation_mapping(groups).unwrap();
+        assert_eq!(latest.height, 200);
+        assert_eq!(latest.tx_id, "tx_c");
+        assert!(latest_delegation_mapping(Vec::new()).is_none());
+    }
+}
@@ -1,98 +1,403 @@
 use crate::routes::{
-    get_all_projects_metadata_handler, get_ao_token_frequency, get_ao_token_indexing_info,
+    debug_parse_tx, get_all_flp_snapshots, get_all_projects_metadata_handler, get_ao_token_balance,
+    get_ao_token_frequency, get_ao_token_holders, get_ao_token_indexing_info,
     get_ao_token_messages_by_tag, get_ao_token_richlist, get_ao_token_tx, get_ao_token_txs,
-    get_ar_wallet_identity, get_delegation_mapping_heights, get_eoa_wallet_identity,
-    get_explorer_blocks, get_explorer_day_stats, get_explorer_recent_days,
-    get_flp_own_minting_report_handler, get_flp_snapshot_handler, get_mainnet_block_messages,
+    get_ar_wallet_identity, get_current_wallet_identity, get_delegation_mapping_by_tx,
+    get_delegation_mapping_heights, get_delegation_split_distribution, get_eoa_wallet_identity,
+    get_explorer_block, get_explorer_block_time, get_explorer_blocks, get_explorer_day_stats,
+    get_explorer_recent_days, get_explorer_reconcile, get_flp_own_minting_report_handler,
+    get_flp_snapshot_handler, get_latest_delegation_csv, get_latest_delegation_snapshot,
+    get_mainnet_block_messages, get_mainnet_block_metrics, get_mainnet_daily_active_users,
+    get_mainnet_explorer_activity, get_mainnet_explorer_block, get_mainnet_explorer_block_time,
     get_mainnet_explorer_blocks, get_mainnet_explorer_day_stats, get_mainnet_explorer_recent_days,
     get_mainnet_indexing_info, get_mainnet_messages_by_tag, get_mainnet_recent_messages,
-    get_multi_project_delegators, get_oracle_data_handler, get_oracle_feed,
-    get_project_cycle_totals, get_wallet_delegation_mappings_history,
-    get_wallet_delegations_handler, handle_route, parse_set_balance_report,
+    get_mainnet_top_processes, get_metrics, get_multi_project_delegators,
+    get_oracle_balance_deltas, get_oracle_data_handler, get_oracle_divergence, get_oracle_feed,
+    get_oracle_overview, get_oracle_tickers, get_oracle_value_daily, get_project_concentration,
+    get_project_cycle_totals, get_project_mint_vs_delegation, get_project_position_deltas,
+    get_token_transfer_volume_daily, get_version, get_wallet_delegation_mappings_history,
+    get_wallet_delegations_batch, get_wallet_delegations_handler, get_zero_balance_delegators,
+    handle_route, is_flag_set, method_not_allowed_handler, not_found_handler,
+    parse_set_balance_report,
+};
+use anyhow::Context;
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{MethodRouter, get, post},
 };
-use axum::{Router, extract::DefaultBodyLimit, routing::get};
 use common::env::get_env_var;
+use std::net::{IpAddr, SocketAddr};
 use tower_http::{cors::CorsLayer, limit::RequestBodyLimitLayer};
 
 const REQ_SIZE_LIMIT: usize = 50 * 1024 * 1024; // 50 MB
 
+#[cfg(test)]
+mod clickhouse_tests;
 mod errors;
+mod freshness;
 mod indexer;
+#[cfg(feature = "openapi")]
+mod openapi;
 mod routes;
 
 #[tokio::main]
 async fn main() {
+    let router = router();
+    let bind = get_env_var("SERVER_BIND").unwrap_or_else(|_| "0.0.0.0".to_string());
+    // 12 titans :D
+    let port = get_env_var("SERVER_PORT").unwrap_or_else(|_| "1212".to_string());
+    let addr = match parse_bind_addr(&bind, &port) {
+        Ok(addr) => addr,
+        Err(err) => {
+            eprintln!("invalid server address: {err:#}");
+            std::process::exit(1);
+        }
+    };
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    println!("Server listening on {addr}");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+}
+
+/// `SERVER_BIND` (an IP, v4 or v6) and `SERVER_PORT` into the listen address
+fn parse_bind_addr(bind: &str, port: &str) -> anyhow::Result<SocketAddr> {
+    let ip: IpAddr = bind
+        .trim()
+        .parse()
+        .with_context(|| format!("SERVER_BIND {bind:?} is not an IP address"))?;
+    let port: u16 = port
+        .trim()
+        .parse()
+        .with_context(|| format!("SERVER_PORT {port:?} is not a port number"))?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+fn router() -> Router {
     let cors = CorsLayer::new()
         .allow_origin(tower_http::cors::Any)
         .allow_methods(tower_http::cors::Any)
-        .allow_headers(tower_http::cors::Any);
+        .allow_headers(tower_http::cors::Any)
+        .expose_headers([
+            freshness::INDEXED_HEIGHT_HEADER,
+            freshness::SNAPSHOT_TS_HEADER,
+        ]);
 
-    let router = Router::new()
-        .route("/", get(handle_route))
+    let mut router = Router::new();
+    for (path, method_router) in public_routes() {
+        router = router.route(path, method_router);
+    }
+    router
+        .merge(debug_router())
+        .merge(openapi_router())
+        .fallback(not_found_handler)
+        .method_not_allowed_fallback(method_not_allowed_handler)
+        .layer(middleware::from_fn(freshness::indexed_height_header))
+        .layer(DefaultBodyLimit::max(REQ_SIZE_LIMIT))
+        .layer(RequestBodyLimitLayer::new(REQ_SIZE_LIMIT))
+        .layer(cors)
+}
+
+/// every public route, each documented in `openapi::ROUTES`
+fn public_routes() -> Vec<(&'static str, MethodRouter)> {
+    vec![
+        ("/", get(handle_route)),
+        ("/version", get(get_version)),
+        ("/metrics", get(get_metrics)),
         // wallet operations
-        .route(
+        (
+            "/wallet/delegations/batch",
+            post(get_wallet_delegations_batch),
+        ),
+        (
             "/wallet/delegations/{address}",
             get(get_wallet_delegations_handler),
-        )
-        .route("/wallet/identity/eoa/{eoa}", get(get_eoa_wallet_identity))
-        .route(
+        ),
+        ("/wallet/identity/eoa/{eoa}", get(get_eoa_wallet_identity)),
+        (
+            "/wallet/identity/current/{address}",
+            get(get_current_wallet_identity),
+        ),
+        (
             "/wallet/identity/ar-wallet/{address}",
             get(get_ar_wallet_identity),
-        )
-        .route(
+        ),
+        (
             "/wallet/delegation-mappings/{address}",
             get(get_wallet_delegation_mappings_history),
-        )
-        .route(
+        ),
+        (
             "/delegation-mappings/heights",
             get(get_delegation_mapping_heights),
-        )
-        .route("/flp/delegators/multi", get(get_multi_project_delegators))
-        .route("/oracle/{ticker}", get(get_oracle_data_handler))
-        .route("/oracle/feed/{ticker}", get(get_oracle_feed))
+        ),
+        ("/delegation/latest", get(get_latest_delegation_snapshot)),
+        ("/delegation/latest.csv", get(get_latest_delegation_csv)),
+        (
+            "/delegation/split-distribution",
+            get(get_delegation_split_distribution),
+        ),
+        (
+            "/delegation/mapping/{tx_id}",
+            get(get_delegation_mapping_by_tx),
+        ),
+        ("/flp/delegators/multi", get(get_multi_project_delegators)),
+        ("/oracle/overview", get(get_oracle_overview)),
+        ("/oracle/tickers", get(get_oracle_tickers)),
+        ("/oracle/divergence/{ticker}", get(get_oracle_divergence)),
+        ("/oracle/{ticker}", get(get_oracle_data_handler)),
+        ("/oracle/feed/{ticker}", get(get_oracle_feed)),
+        ("/oracle/deltas/{ticker}", get(get_oracle_balance_deltas)),
+        ("/oracle/value/daily/{ticker}", get(get_oracle_value_daily)),
         // returns the direct delegation data per FLP ID: LSTs + AR -- factored data
-        .route("/flp/delegators/{project}", get(get_flp_snapshot_handler))
-        .route("/flp/{project}/cycles", get(get_project_cycle_totals))
-        .route(
+        ("/flp/delegators/{project}", get(get_flp_snapshot_handler)),
+        // latest totals of every FLP in one call, without the delegator lists
+        ("/flp/snapshots", get(get_all_flp_snapshots)),
+        (
+            "/flp/concentration/{project}",
+            get(get_project_concentration),
+        ),
+        ("/flp/deltas/{project}", get(get_project_position_deltas)),
+        (
+            "/flp/zero-balance-delegators/{project}",
+            get(get_zero_balance_delegators),
+        ),
+        ("/flp/{project}/cycles", get(get_project_cycle_totals)),
+        (
             "/flp/minting/{project}",
             get(get_flp_own_minting_report_handler),
-        )
-        .route("/flp/metadata/all", get(get_all_projects_metadata_handler))
-        .route("/explorer/blocks", get(get_explorer_blocks))
-        .route("/explorer/day", get(get_explorer_day_stats))
-        .route("/explorer/days", get(get_explorer_recent_days))
+        ),
+        (
+            "/flp/mint-vs-delegation/{project}",
+            get(get_project_mint_vs_delegation),
+        ),
+        ("/flp/metadata/all", get(get_all_projects_metadata_handler)),
+        ("/explorer/blocks", get(get_explorer_blocks)),
+        ("/explorer/day", get(get_explorer_day_stats)),
+        ("/explorer/days", get(get_explorer_recent_days)),
+        ("/explorer/block-time", get(get_explorer_block_time)),
+        ("/explorer/reconcile", get(get_explorer_reconcile)),
+        ("/explorer/block/{height}", get(get_explorer_block)),
         // mainnet (ao.N.1)
-        .route("/mainnet/explorer/blocks", get(get_mainnet_explorer_blocks))
-        .route("/mainnet/explorer/day", get(get_mainnet_explorer_day_stats))
-        .route(
+        ("/mainnet/explorer/blocks", get(get_mainnet_explorer_blocks)),
+        (
+            "/mainnet/explorer/block/{height}",
+            get(get_mainnet_explorer_block),
+        ),
+        (
+            "/mainnet/explorer/activity",
+            get(get_mainnet_explorer_activity),
+        ),
+        ("/mainnet/explorer/day", get(get_mainnet_explorer_day_stats)),
+        (
+            "/mainnet/explorer/block-time",
+            get(get_mainnet_explorer_block_time),
+        ),
+        ("/mainnet/explorer/dau", get(get_mainnet_daily_active_users)),
+        (
             "/mainnet/explorer/days",
             get(get_mainnet_explorer_recent_days),
-        )
-        .route("/mainnet/messages/recent", get(get_mainnet_recent_messages))
-        .route(
+        ),
+        ("/mainnet/blocks", get(get_mainnet_block_metrics)),
+        ("/mainnet/messages/recent", get(get_mainnet_recent_messages)),
+        (
             "/mainnet/messages/block/{height}",
             get(get_mainnet_block_messages),
-        )
-        .route("/mainnet/messages/tags", get(get_mainnet_messages_by_tag))
-        .route("/mainnet/info", get(get_mainnet_indexing_info))
-        .route("/token/{token}/txs", get(get_ao_token_txs))
-        .route("/token/{token}/txs/{msg_id}", get(get_ao_token_tx))
-        .route("/token/{token}/txs/tags", get(get_ao_token_messages_by_tag))
-        .route("/token/{token}/info", get(get_ao_token_indexing_info))
-        .route("/token/{token}/top/frequency", get(get_ao_token_frequency))
-        .route("/token/{token}/top/richlist", get(get_ao_token_richlist))
-        .route(
+        ),
+        ("/mainnet/messages/tags", get(get_mainnet_messages_by_tag)),
+        ("/mainnet/top-processes", get(get_mainnet_top_processes)),
+        ("/mainnet/info", get(get_mainnet_indexing_info)),
+        ("/token/{token}/txs", get(get_ao_token_txs)),
+        ("/token/{token}/txs/{msg_id}", get(get_ao_token_tx)),
+        ("/token/{token}/txs/tags", get(get_ao_token_messages_by_tag)),
+        ("/token/{token}/info", get(get_ao_token_indexing_info)),
+        ("/token/{token}/top/frequency", get(get_ao_token_frequency)),
+        ("/token/{token}/top/richlist", get(get_ao_token_richlist)),
+        (
+            "/token/{token}/balance/{address}",
+            get(get_ao_token_balance),
+        ),
+        ("/token/{token}/holders", get(get_ao_token_holders)),
+        (
+            "/token/{token}/volume/daily",
+            get(get_token_transfer_volume_daily),
+        ),
+        (
             "/codec/parse/set-balances/{msg_id}",
             get(parse_set_balance_report),
-        )
-        .layer(DefaultBodyLimit::max(REQ_SIZE_LIMIT))
-        .layer(RequestBodyLimitLayer::new(REQ_SIZE_LIMIT))
-        .layer(cors);
-    // 12 titans :D
-    let port = get_env_var("SERVER_PORT").unwrap_or_else(|_| "1212".to_string());
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
+        ),
+    ]
+}
+
+/// operator-only routes, mounted when `DEBUG_ROUTES` is `1`/`true`
+fn debug_router() -> Router {
+    let enabled = get_env_var("DEBUG_ROUTES").is_ok_and(|v| is_flag_set(&v));
+    if !enabled {
+        return Router::new();
+    }
+    Router::new().route("/debug/tx/{tx_id}", get(debug_parse_tx))
+}
+
+/// `/openapi.json` and the `/docs` Swagger UI, built with the `openapi` feature
+fn openapi_router() -> Router {
+    #[cfg(feature = "openapi")]
+    return openapi::router();
+    #[cfg(not(feature = "openapi"))]
+    Router::new()
+}
+
+/// resolves on Ctrl-C or SIGTERM so in-flight requests can finish before exit
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    println!("shutdown signal received, draining in-flight requests");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::resolve_delegations_batch;
+    use axum::{
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use flp::{error::FlpError, types::DelegationsRes};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn call(method: &str, uri: &str) -> (StatusCode, Value) {
+        call_with_body(method, uri, Body::empty()).await
+    }
+
+    async fn call_with_body(method: &str, uri: &str, body: Body) -> (StatusCode, Value) {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let res = router().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn unknown_route_returns_json_404_test() {
+        let (status, body) = call("GET", "/does/not/exist").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "not found");
+        assert_eq!(body["error_code"], "not_found");
+        assert_eq!(body["path"], "/does/not/exist");
+    }
+
+    #[tokio::test]
+    async fn debug_routes_disabled_by_default_test() {
+        if get_env_var("DEBUG_ROUTES").is_ok() {
+            return;
+        }
+        let (status, _) = call("GET", "/debug/tx/abc?as=mappings").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn parse_bind_addr_test() {
+        assert_eq!(
+            parse_bind_addr("127.0.0.1", "1212").unwrap().to_string(),
+            "127.0.0.1:1212"
+        );
+        assert_eq!(
+            parse_bind_addr("::1", "8080").unwrap().to_string(),
+            "[::1]:8080"
+        );
+        assert!(parse_bind_addr("localhost", "1212").is_err());
+        assert!(parse_bind_addr("0.0.0.0", "70000").is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_min_amount_is_rejected_test() {
+        for uri in [
+            "/flp/delegators/pid?min_amount=-1",
+            "/flp/delegators/pid?min_amount=lots",
+        ] {
+            let (status, body) = call("GET", uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(body["error_code"], "bad_request");
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_delegations_batch_is_rejected_test() {
+        let address = "NHPqZT_mHJikcSMXNqq398tqFah_IrVL5ujG7vlBpD0";
+        let too_many = serde_json::to_string(&vec![address; 51]).unwrap();
+        for body in [
+            "[]".to_string(),
+            "{\"addresses\": []}".to_string(),
+            format!("[\"{address}\", \"not-an-address\"]"),
+            too_many,
+        ] {
+            let (status, res) = call_with_body(
+                "POST",
+                "/wallet/delegations/batch",
+                Body::from(body.clone()),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+            assert_eq!(res["error_code"], "bad_request");
+        }
+    }
+
+    #[tokio::test]
+    async fn delegations_batch_isolates_failed_lookups_test() {
+        // the stub fails for the EVM address only, the wallet still resolves
+        let wallet = "NHPqZT_mHJikcSMXNqq398tqFah_IrVL5ujG7vlBpD0";
+        let evm = "0x0000000000000000000000000000000000000000";
+        let addresses = [wallet, evm].map(String::from).into();
+        let res = resolve_delegations_batch(addresses, |address| {
+            if address.starts_with("0x") {
+                Err(FlpError::NotFound(format!("no delegations for {address}")))
+            } else {
+                Ok(DelegationsRes::pi_default(address))
+            }
+        })
         .await
-        .unwrap();
-    println!("Server running on PORT: {port}");
-    axum::serve(listener, router).await.unwrap();
+        .unwrap_or_else(|_| panic!("batch failed"));
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[wallet]["wallet"], wallet);
+        assert!(
+            res[evm]["error"]
+                .as_str()
+                .unwrap()
+                .contains("no delegations")
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_method_returns_json_405_test() {
+        let (status, body) = call("POST", "/flp/metadata/all").await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(body["error"], "method not allowed");
+        assert_eq!(body["error_code"], "method_not_allowed");
+    }
 }
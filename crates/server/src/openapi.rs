@@ -0,0 +1,902 @@
+//! hand-maintained OpenAPI 3.1 description of the HTTP API, served at
+//! `/openapi.json` (and a Swagger UI page at `/docs`) with the `openapi` feature.
+//! `ROUTES` must list every route of `public_routes()`, and the component
+//! schemas must match what the response types serialize to. tests keep both
+//! in sync.
+
+use crate::errors::ErrorCode;
+use axum::{Json, Router, response::Html, routing::get};
+use serde_json::{Map, Value, json};
+
+struct RouteDoc {
+    /// lowercase HTTP method
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    query: &'static [&'static str],
+    /// component schema of the response body, if described
+    schema: Option<&'static str>,
+    /// component schema of the request body
+    body: Option<&'static str>,
+}
+
+const fn route(
+    path: &'static str,
+    summary: &'static str,
+    query: &'static [&'static str],
+) -> RouteDoc {
+    RouteDoc {
+        method: "get",
+        path,
+        summary,
+        query,
+        schema: None,
+        body: None,
+    }
+}
+
+const fn typed(
+    path: &'static str,
+    summary: &'static str,
+    query: &'static [&'static str],
+    schema: &'static str,
+) -> RouteDoc {
+    RouteDoc {
+        method: "get",
+        path,
+        summary,
+        query,
+        schema: Some(schema),
+        body: None,
+    }
+}
+
+/// a POST route taking a JSON body of the `body` component schema
+const fn posted(
+    path: &'static str,
+    summary: &'static str,
+    body: &'static str,
+    schema: &'static str,
+) -> RouteDoc {
+    RouteDoc {
+        method: "post",
+        path,
+        summary,
+        query: &[],
+        schema: Some(schema),
+        body: Some(body),
+    }
+}
+
+const PAGE: &[&str] = &["limit", "offset", "count"];
+const DAY_RANGE: &[&str] = &["from", "to"];
+
+const ROUTES: &[RouteDoc] = &[
+    route("/", "health info", &[]),
+    route("/version", "build version, commit and endpoint config", &[]),
+    route("/metrics", "gateway circuit breaker counters", &[]),
+    posted(
+        "/wallet/delegations/batch",
+        "delegations of up to 50 wallets, failed lookups as {error}",
+        "AddressList",
+        "DelegationsBatch",
+    ),
+    route(
+        "/wallet/delegations/{address}",
+        "latest Set-Delegation payload of a wallet",
+        &[],
+    ),
+    route(
+        "/wallet/identity/eoa/{eoa}",
+        "Arweave addresses linked to an EOA",
+        &[],
+    ),
+    route(
+        "/wallet/identity/current/{address}",
+        "latest (wallet, eoa) link of an Arweave address or EOA",
+        &[],
+    ),
+    route(
+        "/wallet/identity/ar-wallet/{address}",
+        "EOAs linked to an Arweave address",
+        &[],
+    ),
+    typed(
+        "/wallet/delegation-mappings/{address}",
+        "delegation preference history of a wallet",
+        &["order", "at_height", "count_only"],
+        "DelegationMappingHistory",
+    ),
+    route(
+        "/delegation-mappings/heights",
+        "page of indexed delegation mapping txs and their heights",
+        PAGE,
+    ),
+    route(
+        "/delegation/latest",
+        "newest indexed delegation mapping snapshot",
+        &[],
+    ),
+    route(
+        "/delegation/latest.csv",
+        "newest delegation mapping snapshot as wallet_from,wallet_to,factor CSV",
+        &[],
+    ),
+    route(
+        "/delegation/split-distribution",
+        "wallets by number of delegation targets in the newest mapping",
+        &[],
+    ),
+    route(
+        "/delegation/mapping/{tx_id}",
+        "all wallets' preferences of a delegation mapping tx",
+        &[],
+    ),
+    typed(
+        "/flp/delegators/multi",
+        "page of delegators delegating to at least min_projects FLPs",
+        &["min_projects", "limit", "offset", "count", "count_only"],
+        "MultiDelegatorPage",
+    ),
+    route("/oracle/overview", "latest stats of each oracle", &[]),
+    route(
+        "/oracle/tickers",
+        "registered oracles and their metadata",
+        &[],
+    ),
+    route(
+        "/oracle/divergence/{ticker}",
+        "recent AO vs EVM stake checks of an oracle",
+        &["limit"],
+    ),
+    route(
+        "/oracle/{ticker}",
+        "raw Set-Balances payload of an oracle",
+        &[],
+    ),
+    typed(
+        "/oracle/feed/{ticker}",
+        "recent indexed oracle snapshots",
+        &["order", "max_age_secs"],
+        "OracleFeed",
+    ),
+    route(
+        "/oracle/deltas/{ticker}",
+        "per-wallet Set-Balances changes between the two latest cycles",
+        &[],
+    ),
+    route(
+        "/oracle/value/daily/{ticker}",
+        "gap-filled daily total delegated value",
+        DAY_RANGE,
+    ),
+    typed(
+        "/flp/delegators/{project}",
+        "merged latest snapshot of an FLP's delegators",
+        &["min_amount", "count_only", "max_age_secs"],
+        "ProjectSnapshot",
+    ),
+    route("/flp/snapshots", "latest totals of every FLP", &[]),
+    route(
+        "/flp/concentration/{project}",
+        "per-ticker delegation concentration",
+        &[],
+    ),
+    route(
+        "/flp/deltas/{project}",
+        "per-wallet movement between the two latest snapshots",
+        &["ticker"],
+    ),
+    route(
+        "/flp/zero-balance-delegators/{project}",
+        "delegators whose AR balance is 0",
+        &[],
+    ),
+    route(
+        "/flp/{project}/cycles",
+        "page of per-cycle totals of an FLP",
+        &["ticker", "limit", "offset", "count"],
+    ),
+    route(
+        "/flp/minting/{project}",
+        "latest Own-Minting-Report of an FLP",
+        &[],
+    ),
+    typed(
+        "/flp/mint-vs-delegation/{project}",
+        "stored minting reports with the delegated totals of the nearest cycles",
+        &["limit"],
+        "MintVsDelegation",
+    ),
+    route(
+        "/flp/metadata/all",
+        "tracked FLPs and their metadata",
+        &["with_totals"],
+    ),
+    route(
+        "/explorer/blocks",
+        "last indexed blocks",
+        &["limit", "order"],
+    ),
+    route("/explorer/day", "stats of a day", &["day"]),
+    route("/explorer/days", "stats of the last N days", &["limit"]),
+    route(
+        "/explorer/block-time",
+        "block time statistics over a height window",
+        &["from", "to"],
+    ),
+    route(
+        "/explorer/reconcile",
+        "per-block stat diffs between the legacy and mainnet explorer rollups",
+        &["from", "to"],
+    ),
+    route(
+        "/explorer/block/{height}",
+        "indexed stats of one block",
+        &["live"],
+    ),
+    route(
+        "/mainnet/explorer/blocks",
+        "last indexed mainnet blocks",
+        &["limit", "since"],
+    ),
+    route(
+        "/mainnet/explorer/block/{height}",
+        "indexed stats of one mainnet block",
+        &[],
+    ),
+    route(
+        "/mainnet/explorer/activity",
+        "per-block activity over a height window",
+        &["from", "to", "limit"],
+    ),
+    route("/mainnet/explorer/day", "mainnet stats of a day", &["day"]),
+    route(
+        "/mainnet/explorer/block-time",
+        "mainnet block time statistics over a height window",
+        &["from", "to"],
+    ),
+    route(
+        "/mainnet/explorer/dau",
+        "daily unique active users",
+        DAY_RANGE,
+    ),
+    route(
+        "/mainnet/explorer/days",
+        "mainnet stats of the last N days",
+        &["limit"],
+    ),
+    route(
+        "/mainnet/blocks",
+        "per-block metrics from the raw messages tables",
+        &["after", "limit"],
+    ),
+    route(
+        "/mainnet/messages/recent",
+        "recently indexed mainnet messages",
+        &["limit", "protocol"],
+    ),
+    route(
+        "/mainnet/messages/block/{height}",
+        "indexed mainnet messages of a block",
+        &["limit", "protocol"],
+    ),
+    route(
+        "/mainnet/messages/tags",
+        "mainnet messages by tag",
+        &["key", "value", "protocol", "limit"],
+    ),
+    route(
+        "/mainnet/top-processes",
+        "most active processes over the last N blocks",
+        &["blocks", "protocol", "limit"],
+    ),
+    route("/mainnet/info", "mainnet indexer info", &[]),
+    route(
+        "/token/{token}/txs",
+        "indexed token messages",
+        &[
+            "limit",
+            "offset",
+            "source",
+            "action",
+            "recipient",
+            "sender",
+            "sort",
+            "order",
+            "min_amount",
+            "max_amount",
+            "from_ts",
+            "to_ts",
+            "block_min",
+            "block_max",
+        ],
+    ),
+    route("/token/{token}/txs/{msg_id}", "token message by id", &[]),
+    route(
+        "/token/{token}/txs/tags",
+        "token messages by tag",
+        &["key", "value", "source", "limit"],
+    ),
+    route("/token/{token}/info", "token indexer info", &[]),
+    route(
+        "/token/{token}/top/frequency",
+        "action counts and top senders/recipients",
+        &["limit"],
+    ),
+    route(
+        "/token/{token}/top/richlist",
+        "top spenders/receivers by quantity",
+        &["limit"],
+    ),
+    route(
+        "/token/{token}/balance/{address}",
+        "net balance of an address from indexed transfers",
+        &[],
+    ),
+    route(
+        "/token/{token}/holders",
+        "top holders by net balance",
+        &["limit"],
+    ),
+    route(
+        "/token/{token}/volume/daily",
+        "daily transfer count and volume",
+        &["from", "to"],
+    ),
+    route(
+        "/codec/parse/set-balances/{msg_id}",
+        "parsed Set-Balances report",
+        &[],
+    ),
+];
+
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+}
+
+fn param(name: &str, location: &str) -> Value {
+    json!({
+        "name": name,
+        "in": location,
+        "required": location == "path",
+        "schema": { "type": "string" },
+    })
+}
+
+fn operation(doc: &RouteDoc) -> Value {
+    let parameters: Vec<Value> = path_params(doc.path)
+        .map(|name| param(name, "path"))
+        .chain(doc.query.iter().map(|name| param(name, "query")))
+        .collect();
+    let schema = match doc.schema {
+        Some(name) => json!({ "$ref": format!("#/components/schemas/{name}") }),
+        None => json!({}),
+    };
+    let mut op = json!({
+        "summary": doc.summary,
+        "parameters": parameters,
+        "responses": {
+            "200": {
+                "description": "OK",
+                "content": { "application/json": { "schema": schema } },
+            },
+            "default": {
+                "description": "error",
+                "content": {
+                    "application/json": {
+                        "schema": { "$ref": "#/components/schemas/Error" },
+                    },
+                },
+            },
+        },
+    });
+    if let Some(body) = doc.body {
+        op["requestBody"] = json!({
+            "required": true,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": format!("#/components/schemas/{body}") },
+                },
+            },
+        });
+    }
+    let mut item = Map::new();
+    item.insert(doc.method.to_string(), op);
+    Value::Object(item)
+}
+
+fn schemas() -> Value {
+    let amount = json!({ "type": "string", "description": "decimal token amount" });
+    let factor = json!({ "type": "integer", "description": "basis points, 10000 = 100%" });
+    let error_codes: Vec<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+    let project_label = json!({
+        "type": ["object", "null"],
+        "description": "the FLP the target pid belongs to, null for other targets",
+        "properties": {
+            "name": { "type": "string" },
+            "ticker": { "type": "string" },
+        },
+    });
+    json!({
+        "Error": {
+            "type": "object",
+            "properties": {
+                "error": { "type": "string" },
+                "error_code": { "type": "string", "enum": error_codes },
+                "method": { "type": "string", "description": "only on 405" },
+                "path": { "type": "string", "description": "only on 404 and 405" },
+            },
+        },
+        "ProjectSnapshot": {
+            "type": "object",
+            "properties": {
+                "project": { "type": "string" },
+                "ts": { "type": "string", "format": "date-time" },
+                "age_secs": { "type": "integer" },
+                "stale": { "type": "boolean" },
+                "totals": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "ticker": { "type": "string" },
+                            "amount": amount,
+                            "delegators_count": { "type": "integer" },
+                            "ar_amount": amount,
+                        },
+                    },
+                },
+                "delegators": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "wallet": { "type": "string" },
+                            "eoa": { "type": "string" },
+                            "ticker": { "type": "string" },
+                            "factor": factor,
+                            "percent": { "type": "number" },
+                            "amount": amount,
+                            "ar_amount": amount,
+                        },
+                    },
+                },
+            },
+        },
+        "OracleFeed": {
+            "type": "object",
+            "properties": {
+                "oracle_pid": { "type": "string" },
+                "oracle_evm_address": { "type": "string" },
+                "age_secs": { "type": ["integer", "null"] },
+                "stale": { "type": "boolean" },
+                "recent_indexed_feeds": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "ts": { "type": "integer", "description": "unix millis" },
+                            "ticker": { "type": "string" },
+                            "tx_id": { "type": "string" },
+                            "total": amount,
+                            "delegators": { "type": "integer" },
+                        },
+                    },
+                },
+            },
+        },
+        "MultiDelegatorPage": {
+            "type": "object",
+            "properties": {
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "wallet": { "type": "string" },
+                            "eoa": { "type": "string" },
+                            "project_count": { "type": "integer" },
+                            "projects": { "type": "array", "items": { "type": "string" } },
+                            "projects_truncated": { "type": "boolean" },
+                        },
+                    },
+                },
+                "next_offset": { "type": ["integer", "null"] },
+                "total": { "type": ["integer", "null"], "description": "only with count=1" },
+            },
+        },
+        "MintVsDelegation": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "ts": { "type": "string", "format": "date-time" },
+                    "report_id": { "type": "string" },
+                    "distribution_tick": { "type": "integer" },
+                    "total_minted": { "type": "string", "description": "raw amount, as reported" },
+                    "delegated_total": {
+                        "type": "object",
+                        "description": "ticker -> delegated total of its nearest cycle",
+                        "additionalProperties": amount,
+                    },
+                    "cycle_gap_secs": { "type": ["integer", "null"] },
+                },
+            },
+        },
+        "AddressList": {
+            "type": "array",
+            "items": { "type": "string" },
+            "maxItems": 50,
+        },
+        "DelegationsBatch": {
+            "type": "object",
+            "description": "address -> delegation preferences, or {error} for failed lookups",
+            "additionalProperties": { "type": "object" },
+        },
+        "DelegationMappingHistory": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "ts": { "type": "string", "format": "date-time" },
+                    "height": { "type": "integer" },
+                    "tx_id": { "type": "string" },
+                    "wallet": { "type": "string" },
+                    "preferences": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "wallet_to": { "type": "string" },
+                                "factor": factor,
+                                "percent": { "type": "number" },
+                                "target_project": project_label,
+                            },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for doc in ROUTES {
+        paths.insert(doc.path.to_string(), operation(doc));
+    }
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "atlas",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas() },
+    })
+}
+
+async fn get_openapi() -> Json<Value> {
+    Json(spec())
+}
+
+async fn get_docs() -> Html<&'static str> {
+    Html(
+        r##"<!doctype html>
+<html>
+<head>
+<title>atlas API</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>"##,
+    )
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/openapi.json", get(get_openapi))
+        .route("/docs", get(get_docs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ServerError;
+    use crate::freshness::{Aged, SnapshotAge};
+    use crate::indexer::{
+        DelegationMappingHistory, DelegationPreference, Delegator, Factor, MintVsDelegation,
+        MultiDelegator, OracleSnapshot, Page, ProjectSnapshot, ProjectTotal,
+    };
+    use crate::routes::{OracleFeed, resolve_delegations_batch};
+    use chrono::{DateTime, Utc};
+    use common::projects::ProjectLabel;
+    use flp::{error::FlpError, types::DelegationsRes};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn every_route_is_documented_test() {
+        // the public routes mounted by `router()`, debug routes excluded
+        let registered: BTreeSet<&str> = crate::public_routes()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        let documented: BTreeSet<&str> = ROUTES.iter().map(|doc| doc.path).collect();
+        assert_eq!(registered, documented);
+        assert_eq!(documented.len(), ROUTES.len(), "duplicate route docs");
+    }
+
+    fn is_type(value: &Value, name: &str) -> bool {
+        match name {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        }
+    }
+
+    /// checks `value` against the JSON Schema subset `schemas()` uses: every
+    /// key of an object with `properties` must be declared there. records the
+    /// property paths `value` covers in `seen`.
+    fn check(
+        value: &Value,
+        schema: &Value,
+        path: &str,
+        seen: &mut BTreeSet<String>,
+        errors: &mut Vec<String>,
+    ) {
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| is_type(value, name)) {
+            errors.push(format!("{path}: {value} is not {types:?}"));
+            return;
+        }
+        if let Some(allowed) = schema["enum"].as_array()
+            && !allowed.contains(value)
+        {
+            errors.push(format!("{path}: {value} is not one of {allowed:?}"));
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, field) in map {
+                    let field_path = format!("{path}.{key}");
+                    if let Some(field_schema) = schema["properties"].get(key) {
+                        check(field, field_schema, &field_path, seen, errors);
+                        seen.insert(field_path);
+                    } else if let Some(extra) = schema.get("additionalProperties") {
+                        check(field, extra, &format!("{path}.*"), seen, errors);
+                    } else if schema.get("properties").is_some() {
+                        errors.push(format!("{field_path} is not documented"));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    check(item, &schema["items"], &format!("{path}[]"), seen, errors);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// every property path `schema` declares
+    fn declared(schema: &Value, path: &str, out: &mut BTreeSet<String>) {
+        if let Some(properties) = schema["properties"].as_object() {
+            for (key, field) in properties {
+                let field_path = format!("{path}.{key}");
+                declared(field, &field_path, out);
+                out.insert(field_path);
+            }
+        }
+        if schema.get("items").is_some() {
+            declared(&schema["items"], &format!("{path}[]"), out);
+        }
+    }
+
+    fn to_json(value: &impl serde::Serialize) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    /// the response bodies of each component schema, as the handlers build
+    /// them. together they must use every documented property.
+    async fn samples() -> Vec<(&'static str, Vec<Value>)> {
+        let ts = DateTime::<Utc>::from_timestamp(1_750_000_000, 0).unwrap();
+        let amount = "1.5".parse().unwrap();
+        let wallet = "NHPqZT_mHJikcSMXNqq398tqFah_IrVL5ujG7vlBpD0";
+        let evm = "0x0000000000000000000000000000000000000000";
+        let snapshot = Aged {
+            data: ProjectSnapshot {
+                project: "pid_a".to_string(),
+                ts,
+                oldest_ts: ts,
+                totals: vec![ProjectTotal {
+                    ticker: "usds".to_string(),
+                    amount,
+                    delegators_count: 1,
+                    ar_amount: amount,
+                }],
+                delegators: vec![Delegator {
+                    wallet: wallet.to_string(),
+                    eoa: evm.to_string(),
+                    ticker: "usds".to_string(),
+                    factor: Factor(5000),
+                    amount,
+                    ar_amount: amount,
+                }],
+            },
+            age: SnapshotAge::new(Some(ts), ts, None),
+        };
+        let feed = |recent_indexed_feeds: Vec<OracleSnapshot>| {
+            let newest = recent_indexed_feeds.first().map(|snapshot| snapshot.ts);
+            Aged {
+                data: OracleFeed {
+                    oracle_pid: "oracle_pid".to_string(),
+                    oracle_evm_address: evm.to_string(),
+                    recent_indexed_feeds,
+                },
+                age: SnapshotAge::new(newest, ts, Some(60)),
+            }
+        };
+        let feeds = vec![
+            to_json(&feed(vec![OracleSnapshot {
+                ts,
+                ticker: "usds".to_string(),
+                tx_id: "tx_1".to_string(),
+                total: amount,
+                delegators: 1,
+            }])),
+            to_json(&feed(Vec::new())),
+        ];
+        let page = Page {
+            items: vec![MultiDelegator {
+                wallet: wallet.to_string(),
+                eoa: evm.to_string(),
+                project_count: 2,
+                projects: vec!["pid_a".to_string(), "pid_b".to_string()],
+                projects_truncated: false,
+            }],
+            next_offset: Some(1),
+            total: Some(2),
+        };
+        let batch = resolve_delegations_batch([wallet, evm].map(String::from).into(), |address| {
+            if address.starts_with("0x") {
+                Err(FlpError::NotFound(format!("no delegations for {address}")))
+            } else {
+                Ok(DelegationsRes::pi_default(address))
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("batch failed"));
+        let mint = vec![
+            MintVsDelegation {
+                ts,
+                report_id: "report_1".to_string(),
+                distribution_tick: 7,
+                total_minted: "1000".to_string(),
+                delegated_total: [("usds".to_string(), amount)].into(),
+                cycle_gap_secs: Some(120),
+            },
+            MintVsDelegation {
+                ts,
+                report_id: "report_0".to_string(),
+                distribution_tick: 6,
+                total_minted: "900".to_string(),
+                delegated_total: Default::default(),
+                cycle_gap_secs: None,
+            },
+        ];
+        let history = vec![DelegationMappingHistory {
+            ts,
+            height: 1_700_000,
+            tx_id: "tx_1".to_string(),
+            wallet: wallet.to_string(),
+            preferences: vec![
+                DelegationPreference {
+                    wallet_to: "pid_a".to_string(),
+                    factor: Factor(5000),
+                    target_project: Some(ProjectLabel {
+                        name: "Project A".to_string(),
+                        ticker: "PA".to_string(),
+                    }),
+                },
+                DelegationPreference {
+                    wallet_to: wallet.to_string(),
+                    factor: Factor(5000),
+                    target_project: None,
+                },
+            ],
+        }];
+        vec![
+            (
+                "Error",
+                vec![
+                    ServerError::bad_request("invalid day").body(),
+                    ServerError::not_found("not found")
+                        .with_context("path", "/nope")
+                        .body(),
+                    ServerError::method_not_allowed("method not allowed")
+                        .with_context("method", "POST")
+                        .with_context("path", "/version")
+                        .body(),
+                ],
+            ),
+            ("ProjectSnapshot", vec![to_json(&snapshot)]),
+            ("OracleFeed", feeds),
+            ("MultiDelegatorPage", vec![to_json(&page)]),
+            ("MintVsDelegation", vec![to_json(&mint)]),
+            ("AddressList", vec![json!([wallet, evm])]),
+            ("DelegationsBatch", vec![to_json(&batch)]),
+            ("DelegationMappingHistory", vec![to_json(&history)]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn schemas_match_the_response_types_test() {
+        let schemas = schemas();
+        let samples = samples().await;
+        let sampled: BTreeSet<&str> = samples.iter().map(|(name, _)| *name).collect();
+        let components: BTreeSet<&str> = schemas
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(sampled, components, "every component schema needs samples");
+        for (name, values) in &samples {
+            let schema = &schemas[*name];
+            let (mut seen, mut errors) = (BTreeSet::new(), Vec::new());
+            for value in values {
+                check(value, schema, name, &mut seen, &mut errors);
+            }
+            assert!(errors.is_empty(), "{errors:#?}");
+            let mut documented = BTreeSet::new();
+            declared(schema, name, &mut documented);
+            let unused: Vec<_> = documented.difference(&seen).collect();
+            assert!(
+                unused.is_empty(),
+                "{name} documents unsent fields {unused:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn spec_lists_path_and_query_params_test() {
+        let spec = spec();
+        let params = &spec["paths"]["/token/{token}/balance/{address}"]["get"]["parameters"];
+        assert_eq!(params[0]["name"], "token");
+        assert_eq!(params[1]["name"], "address");
+        assert_eq!(params[1]["required"], true);
+
+        let op = &spec["paths"]["/flp/delegators/{project}"]["get"];
+        let schema = &op["responses"]["200"]["content"]["application/json"]["schema"]["$ref"];
+        assert_eq!(schema, "#/components/schemas/ProjectSnapshot");
+        assert!(spec["components"]["schemas"]["ProjectSnapshot"].is_object());
+
+        let query: Vec<&Value> = spec["paths"]["/flp/{project}/cycles"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|p| p["in"] == "query")
+            .map(|p| &p["name"])
+            .collect();
+        assert_eq!(query, ["ticker", "limit", "offset", "count"]);
+
+        let batch = &spec["paths"]["/wallet/delegations/batch"];
+        assert!(batch["get"].is_null());
+        let body = &batch["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"];
+        assert_eq!(body, "#/components/schemas/AddressList");
+    }
+}
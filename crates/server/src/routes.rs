@@ -1,26 +1,45 @@
 use crate::{
     errors::ServerError,
+    freshness::{Aged, SnapshotAge, parse_max_age, snapshot_ts_headers},
     indexer::{
-        AoTokenMessage, AtlasIndexerClient, DelegationHeight, DelegationMappingHistory,
-        ExplorerBlock, ExplorerDayStats, MultiDelegator, ProjectCycleTotal,
+        AoTokenMessage, AtlasIndexerClient, BLOCK_TIME_MAX_WINDOW, BlockTimeStats,
+        DelegationHeight, DelegationMappingHistory, DelegationSnapshot, ExplorerActivity,
+        ExplorerBlock, ExplorerDayStats, ExplorerReconciliation, MultiDelegator, OracleSnapshot,
+        Page, ProjectConcentration, ProjectCycleTotal, ProjectPositionDeltas,
+        ProjectSnapshotSummary, ProjectTotal, ProjectTotals, SortOrder,
     },
 };
 use anyhow::anyhow;
 use axum::{
     Json,
-    extract::{Path, Query},
+    body::Body,
+    extract::{Path, Query, rejection::JsonRejection},
+    http::{HeaderMap, HeaderValue, Method, Uri, header},
 };
 use chrono::{NaiveDate, Utc};
 use common::{
-    env::get_env_var, gql::OracleStakers, minting::get_flp_own_minting_report, projects::Project,
+    amount::TokenAmount,
+    build_info::{BuildInfo, GIT_COMMIT},
+    constants::{arweave_gateway, explorer_graphql_url},
+    env::{clickhouse_database, clickhouse_url, get_env_var, redact_url_credentials},
+    gateway::{circuit_stats, download_tx_data},
+    gql::{ORACLES, OracleStakers},
+    height::BlockHeight,
+    minting::get_flp_own_minting_report,
+    projects::Project,
 };
-use flp::csv_parser::parse_flp_balances_setting_res;
-use flp::json_parser::parse_own_minting_report;
-use flp::wallet::get_wallet_delegations;
+use flp::csv_parser::{
+    BalancesReader, delegation_mappings_from_slice, parse_flp_balances_setting_res,
+};
+use flp::error::FlpError;
+use flp::json_parser::{own_minting_report_from_slice, parse_own_minting_report};
+use flp::types::DelegationsRes;
+use flp::wallet::{delegations_from_slice, get_wallet_delegations};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::HashMap;
-use std::{fs, io::ErrorKind};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::{fs, io::ErrorKind, sync::Arc};
+use tokio::{sync::Semaphore, task::JoinSet};
 
 #[derive(Deserialize, Serialize, Default)]
 struct AtlasConfig {
@@ -71,23 +90,196 @@ pub async fn handle_route() -> Json<Value> {
         "status": "running",
         "name": "atlas-server",
         "version": env!("CARGO_PKG_VERSION"),
+        "commit": GIT_COMMIT,
         "config": config
     }))
 }
 
+/// build of the running server and the endpoints it reads from, credentials
+/// left out
+pub async fn get_version() -> Json<Value> {
+    Json(json!({
+        "build": BuildInfo::new("atlas-server"),
+        "config": {
+            "clickhouse_url": redact_url_credentials(&clickhouse_url()),
+            "clickhouse_database": clickhouse_database(),
+            "arweave_gateway": arweave_gateway(),
+            "explorer_graphql_url": explorer_graphql_url(),
+            "atlas": load_atlas_config(),
+        }
+    }))
+}
+
+/// process counters: opened / rejected calls of the gateway circuit breaker
+pub async fn get_metrics() -> Json<Value> {
+    Json(json!({ "gateway": { "circuit": circuit_stats() } }))
+}
+
+pub async fn not_found_handler(uri: Uri) -> ServerError {
+    ServerError::not_found("not found").with_context("path", uri.path())
+}
+
+pub async fn method_not_allowed_handler(method: Method, uri: Uri) -> ServerError {
+    ServerError::method_not_allowed("method not allowed")
+        .with_context("method", method.as_str())
+        .with_context("path", uri.path())
+}
+
 pub async fn parse_set_balance_report(Path(id): Path<String>) -> Result<Json<Value>, ServerError> {
     let res = parse_flp_balances_setting_res(&id)?;
     Ok(Json(serde_json::to_value(&res)?))
 }
 
+/// downloads `tx_id` and runs the parser picked by `as` over it, to see why a
+/// tx did (not) index: `set_balances`, `mappings`, `delegation` or `minting`.
+/// parse failures are returned as 422 with the parser's error.
+pub async fn debug_parse_tx(
+    Path(tx_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let kind = params.get("as").map(String::as_str).unwrap_or_default();
+    if !["set_balances", "mappings", "delegation", "minting"].contains(&kind) {
+        return Err(ServerError::bad_request(
+            "as must be one of set_balances, mappings, delegation, minting",
+        ));
+    }
+    let data = download_tx_data(&tx_id)?;
+    let parse_error = |err: FlpError| ServerError::unprocessable(format!("{kind}: {err}"));
+    let parsed = match kind {
+        "set_balances" => {
+            let mut reader = BalancesReader::for_tx(&tx_id, data.as_slice());
+            let rows = reader
+                .by_ref()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(parse_error)?;
+            let skipped = reader.skipped();
+            json!({
+                "rows": rows,
+                "skipped": { "count": skipped.count, "sample": skipped.sample },
+            })
+        }
+        "mappings" => {
+            let (rows, skipped) =
+                delegation_mappings_from_slice(&tx_id, &data).map_err(parse_error)?;
+            json!({
+                "rows": rows,
+                "skipped": { "count": skipped.count, "sample": skipped.sample },
+            })
+        }
+        "delegation" => {
+            serde_json::to_value(delegations_from_slice(&tx_id, &data).map_err(parse_error)?)?
+        }
+        _ => serde_json::to_value(
+            own_minting_report_from_slice(&tx_id, &data).map_err(parse_error)?,
+        )?,
+    };
+    Ok(Json(json!({
+        "tx_id": tx_id,
+        "as": kind,
+        "size": data.len(),
+        "parsed": parsed,
+    })))
+}
+
 pub async fn get_wallet_delegations_handler(
     Path(address): Path<String>,
 ) -> Result<Json<Value>, ServerError> {
     let res = get_wallet_delegations(&address)
-        .map_err(|err| ServerError::from(anyhow!("wallet delegations error: {err}")))?;
+        .map_err(|err| ServerError::from(anyhow!("wallet delegations error: {err}")))?
+        .with_target_projects();
     Ok(Json(serde_json::to_value(&res)?))
 }
 
+/// most addresses `/wallet/delegations/batch` resolves per request
+const DELEGATIONS_BATCH_MAX: usize = 50;
+/// gateway lookups in flight per batch request
+const DELEGATIONS_BATCH_PARALLELISM: usize = 8;
+
+/// resolves the delegations of a JSON array of addresses in one call, as an
+/// address -> `DelegationsRes` map. a failed lookup is reported as
+/// `{"error": ...}` under its address instead of failing the batch.
+pub async fn get_wallet_delegations_batch(
+    body: Result<Json<Vec<String>>, JsonRejection>,
+) -> Result<Json<Value>, ServerError> {
+    let Json(addresses) = body.map_err(|rejection| {
+        ServerError::bad_request(format!(
+            "expected a JSON array of addresses: {}",
+            rejection.body_text()
+        ))
+    })?;
+    let addresses = parse_batch_addresses(addresses)?;
+    let resolved = resolve_delegations_batch(addresses, get_wallet_delegations).await?;
+    Ok(Json(serde_json::to_value(resolved)?))
+}
+
+/// runs `lookup` for each address, at most `DELEGATIONS_BATCH_PARALLELISM` at
+/// a time, keeping a failed lookup as an `{"error": ...}` entry
+pub(crate) async fn resolve_delegations_batch(
+    addresses: BTreeSet<String>,
+    lookup: fn(&str) -> Result<DelegationsRes, FlpError>,
+) -> Result<BTreeMap<String, Value>, ServerError> {
+    let permits = Arc::new(Semaphore::new(DELEGATIONS_BATCH_PARALLELISM));
+    let mut lookups = JoinSet::new();
+    for address in addresses {
+        let permits = permits.clone();
+        lookups.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let wallet = address.clone();
+            let res = tokio::task::spawn_blocking(move || lookup(&wallet)).await;
+            (address, res)
+        });
+    }
+    let mut resolved = BTreeMap::new();
+    while let Some(joined) = lookups.join_next().await {
+        let (address, res) = joined.map_err(|err| ServerError::from(anyhow!(err)))?;
+        let value = match res {
+            Ok(Ok(delegations)) => serde_json::to_value(delegations.with_target_projects())?,
+            Ok(Err(err)) => json!({ "error": format!("wallet delegations error: {err}") }),
+            Err(err) => json!({ "error": format!("wallet delegations lookup failed: {err}") }),
+        };
+        resolved.insert(address, value);
+    }
+    Ok(resolved)
+}
+
+/// 1..=`DELEGATIONS_BATCH_MAX` wallet addresses, deduplicated
+fn parse_batch_addresses(addresses: Vec<String>) -> Result<BTreeSet<String>, ServerError> {
+    if addresses.is_empty() {
+        return Err(ServerError::bad_request("no addresses given"));
+    }
+    if addresses.len() > DELEGATIONS_BATCH_MAX {
+        return Err(ServerError::bad_request(format!(
+            "at most {DELEGATIONS_BATCH_MAX} addresses per batch, got {}",
+            addresses.len()
+        )));
+    }
+    addresses
+        .into_iter()
+        .map(|address| {
+            let address = address.trim().to_string();
+            if is_wallet_address(&address) {
+                Ok(address)
+            } else {
+                Err(ServerError::bad_request(format!(
+                    "invalid address: {address:?}"
+                )))
+            }
+        })
+        .collect()
+}
+
+/// an Arweave address (43 base64url chars) or an EVM `0x` address
+fn is_wallet_address(address: &str) -> bool {
+    let arweave = address.len() == 43
+        && address
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    let evm = address
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+    arweave || evm
+}
+
 pub async fn get_oracle_data_handler(
     Path(ticker): Path<String>,
 ) -> Result<Json<Value>, ServerError> {
@@ -99,10 +291,79 @@ pub async fn get_oracle_data_handler(
 
 pub async fn get_flp_snapshot_handler(
     Path(project): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<(HeaderMap, Json<Value>), ServerError> {
+    let min_amount = parse_min_amount(params.get("min_amount"))?;
+    let client = AtlasIndexerClient::new().await?;
+    if parse_flag(&params, "count_only") {
+        let count = client
+            .count_project_delegators(&project, min_amount)
+            .await?;
+        return Ok((HeaderMap::new(), Json(json!({ "count": count }))));
+    }
+    let max_age_secs = parse_max_age(params.get("max_age_secs"))?;
+    let snapshot = client.latest_project_snapshot(&project, min_amount).await?;
+    let headers = snapshot_ts_headers(snapshot.ts);
+    let age = SnapshotAge::new(Some(snapshot.oldest_ts), Utc::now(), max_age_secs);
+    Ok((
+        headers,
+        Json(serde_json::to_value(Aged {
+            data: snapshot,
+            age,
+        })?),
+    ))
+}
+
+pub async fn get_all_flp_snapshots() -> Result<(HeaderMap, Json<Value>), ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let snapshots: Vec<ProjectSnapshotSummary> = client.all_project_snapshots().await?;
+    // the newest of the per-project snapshots
+    let headers = snapshots
+        .iter()
+        .map(|snapshot| snapshot.ts)
+        .max()
+        .map(snapshot_ts_headers)
+        .unwrap_or_default();
+    Ok((headers, Json(serde_json::to_value(&snapshots)?)))
+}
+
+pub async fn get_project_concentration(
+    Path(project): Path<String>,
+) -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let concentration: ProjectConcentration = client.project_concentration(&project).await?;
+    Ok(Json(serde_json::to_value(&concentration)?))
+}
+
+pub async fn get_project_position_deltas(
+    Path(project): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let ticker = params
+        .get("ticker")
+        .ok_or_else(|| ServerError::bad_request("missing ticker query param"))?;
+    let client = AtlasIndexerClient::new().await?;
+    let deltas: ProjectPositionDeltas = client.project_position_deltas(&project, ticker).await?;
+    Ok(Json(serde_json::to_value(&deltas)?))
+}
+
+pub async fn get_oracle_balance_deltas(
+    Path(ticker): Path<String>,
+) -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let deltas = client
+        .oracle_balance_deltas(&ticker)
+        .await?
+        .ok_or_else(|| ServerError::not_found(format!("no {ticker} oracle cycles indexed")))?;
+    Ok(Json(serde_json::to_value(&deltas)?))
+}
+
+pub async fn get_zero_balance_delegators(
+    Path(project): Path<String>,
 ) -> Result<Json<Value>, ServerError> {
     let client = AtlasIndexerClient::new().await?;
-    let snapshot = client.latest_project_snapshot(&project).await?;
-    Ok(Json(serde_json::to_value(snapshot)?))
+    let delegators = client.zero_balance_delegators(&project).await?;
+    Ok(Json(serde_json::to_value(&delegators)?))
 }
 
 pub async fn get_eoa_wallet_identity(Path(eoa): Path<String>) -> Result<Json<Value>, ServerError> {
@@ -119,50 +380,274 @@ pub async fn get_ar_wallet_identity(
     Ok(Json(serde_json::to_value(&identities)?))
 }
 
-pub async fn get_oracle_feed(Path(ticker): Path<String>) -> Result<Json<Value>, ServerError> {
+pub async fn get_current_wallet_identity(
+    Path(address): Path<String>,
+) -> Result<Json<Value>, ServerError> {
     let client = AtlasIndexerClient::new().await?;
-    let feed = client.oracle_snapshot_feed(&ticker, 25).await?;
+    let identity = client
+        .current_identity(&address)
+        .await?
+        .ok_or_else(|| ServerError::not_found(format!("no identity found for {address}")))?;
+    Ok(Json(serde_json::to_value(&identity)?))
+}
+
+pub async fn get_oracle_feed(
+    Path(ticker): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let order = parse_order(params.get("order"))?;
+    let max_age_secs = parse_max_age(params.get("max_age_secs"))?;
+    let client = AtlasIndexerClient::new().await?;
+    let feed = client.oracle_snapshot_feed(&ticker, 25, order).await?;
     let metadata = OracleStakers::new(&ticker).oracle.metadata()?;
-    let res = json!({
+    // age of the newest feed whatever the requested order
+    let age = SnapshotAge::new(
+        feed.iter().map(|snapshot| snapshot.ts).max(),
+        Utc::now(),
+        max_age_secs,
+    );
+    let res = Aged {
+        data: OracleFeed {
+            oracle_pid: metadata.ao_pid_mainnet,
+            oracle_evm_address: metadata.evm_address,
+            recent_indexed_feeds: feed,
+        },
+        age,
+    };
+    Ok(Json(serde_json::to_value(&res)?))
+}
+
+/// body of `/oracle/feed/{ticker}`, next to its `SnapshotAge`
+#[derive(Serialize)]
+pub(crate) struct OracleFeed {
+    pub oracle_pid: String,
+    pub oracle_evm_address: String,
+    pub recent_indexed_feeds: Vec<OracleSnapshot>,
+}
+
+const ORACLE_VALUE_MAX_RANGE_DAYS: i64 = 366;
+
+pub async fn get_oracle_value_daily(
+    Path(ticker): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let (from, to) = parse_day_range(&params, 29, ORACLE_VALUE_MAX_RANGE_DAYS)?;
+    let client = AtlasIndexerClient::new().await?;
+    let days = client.oracle_value_daily(&ticker, from, to).await?;
+    if days.iter().all(|day| day.total.is_none()) {
+        return Err(ServerError::not_found(format!(
+            "no oracle snapshots found for ticker {ticker} up to {to}"
+        )));
+    }
+    Ok(Json(serde_json::to_value(&days)?))
+}
+
+/// the ticker's recent AO vs EVM stake checks, newest first
+pub async fn get_oracle_divergence(
+    Path(ticker): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let limit = PageParams::parse(&params, 25)
+        .limit
+        .min(ORACLE_DIVERGENCE_MAX_LIMIT);
+    let client = AtlasIndexerClient::new().await?;
+    let checks = client.oracle_divergence(&ticker, limit).await?;
+    if checks.is_empty() {
+        return Err(ServerError::not_found(format!(
+            "no evm divergence checks stored for ticker {ticker}"
+        )));
+    }
+    Ok(Json(serde_json::to_value(&checks)?))
+}
+
+const ORACLE_DIVERGENCE_MAX_LIMIT: u64 = 500;
+
+/// the project's stored minting reports, each with the delegated totals of
+/// the nearest indexed cycles
+pub async fn get_project_mint_vs_delegation(
+    Path(project): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let limit = PageParams::parse(&params, 30)
+        .limit
+        .min(MINT_VS_DELEGATION_MAX_LIMIT);
+    let client = AtlasIndexerClient::new().await?;
+    let rows = client.project_mint_vs_delegation(&project, limit).await?;
+    if rows.is_empty() {
+        return Err(ServerError::not_found(format!(
+            "no minting reports stored for project {project}"
+        )));
+    }
+    Ok(Json(serde_json::to_value(&rows)?))
+}
+
+const MINT_VS_DELEGATION_MAX_LIMIT: u64 = 500;
+
+/// every registered oracle with its pids, staking address and denomination.
+/// `indexed` tells whether this deployment's `ORACLE_TICKERS` tracks it.
+pub async fn get_oracle_tickers() -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let indexed = client.indexed_oracle_tickers().await?;
+    let mut oracles = Vec::with_capacity(ORACLES.len());
+    for oracle in ORACLES {
+        let Some(ticker) = oracle.ticker() else {
+            continue;
+        };
+        let metadata = oracle.metadata()?;
+        oracles.push(json!({
+            "ticker": ticker,
+            "indexed": indexed.iter().any(|t| t == ticker),
+            "oracle_pid": metadata.ao_pid_mainnet,
+            "oracle_legacy_pid": metadata.ao_pid_legacy,
+            "oracle_evm_address": metadata.evm_address,
+            "token_evm_address": metadata.evm_token_address,
+            "denomination": metadata.denomination,
+        }));
+    }
+    Ok(Json(Value::Array(oracles)))
+}
+
+pub async fn get_oracle_overview() -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let (usds, dai, steth) = tokio::try_join!(
+        oracle_overview_entry(&client, "usds"),
+        oracle_overview_entry(&client, "dai"),
+        oracle_overview_entry(&client, "steth"),
+    )?;
+    Ok(Json(json!({
+        "usds": usds,
+        "dai": dai,
+        "steth": steth
+    })))
+}
+
+// an oracle without indexed snapshots is reported with null stats instead of
+// failing the whole overview
+async fn oracle_overview_entry(
+    client: &AtlasIndexerClient,
+    ticker: &str,
+) -> Result<Value, ServerError> {
+    let metadata = OracleStakers::new(ticker).oracle.metadata()?;
+    let latest = match client
+        .oracle_snapshot_feed(ticker, 1, SortOrder::Desc)
+        .await
+    {
+        Ok(feed) => feed.into_iter().next(),
+        Err(err) => {
+            eprintln!("oracle overview {ticker}: {err:?}");
+            None
+        }
+    };
+    Ok(json!({
         "oracle_pid": metadata.ao_pid_mainnet,
         "oracle_evm_address": metadata.evm_address,
-        "recent_indexed_feeds": feed
-    });
-    Ok(Json(res))
+        "latest_tx_id": latest.as_ref().map(|s| s.tx_id.clone()),
+        "total_delegated": latest.as_ref().map(|s| s.total),
+        "delegators": latest.as_ref().map(|s| s.delegators),
+        "last_update": latest.as_ref().map(|s| s.ts),
+    }))
 }
 
 pub async fn get_wallet_delegation_mappings_history(
     Path(address): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
     let client = AtlasIndexerClient::new().await?;
+    if let Some(raw) = params.get("at_height") {
+        let height: BlockHeight = raw
+            .parse()
+            .map_err(|_| ServerError::bad_request(format!("invalid at_height: {raw}")))?;
+        let mapping = client
+            .wallet_delegation_at(&address, height)
+            .await?
+            .ok_or_else(|| {
+                ServerError::not_found(format!(
+                    "no delegation mapping found for wallet {address} at height {height}"
+                ))
+            })?;
+        return Ok(Json(serde_json::to_value(&mapping)?));
+    }
+    if parse_flag(&params, "count_only") {
+        let count = client.count_wallet_delegation_mappings(&address).await?;
+        return Ok(Json(json!({ "count": count })));
+    }
+    let order = parse_order(params.get("order"))?;
     let history: Vec<DelegationMappingHistory> =
-        client.wallet_delegation_mappings(&address).await?;
+        client.wallet_delegation_mappings(&address, order).await?;
     Ok(Json(serde_json::to_value(&history)?))
 }
 
+pub async fn get_delegation_mapping_by_tx(
+    Path(tx_id): Path<String>,
+) -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let mapping: Vec<DelegationMappingHistory> = client.delegation_mapping_by_tx(&tx_id).await?;
+    if mapping.is_empty() {
+        return Err(ServerError::not_found(format!(
+            "no delegation mapping found for tx {tx_id}"
+        )));
+    }
+    Ok(Json(serde_json::to_value(&mapping)?))
+}
+
+pub async fn get_latest_delegation_snapshot() -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let snapshot: DelegationSnapshot = client.latest_delegation_snapshot().await?;
+    Ok(Json(serde_json::to_value(&snapshot)?))
+}
+
+/// the newest delegation mapping snapshot as the `wallet_from,wallet_to,factor`
+/// CSV it was published as, streamed. a failure mid-stream aborts the body
+/// rather than ending it early.
+pub async fn get_latest_delegation_csv() -> Result<(HeaderMap, Body), ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let (latest, chunks) = client.latest_delegation_mappings_csv().await?;
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/csv; charset=utf-8"),
+    );
+    let disposition = format!(
+        "attachment; filename=\"delegation-mappings-{}.csv\"",
+        latest.height
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition)?,
+    );
+    Ok((headers, Body::from_stream(chunks)))
+}
+
+pub async fn get_delegation_split_distribution() -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let distribution = client.delegation_split_distribution().await?;
+    Ok(Json(serde_json::to_value(&distribution)?))
+}
+
 pub async fn get_delegation_mapping_heights(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
-    let limit = params
-        .get("limit")
-        .and_then(|v| v.parse::<u64>().ok())
-        .filter(|v| *v > 0)
-        .unwrap_or(25);
+    let page = PageParams::parse(&params, 25);
     let client = AtlasIndexerClient::new().await?;
-    let rows: Vec<DelegationHeight> = client.latest_delegation_heights(limit).await?;
+    let rows: Page<DelegationHeight> = client
+        .latest_delegation_heights(page.limit, page.offset, page.count)
+        .await?;
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
 pub async fn get_multi_project_delegators(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
-    let limit = params
-        .get("limit")
-        .and_then(|v| v.parse::<u64>().ok())
-        .filter(|v| *v > 0)
-        .unwrap_or(100);
+    let min_projects = parse_min_projects(params.get("min_projects"))?;
     let client = AtlasIndexerClient::new().await?;
-    let rows: Vec<MultiDelegator> = client.multi_project_delegators(limit).await?;
+    if parse_flag(&params, "count_only") {
+        let count = client.count_multi_project_delegators(min_projects).await?;
+        return Ok(Json(json!({ "count": count })));
+    }
+    let page = PageParams::parse(&params, 100);
+    let rows: Page<MultiDelegator> = client
+        .multi_project_delegators(min_projects, page.limit, page.offset, page.count)
+        .await?;
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
@@ -170,19 +655,58 @@ pub async fn get_project_cycle_totals(
     Path(project): Path<String>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
-    let limit = params
-        .get("limit")
-        .and_then(|v| v.parse::<u64>().ok())
-        .filter(|v| *v > 0)
-        .unwrap_or(25);
+    let page = PageParams::parse(&params, 25);
     let ticker = params.get("ticker").cloned();
     let client = AtlasIndexerClient::new().await?;
-    let rows: Vec<ProjectCycleTotal> = client
-        .project_cycle_totals(&project, ticker.as_deref(), limit)
+    let rows: Page<ProjectCycleTotal> = client
+        .project_cycle_totals(
+            &project,
+            ticker.as_deref(),
+            page.limit,
+            page.offset,
+            page.count,
+        )
         .await?;
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
+/// a boolean query flag, set by `1` or `true`
+fn parse_flag(params: &HashMap<String, String>, name: &str) -> bool {
+    params.get(name).is_some_and(|v| is_flag_set(v))
+}
+
+/// `1` or `true`, the values that switch on query and env flags
+pub(crate) fn is_flag_set(value: &str) -> bool {
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+/// `limit`/`offset`/`count` query params shared by the paginated endpoints
+struct PageParams {
+    limit: u64,
+    offset: u64,
+    count: bool,
+}
+
+impl PageParams {
+    fn parse(params: &HashMap<String, String>, default_limit: u64) -> Self {
+        let limit = params
+            .get("limit")
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(default_limit);
+        let offset = params
+            .get("offset")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0);
+        let count = parse_flag(params, "count");
+        Self {
+            limit,
+            offset,
+            count,
+        }
+    }
+}
+
 pub async fn get_flp_own_minting_report_handler(
     Path(project): Path<String>,
 ) -> Result<Json<Value>, ServerError> {
@@ -191,9 +715,41 @@ pub async fn get_flp_own_minting_report_handler(
     Ok(Json(serde_json::to_value(&report)?))
 }
 
-pub async fn get_all_projects_metadata_handler() -> Result<Json<Value>, ServerError> {
+#[derive(Serialize)]
+struct ProjectWithTotals {
+    #[serde(flatten)]
+    project: Project,
+    delegators_count: u32,
+    totals: Vec<ProjectTotal>,
+}
+
+pub async fn get_all_projects_metadata_handler(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
     let projects = Project::get_all();
-    Ok(Json(serde_json::to_value(&projects)?))
+    if !parse_flag(&params, "with_totals") {
+        return Ok(Json(serde_json::to_value(&projects)?));
+    }
+    let client = AtlasIndexerClient::new().await?;
+    let mut totals: HashMap<String, ProjectTotals> = client
+        .all_projects_totals()
+        .await?
+        .into_iter()
+        .map(|row| (row.project.clone(), row))
+        .collect();
+    // projects without indexed positions are reported with zeroed totals
+    let rows: Vec<ProjectWithTotals> = projects
+        .into_iter()
+        .map(|project| {
+            let indexed = totals.remove(&project.pid);
+            ProjectWithTotals {
+                delegators_count: indexed.as_ref().map_or(0, |t| t.delegators_count),
+                totals: indexed.map(|t| t.totals).unwrap_or_default(),
+                project,
+            }
+        })
+        .collect();
+    Ok(Json(serde_json::to_value(&rows)?))
 }
 
 pub async fn get_explorer_blocks(
@@ -204,8 +760,9 @@ pub async fn get_explorer_blocks(
         .and_then(|v| v.parse::<u64>().ok())
         .filter(|v| *v > 0)
         .unwrap_or(100);
+    let order = parse_order(params.get("order"))?;
     let client = AtlasIndexerClient::new().await?;
-    let rows: Vec<ExplorerBlock> = client.latest_explorer_blocks(limit).await?;
+    let rows: Vec<ExplorerBlock> = client.latest_explorer_blocks(limit, order).await?;
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
@@ -236,6 +793,94 @@ pub async fn get_explorer_recent_days(
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
+pub async fn get_explorer_block_time(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let (from, to) = parse_height_window(&params)?;
+    let client = AtlasIndexerClient::new().await?;
+    let stats: BlockTimeStats = client.explorer_block_time(from, to).await?;
+    Ok(Json(serde_json::to_value(&stats)?))
+}
+
+/// per-block diffs between the legacy and mainnet explorer rollups
+pub async fn get_explorer_reconcile(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let (from, to) = parse_height_window(&params)?;
+    let client = AtlasIndexerClient::new().await?;
+    let res: ExplorerReconciliation = client.compare_explorer_sources(from, to).await?;
+    Ok(Json(serde_json::to_value(&res)?))
+}
+
+pub async fn get_mainnet_explorer_block_time(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let (from, to) = parse_height_window(&params)?;
+    let client = AtlasIndexerClient::new().await?;
+    let stats: BlockTimeStats = client.mainnet_explorer_block_time(from, to).await?;
+    Ok(Json(serde_json::to_value(&stats)?))
+}
+
+/// an optional block height param
+fn parse_height(params: &HashMap<String, String>, key: &str) -> Result<Option<u64>, ServerError> {
+    params
+        .get(key)
+        .map(|v| {
+            v.trim()
+                .parse::<u64>()
+                .map_err(|_| ServerError::bad_request(format!("invalid {key} height: {v}")))
+        })
+        .transpose()
+}
+
+/// optional `from`/`to` block heights, at most `BLOCK_TIME_MAX_WINDOW` apart
+fn parse_height_window(
+    params: &HashMap<String, String>,
+) -> Result<(Option<u64>, Option<u64>), ServerError> {
+    let (from, to) = (parse_height(params, "from")?, parse_height(params, "to")?);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(ServerError::bad_request("from must not be after to"));
+        }
+        if to - from >= BLOCK_TIME_MAX_WINDOW {
+            return Err(ServerError::bad_request(format!(
+                "range too large (max {BLOCK_TIME_MAX_WINDOW} blocks)"
+            )));
+        }
+    }
+    Ok((from, to))
+}
+
+const EXPLORER_BLOCKS_MAX_LIMIT: u64 = 1000;
+
+/// `live=1` computes a not yet indexed block from the gateway
+pub async fn get_explorer_block(
+    Path(height): Path<u64>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let live = parse_flag(&params, "live");
+    let client = AtlasIndexerClient::new().await?;
+    let block = match client.explorer_block(height).await? {
+        Some(block) => Some(block),
+        None if live => client.live_explorer_block(height).await?,
+        None => None,
+    };
+    let block: ExplorerBlock =
+        block.ok_or_else(|| ServerError::not_found(format!("block {height} not indexed yet")))?;
+    Ok(Json(serde_json::to_value(&block)?))
+}
+
+pub async fn get_mainnet_explorer_block(
+    Path(height): Path<u64>,
+) -> Result<Json<Value>, ServerError> {
+    let client = AtlasIndexerClient::new().await?;
+    let block = client
+        .mainnet_explorer_block(height)
+        .await?
+        .ok_or_else(|| ServerError::not_found(format!("block {height} not indexed yet")))?;
+    Ok(Json(serde_json::to_value(&block)?))
+}
+
 pub async fn get_mainnet_explorer_blocks(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
@@ -243,9 +888,64 @@ pub async fn get_mainnet_explorer_blocks(
         .get("limit")
         .and_then(|v| v.parse::<u64>().ok())
         .filter(|v| *v > 0)
-        .unwrap_or(100);
+        .unwrap_or(100)
+        .min(EXPLORER_BLOCKS_MAX_LIMIT);
+    let since = params.get("since").and_then(|v| v.parse::<u64>().ok());
     let client = AtlasIndexerClient::new().await?;
-    let rows = client.mainnet_explorer_blocks(limit).await?;
+    let rows = match since {
+        Some(height) => client.explorer_blocks_since(height, limit).await?,
+        None => client.mainnet_explorer_blocks(limit).await?,
+    };
+    Ok(Json(serde_json::to_value(&rows)?))
+}
+
+pub async fn get_mainnet_explorer_activity(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    // `from`/`to` like the other height windows, the original
+    // `from_height`/`to_height` names still work
+    let from_height = match parse_height(&params, "from")? {
+        Some(height) => Some(height),
+        None => parse_height(&params, "from_height")?,
+    };
+    let to_height = match parse_height(&params, "to")? {
+        Some(height) => Some(height),
+        None => parse_height(&params, "to_height")?,
+    };
+    let (from_height, to_height) = (from_height.unwrap_or(0), to_height.unwrap_or(u64::MAX));
+    if from_height > to_height {
+        return Err(ServerError::bad_request("from must not be after to"));
+    }
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(100)
+        .min(EXPLORER_BLOCKS_MAX_LIMIT);
+    let client = AtlasIndexerClient::new().await?;
+    let rows: Vec<ExplorerActivity> = client
+        .explorer_activity(from_height, to_height, limit)
+        .await?;
+    Ok(Json(serde_json::to_value(&rows)?))
+}
+
+const MAINNET_BLOCK_METRICS_MAX_LIMIT: u64 = 1000;
+
+pub async fn get_mainnet_block_metrics(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let after = params
+        .get("after")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(0);
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(100)
+        .min(MAINNET_BLOCK_METRICS_MAX_LIMIT);
+    let client = AtlasIndexerClient::new().await?;
+    let rows = client.mainnet_block_metrics(after, limit).await?;
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
@@ -263,6 +963,47 @@ pub async fn get_mainnet_explorer_day_stats(
     Ok(Json(serde_json::to_value(&stats)?))
 }
 
+const DAU_MAX_RANGE_DAYS: i64 = 366;
+
+pub async fn get_mainnet_daily_active_users(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let (from, to) = parse_day_range(&params, 29, DAU_MAX_RANGE_DAYS)?;
+    let client = AtlasIndexerClient::new().await?;
+    let rows = client.daily_active_users(from, to).await?;
+    Ok(Json(serde_json::to_value(&rows)?))
+}
+
+/// optional `from`/`to` YYYY-MM-DD params: `to` defaults to today and `from`
+/// to `default_days` before it; the range must span fewer than `max_days`
+fn parse_day_range(
+    params: &HashMap<String, String>,
+    default_days: i64,
+    max_days: i64,
+) -> Result<(NaiveDate, NaiveDate), ServerError> {
+    let parse_day = |key: &str| -> Result<Option<NaiveDate>, ServerError> {
+        params
+            .get(key)
+            .map(|v| {
+                NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d").map_err(|_| {
+                    ServerError::bad_request(format!("invalid {key} format (expected YYYY-MM-DD)"))
+                })
+            })
+            .transpose()
+    };
+    let to = parse_day("to")?.unwrap_or_else(|| Utc::now().date_naive());
+    let from = parse_day("from")?.unwrap_or(to - chrono::Duration::days(default_days));
+    if from > to {
+        return Err(ServerError::bad_request("from must not be after to"));
+    }
+    if (to - from).num_days() >= max_days {
+        return Err(ServerError::bad_request(format!(
+            "range too large (max {max_days} days)"
+        )));
+    }
+    Ok((from, to))
+}
+
 pub async fn get_mainnet_explorer_recent_days(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<Value>, ServerError> {
@@ -339,6 +1080,32 @@ pub async fn get_mainnet_messages_by_tag(
     Ok(Json(serde_json::to_value(&rows)?))
 }
 
+const TOP_PROCESSES_MAX_BLOCKS: u32 = 10_000;
+const TOP_PROCESSES_MAX_LIMIT: u64 = 100;
+
+pub async fn get_mainnet_top_processes(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let blocks = params
+        .get("blocks")
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(100)
+        .min(TOP_PROCESSES_MAX_BLOCKS);
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(25)
+        .min(TOP_PROCESSES_MAX_LIMIT);
+    let protocol = parse_protocol(params.get("protocol"))?;
+    let client = AtlasIndexerClient::new().await?;
+    let rows = client
+        .top_processes(protocol.as_deref(), blocks, limit)
+        .await?;
+    Ok(Json(serde_json::to_value(&rows)?))
+}
+
 pub async fn get_mainnet_indexing_info() -> Result<Json<Value>, ServerError> {
     let client = AtlasIndexerClient::new().await?;
     let rows = client.mainnet_indexing_info().await?;
@@ -375,6 +1142,10 @@ pub async fn get_ao_token_txs(
         .get("sender")
         .map(|v| v.trim().to_string())
         .filter(|v| !v.is_empty());
+    let sort = params
+        .get("sort")
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty());
     let order = params
         .get("order")
         .map(|v| v.trim().to_ascii_lowercase())
@@ -399,6 +1170,7 @@ pub async fn get_ao_token_txs(
             block_max,
             recipient.as_deref(),
             sender.as_deref(),
+            sort.as_deref(),
             order.as_deref(),
             limit,
             offset,
@@ -486,6 +1258,56 @@ pub async fn get_ao_token_richlist(
     Ok(Json(serde_json::to_value(&info)?))
 }
 
+pub async fn get_ao_token_balance(
+    Path((token, address)): Path<(String, String)>,
+) -> Result<Json<Value>, ServerError> {
+    let token = parse_token(&token)?;
+    let client = AtlasIndexerClient::new().await?;
+    let balance = client.ao_token_balance(&token, &address).await?;
+    Ok(Json(serde_json::to_value(&balance)?))
+}
+
+const TOKEN_HOLDERS_MAX_LIMIT: u64 = 500;
+
+pub async fn get_ao_token_holders(
+    Path(token): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let token = parse_token(&token)?;
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(25)
+        .min(TOKEN_HOLDERS_MAX_LIMIT);
+    let client = AtlasIndexerClient::new().await?;
+    let holders = client.ao_token_top_holders(&token, limit).await?;
+    Ok(Json(serde_json::to_value(&holders)?))
+}
+
+const TRANSFER_VOLUME_MAX_RANGE_DAYS: i64 = 366;
+
+pub async fn get_token_transfer_volume_daily(
+    Path(token): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, ServerError> {
+    let token = parse_token(&token)?;
+    let (from, to) = parse_day_range(&params, 29, TRANSFER_VOLUME_MAX_RANGE_DAYS)?;
+    let client = AtlasIndexerClient::new().await?;
+    let days = client.token_transfer_volume_daily(&token, from, to).await?;
+    Ok(Json(serde_json::to_value(&days)?))
+}
+
+fn parse_order(value: Option<&String>) -> Result<SortOrder, ServerError> {
+    match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("desc") => Ok(SortOrder::Desc),
+        Some("asc") => Ok(SortOrder::Asc),
+        Some(_) => Err(ServerError::bad_request(
+            "invalid order (expected asc or desc)",
+        )),
+    }
+}
+
 fn parse_protocol(value: Option<&String>) -> Result<Option<String>, ServerError> {
     if let Some(p) = value {
         let normalized = p.trim().to_ascii_uppercase();
@@ -567,6 +1389,35 @@ fn parse_u32_param(value: Option<&String>) -> Result<Option<u32>, ServerError> {
     Ok(Some(parsed))
 }
 
+/// a non-negative whole-token amount, e.g. `0.5`
+fn parse_min_amount(value: Option<&String>) -> Result<Option<TokenAmount>, ServerError> {
+    let Some(raw) = value.map(|v| v.trim()).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    let amount = raw
+        .parse::<TokenAmount>()
+        .map_err(|_| ServerError::bad_request(format!("invalid min_amount: {raw}")))?;
+    if amount < TokenAmount::ZERO {
+        return Err(ServerError::bad_request("min_amount must not be negative"));
+    }
+    Ok(Some(amount))
+}
+
+/// distinct FLPs a wallet must delegate to, at least 1 and 2 when unset
+fn parse_min_projects(value: Option<&String>) -> Result<u64, ServerError> {
+    let Some(raw) = value.map(|v| v.trim()).filter(|v| !v.is_empty()) else {
+        return Ok(DEFAULT_MIN_PROJECTS);
+    };
+    match raw.parse::<u64>() {
+        Ok(min) if min >= 1 => Ok(min),
+        _ => Err(ServerError::bad_request(format!(
+            "invalid min_projects: {raw} (expected a whole number >= 1)"
+        ))),
+    }
+}
+
+const DEFAULT_MIN_PROJECTS: u64 = 2;
+
 fn parse_amount_param(value: Option<&String>) -> Result<Option<String>, ServerError> {
     let Some(raw) = value else {
         return Ok(None);